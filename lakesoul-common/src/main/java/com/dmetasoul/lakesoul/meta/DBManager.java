@@ -13,6 +13,7 @@ import com.dmetasoul.lakesoul.meta.jnr.NativeUtils;
 import com.dmetasoul.lakesoul.meta.rbac.AuthZContext;
 import com.dmetasoul.lakesoul.meta.rbac.AuthZEnforcer;
 import org.apache.commons.lang3.StringUtils;
+import org.apache.hadoop.fs.Path;
 import org.slf4j.Logger;
 import org.slf4j.LoggerFactory;
 
@@ -259,7 +260,8 @@ public class DBManager {
         if (rearVersionPartition == null) {
             return singlePartitionAllVersionList;
         } else if (rearVersionPartition.getCommitOp().equals(CommitOp.CompactionCommit) ||
-                rearVersionPartition.getCommitOp().equals(CommitOp.UpdateCommit) || filterPartition.size() == 0) {
+                rearVersionPartition.getCommitOp().equals(CommitOp.UpdateCommit) ||
+                rearVersionPartition.getCommitOp().equals(CommitOp.RollbackCommit) || filterPartition.size() == 0) {
             return filterPartition;
         } else {
             throw new IllegalStateException(
@@ -489,7 +491,8 @@ public class DBManager {
                             curPartitionInfo = updateSubmitPartitionSnapshot(partitionInfo, curPartitionInfo, readPartition);
                         }
                     } else {
-                        if (middleCommitOps.contains(CommitOp.UpdateCommit) || middleCommitOps.contains(CommitOp.CompactionCommit)) {
+                        if (middleCommitOps.contains(CommitOp.UpdateCommit) || middleCommitOps.contains(CommitOp.RollbackCommit) ||
+                                middleCommitOps.contains(CommitOp.CompactionCommit)) {
                             partitionDescList.remove(partitionDesc);
                             snapshotList.removeAll(partitionInfo.getSnapshotList());
                             continue;
@@ -574,7 +577,7 @@ public class DBManager {
                 int newVersion = curVersion + 1;
                 PartitionInfo partitionInfo = rawMap.get(partitionDesc);
                 if (curCommitOp.equals(CommitOp.CompactionCommit) || curCommitOp.equals(CommitOp.AppendCommit) ||
-                        curCommitOp.equals(CommitOp.UpdateCommit)) {
+                        curCommitOp.equals(CommitOp.UpdateCommit) || curCommitOp.equals(CommitOp.RollbackCommit)) {
                     curPartitionInfo
                             .setVersion(newVersion)
                             .addAllSnapshot(partitionInfo.getSnapshotList())
@@ -621,7 +624,8 @@ public class DBManager {
                 Set<CommitOp> middleCommitOps =
                         partitionInfoDao.getCommitOpsBetweenVersions(tableId, partitionDesc, readPartitionVersion + 1,
                                 curVersion);
-                if (middleCommitOps.contains(CommitOp.UpdateCommit) || middleCommitOps.contains(CommitOp.CompactionCommit)) {
+                if (middleCommitOps.contains(CommitOp.UpdateCommit) || middleCommitOps.contains(CommitOp.RollbackCommit) ||
+                        middleCommitOps.contains(CommitOp.CompactionCommit)) {
                     partitionDescList.remove(i);
                     snapshotList.removeAll(rawPartitionInfo.getSnapshotList());
                     i = i - 1;
@@ -667,7 +671,7 @@ public class DBManager {
                 Set<CommitOp> middleCommitOps =
                         partitionInfoDao.getCommitOpsBetweenVersions(tableId, partitionDesc, readPartitionVersion + 1,
                                 curVersion);
-                if (middleCommitOps.contains(CommitOp.UpdateCommit) ||
+                if (middleCommitOps.contains(CommitOp.UpdateCommit) || middleCommitOps.contains(CommitOp.RollbackCommit) ||
                         (middleCommitOps.size() > 1 && middleCommitOps.contains(CommitOp.CompactionCommit))) {
                     throw new IllegalStateException(
                             "current operation conflicts with other write data tasks, table id is: " + tableId);
@@ -720,7 +724,7 @@ public class DBManager {
                 PartitionInfo partitionInfo = rawMap.get(partitionDesc);
                 int newVersion = curVersion + 1;
                 if (curCommitOp.equals(CommitOp.CompactionCommit) || curCommitOp.equals(CommitOp.UpdateCommit) ||
-                        curCommitOp.equals(CommitOp.MergeCommit)) {
+                        curCommitOp.equals(CommitOp.RollbackCommit) || curCommitOp.equals(CommitOp.MergeCommit)) {
                     curPartitionInfo
                             .setVersion(newVersion)
                             .addAllSnapshot(partitionInfo.getSnapshotList())
@@ -790,13 +794,38 @@ public class DBManager {
         String partitionDesc = partitionInfo.getPartitionDesc();
         List<Uuid> snapshotList = partitionInfo.getSnapshotList();
 
-        return dataCommitInfoDao.selectByTableIdPartitionDescCommitList(tableId, partitionDesc, snapshotList);
+        return resolveFilePaths(tableId,
+                dataCommitInfoDao.selectByTableIdPartitionDescCommitList(tableId, partitionDesc, snapshotList));
     }
 
     public List<DataCommitInfo> getPartitionSnapshot(String tableId, String partitionDesc, int version) {
         PartitionInfo partitionInfo = partitionInfoDao.findByKey(tableId, partitionDesc, version);
         List<Uuid> commitList = partitionInfo.getSnapshotList();
-        return dataCommitInfoDao.selectByTableIdPartitionDescCommitList(tableId, partitionDesc, commitList);
+        return resolveFilePaths(tableId,
+                dataCommitInfoDao.selectByTableIdPartitionDescCommitList(tableId, partitionDesc, commitList));
+    }
+
+    /**
+     * Qualify the paths of file ops stored relative to the table path, as written by a native client storing them
+     * relative, by the table path fetched once.
+     */
+    private List<DataCommitInfo> resolveFilePaths(String tableId, List<DataCommitInfo> dataCommitInfoList) {
+        boolean anyRelative = dataCommitInfoList.stream()
+                .flatMap(dataCommitInfo -> dataCommitInfo.getFileOpsList().stream())
+                .anyMatch(fileOp -> !new Path(fileOp.getPath()).isAbsolute());
+        if (!anyRelative) {
+            return dataCommitInfoList;
+        }
+        Path tablePath = new Path(tableInfoDao.selectByTableId(tableId).getTablePath());
+        return dataCommitInfoList.stream().map(dataCommitInfo -> {
+            DataCommitInfo.Builder builder = dataCommitInfo.toBuilder();
+            for (DataFileOp.Builder fileOp : builder.getFileOpsBuilderList()) {
+                if (!new Path(fileOp.getPath()).isAbsolute()) {
+                    fileOp.setPath(new Path(tablePath, fileOp.getPath()).toString());
+                }
+            }
+            return builder.build();
+        }).collect(Collectors.toList());
     }
 
     public List<PartitionInfo> getIncrementalPartitions(String tableId, String partitionDesc, int startVersion,
@@ -819,7 +848,8 @@ public class DBManager {
 
     public List<DataCommitInfo> getDataCommitInfosFromUUIDs(String tableId, String partitionDesc,
                                                             List<Uuid> dataCommitUUIDs) {
-        return dataCommitInfoDao.selectByTableIdPartitionDescCommitList(tableId, partitionDesc, dataCommitUUIDs);
+        return resolveFilePaths(tableId,
+                dataCommitInfoDao.selectByTableIdPartitionDescCommitList(tableId, partitionDesc, dataCommitUUIDs));
     }
 
     public void rollbackPartitionByVersion(String tableId, String partitionDesc, int version) {
@@ -44,6 +44,8 @@ public class DBUtil {
     private static final String passwordEnv = "LAKESOUL_PG_PASSWORD";
     private static final String domainENV = "LAKESOUL_CURRENT_DOMAIN";
     public static final String domainKey = "lakesoul.current.domain";
+    private static final String namespaceENV = "LAKESOUL_CURRENT_NAMESPACE";
+    public static final String namespaceKey = "lakesoul.current.namespace";
 
     private static final String lakeSoulHomeEnv = "LAKESOUL_HOME";
 
@@ -113,6 +115,10 @@ public class DBUtil {
         return getConfigValue(domainENV, domainKey, "public");
     }
 
+    public static String getNamespace() {
+        return getConfigValue(namespaceENV, namespaceKey, LAKESOUL_DEFAULT_NAMESPACE);
+    }
+
     public static String getUser() {
         return getConfigValue(usernameEnv, usernameKey, null);
     }
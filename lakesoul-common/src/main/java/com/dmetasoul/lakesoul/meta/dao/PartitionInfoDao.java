@@ -235,17 +235,13 @@ public class PartitionInfoDao {
     public long getLatestTimestamp(String tableId, String partitionDesc) {
         if (NativeUtils.NATIVE_METADATA_QUERY_ENABLED) {
             if (null == partitionDesc || "".equals(partitionDesc)) {
-                List<String> result = NativeMetadataJavaClient.queryScalar(
+                return NativeMetadataJavaClient.queryScalarValue(
                         NativeUtils.CodedDaoType.GetLatestTimestampFromPartitionInfoWithoutPartitionDesc,
-                        Collections.singletonList(tableId));
-                if (result == null || result.isEmpty()) return -1;
-                return Long.parseLong(result.get(0));
+                        Collections.singletonList(tableId)).map(Long::parseLong).orElse(-1L);
             } else {
-                List<String> result = NativeMetadataJavaClient.queryScalar(
+                return NativeMetadataJavaClient.queryScalarValue(
                         NativeUtils.CodedDaoType.GetLatestTimestampFromPartitionInfo,
-                        Arrays.asList(tableId, partitionDesc));
-                if (result == null || result.isEmpty()) return -1;
-                return Long.parseLong(result.get(0));
+                        Arrays.asList(tableId, partitionDesc)).map(Long::parseLong).orElse(-1L);
             }
         }
         Connection conn = null;
@@ -279,11 +275,9 @@ public class PartitionInfoDao {
 
     public int getLastedVersionUptoTime(String tableId, String partitionDesc, long utcMills) {
         if (NativeUtils.NATIVE_METADATA_QUERY_ENABLED) {
-            List<String> result = NativeMetadataJavaClient.queryScalar(
+            return NativeMetadataJavaClient.queryScalarValue(
                     NativeUtils.CodedDaoType.GetLatestVersionUpToTimeFromPartitionInfo,
-                    Arrays.asList(tableId, partitionDesc, Long.toString(utcMills)));
-            if (result == null || result.isEmpty()) return -1;
-            return Integer.parseInt(result.get(0));
+                    Arrays.asList(tableId, partitionDesc, Long.toString(utcMills))).map(Integer::parseInt).orElse(-1);
         }
         Connection conn = null;
         PreparedStatement pstmt = null;
@@ -316,11 +310,9 @@ public class PartitionInfoDao {
 
     public long getLastedVersionTimestampUptoTime(String tableId, String partitionDesc, long utcMills) {
         if (NativeUtils.NATIVE_METADATA_QUERY_ENABLED) {
-            List<String> result = NativeMetadataJavaClient.queryScalar(
+            return NativeMetadataJavaClient.queryScalarValue(
                     NativeUtils.CodedDaoType.GetLatestVersionTimestampUpToTimeFromPartitionInfo,
-                    Arrays.asList(tableId, partitionDesc, Long.toString(utcMills)));
-            if (result == null || result.isEmpty()) return 0;
-            return Long.parseLong(result.get(0));
+                    Arrays.asList(tableId, partitionDesc, Long.toString(utcMills))).map(Long::parseLong).orElse(0L);
         }
         Connection conn = null;
         PreparedStatement pstmt = null;
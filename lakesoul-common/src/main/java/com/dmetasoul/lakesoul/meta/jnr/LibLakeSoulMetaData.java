@@ -21,24 +21,44 @@ public interface LibLakeSoulMetaData {
 
     void free_tokio_postgres_client(Pointer client);
 
+    void set_result_format(BooleanCallback booleanCallback, Pointer client, Integer version);
+
     Pointer execute_query(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, String texts);
 
+    Pointer meta_get_partition_history(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, String tableId, String partitionDesc, @LongLong long limit);
+
+    Pointer meta_get_table_stats(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, String tableId);
+
+    Pointer meta_list_table_info_by_domain(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, String domain, String afterTableId, @LongLong long limit);
+
     void export_bytes_result(BooleanCallback booleanCallback, Pointer bytes, Integer len, @LongLong long addr);
 
+    void execute_query_into(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, String texts, @LongLong long addr, int cap);
+
     void free_bytes_result(Pointer bytes);
 
     void execute_update(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, String texts);
 
     void execute_query_scalar(StringCallback stringCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, String texts);
 
+    void execute_query_scalar_value(ScalarCallback scalarCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, String texts);
+
     void execute_insert(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, @LongLong long addr, int length);
 
+    void execute_insert_with_timestamp_mode(IntegerCallback integerCallback, Pointer runtime, Pointer client, Pointer prepared, Integer type, @LongLong long addr, int length, boolean serverSideTimestamp);
+
     void clean_meta_for_test(IntegerCallback integerCallback, Pointer runtime, Pointer client);
 
+    void clean_meta_for_test_with_confirmation(IntegerCallback integerCallback, Pointer runtime, Pointer client, String databasePattern, boolean iKnowWhatIAmDoing);
+
     Pointer create_split_desc_array(BooleanCallback booleanCallback, Pointer client, Pointer prepared, Pointer runtime, String tableName, String namespace);
 
     void free_split_desc_array(Pointer json);
 
+    Pointer create_lakesoul_metadata_client_v2(BooleanCallback booleanCallback, Pointer runtime, String config, String defaultNamespace, String defaultDomain);
+
+    void free_lakesoul_metadata_client(Pointer client);
+
     /**
      * caller should ensure that ptr is valid
      *
@@ -85,5 +105,10 @@ public interface LibLakeSoulMetaData {
         void invoke(String status, String err); // function name doesn't matter, it just needs to be the only function and have @Delegate
     }
 
+    interface ScalarCallback { // type representing callback
+        @Delegate
+        void invoke(Integer state, String value, String err); // state is one of NativeUtils.SCALAR_VALUE, SCALAR_NULL and SCALAR_NO_ROWS, or -1 on error
+    }
+
 
 }
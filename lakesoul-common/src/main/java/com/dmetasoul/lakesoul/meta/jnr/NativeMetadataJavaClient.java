@@ -19,6 +19,7 @@ import java.sql.SQLException;
 import java.util.Arrays;
 import java.util.Collections;
 import java.util.List;
+import java.util.Optional;
 import java.util.concurrent.CompletableFuture;
 import java.util.concurrent.ExecutionException;
 import java.util.concurrent.TimeUnit;
@@ -45,6 +46,8 @@ public class NativeMetadataJavaClient implements AutoCloseable {
 
     private Pointer preparedStatement = null;
 
+    private Pointer lakesoulMetadataClient = null;
+
 
     protected final LibLakeSoulMetaData libLakeSoulMetaData;
 
@@ -54,6 +57,8 @@ public class NativeMetadataJavaClient implements AutoCloseable {
 
     protected final ObjectReferenceManager<LibLakeSoulMetaData.IntegerCallback> integerCallbackObjectReferenceManager;
 
+    protected final ObjectReferenceManager<LibLakeSoulMetaData.ScalarCallback> scalarCallbackObjectReferenceManager;
+
     private static NativeMetadataJavaClient instance = null;
 
     private final ReentrantReadWriteLock lock;
@@ -74,6 +79,7 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         booleanCallbackObjectReferenceManager = Runtime.getRuntime(libLakeSoulMetaData).newObjectReferenceManager();
         stringCallbackObjectReferenceManager = Runtime.getRuntime(libLakeSoulMetaData).newObjectReferenceManager();
         integerCallbackObjectReferenceManager = Runtime.getRuntime(libLakeSoulMetaData).newObjectReferenceManager();
+        scalarCallbackObjectReferenceManager = Runtime.getRuntime(libLakeSoulMetaData).newObjectReferenceManager();
 
         fixedBuffer = Runtime.getRuntime(libLakeSoulMetaData).getMemoryManager().allocateDirect(bufferSize);
         mutableBuffer = Runtime.getRuntime(libLakeSoulMetaData).getMemoryManager().allocateDirect(bufferSize);
@@ -94,6 +100,10 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         return tokioPostgresClient;
     }
 
+    public Pointer getLakeSoulMetadataClient() {
+        return lakesoulMetadataClient;
+    }
+
     public ObjectReferenceManager<LibLakeSoulMetaData.BooleanCallback> getbooleanCallbackObjectReferenceManager() {
         return booleanCallbackObjectReferenceManager;
     }
@@ -106,6 +116,10 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         return integerCallbackObjectReferenceManager;
     }
 
+    public ObjectReferenceManager<LibLakeSoulMetaData.ScalarCallback> getScalarCallbackObjectReferenceManager() {
+        return scalarCallbackObjectReferenceManager;
+    }
+
     public Runtime getRuntime() {
         return Runtime.getRuntime(libLakeSoulMetaData);
     }
@@ -187,6 +201,31 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         }
     }
 
+    static class ReferencedScalarCallback implements LibLakeSoulMetaData.ScalarCallback, AutoCloseable {
+        public final BiConsumer<Optional<String>, String> callback;
+        private final Pointer key;
+        private final ObjectReferenceManager<LibLakeSoulMetaData.ScalarCallback> referenceManager;
+
+        public ReferencedScalarCallback(BiConsumer<Optional<String>, String> callback, ObjectReferenceManager<LibLakeSoulMetaData.ScalarCallback> referenceManager) {
+            this.callback = callback;
+            this.referenceManager = referenceManager;
+            key = this.referenceManager.add(this);
+        }
+
+        @Override
+        public void invoke(Integer state, String value, String msg) {
+            callback.accept(state == SCALAR_VALUE ? Optional.of(value) : Optional.empty(), msg);
+            close();
+        }
+
+        @Override
+        public void close() {
+            if (key != null) {
+                referenceManager.remove(key);
+            }
+        }
+    }
+
     private void initialize() {
         libLakeSoulMetaData.rust_logger_init();
         DataBaseProperty dataBaseProperty = NativeMetadataJavaClient.dataBaseProperty;
@@ -224,6 +263,30 @@ public class NativeMetadataJavaClient implements AutoCloseable {
             LOG.error("Configuring postgres with {} timeout", dataBaseProperty);
             throw new RuntimeException(e);
         }
+
+        // the client falls back to the configured namespace and domain instead of "default" and "public"
+        final CompletableFuture<Boolean> clientFuture = new CompletableFuture<>();
+        lakesoulMetadataClient = libLakeSoulMetaData.create_lakesoul_metadata_client_v2(
+                new ReferencedBooleanCallback((bool, msg) -> {
+                    if (msg.isEmpty()) {
+                        clientFuture.complete(bool);
+                    } else {
+                        clientFuture.completeExceptionally(new IOException(msg));
+                    }
+                }, getbooleanCallbackObjectReferenceManager()),
+                tokioRuntime,
+                config,
+                DBUtil.getNamespace(),
+                DBUtil.getDomain()
+        );
+        try {
+            clientFuture.get(timeout, TimeUnit.MILLISECONDS);
+        } catch (InterruptedException | ExecutionException e) {
+            throw new RuntimeException(e);
+        } catch (TimeoutException e) {
+            LOG.error("Creating metadata client with {} timeout", dataBaseProperty);
+            throw new RuntimeException(e);
+        }
     }
 
 
@@ -431,31 +494,32 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         return -1;
     }
 
-    public List<String> executeQueryScalar(Integer queryScalarType, List<String> params) {
+    /**
+     * Value of a scalar query, empty if it returned NULL or no rows. An empty string is a value like any other.
+     */
+    public Optional<String> executeQueryScalarValue(Integer queryScalarType, List<String> params) {
         try {
             getReadLock();
             int retryCounter = NATIVE_METADATA_MAX_RETRY_ATTEMPTS;
             while (retryCounter >= 0) {
                 try {
-                    final CompletableFuture<String> future = new CompletableFuture<>();
+                    final CompletableFuture<Optional<String>> future = new CompletableFuture<>();
 
-                    getLibLakeSoulMetaData().execute_query_scalar(
-                            new ReferencedStringCallback((result, msg) -> {
+                    getLibLakeSoulMetaData().execute_query_scalar_value(
+                            new ReferencedScalarCallback((result, msg) -> {
                                 if (msg.isEmpty()) {
                                     future.complete(result);
                                 } else {
                                     future.completeExceptionally(new SQLException(msg));
                                 }
-                            }, getStringCallbackObjectReferenceManager()),
+                            }, getScalarCallbackObjectReferenceManager()),
                             tokioRuntime,
                             tokioPostgresClient,
                             preparedStatement,
                             queryScalarType,
                             String.join(PARAM_DELIM, params)
                     );
-                    String result = future.get(timeout, TimeUnit.MILLISECONDS);
-                    if (result.isEmpty()) return Collections.emptyList();
-                    return Arrays.stream(result.split(PARAM_DELIM)).collect(Collectors.toList());
+                    return future.get(timeout, TimeUnit.MILLISECONDS);
                 } catch (InterruptedException | ExecutionException e) {
                     if (retryCounter == 0) {
                         throw new RuntimeException(e);
@@ -476,7 +540,18 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         } finally {
             unlockReadLock();
         }
-        return Collections.emptyList();
+        return Optional.empty();
+    }
+
+    /**
+     * @deprecated an empty string cannot be told apart from NULL and no rows, use {@link #executeQueryScalarValue}
+     */
+    @Deprecated
+    public List<String> executeQueryScalar(Integer queryScalarType, List<String> params) {
+        return executeQueryScalarValue(queryScalarType, params)
+                .filter(result -> !result.isEmpty())
+                .map(result -> Arrays.stream(result.split(PARAM_DELIM)).collect(Collectors.toList()))
+                .orElse(Collections.emptyList());
     }
 
     public static Integer insert(NativeUtils.CodedDaoType insertType, JniWrapper jniWrapper) {
@@ -497,6 +572,17 @@ public class NativeMetadataJavaClient implements AutoCloseable {
         return getInstance().executeUpdate(updateType.getCode(), params);
     }
 
+    public static Optional<String> queryScalarValue(NativeUtils.CodedDaoType queryScalarType, List<String> params) {
+        if (params.size() != queryScalarType.getParamsNum()) {
+            throw new RuntimeException("Params Num mismatch for " + queryScalarType.name() + ", params=" + params + " paramsNum=" + params.size());
+        }
+        return getInstance().executeQueryScalarValue(queryScalarType.getCode(), params);
+    }
+
+    /**
+     * @deprecated an empty string cannot be told apart from NULL and no rows, use {@link #queryScalarValue}
+     */
+    @Deprecated
     public static List<String> queryScalar(NativeUtils.CodedDaoType queryScalarType, List<String> params) {
         if (params.size() != queryScalarType.getParamsNum()) {
             throw new RuntimeException("Params Num mismatch for " + queryScalarType.name() + ", params=" + params + " paramsNum=" + params.size());
@@ -534,6 +620,11 @@ public class NativeMetadataJavaClient implements AutoCloseable {
 
     @Override
     public void close() {
+        // the connections of the metadata client are driven by the runtime, free it first
+        if (lakesoulMetadataClient != null) {
+            libLakeSoulMetaData.free_lakesoul_metadata_client(lakesoulMetadataClient);
+            lakesoulMetadataClient = null;
+        }
         if (tokioRuntime != null) {
             libLakeSoulMetaData.free_tokio_runtime(tokioRuntime);
             tokioRuntime = null;
@@ -23,6 +23,11 @@ public class NativeUtils {
 
     public static final String PARTITION_DESC_DELIM = "_DELIM_";
 
+    // states of a scalar query result passed to LibLakeSoulMetaData.ScalarCallback
+    public static final int SCALAR_VALUE = 0;
+    public static final int SCALAR_NULL = 1;
+    public static final int SCALAR_NO_ROWS = 2;
+
     public enum CodedDaoType {
         // ==== Query One ====
         SelectNamespaceByNamespace(DAO_TYPE_QUERY_ONE_OFFSET, 1),
@@ -38,6 +43,8 @@ public class NativeUtils {
 
         SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId(DAO_TYPE_QUERY_ONE_OFFSET + 9, 3),
         SelectOneDataCommitInfoByTableId(DAO_TYPE_QUERY_ONE_OFFSET + 10, 3),
+        SelectTableNameIdByTableId(DAO_TYPE_QUERY_ONE_OFFSET + 11, 1),
+        SelectPartitionStatsByTableIdAndPartitionDesc(DAO_TYPE_QUERY_ONE_OFFSET + 12, 2),
 
         // ==== Query List ====
 
@@ -45,6 +52,7 @@ public class NativeUtils {
         ListTableNameByNamespace(DAO_TYPE_QUERY_LIST_OFFSET + 1, 1),
         ListAllTablePath(DAO_TYPE_QUERY_LIST_OFFSET + 2),
         ListAllPathTablePathByNamespace(DAO_TYPE_QUERY_LIST_OFFSET + 3, 1),
+        ListTableNameIdByTableIds(DAO_TYPE_QUERY_LIST_OFFSET + 11),
 
         // Query Partition List
         ListPartitionByTableId(DAO_TYPE_QUERY_LIST_OFFSET + 4, 1),
@@ -57,6 +65,18 @@ public class NativeUtils {
         // Query DataCommitInfo List
         ListDataCommitInfoByTableIdAndPartitionDescAndCommitList(DAO_TYPE_QUERY_LIST_OFFSET + 10, 3),
 
+        ListPartitionSnapshotByTableIdAndTimestamp(DAO_TYPE_QUERY_LIST_OFFSET + 12, 2),
+        ListOperationLogByTableId(DAO_TYPE_QUERY_LIST_OFFSET + 13, 2),
+        ListPartitionByTableIdAndFilter(DAO_TYPE_QUERY_LIST_OFFSET + 14, 4),
+        ListFileStatsByTableIdAndPartitionDescAndVersion(DAO_TYPE_QUERY_LIST_OFFSET + 15, 3),
+        ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList(DAO_TYPE_QUERY_LIST_OFFSET + 16, 3),
+        ListOperationLogProvenanceByTableId(DAO_TYPE_QUERY_LIST_OFFSET + 17, 2),
+        ListPartitionByTableIdAndTag(DAO_TYPE_QUERY_LIST_OFFSET + 18, 2),
+        ListTableTagByTableId(DAO_TYPE_QUERY_LIST_OFFSET + 19, 1),
+        ListEmptyTableInfoByNamespace(DAO_TYPE_QUERY_LIST_OFFSET + 20, 1),
+        ListPartitionValueByTableIdAndColumn(DAO_TYPE_QUERY_LIST_OFFSET + 21, 3),
+        ListTableInfoByTableNamesAndNamespaces(DAO_TYPE_QUERY_LIST_OFFSET + 22),
+
         // ==== Insert One ====
         InsertNamespace(DAO_TYPE_INSERT_ONE_OFFSET),
         InsertTablePathId(DAO_TYPE_INSERT_ONE_OFFSET + 1),
@@ -68,12 +88,22 @@ public class NativeUtils {
         // ==== Transaction Insert List ====
         TransactionInsertPartitionInfo(DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET),
         TransactionInsertDataCommitInfo(DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 1),
+        TransactionInsertPartitionInfoWithStats(DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 2),
+        TransactionInsertFileStats(DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 3),
+        TransactionInsertCommitProvenance(DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 4),
 
         // ==== Query SCALAR ====
         GetLatestTimestampFromPartitionInfo(DAO_TYPE_QUERY_SCALAR_OFFSET, 2),
         GetLatestTimestampFromPartitionInfoWithoutPartitionDesc(DAO_TYPE_QUERY_SCALAR_OFFSET + 1, 1),
         GetLatestVersionUpToTimeFromPartitionInfo(DAO_TYPE_QUERY_SCALAR_OFFSET + 2, 3),
         GetLatestVersionTimestampUpToTimeFromPartitionInfo(DAO_TYPE_QUERY_SCALAR_OFFSET + 3, 3),
+        GetDbTimestamp(DAO_TYPE_QUERY_SCALAR_OFFSET + 4),
+        CountPartitionByTableIdAndFilter(DAO_TYPE_QUERY_SCALAR_OFFSET + 5, 4),
+        GetTagPartitionCountByTableIdAndTag(DAO_TYPE_QUERY_SCALAR_OFFSET + 6, 2),
+        GetTablePropertyByTableIdAndKey(DAO_TYPE_QUERY_SCALAR_OFFSET + 7, 2),
+        PartitionExistsByTableIdAndPartitionDesc(DAO_TYPE_QUERY_SCALAR_OFFSET + 8, 2),
+        PartitionIsEmptyByTableIdAndPartitionDesc(DAO_TYPE_QUERY_SCALAR_OFFSET + 9, 2),
+        GetTableSchemaByTableIdAndVersion(DAO_TYPE_QUERY_SCALAR_OFFSET + 10, 2),
 
         // ==== Update ====
         // Update Namespace
@@ -100,6 +130,33 @@ public class NativeUtils {
         DeleteDataCommitInfoByTableIdAndPartitionDescAndCommitIdList(DAO_TYPE_UPDATE_OFFSET + 13, 3),
         DeleteDataCommitInfoByTableIdAndPartitionDesc(DAO_TYPE_UPDATE_OFFSET + 14, 2),
         DeleteDataCommitInfoByTableId(DAO_TYPE_UPDATE_OFFSET + 15, 1),
+
+        UpdateTableNamespaceByTableId(DAO_TYPE_UPDATE_OFFSET + 16, 2),
+
+        // Update PartitionStats
+        DeletePartitionStatsByTableId(DAO_TYPE_UPDATE_OFFSET + 17, 1),
+        RebuildPartitionStatsByTableId(DAO_TYPE_UPDATE_OFFSET + 18, 1),
+
+        // Update FileStats
+        DeleteFileStatsByTableId(DAO_TYPE_UPDATE_OFFSET + 19, 1),
+
+        // Update CommitProvenance
+        DeleteCommitProvenanceByTableId(DAO_TYPE_UPDATE_OFFSET + 20, 1),
+
+        // Update TableTag
+        InsertTableTagByTableIdAndTag(DAO_TYPE_UPDATE_OFFSET + 21, 2),
+        DeleteTableTagByTableId(DAO_TYPE_UPDATE_OFFSET + 22, 1),
+        DeleteTableTagByTableIdAndTag(DAO_TYPE_UPDATE_OFFSET + 23, 2),
+
+        // Update TableSchemaHistory
+        DeleteTableSchemaHistoryByTableId(DAO_TYPE_UPDATE_OFFSET + 24, 1),
+
+        // Update PartitionInfo and DataCommitInfo together
+        TruncateTableByTableId(DAO_TYPE_UPDATE_OFFSET + 25, 1),
+        RemoveDanglingSnapshotCommitByTableIdAndPartitionDesc(DAO_TYPE_UPDATE_OFFSET + 27, 2),
+
+        // Update CommitSchemaVersion
+        DeleteCommitSchemaVersionByTableId(DAO_TYPE_UPDATE_OFFSET + 28, 1),
         ;
 
         private final int code;
@@ -7,52 +7,140 @@
 extern crate core;
 
 use core::ffi::c_ptrdiff_t;
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{c_char, c_uchar, CStr, CString};
 use std::io::Write;
-use std::ptr::{NonNull, null, null_mut};
+use std::marker::PhantomData;
+use std::ptr::{null, null_mut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::Duration;
 
 use log::debug;
 use prost::bytes::BufMut;
 use prost::Message;
 
-use lakesoul_metadata::{Builder, Client, MetaDataClient, PreparedStatementMap, Runtime};
 use lakesoul_metadata::error::LakeSoulMetaDataError;
+use lakesoul_metadata::schema_compat::ColumnFallbacks;
 use lakesoul_metadata::transfusion::SplitDesc;
+use lakesoul_metadata::{
+    Builder, Client, MetaDataClient, MetaDataClientBuilder, OpOptions, PreparedStatementMap, Runtime, ScalarValue,
+};
 use proto::proto::entity;
 
-#[repr(C)]
-pub struct CResult<OpaqueT> {
-    ptr: *mut OpaqueT,
-    err: *const c_char,
+/// Opaque handle given out to the host language.
+///
+/// A handle only carries a token into the handle registry and is never dereferenced, so a stale or
+/// double-freed handle is reported through the error callback instead of touching freed memory.
+/// It has the size of a pointer, which keeps the JNR and ctypes bindings unchanged.
+#[repr(transparent)]
+pub struct Handle<OpaqueT> {
+    token: usize,
+    _marker: PhantomData<*const OpaqueT>,
 }
 
-impl<OpaqueT> CResult<OpaqueT> {
-    pub fn new<T>(obj: T) -> Self {
-        CResult {
-            ptr: convert_to_opaque_raw::<T, OpaqueT>(obj),
-            err: std::ptr::null(),
-        }
+impl<OpaqueT> Clone for Handle<OpaqueT> {
+    fn clone(&self) -> Self {
+        *self
     }
+}
 
-    pub fn error(err_msg: &str) -> Self {
-        CResult {
-            ptr: std::ptr::null_mut(),
-            err: CString::new(err_msg).unwrap().into_raw(),
+impl<OpaqueT> Copy for Handle<OpaqueT> {}
+
+impl<OpaqueT> Handle<OpaqueT> {
+    /// The null handle, returned when creation fails. It never maps to a registry entry.
+    fn null() -> Self {
+        Handle {
+            token: 0,
+            _marker: PhantomData,
         }
     }
+}
 
-    pub fn free<T>(&mut self) {
-        unsafe {
-            if !self.ptr.is_null() {
-                // Invoking `std::mem::drop` with a value that implements `Copy` does nothing
-                drop(from_opaque::<OpaqueT, T>(NonNull::new_unchecked(self.ptr)));
-            }
-            if !self.err.is_null() {
-                drop(CString::from_raw(self.err as *mut c_char));
-            }
-        }
+struct RegistryEntry {
+    type_name: &'static str,
+    obj: Arc<dyn Any + Send + Sync>,
+}
+
+/// Live handles by token. Tokens are never reused, so a freed handle can not alias a newer one.
+struct HandleRegistry {
+    next_token: usize,
+    entries: BTreeMap<usize, RegistryEntry>,
+}
+
+static HANDLE_REGISTRY: Mutex<HandleRegistry> = Mutex::new(HandleRegistry {
+    next_token: 1,
+    entries: BTreeMap::new(),
+});
+
+fn lock_registry() -> MutexGuard<'static, HandleRegistry> {
+    // the registry stays consistent even if a holder panicked
+    HANDLE_REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn register_handle<OpaqueT, T: Send + Sync + 'static>(obj: T) -> Handle<OpaqueT> {
+    let mut registry = lock_registry();
+    let token = registry.next_token;
+    registry.next_token += 1;
+    registry.entries.insert(
+        token,
+        RegistryEntry {
+            type_name: std::any::type_name::<T>(),
+            obj: Arc::new(obj),
+        },
+    );
+    Handle {
+        token,
+        _marker: PhantomData,
+    }
+}
+
+fn lookup_handle<OpaqueT, T: Send + Sync + 'static>(handle: Handle<OpaqueT>) -> Result<Arc<T>, LakeSoulMetaDataError> {
+    let obj = lock_registry()
+        .entries
+        .get(&handle.token)
+        .map(|entry| entry.obj.clone())
+        .ok_or_else(|| {
+            LakeSoulMetaDataError::InvalidInput(format!(
+                "stale or invalid {} handle {}",
+                std::any::type_name::<T>(),
+                handle.token
+            ))
+        })?;
+    obj.downcast::<T>().map_err(|_| {
+        LakeSoulMetaDataError::InvalidInput(format!(
+            "handle {} is not a {}",
+            handle.token,
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+/// Remove the registry entry of a handle, the object is dropped once no in-flight call holds it.
+fn unregister_handle<OpaqueT>(handle: Handle<OpaqueT>) {
+    // drop the object outside of the registry lock, dropping a runtime joins its worker threads
+    let entry = lock_registry().entries.remove(&handle.token);
+    if entry.is_none() {
+        debug!("free of stale or invalid handle {} ignored", handle.token);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn error_string(e: LakeSoulMetaDataError) -> *const c_char {
+    CString::new(e.to_string().as_str()).unwrap().into_raw()
+}
+
+/// Number of live handles, each of which is logged at debug level. Used to track leaks of bindings.
+#[no_mangle]
+pub extern "C" fn lakesoul_metadata_c_live_handles() -> i64 {
+    let registry = lock_registry();
+    for (token, entry) in registry.entries.iter() {
+        debug!("live handle {}: {}", token, entry.type_name);
     }
+    registry.entries.len() as i64
 }
 
 pub type ResultCallback = extern "C" fn(bool, *const c_char);
@@ -113,87 +201,137 @@ pub struct BytesResult {
     private: [u8; 0],
 }
 
-fn convert_to_opaque_raw<F, T>(obj: F) -> *mut T {
-    Box::into_raw(Box::new(obj)) as *mut T
+fn string_from_ptr(ptr: *const c_char) -> String {
+    unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() }
 }
 
-fn convert_to_nonnull<T>(obj: T) -> NonNull<T> {
-    unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(obj))) }
-}
+type ClientHandles = (Arc<Runtime>, Arc<RwLock<Client>>, Arc<Mutex<PreparedStatementMap>>);
 
-fn from_opaque<F, T>(obj: NonNull<F>) -> T {
-    unsafe { *Box::from_raw(obj.as_ptr() as *mut T) }
+fn lookup_client_handles(
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+) -> Result<ClientHandles, LakeSoulMetaDataError> {
+    Ok((
+        lookup_handle(runtime)?,
+        lookup_handle(client)?,
+        lookup_handle(prepared)?,
+    ))
 }
 
-fn from_nonnull<T>(obj: NonNull<T>) -> T {
-    unsafe { *Box::from_raw(obj.as_ptr()) }
-}
+/// Insert the entities of the JniWrapper encoded at `addr`, the timestamps of DataCommitInfo taken from the database
+/// clock, see [`execute_insert_with_timestamp_mode`] for the legacy client-clock mode.
+#[no_mangle]
+pub extern "C" fn execute_insert(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    insert_type: i32,
+    addr: c_ptrdiff_t,
+    len: i32,
+) {
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => return callback(-1, error_string(e)),
+    };
 
-fn string_from_ptr(ptr: *const c_char) -> String {
-    unsafe { CStr::from_ptr(ptr).to_str().unwrap().to_string() }
+    let raw_parts = unsafe { std::slice::from_raw_parts(addr as *const u8, len as usize) };
+    let wrapper = entity::JniWrapper::decode(prost::bytes::Bytes::from(raw_parts)).unwrap();
+    let mut client = client.write().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
+    let result = runtime
+        .block_on(async { lakesoul_metadata::execute_insert(&mut client, &mut prepared, insert_type, wrapper).await });
+    match result {
+        Ok(count) => callback(count, CString::new("").unwrap().into_raw()),
+        Err(e) => callback(-1, error_string(e)),
+    }
 }
 
+/// [`execute_insert`] keeping the client-clock timestamps of the inserted commits unless `server_side_timestamp` is
+/// set, for callers relying on the legacy mode. [`execute_insert`] takes them from the database clock.
 #[no_mangle]
-pub extern "C" fn execute_insert(
+pub extern "C" fn execute_insert_with_timestamp_mode(
     callback: extern "C" fn(i32, *const c_char),
-    runtime: NonNull<CResult<TokioRuntime>>,
-    client: NonNull<CResult<TokioPostgresClient>>,
-    prepared: NonNull<CResult<PreparedStatement>>,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
     insert_type: i32,
     addr: c_ptrdiff_t,
     len: i32,
+    server_side_timestamp: bool,
 ) {
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
-    let client = unsafe { NonNull::new_unchecked(client.as_ref().ptr as *mut Client).as_mut() };
-    let prepared = unsafe { NonNull::new_unchecked(prepared.as_ref().ptr as *mut PreparedStatementMap).as_mut() };
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => return callback(-1, error_string(e)),
+    };
 
     let raw_parts = unsafe { std::slice::from_raw_parts(addr as *const u8, len as usize) };
     let wrapper = entity::JniWrapper::decode(prost::bytes::Bytes::from(raw_parts)).unwrap();
-    let result =
-        runtime.block_on(async { lakesoul_metadata::execute_insert(client, prepared, insert_type, wrapper).await });
+    let mut client = client.write().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
+    let result = runtime.block_on(async {
+        lakesoul_metadata::execute_insert_with_timestamp_mode(
+            &mut client,
+            &mut prepared,
+            insert_type,
+            wrapper,
+            server_side_timestamp,
+        )
+        .await
+    });
     match result {
         Ok(count) => callback(count, CString::new("").unwrap().into_raw()),
-        Err(e) => callback(-1, CString::new(e.to_string().as_str()).unwrap().into_raw()),
+        Err(e) => callback(-1, error_string(e)),
     }
 }
 
 #[no_mangle]
 pub extern "C" fn execute_update(
     callback: extern "C" fn(i32, *const c_char),
-    runtime: NonNull<CResult<TokioRuntime>>,
-    client: NonNull<CResult<TokioPostgresClient>>,
-    prepared: NonNull<CResult<PreparedStatement>>,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
     update_type: i32,
     joined_string: *const c_char,
 ) {
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
-    let client = unsafe { NonNull::new_unchecked(client.as_ref().ptr as *mut Client).as_mut() };
-    let prepared = unsafe { NonNull::new_unchecked(prepared.as_ref().ptr as *mut PreparedStatementMap).as_mut() };
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => return callback(-1, error_string(e)),
+    };
 
+    let mut client = client.write().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
     let result = runtime.block_on(async {
-        lakesoul_metadata::execute_update(client, prepared, update_type, string_from_ptr(joined_string)).await
+        lakesoul_metadata::execute_update(&mut client, &mut prepared, update_type, string_from_ptr(joined_string)).await
     });
     match result {
         Ok(count) => callback(count, CString::new("").unwrap().into_raw()),
-        Err(e) => callback(-1, CString::new(e.to_string().as_str()).unwrap().into_raw()),
+        Err(e) => callback(-1, error_string(e)),
     }
 }
 
+/// Calls back with the scalar and an error string. A NULL, an empty string and no rows all reach the callback as an
+/// empty scalar, use [`execute_query_scalar_value`] to tell them apart.
 #[no_mangle]
 pub extern "C" fn execute_query_scalar(
     callback: extern "C" fn(*const c_char, *const c_char),
-    runtime: NonNull<CResult<TokioRuntime>>,
-    client: NonNull<CResult<TokioPostgresClient>>,
-    prepared: NonNull<CResult<PreparedStatement>>,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
     update_type: i32,
     joined_string: *const c_char,
 ) {
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
-    let client = unsafe { NonNull::new_unchecked(client.as_ref().ptr as *mut Client).as_mut() };
-    let prepared = unsafe { NonNull::new_unchecked(prepared.as_ref().ptr as *mut PreparedStatementMap).as_mut() };
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => return callback(CString::new("").unwrap().into_raw(), error_string(e)),
+    };
 
+    let mut client = client.write().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
     let result = runtime.block_on(async {
-        lakesoul_metadata::execute_query_scalar(client, prepared, update_type, string_from_ptr(joined_string)).await
+        lakesoul_metadata::execute_query_scalar(&mut client, &mut prepared, update_type, string_from_ptr(joined_string))
+            .await
     });
     match result {
         Ok(Some(result)) => callback(
@@ -204,51 +342,477 @@ pub extern "C" fn execute_query_scalar(
             CString::new("").unwrap().into_raw(),
             CString::new("").unwrap().into_raw(),
         ),
-        Err(e) => callback(
-            CString::new("").unwrap().into_raw(),
-            CString::new(e.to_string().as_str()).unwrap().into_raw(),
-        ),
+        Err(e) => callback(CString::new("").unwrap().into_raw(), error_string(e)),
     }
 }
 
+/// State passed to the callback of [`execute_query_scalar_value`] when the query returned a value.
+pub const SCALAR_VALUE: i32 = 0;
+/// State passed to the callback of [`execute_query_scalar_value`] when the query returned NULL.
+pub const SCALAR_NULL: i32 = 1;
+/// State passed to the callback of [`execute_query_scalar_value`] when the query returned no rows.
+pub const SCALAR_NO_ROWS: i32 = 2;
+
+/// Calls back with the state of the result, the scalar and an error string. The state is one of [`SCALAR_VALUE`],
+/// [`SCALAR_NULL`] and [`SCALAR_NO_ROWS`], or -1 on error.
+#[no_mangle]
+pub extern "C" fn execute_query_scalar_value(
+    callback: extern "C" fn(i32, *const c_char, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    query_type: i32,
+    joined_string: *const c_char,
+) {
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => return callback(-1, CString::new("").unwrap().into_raw(), error_string(e)),
+    };
+
+    let mut client = client.write().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
+    let result = runtime.block_on(async {
+        lakesoul_metadata::execute_query_scalar_value(
+            &mut client,
+            &mut prepared,
+            query_type,
+            string_from_ptr(joined_string),
+        )
+        .await
+    });
+    let (state, value) = match result {
+        Ok(ScalarValue::Value(value)) => (SCALAR_VALUE, value),
+        Ok(ScalarValue::Null) => (SCALAR_NULL, String::new()),
+        Ok(ScalarValue::NoRows) => (SCALAR_NO_ROWS, String::new()),
+        Err(e) => return callback(-1, CString::new("").unwrap().into_raw(), error_string(e)),
+    };
+    callback(
+        state,
+        CString::new(value.as_str()).unwrap().into_raw(),
+        CString::new("").unwrap().into_raw(),
+    )
+}
+
 #[no_mangle]
 pub extern "C" fn execute_query(
     callback: extern "C" fn(i32, *const c_char),
-    runtime: NonNull<CResult<TokioRuntime>>,
-    client: NonNull<CResult<TokioPostgresClient>>,
-    prepared: NonNull<CResult<PreparedStatement>>,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
     query_type: i32,
     joined_string: *const c_char,
-) -> NonNull<CResult<BytesResult>> {
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
-    let client = unsafe { NonNull::new_unchecked(client.as_ref().ptr as *mut Client).as_ref() };
-    let prepared = unsafe { NonNull::new_unchecked(prepared.as_ref().ptr as *mut PreparedStatementMap).as_mut() };
+) -> Handle<BytesResult> {
+    let format = result_format(client);
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => {
+            callback(-1, error_string(e));
+            return Handle::null();
+        }
+    };
 
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
     let result = runtime.block_on(async {
-        lakesoul_metadata::execute_query(client, prepared, query_type, string_from_ptr(joined_string)).await
+        lakesoul_metadata::execute_query(&client, &mut prepared, query_type, string_from_ptr(joined_string)).await
     });
     match result {
         Ok(u8_vec) => {
+            let u8_vec = format_result(format, query_type, u8_vec);
             let len = u8_vec.len();
             callback(len as i32, CString::new("").unwrap().into_raw());
-            convert_to_nonnull(CResult::<BytesResult>::new::<Vec<u8>>(u8_vec))
+            register_handle(Mutex::new(u8_vec))
         }
         Err(e) => {
-            callback(-1, CString::new(e.to_string().as_str()).unwrap().into_raw());
-            convert_to_nonnull(CResult::<BytesResult>::new::<Vec<u8>>(vec![]))
+            callback(-1, error_string(e));
+            register_handle(Mutex::new(Vec::<u8>::new()))
         }
     }
 }
 
+/// Flag passed to the callback of [`execute_query_compressed`] when the result is zstd-compressed.
+pub const RESULT_COMPRESSED: i32 = 1;
+/// zstd level of compressed results, favoring speed as they are decompressed right away.
+const RESULT_COMPRESSION_LEVEL: i32 = 1;
+
+/// Compress `bytes` if they take at least `min_compress_len` bytes and compression pays off, returning the
+/// bytes to hand out with their flag.
+fn compress_result(bytes: Vec<u8>, min_compress_len: usize) -> std::io::Result<(Vec<u8>, i32)> {
+    if bytes.len() < min_compress_len {
+        return Ok((bytes, 0));
+    }
+    let compressed = zstd::bulk::compress(&bytes, RESULT_COMPRESSION_LEVEL)?;
+    if compressed.len() < bytes.len() {
+        Ok((compressed, RESULT_COMPRESSED))
+    } else {
+        Ok((bytes, 0))
+    }
+}
+
+/// [`execute_query`] compressing results of at least `min_compress_len` bytes with zstd, trading CPU for the size
+/// of the copy to the caller. Calls back with the length of the result, [`RESULT_COMPRESSED`] or 0, and an error
+/// string. A compressed result decompresses to the encoded JniWrapper returned by [`execute_query`].
+#[no_mangle]
+pub extern "C" fn execute_query_compressed(
+    callback: extern "C" fn(i32, i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    query_type: i32,
+    joined_string: *const c_char,
+    min_compress_len: i32,
+) -> Handle<BytesResult> {
+    let format = result_format(client);
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => {
+            callback(-1, 0, error_string(e));
+            return Handle::null();
+        }
+    };
+
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
+    let result = runtime
+        .block_on(async {
+            lakesoul_metadata::execute_query(&client, &mut prepared, query_type, string_from_ptr(joined_string)).await
+        })
+        .and_then(|u8_vec| {
+            compress_result(u8_vec, min_compress_len.max(0) as usize).map_err(LakeSoulMetaDataError::from)
+        })
+        .map(|(u8_vec, flag)| (format_result(format, query_type, u8_vec), flag));
+    match result {
+        Ok((u8_vec, flag)) => {
+            callback(u8_vec.len() as i32, flag, CString::new("").unwrap().into_raw());
+            register_handle(Mutex::new(u8_vec))
+        }
+        Err(e) => {
+            callback(-1, 0, error_string(e));
+            register_handle(Mutex::new(Vec::<u8>::new()))
+        }
+    }
+}
+
+/// Result format of [`set_result_format`]: the encoded message as it is, the default.
+pub const RESULT_FORMAT_RAW: i32 = 1;
+/// Result format of [`set_result_format`]: the encoded message in the envelope of [`frame_result`].
+pub const RESULT_FORMAT_FRAMED: i32 = 2;
+/// Message type in the envelope of the results of [`meta_get_partition_history`], the results of
+/// [`execute_query`] being typed by their query type.
+pub const RESULT_TYPE_PARTITION_HISTORY: i32 = -1;
+/// Message type in the envelope of the results of [`meta_get_table_stats`].
+pub const RESULT_TYPE_TABLE_STATS: i32 = -2;
+/// Message type in the envelope of the results of [`meta_list_table_info_by_domain`].
+pub const RESULT_TYPE_TABLE_INFO_BY_DOMAIN: i32 = -3;
+/// Leads a framed result. No encoded message starts with it, its first byte being an end group tag.
+const RESULT_ENVELOPE_MAGIC: [u8; 4] = *b"LSMR";
+const RESULT_ENVELOPE_HEADER_LEN: usize = 17;
+
+/// Result format of each TokioPostgresClient handle set by [`set_result_format`], raw if absent.
+static RESULT_FORMATS: Mutex<BTreeMap<usize, i32>> = Mutex::new(BTreeMap::new());
+
+fn result_format(client: Handle<TokioPostgresClient>) -> i32 {
+    lock(&RESULT_FORMATS)
+        .get(&client.token)
+        .copied()
+        .unwrap_or(RESULT_FORMAT_RAW)
+}
+
+/// CRC-32 (IEEE) of `bytes`, as computed by `java.util.zip.CRC32`.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Frame `payload` so that the caller can tell a result of another type or a truncated one from the expected
+/// message: the magic `LSMR`, the format version [`RESULT_FORMAT_FRAMED`] as one byte, then `message_type`, the
+/// payload length and its CRC-32 as big-endian 32-bit integers, then the payload.
+fn frame_result(message_type: i32, payload: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(RESULT_ENVELOPE_HEADER_LEN + payload.len());
+    framed.extend_from_slice(&RESULT_ENVELOPE_MAGIC);
+    framed.push(RESULT_FORMAT_FRAMED as u8);
+    framed.extend_from_slice(&message_type.to_be_bytes());
+    framed.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    framed.extend_from_slice(&crc32(payload).to_be_bytes());
+    framed.extend_from_slice(payload);
+    framed
+}
+
+/// The message type and the payload of a result framed by [`frame_result`], failing with InvalidInput if it is
+/// not a whole, intact envelope.
+fn open_result(framed: &[u8]) -> Result<(i32, &[u8]), LakeSoulMetaDataError> {
+    let invalid = |reason: &str| LakeSoulMetaDataError::InvalidInput(format!("invalid result envelope: {}", reason));
+    if framed.len() < RESULT_ENVELOPE_HEADER_LEN || framed[..4] != RESULT_ENVELOPE_MAGIC {
+        return Err(invalid("no envelope header"));
+    }
+    if framed[4] != RESULT_FORMAT_FRAMED as u8 {
+        return Err(invalid(&format!("unknown format version {}", framed[4])));
+    }
+    let int_at = |offset: usize| u32::from_be_bytes(framed[offset..offset + 4].try_into().unwrap());
+    let (message_type, len, crc) = (int_at(5) as i32, int_at(9) as usize, int_at(13));
+    let payload = &framed[RESULT_ENVELOPE_HEADER_LEN..];
+    if payload.len() != len {
+        return Err(invalid(&format!(
+            "payload of {} bytes instead of {}",
+            payload.len(),
+            len
+        )));
+    }
+    if crc32(payload) != crc {
+        return Err(invalid("checksum mismatch"));
+    }
+    Ok((message_type, payload))
+}
+
+/// `bytes` framed as `message_type` if `format` asks for it.
+fn format_result(format: i32, message_type: i32, bytes: Vec<u8>) -> Vec<u8> {
+    match format {
+        RESULT_FORMAT_FRAMED => frame_result(message_type, &bytes),
+        _ => bytes,
+    }
+}
+
+/// Hand out the results of the queries on `client` in `version`, [`RESULT_FORMAT_RAW`] or [`RESULT_FORMAT_FRAMED`]
+/// which has them framed by [`frame_result`] so that a caller expecting another message or reading a truncated
+/// result fails instead of decoding garbage. Compressed results are framed after compression.
+#[no_mangle]
+pub extern "C" fn set_result_format(
+    callback: extern "C" fn(bool, *const c_char),
+    client: Handle<TokioPostgresClient>,
+    version: i32,
+) {
+    if let Err(e) = lookup_handle::<_, RwLock<Client>>(client) {
+        return callback(false, error_string(e));
+    }
+    if version != RESULT_FORMAT_RAW && version != RESULT_FORMAT_FRAMED {
+        return callback(
+            false,
+            error_string(LakeSoulMetaDataError::InvalidInput(format!(
+                "unknown result format {}",
+                version
+            ))),
+        );
+    }
+    lock(&RESULT_FORMATS).insert(client.token, version);
+    callback(true, CString::new("").unwrap().into_raw());
+}
+
+/// Hand out `result`, an encoded JniWrapper, as [`execute_query`] does.
+fn register_bytes_result(
+    callback: extern "C" fn(i32, *const c_char),
+    format: i32,
+    message_type: i32,
+    result: Result<Vec<u8>, LakeSoulMetaDataError>,
+) -> Handle<BytesResult> {
+    match result {
+        Ok(u8_vec) => {
+            let u8_vec = format_result(format, message_type, u8_vec);
+            callback(u8_vec.len() as i32, CString::new("").unwrap().into_raw());
+            register_handle(Mutex::new(u8_vec))
+        }
+        Err(e) => {
+            callback(-1, error_string(e));
+            register_handle(Mutex::new(Vec::<u8>::new()))
+        }
+    }
+}
+
+/// Return the versions of a partition from the latest down, at most `limit` unless it is not positive, as an
+/// encoded JniWrapper of PartitionVersionEntry, see [`lakesoul_metadata::get_partition_history`]. Calls back as
+/// [`execute_query`] does, an empty history being an empty result.
+#[no_mangle]
+pub extern "C" fn meta_get_partition_history(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    table_id: *const c_char,
+    partition_desc: *const c_char,
+    limit: i64,
+) -> Handle<BytesResult> {
+    let format = result_format(client);
+    let (runtime, client, _) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => {
+            callback(-1, error_string(e));
+            return Handle::null();
+        }
+    };
+
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let result = runtime.block_on(async {
+        lakesoul_metadata::get_partition_history(
+            &client,
+            c_char2str(table_id),
+            c_char2str(partition_desc),
+            (limit > 0).then_some(limit),
+        )
+        .await
+    });
+    register_bytes_result(
+        callback,
+        format,
+        RESULT_TYPE_PARTITION_HISTORY,
+        result.map(|partition_version_entry| {
+            entity::JniWrapper {
+                partition_version_entry,
+                ..Default::default()
+            }
+            .encode_to_vec()
+        }),
+    )
+}
+
+/// Return the file statistics of a table as an encoded JniWrapper of one TableStats, see
+/// [`lakesoul_metadata::get_table_stats`]. Calls back as [`execute_query`] does.
+#[no_mangle]
+pub extern "C" fn meta_get_table_stats(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    table_id: *const c_char,
+) -> Handle<BytesResult> {
+    let format = result_format(client);
+    let (runtime, client, _) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => {
+            callback(-1, error_string(e));
+            return Handle::null();
+        }
+    };
+
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let result = runtime.block_on(async { lakesoul_metadata::get_table_stats(&client, c_char2str(table_id)).await });
+    register_bytes_result(
+        callback,
+        format,
+        RESULT_TYPE_TABLE_STATS,
+        result.map(|table_stats| {
+            entity::JniWrapper {
+                table_stats: vec![table_stats],
+                ..Default::default()
+            }
+            .encode_to_vec()
+        }),
+    )
+}
+
+/// Return a page of at most `limit` tables of the domain across namespaces, those after the table `after_table_id`
+/// in the order of their table_id, from the start if it is empty, as an encoded JniWrapper of TableInfo, see
+/// [`lakesoul_metadata::list_table_info_by_domain`]. A page shorter than `limit` is the last one. The tables are
+/// those the metadata records in the domain, whatever access the storage grants. Calls back as [`execute_query`]
+/// does.
+#[no_mangle]
+pub extern "C" fn meta_list_table_info_by_domain(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    domain: *const c_char,
+    after_table_id: *const c_char,
+    limit: i64,
+) -> Handle<BytesResult> {
+    let format = result_format(client);
+    let (runtime, client, _) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => {
+            callback(-1, error_string(e));
+            return Handle::null();
+        }
+    };
+
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let after_table_id = c_char2str(after_table_id);
+    let result = runtime.block_on(async {
+        lakesoul_metadata::list_table_info_by_domain(
+            &client,
+            &ColumnFallbacks::default(),
+            c_char2str(domain),
+            (!after_table_id.is_empty()).then_some(after_table_id),
+            limit,
+        )
+        .await
+    });
+    register_bytes_result(
+        callback,
+        format,
+        RESULT_TYPE_TABLE_INFO_BY_DOMAIN,
+        result.map(|table_info| {
+            entity::JniWrapper {
+                table_info,
+                ..Default::default()
+            }
+            .encode_to_vec()
+        }),
+    )
+}
+
+/// Write `bytes` to the buffer of `cap` bytes at `addr` if they fit, returning whether they were written.
+fn write_into_buffer(bytes: &[u8], addr: c_ptrdiff_t, cap: usize) -> bool {
+    if bytes.len() > cap {
+        return false;
+    }
+    let dst = unsafe { std::slice::from_raw_parts_mut(addr as *mut u8, bytes.len()) };
+    dst.copy_from_slice(bytes);
+    true
+}
+
+/// [`execute_query`] writing the result straight into the caller's buffer of `out_cap` bytes at `out_addr`,
+/// without the result handle and the copy of [`export_bytes_result`]. Calls back with the length of the result
+/// and an error string, the result is written only if its length is at most `out_cap`. Otherwise the buffer is
+/// left as is and the caller retries with a buffer of the length called back. Calls back -1 on failure.
+#[no_mangle]
+pub extern "C" fn execute_query_into(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    query_type: i32,
+    joined_string: *const c_char,
+    out_addr: c_ptrdiff_t,
+    out_cap: i32,
+) {
+    let format = result_format(client);
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => return callback(-1, error_string(e)),
+    };
+
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
+    let result = runtime.block_on(async {
+        lakesoul_metadata::execute_query(&client, &mut prepared, query_type, string_from_ptr(joined_string)).await
+    });
+    match result {
+        Ok(u8_vec) => {
+            let u8_vec = format_result(format, query_type, u8_vec);
+            write_into_buffer(&u8_vec, out_addr, out_cap.max(0) as usize);
+            callback(u8_vec.len() as i32, CString::new("").unwrap().into_raw())
+        }
+        Err(e) => callback(-1, error_string(e)),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn export_bytes_result(
     callback: extern "C" fn(bool, *const c_char),
-    bytes: NonNull<CResult<BytesResult>>,
+    bytes: Handle<BytesResult>,
     len: i32,
     addr: c_ptrdiff_t,
 ) {
     let len = len as usize;
-    let bytes = unsafe { NonNull::new_unchecked(bytes.as_ref().ptr as *mut Vec<c_uchar>).as_mut() };
+    let bytes = match lookup_handle::<_, Mutex<Vec<c_uchar>>>(bytes) {
+        Ok(bytes) => bytes,
+        Err(e) => return callback(false, error_string(e)),
+    };
+    let mut bytes = lock(&bytes);
 
     if bytes.len() != len {
         callback(
@@ -259,6 +823,11 @@ pub extern "C" fn export_bytes_result(
         );
         return;
     }
+    if bytes.starts_with(&RESULT_ENVELOPE_MAGIC) {
+        if let Err(e) = open_result(&bytes) {
+            return callback(false, error_string(e));
+        }
+    }
     bytes.push(0u8);
     bytes.shrink_to_fit();
 
@@ -270,90 +839,288 @@ pub extern "C" fn export_bytes_result(
 }
 
 #[no_mangle]
-pub extern "C" fn free_bytes_result(bytes: NonNull<CResult<BytesResult>>) {
-    from_nonnull(bytes).free::<Vec<u8>>();
+pub extern "C" fn free_bytes_result(bytes: Handle<BytesResult>) {
+    unregister_handle(bytes);
 }
 
+/// Empty the metadata tables, refused unless the database name matches
+/// [`lakesoul_metadata::DEFAULT_CLEAN_META_DATABASE_PATTERN`], see [`clean_meta_for_test_with_confirmation`].
 #[no_mangle]
 pub extern "C" fn clean_meta_for_test(
     callback: extern "C" fn(i32, *const c_char),
-    runtime: NonNull<CResult<TokioRuntime>>,
-    client: NonNull<CResult<TokioPostgresClient>>,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
 ) {
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
-    let client = unsafe { NonNull::new_unchecked(client.as_ref().ptr as *mut Client).as_ref() };
-    let result = runtime.block_on(async { lakesoul_metadata::clean_meta_for_test(client).await });
+    clean_meta(
+        callback,
+        runtime,
+        client,
+        lakesoul_metadata::DEFAULT_CLEAN_META_DATABASE_PATTERN,
+        false,
+    )
+}
+
+/// Empty the metadata tables if the database name matches the glob `database_pattern`, the default one if null, or
+/// if `i_know_what_i_am_doing` is set. The callback gets the number of deleted rows.
+#[no_mangle]
+pub extern "C" fn clean_meta_for_test_with_confirmation(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    database_pattern: *const c_char,
+    i_know_what_i_am_doing: bool,
+) {
+    let database_pattern = if database_pattern.is_null() {
+        lakesoul_metadata::DEFAULT_CLEAN_META_DATABASE_PATTERN
+    } else {
+        c_char2str(database_pattern)
+    };
+    clean_meta(callback, runtime, client, database_pattern, i_know_what_i_am_doing)
+}
+
+fn clean_meta(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<TokioPostgresClient>,
+    database_pattern: &str,
+    i_know_what_i_am_doing: bool,
+) {
+    let handles = lookup_handle::<_, Runtime>(runtime)
+        .and_then(|runtime| Ok((runtime, lookup_handle::<_, RwLock<Client>>(client)?)));
+    let (runtime, client) = match handles {
+        Ok(handles) => handles,
+        Err(e) => return callback(-1, error_string(e)),
+    };
+    let mut client = client.write().unwrap_or_else(PoisonError::into_inner);
+    let result = runtime.block_on(async {
+        lakesoul_metadata::clean_meta_for_test(&mut client, database_pattern, i_know_what_i_am_doing).await
+    });
     match result {
         Ok(count) => callback(count, CString::new("").unwrap().into_raw()),
-        Err(e) => callback(-1, CString::new(e.to_string().as_str()).unwrap().into_raw()),
+        Err(e) => callback(-1, error_string(e)),
     }
 }
 
 #[no_mangle]
-pub extern "C" fn create_tokio_runtime() -> NonNull<CResult<TokioRuntime>> {
+pub extern "C" fn create_tokio_runtime() -> Handle<TokioRuntime> {
     let runtime = Builder::new_multi_thread()
         .enable_all()
         .worker_threads(2)
         .max_blocking_threads(8)
         .build()
         .unwrap();
-    convert_to_nonnull(CResult::<TokioRuntime>::new(runtime))
+    register_handle(runtime)
 }
 
 #[no_mangle]
-pub extern "C" fn free_tokio_runtime(runtime: NonNull<CResult<TokioRuntime>>) {
-    from_nonnull(runtime).free::<Runtime>();
+pub extern "C" fn free_tokio_runtime(runtime: Handle<TokioRuntime>) {
+    unregister_handle(runtime);
 }
 
 #[no_mangle]
 pub extern "C" fn create_tokio_postgres_client(
     callback: extern "C" fn(bool, *const c_char),
     config: *const c_char,
-    runtime: NonNull<CResult<TokioRuntime>>,
-) -> NonNull<CResult<TokioPostgresClient>> {
+    runtime: Handle<TokioRuntime>,
+) -> Handle<TokioPostgresClient> {
     let config = string_from_ptr(config);
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
+    let runtime = match lookup_handle::<_, Runtime>(runtime) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            callback(false, error_string(e));
+            return Handle::null();
+        }
+    };
 
-    let result = runtime.block_on(async { lakesoul_metadata::create_connection(config).await });
+    let result = runtime.block_on(async { lakesoul_metadata::create_connection_on(config, runtime.handle()).await });
 
-    let result = match result {
+    match result {
         Ok(client) => {
             callback(true, CString::new("").unwrap().into_raw());
-            CResult::<TokioPostgresClient>::new(client)
+            register_handle(RwLock::new(client))
         }
         Err(e) => {
-            callback(false, CString::new(e.to_string().as_str()).unwrap().into_raw());
-            CResult::<TokioPostgresClient>::error(format!("{}", e).as_str())
+            callback(false, error_string(e));
+            Handle::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn free_tokio_postgres_client(client: Handle<TokioPostgresClient>) {
+    lock(&RESULT_FORMATS).remove(&client.token);
+    unregister_handle(client);
+}
+
+#[no_mangle]
+pub extern "C" fn create_prepared_statement() -> Handle<PreparedStatement> {
+    register_handle(Mutex::new(PreparedStatementMap::new()))
+}
+
+#[no_mangle]
+pub extern "C" fn free_prepared_statement(prepared: Handle<PreparedStatement>) {
+    unregister_handle(prepared);
+}
+
+/// Create a MetaDataClient from the properties file of `lakesoul_home`.
+/// The connections of the client are driven by `runtime`, which must be freed after the client.
+#[no_mangle]
+pub extern "C" fn create_lakesoul_metadata_client(
+    callback: extern "C" fn(bool, *const c_char),
+    runtime: Handle<TokioRuntime>,
+) -> Handle<MetaDataClient> {
+    let runtime = match lookup_handle::<_, Runtime>(runtime) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            callback(false, error_string(e));
+            return Handle::null();
         }
     };
-    convert_to_nonnull(result)
+
+    let result =
+        MetaDataClientBuilder::from_env().and_then(|builder| builder.build_blocking(Some(runtime.handle().clone())));
+
+    match result {
+        Ok(client) => {
+            callback(true, CString::new("").unwrap().into_raw());
+            register_handle(client)
+        }
+        Err(e) => {
+            callback(false, error_string(e));
+            Handle::null()
+        }
+    }
 }
 
+/// Create a MetaDataClient from a pg config string, reading the properties file of `lakesoul_home` if config is empty.
+/// Empty default_namespace/default_domain keep the configured defaults.
 #[no_mangle]
-pub extern "C" fn free_tokio_postgres_client(client: NonNull<CResult<TokioPostgresClient>>) {
-    from_nonnull(client).free::<Client>();
+pub extern "C" fn create_lakesoul_metadata_client_v2(
+    callback: extern "C" fn(bool, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    config: *const c_char,
+    default_namespace: *const c_char,
+    default_domain: *const c_char,
+) -> Handle<MetaDataClient> {
+    let runtime = match lookup_handle::<_, Runtime>(runtime) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            callback(false, error_string(e));
+            return Handle::null();
+        }
+    };
+    let config = string_from_ptr(config);
+    let default_namespace = string_from_ptr(default_namespace);
+    let default_domain = string_from_ptr(default_domain);
+
+    let result = runtime.block_on(async {
+        let builder = if config.is_empty() {
+            MetaDataClientBuilder::from_env()?
+        } else {
+            MetaDataClientBuilder::new().with_config(config)
+        };
+        builder
+            .with_default_namespace(&default_namespace)
+            .with_default_domain(&default_domain)
+            .with_runtime_handle(runtime.handle().clone())
+            .build()
+            .await
+    });
+
+    match result {
+        Ok(client) => {
+            callback(true, CString::new("").unwrap().into_raw());
+            register_handle(client)
+        }
+        Err(e) => {
+            callback(false, error_string(e));
+            Handle::null()
+        }
+    }
 }
 
 #[no_mangle]
-pub extern "C" fn create_prepared_statement() -> NonNull<CResult<PreparedStatement>> {
-    let prepared = PreparedStatementMap::new();
-    convert_to_nonnull(CResult::<PreparedStatement>::new(prepared))
+pub extern "C" fn free_lakesoul_metadata_client(client: Handle<MetaDataClient>) {
+    unregister_handle(client);
 }
 
+/// Close the client before freeing it, waiting up to `timeout_ms` for the operations in flight, see
+/// [`MetaDataClient::close`]. Calls back with the number of operations cancelled at the timeout, -1 on error.
 #[no_mangle]
-pub extern "C" fn free_prepared_statement(prepared: NonNull<CResult<PreparedStatement>>) {
-    from_nonnull(prepared).free::<PreparedStatementMap>();
+pub extern "C" fn close_metadata_client(
+    callback: extern "C" fn(i32, *const c_char),
+    runtime: Handle<TokioRuntime>,
+    client: Handle<MetaDataClient>,
+    timeout_ms: i64,
+) {
+    let (runtime, client) = match lookup_handle::<_, Runtime>(runtime)
+        .and_then(|runtime| Ok((runtime, lookup_handle::<_, MetaDataClient>(client)?)))
+    {
+        Ok(handles) => handles,
+        Err(e) => {
+            callback(-1, error_string(e));
+            return;
+        }
+    };
+    let timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+    match runtime.block_on(client.close(timeout)) {
+        Ok(report) => callback(report.cancelled as i32, CString::new("").unwrap().into_raw()),
+        Err(e) => callback(-1, error_string(e)),
+    }
 }
 
+/// Return the latest version of each partition of the table as a json array, see
+/// [`MetaDataClient::get_partitions_as_json`]. The string is freed by [`free_c_string`], null on error.
 #[no_mangle]
-pub extern "C" fn create_lakesoul_metadata_client() -> NonNull<CResult<MetaDataClient>> {
-    let client = MetaDataClient::from_env();
-    convert_to_nonnull(CResult::<MetaDataClient>::new(client))
+pub extern "C" fn get_partitions_as_json(
+    callback: ResultCallback,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<MetaDataClient>,
+    table_id: *const c_char,
+) -> *mut c_char {
+    get_partitions_as_json_with_options(callback, runtime, client, table_id, -1, 0, false)
 }
 
+/// [`get_partitions_as_json`] with the options of the call, see [`OpOptions`]. A negative `max_retry` and a
+/// `timeout_ms` of 0 or less keep the defaults of the client, `no_retry` runs each query once.
 #[no_mangle]
-pub extern "C" fn free_lakesoul_metadata_client(client: NonNull<CResult<MetaDataClient>>) {
-    from_nonnull(client).free::<MetaDataClient>();
+pub extern "C" fn get_partitions_as_json_with_options(
+    callback: ResultCallback,
+    runtime: Handle<TokioRuntime>,
+    client: Handle<MetaDataClient>,
+    table_id: *const c_char,
+    max_retry: i32,
+    timeout_ms: i64,
+    no_retry: bool,
+) -> *mut c_char {
+    let (runtime, client) = match lookup_handle::<_, Runtime>(runtime)
+        .and_then(|runtime| Ok((runtime, lookup_handle::<_, MetaDataClient>(client)?)))
+    {
+        Ok(handles) => handles,
+        Err(e) => {
+            call_result_callback(callback, false, error_string(e));
+            return null_mut();
+        }
+    };
+    let table_id = c_char2str(table_id);
+    let options = OpOptions {
+        max_retry: (max_retry >= 0).then_some(max_retry as usize),
+        timeout: (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms as u64)),
+        no_retry,
+    };
+    let result = runtime
+        .block_on(client.get_partitions_as_json_with_options(table_id, options))
+        .and_then(|json| {
+            Ok(CString::new(json)
+                .map_err(|e| LakeSoulMetaDataError::Internal(e.to_string()))?
+                .into_raw())
+        });
+    let (ret, status, e) = match result {
+        Ok(ptr) => (ptr, true, null()),
+        Err(e) => (null_mut(), false, error_string(e)),
+    };
+    call_result_callback(callback, status, e);
+    ret
 }
 
 /// # Safety
@@ -370,19 +1137,26 @@ fn c_char2str<'a>(ptr: *const c_char) -> &'a str {
 #[no_mangle]
 pub extern "C" fn create_split_desc_array(
     callback: ResultCallback,
-    client: NonNull<CResult<TokioPostgresClient>>,
-    prepared: NonNull<CResult<PreparedStatement>>,
-    runtime: NonNull<CResult<TokioRuntime>>,
+    client: Handle<TokioPostgresClient>,
+    prepared: Handle<PreparedStatement>,
+    runtime: Handle<TokioRuntime>,
     table_name: *const c_char,
     namespace: *const c_char,
 ) -> *mut c_char {
-    let runtime = unsafe { NonNull::new_unchecked(runtime.as_ref().ptr as *mut Runtime).as_ref() };
-    let client = unsafe { NonNull::new_unchecked(client.as_ref().ptr as *mut Client).as_ref() };
-    let prepared = unsafe { NonNull::new_unchecked(prepared.as_ref().ptr as *mut PreparedStatementMap).as_mut() };
+    let (runtime, client, prepared) = match lookup_client_handles(runtime, client, prepared) {
+        Ok(handles) => handles,
+        Err(e) => {
+            call_result_callback(callback, false, error_string(e));
+            return null_mut();
+        }
+    };
     let table_name = c_char2str(table_name);
     let namespace = c_char2str(namespace);
+    let client = client.read().unwrap_or_else(PoisonError::into_inner);
+    let mut prepared = lock(&prepared);
     let result: Result<*mut c_char, LakeSoulMetaDataError> = runtime.block_on(async {
-        let ret = lakesoul_metadata::transfusion::split_desc_array(client, prepared, table_name, namespace).await?;
+        let ret =
+            lakesoul_metadata::transfusion::split_desc_array(&client, &mut prepared, table_name, namespace).await?;
         let v = serde_json::to_vec(&ret)?;
         Ok(CString::new(v)
             .map_err(|e| LakeSoulMetaDataError::Internal(e.to_string()))?
@@ -391,8 +1165,7 @@ pub extern "C" fn create_split_desc_array(
 
     let (ret, status, e) = match result {
         Ok(ptr) => (ptr, true, null()),
-        Err(e) =>
-            (null_mut(), false, CString::new(e.to_string()).unwrap().into_raw() as *const c_char),
+        Err(e) => (null_mut(), false, error_string(e)),
     };
     call_result_callback(callback, status, e);
     ret
@@ -414,7 +1187,9 @@ pub extern "C" fn debug(callback: extern "C" fn(bool, *const c_char)) -> *mut c_
             primary_keys: vec![],
             partition_desc: HashMap::new(),
             table_schema: "".to_string(),
-        }; 1];
+        };
+        1
+    ];
     let array = lakesoul_metadata::transfusion::SplitDescArray(x);
     let json_vec = serde_json::to_vec(&array).unwrap();
     let c_string = CString::new(json_vec).unwrap();
@@ -450,3 +1225,191 @@ pub unsafe extern "C" fn free_c_string(c_string: *mut c_char) {
 pub extern "C" fn rust_logger_init() {
     let _ = env_logger::try_init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lakesoul_metadata::DaoType;
+
+    #[test]
+    fn test_compress_result_round_trip() {
+        let bytes = entity::JniWrapper {
+            partition_info: (0..10_000)
+                .map(|version| entity::PartitionInfo {
+                    table_id: "table_compressed".to_string(),
+                    partition_desc: format!("date=2024-01-{:02}", version % 31 + 1),
+                    version,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+        .encode_to_vec();
+
+        let (compressed, flag) = compress_result(bytes.clone(), 1024).unwrap();
+        assert_eq!(flag, RESULT_COMPRESSED);
+        assert!(compressed.len() < bytes.len());
+        assert_eq!(zstd::bulk::decompress(&compressed, bytes.len()).unwrap(), bytes);
+
+        // small results are handed out as they are
+        assert_eq!(compress_result(bytes.clone(), bytes.len() + 1).unwrap(), (bytes, 0));
+    }
+
+    #[test]
+    fn test_write_into_undersized_buffer() {
+        let bytes = entity::JniWrapper {
+            table_info: vec![entity::TableInfo {
+                table_id: "table_into".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .encode_to_vec();
+
+        // the undersized buffer is left as is, the caller retries with the length of the result
+        let mut buffer = vec![0u8; 4];
+        let addr = buffer.as_mut_ptr() as c_ptrdiff_t;
+        assert!(!write_into_buffer(&bytes, addr, buffer.len()));
+        assert_eq!(buffer, [0u8; 4]);
+
+        let mut buffer = vec![0u8; bytes.len()];
+        let addr = buffer.as_mut_ptr() as c_ptrdiff_t;
+        assert!(write_into_buffer(&bytes, addr, buffer.len()));
+        assert_eq!(buffer, bytes);
+    }
+
+    thread_local! {
+        static CALLED_BACK: std::cell::RefCell<Vec<(i32, String)>> = const { std::cell::RefCell::new(vec![]) };
+    }
+
+    extern "C" fn record_result(result: i32, err: *const c_char) {
+        let err = unsafe { CString::from_raw(err as *mut c_char) }.into_string().unwrap();
+        CALLED_BACK.with(|called_back| called_back.borrow_mut().push((result, err)));
+    }
+
+    extern "C" fn expect_success(success: bool, err: *const c_char) {
+        let err = unsafe { CString::from_raw(err as *mut c_char) }.into_string().unwrap();
+        assert!(success, "{}", err);
+    }
+
+    fn last_result() -> (i32, String) {
+        CALLED_BACK.with(|called_back| called_back.borrow_mut().pop().unwrap())
+    }
+
+    #[test]
+    fn test_execute_query_into() {
+        let runtime = create_tokio_runtime();
+        let config =
+            CString::new("host=127.0.0.1 port=5432 dbname=lakesoul_test user=lakesoul_test password=lakesoul_test")
+                .unwrap();
+        let client = create_tokio_postgres_client(expect_success, config.as_ptr(), runtime);
+        let prepared = create_prepared_statement();
+        let namespace = format!(
+            "query_into_{}",
+            std::time::SystemTime::UNIX_EPOCH.elapsed().unwrap().as_nanos()
+        );
+        let inserted = entity::JniWrapper {
+            namespace: vec![entity::Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: "".to_string(),
+                domain: "public".to_string(),
+            }],
+            ..Default::default()
+        }
+        .encode_to_vec();
+        execute_insert(
+            record_result,
+            runtime,
+            client,
+            prepared,
+            DaoType::InsertNamespace as i32,
+            inserted.as_ptr() as c_ptrdiff_t,
+            inserted.len() as i32,
+        );
+        assert_eq!(last_result(), (1, String::new()));
+
+        let joined_string = CString::new(namespace.as_str()).unwrap();
+        let query_into = |buffer: &mut [u8]| {
+            execute_query_into(
+                record_result,
+                runtime,
+                client,
+                prepared,
+                DaoType::SelectNamespaceByNamespace as i32,
+                joined_string.as_ptr(),
+                buffer.as_mut_ptr() as c_ptrdiff_t,
+                buffer.len() as i32,
+            );
+            last_result()
+        };
+        // the buffer too small is left as is, the length of the result called back
+        let mut buffer = vec![0u8; 4];
+        let (len, err) = query_into(&mut buffer);
+        assert!(err.is_empty());
+        assert!(len > 4);
+        assert_eq!(buffer, [0u8; 4]);
+        let mut buffer = vec![0u8; len as usize];
+        assert_eq!(query_into(&mut buffer), (len, String::new()));
+        assert_eq!(
+            entity::JniWrapper::decode(buffer.as_slice()).unwrap().namespace[0].namespace,
+            namespace
+        );
+
+        execute_query_into(
+            record_result,
+            runtime,
+            client,
+            prepared,
+            -1,
+            joined_string.as_ptr(),
+            buffer.as_mut_ptr() as c_ptrdiff_t,
+            buffer.len() as i32,
+        );
+        let (len, err) = last_result();
+        assert_eq!(len, -1);
+        assert!(!err.is_empty());
+
+        execute_update(
+            record_result,
+            runtime,
+            client,
+            prepared,
+            DaoType::DeleteNamespaceByNamespace as i32,
+            joined_string.as_ptr(),
+        );
+        assert_eq!(last_result(), (1, String::new()));
+        free_prepared_statement(prepared);
+        free_tokio_postgres_client(client);
+        free_tokio_runtime(runtime);
+    }
+
+    #[test]
+    fn test_result_envelope() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let payload = entity::JniWrapper {
+            partition_info: vec![entity::PartitionInfo {
+                table_id: "table_framed".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .encode_to_vec();
+
+        let framed = format_result(RESULT_FORMAT_FRAMED, 7, payload.clone());
+        assert_eq!(framed.len(), RESULT_ENVELOPE_HEADER_LEN + payload.len());
+        assert_eq!(open_result(&framed).unwrap(), (7, payload.as_slice()));
+        assert!(!payload.starts_with(&RESULT_ENVELOPE_MAGIC));
+        assert_eq!(format_result(RESULT_FORMAT_RAW, 7, payload.clone()), payload);
+
+        // truncated, corrupted and unframed results are refused
+        let mut corrupted = framed.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        for invalid in [&framed[..framed.len() - 1], corrupted.as_slice(), payload.as_slice()] {
+            assert!(matches!(
+                open_result(invalid),
+                Err(LakeSoulMetaDataError::InvalidInput(_))
+            ));
+        }
+    }
+}
@@ -1,8 +1,8 @@
 #include <cstdint>
 
-template <typename OpaqueT> struct Result {
-  OpaqueT *ptr;
-  const char *err;
+// registry token of a rust object, never dereferenced
+template <typename OpaqueT> struct Handle {
+  uintptr_t token;
 };
 
 struct TokioRuntime {
@@ -11,11 +11,13 @@ struct TokioRuntime {
 
 extern "C" {
 char *create_split_desc_array(const char *, const char *,
-                              Result<TokioRuntime> *runtime);
+                              Handle<TokioRuntime> runtime);
 
 void free_split_desc_array(char *);
 
 char *debug();
 
 void rust_logger_init();
+
+int64_t lakesoul_metadata_c_live_handles();
 }
@@ -0,0 +1,82 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Latency of a backfill committing the first versions of many partitions, by default and with
+//! `CommitHint::NewPartitionsOnly`, median of rounds alternating the two. Needs the metadata database configured by `lakesoul_home`:
+//! `cargo bench -p lakesoul-metadata --bench backfill_commit`.
+
+use std::time::{Duration, Instant};
+
+use lakesoul_metadata::{CommitHint, MetaDataClient};
+use proto::proto::entity::{self, CommitOp, MetaInfo, PartitionInfo, TableInfo};
+
+const PARTITIONS: usize = 10_000;
+const ROUNDS: usize = 5;
+
+/// Commit the first versions of `PARTITIONS` partitions of a new table with `hint`.
+async fn backfill(client: &MetaDataClient, hint: CommitHint) -> Duration {
+    let table_name = format!("bench_backfill_commit_{}", uuid::Uuid::new_v4().simple());
+    let table_info = TableInfo {
+        table_id: format!("table_{}", uuid::Uuid::new_v4()),
+        table_namespace: "default".to_string(),
+        table_path: format!("file:///tmp/lakesoul/metadata_bench/{}", table_name),
+        table_name,
+        table_schema: "{}".to_string(),
+        properties: "{}".to_string(),
+        partitions: "date;".to_string(),
+        domain: "public".to_string(),
+    };
+    client.create_table(table_info.clone()).await.unwrap();
+    let list_partition = (0..PARTITIONS)
+        .map(|i| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            PartitionInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: format!("date={}", i),
+                snapshot: vec![entity::Uuid { high, low }],
+                ..Default::default()
+            }
+        })
+        .collect();
+    let meta_info = MetaInfo {
+        table_info: Some(table_info.clone()),
+        list_partition,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    client
+        .commit_data_with_hint(meta_info, CommitOp::AppendCommit, hint)
+        .await
+        .unwrap();
+    let elapsed = start.elapsed();
+    client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    elapsed
+}
+
+fn main() {
+    tracing_subscriber::fmt().init();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let mut default = Vec::with_capacity(ROUNDS);
+        let mut new_partitions_only = Vec::with_capacity(ROUNDS);
+        for _ in 0..ROUNDS {
+            default.push(backfill(&client, CommitHint::Default).await);
+            new_partitions_only.push(backfill(&client, CommitHint::NewPartitionsOnly).await);
+        }
+        default.sort();
+        new_partitions_only.sort();
+        let (default, new_partitions_only) = (default[ROUNDS / 2], new_partitions_only[ROUNDS / 2]);
+        tracing::info!(
+            "backfill of {} partitions: {:?} by default, {:?} with NewPartitionsOnly, speedup {:.2}x",
+            PARTITIONS,
+            default,
+            new_partitions_only,
+            default.as_secs_f64() / new_partitions_only.as_secs_f64()
+        );
+    });
+}
@@ -0,0 +1,120 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Peak heap allocation of listing the partitions of a large synthetic table, materialized by
+//! `get_all_partition_info` and streamed by `stream_all_partition_info`. Needs the metadata database configured by
+//! `lakesoul_home`: `cargo bench -p lakesoul-metadata --bench listing_memory`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::StreamExt;
+use lakesoul_metadata::MetaDataClient;
+use proto::proto::entity::{self, CommitOp, MetaInfo, PartitionInfo, TableInfo};
+
+const PARTITIONS: usize = 100_000;
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Peak of the heap allocated by `future` on top of what was allocated before it.
+async fn peak_allocation(future: impl Future<Output = ()>) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    future.await;
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+fn main() {
+    tracing_subscriber::fmt().init();
+    // a single thread, so that nothing else allocates while measuring
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("bench_listing_memory_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_path: format!("file:///tmp/lakesoul/metadata_bench/{}", table_name),
+            table_name,
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let list_partition = (0..PARTITIONS)
+            .map(|i| {
+                let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+                PartitionInfo {
+                    table_id: table_info.table_id.clone(),
+                    partition_desc: format!("date={}", i),
+                    snapshot: vec![entity::Uuid { high, low }],
+                    ..Default::default()
+                }
+            })
+            .collect();
+        client
+            .commit_data(
+                MetaInfo {
+                    table_info: Some(table_info.clone()),
+                    list_partition,
+                    ..Default::default()
+                },
+                CommitOp::AppendCommit,
+            )
+            .await
+            .unwrap();
+
+        let table_id = table_info.table_id.as_str();
+        let materialized = peak_allocation(async {
+            let partitions = client.get_all_partition_info(table_id).await.unwrap();
+            assert_eq!(partitions.len(), PARTITIONS);
+        })
+        .await;
+        let streamed = peak_allocation(async {
+            let mut stream = client.stream_all_partition_info(table_id).await.unwrap();
+            let mut count = 0;
+            while let Some(partition_info) = stream.next().await {
+                partition_info.unwrap();
+                count += 1;
+            }
+            assert_eq!(count, PARTITIONS);
+        })
+        .await;
+        tracing::info!(
+            "peak allocation listing {} partitions: {} KiB materialized, {} KiB streamed",
+            PARTITIONS,
+            materialized / 1024,
+            streamed / 1024
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    });
+}
@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Latency of resolving the tables of a 50-table join, one by one with `get_table_info_by_table_name`, at once with
+//! `get_table_infos`, and from the table cache. Needs the metadata database configured by `lakesoul_home`:
+//! `cargo bench -p lakesoul-metadata --bench table_infos`.
+
+use std::time::{Duration, Instant};
+
+use lakesoul_metadata::{MetaDataClient, MetaDataClientBuilder};
+use proto::proto::entity::TableInfo;
+
+const TABLES: usize = 50;
+const ROUNDS: u32 = 20;
+
+fn main() {
+    tracing_subscriber::fmt().init();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_table_cache(Duration::from_secs(600))
+            .build()
+            .await
+            .unwrap();
+        let uncached = MetaDataClient::from_env().await.unwrap();
+        let mut tables = Vec::with_capacity(TABLES);
+        for _ in 0..TABLES {
+            let table_name = format!("bench_table_infos_{}", uuid::Uuid::new_v4().simple());
+            let table_info = TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: "default".to_string(),
+                table_path: format!("file:///tmp/lakesoul/metadata_bench/{}", table_name),
+                table_name,
+                table_schema: "{}".to_string(),
+                properties: "{}".to_string(),
+                partitions: ";".to_string(),
+                domain: "public".to_string(),
+            };
+            client.create_table(table_info.clone()).await.unwrap();
+            tables.push(table_info);
+        }
+        let names = tables
+            .iter()
+            .map(|table_info| (table_info.table_name.clone(), table_info.table_namespace.clone()))
+            .collect::<Vec<_>>();
+
+        let mut sequential = Duration::ZERO;
+        let mut batched = Duration::ZERO;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            for (table_name, namespace) in &names {
+                uncached
+                    .get_table_info_by_table_name(table_name, namespace)
+                    .await
+                    .unwrap();
+            }
+            sequential += start.elapsed();
+            let start = Instant::now();
+            assert_eq!(uncached.get_table_infos(&names).await.unwrap().len(), TABLES);
+            batched += start.elapsed();
+        }
+        // fills the cache
+        client.get_table_infos(&names).await.unwrap();
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            assert_eq!(client.get_table_infos(&names).await.unwrap().len(), TABLES);
+        }
+        let cached = start.elapsed();
+        tracing::info!(
+            "resolving {} tables took {:?} one by one, {:?} batched, {:?} from the cache, mean of {} rounds",
+            TABLES,
+            sequential / ROUNDS,
+            batched / ROUNDS,
+            cached / ROUNDS,
+            ROUNDS
+        );
+
+        for table_info in &tables {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+    });
+}
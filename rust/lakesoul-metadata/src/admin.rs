@@ -0,0 +1,170 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ad-hoc read-only queries for operators, only built with the `admin` feature and not exported over FFI.
+
+use std::collections::HashMap;
+
+use postgres_types::{ToSql, Type};
+
+use crate::error::{LakeSoulMetaDataError, Result};
+use crate::{DaoType, MetaDataClient};
+
+/// Max number of rows returned by [`MetaDataClient::execute_readonly_sql`].
+pub const ADMIN_ROW_LIMIT: usize = 1000;
+/// Statement timeout of [`MetaDataClient::execute_readonly_sql`] in milliseconds.
+pub const ADMIN_STATEMENT_TIMEOUT_MS: u64 = 30_000;
+
+/// Cheap keyword check, the read-only transaction is the real guarantee.
+fn check_single_select(sql: &str) -> Result<&str> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let is_select = sql
+        .split_whitespace()
+        .next()
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("select"));
+    if !is_select || sql.contains(';') {
+        return Err(LakeSoulMetaDataError::InvalidInput(
+            "only a single SELECT statement is allowed".to_string(),
+        ));
+    }
+    Ok(sql)
+}
+
+impl MetaDataClient {
+    /// Run a single SELECT in a read-only transaction with a statement timeout.
+    /// Params are bound as TEXT (`$1`, `$2`, ...), every value of the result is rendered as string, NULL as "null".
+    /// Fails if the result has more than [`ADMIN_ROW_LIMIT`] rows.
+    pub async fn execute_readonly_sql(&self, sql: &str, params: &[&str]) -> Result<Vec<HashMap<String, String>>> {
+        let sql = check_single_select(sql)?;
+        let wrapped = format!(
+            "select to_jsonb(t)::text from ({}) t limit {}",
+            sql,
+            ADMIN_ROW_LIMIT + 1
+        );
+
+        let mut client = self.client.lock().await;
+        let transaction = client.build_transaction().read_only(true).start().await?;
+        transaction
+            .batch_execute(&format!("set local statement_timeout = {}", ADMIN_STATEMENT_TIMEOUT_MS))
+            .await?;
+        let statement = transaction
+            .prepare_typed(&wrapped, &vec![Type::TEXT; params.len()])
+            .await?;
+        let params = params
+            .iter()
+            .map(|param| param as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+        let rows = transaction.query(&statement, &params).await?;
+        transaction.rollback().await?;
+
+        if rows.len() > ADMIN_ROW_LIMIT {
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "result exceeds the row limit of {}",
+                ADMIN_ROW_LIMIT
+            )));
+        }
+        rows.iter()
+            .map(|row| {
+                let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(row.get(0))?;
+                Ok(object
+                    .into_iter()
+                    .map(|(column, value)| match value {
+                        serde_json::Value::String(value) => (column, value),
+                        value => (column, value.to_string()),
+                    })
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Run a read-only DAO under EXPLAIN (ANALYZE, BUFFERS) in a read-only transaction with a statement timeout,
+    /// returning the plan. Params are bound as TEXT and cast by the statement as listed by
+    /// [`MetaDataClient::explain_dao`], None binding NULL.
+    pub async fn explain_analyze(&self, dao: DaoType, params: &[Option<&str>]) -> Result<String> {
+        let description = self.explain_dao(dao).await?;
+        let sql = match description.sql {
+            Some(sql) if description.kind.is_read_only() => sql,
+            _ => {
+                return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                    "{:?} is not a prepared read-only DAO",
+                    dao
+                )))
+            }
+        };
+        if params.len() != description.params.len() {
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "{:?} takes {} params, got {}",
+                dao,
+                description.params.len(),
+                params.len()
+            )));
+        }
+
+        let mut client = self.client.lock().await;
+        let transaction = client.build_transaction().read_only(true).start().await?;
+        transaction
+            .batch_execute(&format!("set local statement_timeout = {}", ADMIN_STATEMENT_TIMEOUT_MS))
+            .await?;
+        let statement = transaction
+            .prepare_typed(
+                &format!("explain (analyze, buffers) {}", sql),
+                &vec![Type::TEXT; params.len()],
+            )
+            .await?;
+        let params = params
+            .iter()
+            .map(|param| param as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+        let rows = transaction.query(&statement, &params).await?;
+        transaction.rollback().await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.get::<_, String>(0))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_single_select;
+    use crate::error::LakeSoulMetaDataError;
+    use crate::{DaoType, MetaDataClient};
+
+    #[test]
+    fn test_check_single_select() {
+        assert_eq!(
+            check_single_select("  SELECT count(*) FROM partition_info; ").unwrap(),
+            "SELECT count(*) FROM partition_info"
+        );
+        assert!(check_single_select("delete from partition_info").is_err());
+        assert!(check_single_select("select 1; delete from partition_info").is_err());
+        assert!(check_single_select("").is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_explain_analyze() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let plan = client
+            .explain_analyze(
+                DaoType::ListPartitionByTableIdAndFilter,
+                &[Some("table_explain"), None, Some("0"), None],
+            )
+            .await
+            .unwrap();
+        assert!(plan.contains("actual time"));
+        assert!(matches!(
+            client
+                .explain_analyze(DaoType::DeleteNamespaceByNamespace, &[Some("explain")])
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            client
+                .explain_analyze(DaoType::ListNamespaces, &[Some("explain")])
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+    }
+}
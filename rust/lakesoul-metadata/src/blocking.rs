@@ -0,0 +1,151 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Synchronous facade over [`MetaDataClient`] for tooling without a runtime of its own, only built with the
+//! `blocking` feature.
+
+use std::future::Future;
+
+use proto::proto::entity::{Namespace, PartitionInfo, TableInfo};
+use tokio::runtime::{self, Runtime};
+
+use crate::error::Result;
+use crate::metadata_client::{MetaDataClient, MetaDataClientBuilder};
+
+/// [`MetaDataClient`] whose operations block the calling thread, run on a current-thread runtime owned by the
+/// client. The connections are only driven while an operation runs.
+///
+/// The client is `Send` and `Sync`, so operations may be called from several threads. They must not be
+/// called from within an async context, where blocking on the runtime panics, use [`MetaDataClient`] there.
+/// Dropping the client is fine anywhere, the runtime is shut down without waiting for its tasks.
+pub struct BlockingMetaDataClient {
+    // dropped before the runtime, see the Drop impl
+    client: Option<MetaDataClient>,
+    runtime: Option<Runtime>,
+}
+
+impl BlockingMetaDataClient {
+    pub fn new(builder: MetaDataClientBuilder) -> Result<Self> {
+        let runtime = runtime::Builder::new_current_thread().enable_all().build()?;
+        let client = runtime.block_on(builder.build())?;
+        Ok(Self {
+            client: Some(client),
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Client configured by the properties file pointed by `lakesoul_home`, see [`MetaDataClientBuilder::from_env`].
+    pub fn from_env() -> Result<Self> {
+        Self::new(MetaDataClientBuilder::from_env()?)
+    }
+
+    pub fn from_config(config: String) -> Result<Self> {
+        Self::new(MetaDataClientBuilder::new().with_config(config))
+    }
+
+    /// The async client, whose operations must be awaited through [`Self::block_on`].
+    pub fn client(&self) -> &MetaDataClient {
+        self.client.as_ref().expect("client taken on drop only")
+    }
+
+    /// Run an operation of the async client not mirrored here, e.g. `client.block_on(client.client().close())`.
+    pub fn block_on<F: Future>(&self, f: F) -> F::Output {
+        self.runtime.as_ref().expect("runtime taken on drop only").block_on(f)
+    }
+
+    pub fn get_all_namespace(&self) -> Result<Vec<Namespace>> {
+        self.block_on(self.client().get_all_namespace())
+    }
+
+    pub fn get_all_table_info_by_namespace(&self, namespace: &str) -> Result<Vec<TableInfo>> {
+        self.block_on(self.client().get_all_table_info_by_namespace(namespace))
+    }
+
+    pub fn get_all_partition_info(&self, table_id: &str) -> Result<Vec<PartitionInfo>> {
+        self.block_on(self.client().get_all_partition_info(table_id))
+    }
+
+    pub fn get_data_files_by_table_name(&self, table_name: &str, namespace: &str) -> Result<Vec<String>> {
+        self.block_on(self.client().get_data_files_by_table_name(table_name, namespace))
+    }
+
+    pub fn create_namespace(&self, namespace: Namespace) -> Result<()> {
+        self.block_on(self.client().create_namespace(namespace))
+    }
+
+    pub fn create_table(&self, table_info: TableInfo) -> Result<()> {
+        self.block_on(self.client().create_table(table_info))
+    }
+}
+
+impl Drop for BlockingMetaDataClient {
+    fn drop(&mut self) {
+        // dropping a runtime blocks on its tasks, which panics within another runtime
+        drop(self.client.take());
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    fn test_blocking_client() {
+        let client = BlockingMetaDataClient::from_env().unwrap();
+        let namespace = format!("blocking_{}", uuid::Uuid::new_v4().simple());
+        client
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: String::new(),
+                domain: String::new(),
+            })
+            .unwrap();
+        assert!(client
+            .get_all_namespace()
+            .unwrap()
+            .iter()
+            .any(|existing| existing.namespace == namespace));
+
+        let table_name = format!("blocking_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: namespace.clone(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/blocking_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: ";".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).unwrap();
+        let table_infos = client.get_all_table_info_by_namespace(&namespace).unwrap();
+        assert_eq!(table_infos.len(), 1);
+        assert_eq!(table_infos[0].table_id, table_info.table_id);
+        assert!(client.get_all_partition_info(&table_info.table_id).unwrap().is_empty());
+        assert!(client
+            .get_data_files_by_table_name(&table_name, &namespace)
+            .unwrap()
+            .is_empty());
+
+        client
+            .block_on(client.client().delete_table_by_table_info_cascade(&table_info))
+            .unwrap();
+        client
+            .block_on(client.client().delete_namespace_by_namespace(&namespace))
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_drop_within_runtime() {
+        let client = tokio::task::spawn_blocking(BlockingMetaDataClient::from_env)
+            .await
+            .unwrap()
+            .unwrap();
+        drop(client);
+    }
+}
@@ -0,0 +1,346 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Format of the catalog exports of [`crate::MetaDataClient::export_catalog`]: a magic header followed by records of
+//! a kind byte, the length of the body as u32 big endian and the protobuf encoded body. The namespaces come first,
+//! then for each table its TableInfo, and for each of its partitions the data commits referred to by its versions
+//! followed by the versions in ascending order. A checkpoint record closes the namespaces and every table, an end
+//! record closes the export, so that an export cut short can be resumed after its last checkpoint.
+
+use std::io::{ErrorKind, Read, Write};
+
+use prost::Message;
+use proto::proto::entity::{DataCommitInfo, Namespace, PartitionInfo, TableInfo};
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+const CATALOG_EXPORT_MAGIC: &[u8; 8] = b"LSCATLG1";
+
+const NAMESPACE_RECORD: u8 = 1;
+const TABLE_RECORD: u8 = 2;
+const DATA_COMMIT_RECORD: u8 = 3;
+const PARTITION_RECORD: u8 = 4;
+const CHECKPOINT_RECORD: u8 = 5;
+const END_RECORD: u8 = 6;
+
+/// How [`crate::MetaDataClient::import_catalog`] treats a table whose id, path or name in its namespace is taken
+/// already. Existing namespaces are kept either way, `Replace` overwrites their properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Fail with AlreadyExists, leaving the tables imported so far in place.
+    Fail,
+    /// Keep the existing table and skip the records of the imported one.
+    Skip,
+    /// Delete the existing tables with their partitions and data commits before importing.
+    Replace,
+}
+
+/// Progress of a catalog export, returned by [`crate::MetaDataClient::export_catalog`] or recovered from an export
+/// cut short by [`ExportManifest::recover`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportManifest {
+    /// Exported namespaces, whose tables make up the export.
+    pub namespaces: Vec<String>,
+    /// Ids of the tables exported completely, in export order.
+    pub exported_tables: Vec<String>,
+    /// Length of the export up to its last checkpoint, where a resumed export continues.
+    pub resume_offset: u64,
+    /// Number of records up to the last checkpoint.
+    pub records: u64,
+    /// True if the export has been closed by its end record.
+    pub complete: bool,
+}
+
+impl ExportManifest {
+    /// Manifest of a possibly truncated export, as of its last checkpoint. Truncate the export to
+    /// [`Self::resume_offset`] before resuming it with [`crate::MetaDataClient::resume_export_catalog`].
+    /// Fails with InvalidInput if the export does not even hold its namespaces.
+    pub fn recover(source: impl Read) -> Result<Self> {
+        let mut reader = CatalogReader::new(source)?;
+        let mut manifest = ExportManifest::default();
+        let mut namespaces = Vec::new();
+        let mut checkpointed = false;
+        loop {
+            let record = match reader.next() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(LakeSoulMetaDataError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(LakeSoulMetaDataError::ProstDecodeError(_)) => break,
+                Err(err) => return Err(err),
+            };
+            match record {
+                CatalogRecord::Namespace(namespace) if !checkpointed => namespaces.push(namespace.namespace),
+                CatalogRecord::Checkpoint(table_id) => {
+                    if checkpointed {
+                        manifest.exported_tables.push(table_id);
+                    } else {
+                        manifest.namespaces = std::mem::take(&mut namespaces);
+                        checkpointed = true;
+                    }
+                    manifest.resume_offset = reader.offset();
+                    manifest.records = reader.records();
+                }
+                CatalogRecord::End => {
+                    manifest.complete = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if !checkpointed {
+            return Err(LakeSoulMetaDataError::InvalidInput(
+                "catalog export has been cut short before its first checkpoint".to_string(),
+            ));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Counts of [`crate::MetaDataClient::import_catalog`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Namespaces created or, with [`ConflictPolicy::Replace`], updated.
+    pub namespaces: usize,
+    pub tables: usize,
+    /// Names of the tables left out under [`ConflictPolicy::Skip`].
+    pub skipped_tables: Vec<String>,
+    pub data_commits: usize,
+    pub partition_versions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CatalogRecord {
+    Namespace(Namespace),
+    Table(TableInfo),
+    DataCommit(DataCommitInfo),
+    Partition(PartitionInfo),
+    /// Id of the table just exported, empty after the namespaces.
+    Checkpoint(String),
+    End,
+}
+
+pub(crate) struct CatalogWriter<W: Write> {
+    sink: W,
+    offset: u64,
+    records: u64,
+}
+
+impl<W: Write> CatalogWriter<W> {
+    /// Start a new export with its header.
+    pub(crate) fn new(mut sink: W) -> Result<Self> {
+        sink.write_all(CATALOG_EXPORT_MAGIC)?;
+        Ok(Self {
+            sink,
+            offset: CATALOG_EXPORT_MAGIC.len() as u64,
+            records: 0,
+        })
+    }
+
+    /// Continue an export truncated to the resume offset of its manifest.
+    pub(crate) fn resume(sink: W, manifest: &ExportManifest) -> Self {
+        Self {
+            sink,
+            offset: manifest.resume_offset,
+            records: manifest.records,
+        }
+    }
+
+    pub(crate) fn write(&mut self, record: &CatalogRecord) -> Result<()> {
+        let (kind, body) = match record {
+            CatalogRecord::Namespace(namespace) => (NAMESPACE_RECORD, namespace.encode_to_vec()),
+            CatalogRecord::Table(table_info) => (TABLE_RECORD, table_info.encode_to_vec()),
+            CatalogRecord::DataCommit(data_commit_info) => (DATA_COMMIT_RECORD, data_commit_info.encode_to_vec()),
+            CatalogRecord::Partition(partition_info) => (PARTITION_RECORD, partition_info.encode_to_vec()),
+            CatalogRecord::Checkpoint(table_id) => (CHECKPOINT_RECORD, table_id.as_bytes().to_vec()),
+            CatalogRecord::End => (END_RECORD, vec![]),
+        };
+        let len = u32::try_from(body.len()).map_err(|_| {
+            LakeSoulMetaDataError::LimitExceeded(format!("catalog record of {} bytes is too large", body.len()))
+        })?;
+        self.sink.write_all(&[kind])?;
+        self.sink.write_all(&len.to_be_bytes())?;
+        self.sink.write_all(&body)?;
+        self.offset += 5 + body.len() as u64;
+        self.records += 1;
+        if matches!(record, CatalogRecord::Checkpoint(_) | CatalogRecord::End) {
+            self.sink.flush()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub(crate) fn records(&self) -> u64 {
+        self.records
+    }
+}
+
+pub(crate) struct CatalogReader<R: Read> {
+    source: R,
+    offset: u64,
+    records: u64,
+}
+
+impl<R: Read> CatalogReader<R> {
+    /// Fails with InvalidInput if the source does not start with the export header.
+    pub(crate) fn new(mut source: R) -> Result<Self> {
+        let mut magic = [0u8; CATALOG_EXPORT_MAGIC.len()];
+        match source.read_exact(&mut magic) {
+            Ok(()) if &magic == CATALOG_EXPORT_MAGIC => {}
+            Ok(()) => {
+                return Err(LakeSoulMetaDataError::InvalidInput(
+                    "not a LakeSoul catalog export".to_string(),
+                ))
+            }
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                return Err(LakeSoulMetaDataError::InvalidInput(
+                    "not a LakeSoul catalog export".to_string(),
+                ))
+            }
+            Err(err) => return Err(err.into()),
+        }
+        Ok(Self {
+            source,
+            offset: CATALOG_EXPORT_MAGIC.len() as u64,
+            records: 0,
+        })
+    }
+
+    /// Next record, None at the end of the source. A record cut short fails with an UnexpectedEof IoError.
+    pub(crate) fn next(&mut self) -> Result<Option<CatalogRecord>> {
+        let mut kind = [0u8; 1];
+        loop {
+            match self.source.read(&mut kind) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let mut len = [0u8; 4];
+        self.source.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        let mut body = Vec::new();
+        (&mut self.source).take(len as u64).read_to_end(&mut body)?;
+        if body.len() != len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let record = match kind[0] {
+            NAMESPACE_RECORD => CatalogRecord::Namespace(Namespace::decode(body.as_slice())?),
+            TABLE_RECORD => CatalogRecord::Table(TableInfo::decode(body.as_slice())?),
+            DATA_COMMIT_RECORD => CatalogRecord::DataCommit(DataCommitInfo::decode(body.as_slice())?),
+            PARTITION_RECORD => CatalogRecord::Partition(PartitionInfo::decode(body.as_slice())?),
+            CHECKPOINT_RECORD => CatalogRecord::Checkpoint(String::from_utf8(body).map_err(|_| {
+                LakeSoulMetaDataError::InvalidInput("checkpoint record holds an invalid table id".to_string())
+            })?),
+            END_RECORD => CatalogRecord::End,
+            kind => {
+                return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                    "unknown catalog record kind {}",
+                    kind
+                )))
+            }
+        };
+        self.offset += 5 + len as u64;
+        self.records += 1;
+        Ok(Some(record))
+    }
+
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub(crate) fn records(&self) -> u64 {
+        self.records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_export() -> Vec<u8> {
+        let mut writer = CatalogWriter::new(Vec::new()).unwrap();
+        writer
+            .write(&CatalogRecord::Namespace(Namespace {
+                namespace: "ns".to_string(),
+                ..Default::default()
+            }))
+            .unwrap();
+        writer.write(&CatalogRecord::Checkpoint(String::new())).unwrap();
+        for table_id in ["table_a", "table_b"] {
+            writer
+                .write(&CatalogRecord::Table(TableInfo {
+                    table_id: table_id.to_string(),
+                    table_namespace: "ns".to_string(),
+                    ..Default::default()
+                }))
+                .unwrap();
+            writer
+                .write(&CatalogRecord::Partition(PartitionInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    version: 1,
+                    ..Default::default()
+                }))
+                .unwrap();
+            writer.write(&CatalogRecord::Checkpoint(table_id.to_string())).unwrap();
+        }
+        writer.write(&CatalogRecord::End).unwrap();
+        assert_eq!(writer.records(), 9);
+        let offset = writer.offset();
+        let export = writer.sink;
+        assert_eq!(offset, export.len() as u64);
+        export
+    }
+
+    #[test]
+    fn test_catalog_records_round_trip() {
+        let export = sample_export();
+        let mut reader = CatalogReader::new(export.as_slice()).unwrap();
+        let mut records = vec![];
+        while let Some(record) = reader.next().unwrap() {
+            records.push(record);
+        }
+        assert_eq!(records.len(), 9);
+        assert!(matches!(&records[0], CatalogRecord::Namespace(namespace) if namespace.namespace == "ns"));
+        assert!(matches!(&records[4], CatalogRecord::Checkpoint(table_id) if table_id == "table_a"));
+        assert_eq!(records[8], CatalogRecord::End);
+
+        assert!(matches!(
+            CatalogReader::new(&b"not an export"[..]),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_recover_export_manifest() {
+        let export = sample_export();
+        let manifest = ExportManifest::recover(export.as_slice()).unwrap();
+        assert!(manifest.complete);
+        assert_eq!(manifest.namespaces, ["ns"]);
+        assert_eq!(manifest.exported_tables, ["table_a", "table_b"]);
+
+        // cut short within the records of table_b
+        let truncated = &export[..export.len() - 12];
+        let manifest = ExportManifest::recover(truncated).unwrap();
+        assert!(!manifest.complete);
+        assert_eq!(manifest.exported_tables, ["table_a"]);
+        assert_eq!(manifest.records, 5);
+        let mut resumed = truncated[..manifest.resume_offset as usize].to_vec();
+        let mut writer = CatalogWriter::resume(&mut resumed, &manifest);
+        writer.write(&CatalogRecord::End).unwrap();
+        let offset = writer.offset();
+        assert_eq!(offset, resumed.len() as u64);
+        let manifest = ExportManifest::recover(resumed.as_slice()).unwrap();
+        assert_eq!(manifest.exported_tables, ["table_a"]);
+
+        assert!(matches!(
+            ExportManifest::recover(&export[..20]),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+    }
+}
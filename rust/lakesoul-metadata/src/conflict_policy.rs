@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! How [`crate::MetaDataClient::commit_data`] resolves a version conflict, i.e. another commit, such as a
+//! compaction racing with an append, writing the version of a partition the commit was about to write.
+//!
+//! The policy is the one named by the `commitConflictPolicy` table property if set, otherwise the one of the client,
+//! see [`crate::MetaDataClientBuilder::with_conflict_policy`]. Without either a conflicting commit is rolled back
+//! without error, as it always has been. For each conflicting partition the policy decides to
+//! - [`ConflictDecision::Merge`]: append the commits the incoming commit adds to the version committed meanwhile,
+//!   as [`crate::Snapshot::merge_append`] does, skipping those the version holds already, and write the result as
+//!   the next version. Only the commits of the incoming commit are appended, not the snapshot it was based on, so
+//!   commits replaced by a compaction meanwhile are not brought back.
+//! - [`ConflictDecision::RetryRead`]: compute all versions of the commit again from the current ones, as if it had
+//!   been issued now.
+//! - [`ConflictDecision::Fail`]: fail the commit with ConcurrentCommit.
+//!
+//! Conflicts are resolved up to `max_retry` times, after which the commit fails with ConcurrentCommit.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use proto::proto::entity::{CommitOp, PartitionInfo};
+
+/// Decision of a [`CommitConflictPolicy`] on a conflicting partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictDecision {
+    Merge,
+    RetryRead,
+    Fail,
+}
+
+/// Resolution of the version conflicts of commits, see the [module documentation](self).
+pub trait CommitConflictPolicy: Debug + Send + Sync {
+    /// Name the policy is selected by in the `commitConflictPolicy` table property.
+    fn name(&self) -> &str;
+
+    /// Decide on the conflict of `incoming`, the version `commit_op` was about to write, with `current`, the latest
+    /// version committed meanwhile.
+    fn resolve(&self, current: &PartitionInfo, incoming: &PartitionInfo, commit_op: CommitOp) -> ConflictDecision;
+}
+
+/// Appends and merges are retried on top of the version committed meanwhile, any other commit, e.g. an update or
+/// a compaction, fails so that the caller decides again on the current content.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RebaseAppends;
+
+impl CommitConflictPolicy for RebaseAppends {
+    fn name(&self) -> &str {
+        "rebase_appends"
+    }
+
+    fn resolve(&self, _current: &PartitionInfo, _incoming: &PartitionInfo, commit_op: CommitOp) -> ConflictDecision {
+        match commit_op {
+            CommitOp::AppendCommit | CommitOp::MergeCommit => ConflictDecision::Merge,
+            _ => ConflictDecision::Fail,
+        }
+    }
+}
+
+/// Every conflicting commit fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FailOnConflict;
+
+impl CommitConflictPolicy for FailOnConflict {
+    fn name(&self) -> &str {
+        "fail"
+    }
+
+    fn resolve(&self, _current: &PartitionInfo, _incoming: &PartitionInfo, _commit_op: CommitOp) -> ConflictDecision {
+        ConflictDecision::Fail
+    }
+}
+
+/// The policies shipped with the client, selectable by name without being registered.
+pub(crate) fn builtin_conflict_policies() -> Vec<Arc<dyn CommitConflictPolicy>> {
+    vec![Arc::new(RebaseAppends), Arc::new(FailOnConflict)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_conflict_policies() {
+        let partition_info = PartitionInfo::default();
+        let decide =
+            |policy: &dyn CommitConflictPolicy, commit_op| policy.resolve(&partition_info, &partition_info, commit_op);
+        assert_eq!(decide(&RebaseAppends, CommitOp::AppendCommit), ConflictDecision::Merge);
+        assert_eq!(decide(&RebaseAppends, CommitOp::MergeCommit), ConflictDecision::Merge);
+        assert_eq!(
+            decide(&RebaseAppends, CommitOp::CompactionCommit),
+            ConflictDecision::Fail
+        );
+        assert_eq!(decide(&RebaseAppends, CommitOp::UpdateCommit), ConflictDecision::Fail);
+        assert_eq!(decide(&FailOnConflict, CommitOp::AppendCommit), ConflictDecision::Fail);
+        assert_eq!(
+            builtin_conflict_policies()
+                .iter()
+                .map(|policy| policy.name().to_string())
+                .collect::<Vec<_>>(),
+            vec!["rebase_appends", "fail"]
+        );
+    }
+}
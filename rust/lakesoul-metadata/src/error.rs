@@ -5,6 +5,9 @@
 use std::{io, num, result, sync::Arc};
 
 use thiserror::Error;
+use tokio_postgres::error::SqlState;
+
+use crate::error_context::ErrorContext;
 
 /// Result type for operations that could result in an [LakeSoulMetaDataError]
 pub type Result<T, E = LakeSoulMetaDataError> = result::Result<T, E>;
@@ -19,7 +22,7 @@ pub type GenericError = Box<dyn std::error::Error + Send + Sync>;
 #[derive(Error, Debug)]
 pub enum LakeSoulMetaDataError {
     #[error("postgres error: {0}")]
-    PostgresError(#[from] tokio_postgres::Error),
+    PostgresError(#[source] tokio_postgres::Error),
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
     #[error("serde_json error: {0}")]
@@ -41,8 +44,102 @@ pub enum LakeSoulMetaDataError {
     Internal(String),
     #[error("Not found error: {0}")]
     NotFound(String),
+    #[error("Already exists error: {0}")]
+    AlreadyExists(String),
+    #[error("Invalid input error: {0}")]
+    InvalidInput(String),
+    #[error("Invalid config error: {0}")]
+    InvalidConfig(String),
+    #[error("Connection error: {0}")]
+    ConnectionFailed(String),
+    #[error("Authentication error: {0}")]
+    AuthenticationFailed(String),
+    #[error("Incompatible schema error: {0}")]
+    IncompatibleSchema(String),
+    #[error("Queue full error: {0}")]
+    QueueFull(String),
+    #[error("Limit exceeded error: {0}")]
+    LimitExceeded(String),
+    #[error("Result too large error: {0}")]
+    ResultTooLarge(String),
+    #[error("Client closed error: {0}")]
+    ClientClosed(String),
+    #[error("Locked error: {0}")]
+    Locked(String),
+    #[error("Timeout error: {0}")]
+    Timeout(String),
+    #[error("Concurrent commit error: {0}")]
+    ConcurrentCommit(String),
+    #[error("Read-only database error: {0}")]
+    ReadOnlyDatabase(String),
     #[error("Other error: {0}")]
     Other(#[from] GenericError),
+    #[error("{source} ({context})")]
+    Context {
+        context: ErrorContext,
+        source: Box<LakeSoulMetaDataError>,
+    },
+}
+
+impl LakeSoulMetaDataError {
+    /// Attach the origin of the error. Errors with a message of their own keep their kind, the context being
+    /// appended to the message, while the errors of other crates are wrapped, see [`Self::root_cause`].
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        use LakeSoulMetaDataError::*;
+        let suffix = format!(" ({})", context);
+        match self {
+            Internal(message) => Internal(message + &suffix),
+            NotFound(message) => NotFound(message + &suffix),
+            AlreadyExists(message) => AlreadyExists(message + &suffix),
+            InvalidInput(message) => InvalidInput(message + &suffix),
+            InvalidConfig(message) => InvalidConfig(message + &suffix),
+            ConnectionFailed(message) => ConnectionFailed(message + &suffix),
+            AuthenticationFailed(message) => AuthenticationFailed(message + &suffix),
+            IncompatibleSchema(message) => IncompatibleSchema(message + &suffix),
+            QueueFull(message) => QueueFull(message + &suffix),
+            LimitExceeded(message) => LimitExceeded(message + &suffix),
+            ResultTooLarge(message) => ResultTooLarge(message + &suffix),
+            ClientClosed(message) => ClientClosed(message + &suffix),
+            Locked(message) => Locked(message + &suffix),
+            Timeout(message) => Timeout(message + &suffix),
+            ConcurrentCommit(message) => ConcurrentCommit(message + &suffix),
+            ReadOnlyDatabase(message) => ReadOnlyDatabase(message + &suffix),
+            err @ Context { .. } => err,
+            err => Context {
+                context,
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// Whether another attempt of the failed query may succeed. Writes to a read-only database fail again until
+    /// the client is connected to another host.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self.root_cause(), Self::ReadOnlyDatabase(_))
+    }
+
+    /// The error without the context attached to it.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::Context { source, .. } => source.root_cause(),
+            err => err,
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for LakeSoulMetaDataError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        if err.code() == Some(&SqlState::READ_ONLY_SQL_TRANSACTION) {
+            // e.g. DNS pointing to a standby after a failover of the database
+            Self::ReadOnlyDatabase(format!(
+                "{}, the metadata database is read-only, e.g. a standby; list the hosts of the database with \
+                target_session_attrs=read-write in the config to connect to the writable one",
+                err
+            ))
+        } else {
+            Self::PostgresError(err)
+        }
+    }
 }
 
 impl From<io::ErrorKind> for LakeSoulMetaDataError {
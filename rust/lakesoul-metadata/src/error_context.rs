@@ -0,0 +1,113 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Origin of the errors of client operations: the operation, the table it ran on and the attempt of the query
+//! which failed, so that the errors of operations running concurrently tell where they come from without tracing.
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+
+use crate::error::Result;
+
+tokio::task_local! {
+    static CURRENT: RefCell<ErrorContext>;
+}
+
+/// Attached to the errors of an operation, see [`crate::error::LakeSoulMetaDataError::with_context`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub operation: String,
+    pub table_id: Option<String>,
+    /// Attempt of the last query the operation made, from 1.
+    pub attempt: Option<usize>,
+}
+
+impl ErrorContext {
+    pub fn new(operation: &str) -> Self {
+        Self {
+            operation: operation.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_table_id(mut self, table_id: &str) -> Self {
+        self.table_id = Some(table_id.to_string());
+        self
+    }
+
+    pub fn with_attempt(mut self, attempt: usize) -> Self {
+        self.attempt = Some(attempt);
+        self
+    }
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "in {}", self.operation)?;
+        if let Some(table_id) = &self.table_id {
+            write!(f, " of table '{}'", table_id)?;
+        }
+        if let Some(attempt) = self.attempt {
+            write!(f, " at attempt {}", attempt)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `operation` in `context`, which is attached to its error. The operations it runs in turn are reported in
+/// the context of the outermost one, the operation the caller asked for.
+pub(crate) async fn in_context<T>(context: ErrorContext, operation: impl Future<Output = Result<T>>) -> Result<T> {
+    // boxed, the future of the operation would otherwise be held by both branches below, overflowing the stack
+    let operation = Box::pin(operation);
+    if CURRENT.try_with(|_| ()).is_ok() {
+        return operation.await;
+    }
+    let (result, context) = CURRENT
+        .scope(RefCell::new(context), async {
+            let result = operation.await;
+            (result, CURRENT.with(RefCell::take))
+        })
+        .await;
+    result.map_err(|err| err.with_context(context))
+}
+
+/// Record the attempt of the query the running operation makes, reported along with its error.
+pub(crate) fn note_attempt(attempt: usize) {
+    let _ = CURRENT.try_with(|context| context.borrow_mut().attempt = Some(attempt));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LakeSoulMetaDataError;
+
+    #[tokio::test]
+    async fn test_in_context() {
+        let context = ErrorContext::new("outer").with_table_id("table_1");
+        let err = in_context(context.clone(), async {
+            in_context(ErrorContext::new("inner"), async {
+                note_attempt(2);
+                Err::<(), _>(LakeSoulMetaDataError::NotFound("no such partition".to_string()))
+            })
+            .await
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, LakeSoulMetaDataError::NotFound(_)));
+        assert_eq!(
+            err.to_string(),
+            "Not found error: no such partition (in outer of table 'table_1' at attempt 2)"
+        );
+
+        let err = in_context(context, async {
+            Err::<(), _>(LakeSoulMetaDataError::from(std::io::ErrorKind::BrokenPipe))
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, LakeSoulMetaDataError::Context { .. }));
+        assert!(matches!(err.root_cause(), LakeSoulMetaDataError::IoError(_)));
+        assert!(err.to_string().ends_with("(in outer of table 'table_1')"));
+    }
+}
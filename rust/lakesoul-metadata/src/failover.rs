@@ -0,0 +1,257 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Stale reads from a secondary metadata database, e.g. a replica in another region, while the primary cannot be
+//! reached. Writes always go to the primary. A health check watches the primary and reconnects to it once it is
+//! back, after which reads are served by the primary again. A primary which turned read-only, e.g. a demoted one,
+//! is replaced alike if the config asks for a writable host by `target_session_attrs=read-write`.
+
+use std::error::Error as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, NoTls};
+use tracing::{debug, info, warn};
+
+use crate::error::{LakeSoulMetaDataError, Result};
+use crate::metadata_client::config_properties;
+use crate::schema_compat::probe_column_fallbacks;
+use crate::{
+    connect_on, execute_query_scalar_with_context, execute_query_with_context, prepare_all_statements_with_context,
+    ConnectionHandle, ConnectionStatements, ScalarValue,
+};
+
+/// How often the primary is checked by default.
+pub(crate) const DEFAULT_FAILBACK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether `err` means the database could not be reached, rather than a statement failing.
+pub(crate) fn is_connection_error(err: &LakeSoulMetaDataError) -> bool {
+    match err.root_cause() {
+        LakeSoulMetaDataError::PostgresError(err) => {
+            err.is_closed()
+                || err.source().is_some_and(|source| source.is::<std::io::Error>())
+                || err.code().is_some_and(|code| {
+                    code.code().starts_with("08")
+                        || *code == SqlState::ADMIN_SHUTDOWN
+                        || *code == SqlState::CANNOT_CONNECT_NOW
+                })
+        }
+        LakeSoulMetaDataError::ConnectionFailed(_) | LakeSoulMetaDataError::IoError(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether the database `client` is connected to only serves reads, e.g. a standby.
+pub(crate) async fn is_read_only(client: &Client) -> Result<bool> {
+    let row = client.query_one("SHOW transaction_read_only", &[]).await?;
+    Ok(row.get::<_, String>(0) == "on")
+}
+
+/// Whether `config` asks for a writable host, i.e. another host may be connected to if the current one is read-only.
+pub(crate) fn seeks_writable_host(config: &str) -> bool {
+    config_properties(config)
+        .iter()
+        .any(|property| property == "target_session_attrs=read-write")
+}
+
+/// The secondary database of a client and whether reads are failed over to it.
+pub(crate) struct Failover {
+    config: String,
+    /// Connected on the first failover, and again after its connection is lost.
+    secondary: Mutex<Option<(Client, ConnectionStatements)>>,
+    /// Of the last connection to the secondary, reachable while a query holds the connection.
+    secondary_handle: std::sync::Mutex<Option<ConnectionHandle>>,
+    primary_down: AtomicBool,
+    /// Set once the client is closed, the secondary is not connected to anymore.
+    closed: AtomicBool,
+    runtime_handle: runtime::Handle,
+}
+
+impl Failover {
+    pub(crate) fn new(config: String, runtime_handle: runtime::Handle) -> Self {
+        Self {
+            config,
+            secondary: Mutex::new(None),
+            secondary_handle: std::sync::Mutex::new(None),
+            primary_down: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            runtime_handle,
+        }
+    }
+
+    pub(crate) fn is_primary_down(&self) -> bool {
+        self.primary_down.load(Ordering::Acquire)
+    }
+
+    /// Serve reads from the secondary until the health check reconnects to the primary.
+    pub(crate) fn primary_failed(&self, err: &LakeSoulMetaDataError) {
+        if !self.primary_down.swap(true, Ordering::AcqRel) {
+            warn!(
+                "primary metadata database unreachable ({}), serving stale reads from the secondary",
+                err
+            );
+        }
+    }
+
+    pub(crate) async fn execute_query(
+        &self,
+        query_type: i32,
+        joined_string: String,
+        max_result_bytes: Option<usize>,
+    ) -> Result<Vec<u8>> {
+        let mut secondary = self.connect().await?;
+        let Some((client, statements)) = secondary.as_mut() else {
+            return Err(LakeSoulMetaDataError::Internal("secondary not connected".to_string()));
+        };
+        debug!("stale read of query {} from the secondary", query_type);
+        let result = execute_query_with_context(
+            client,
+            &mut statements.prepared,
+            &statements.context,
+            query_type,
+            joined_string,
+            max_result_bytes,
+        )
+        .await;
+        if result.as_ref().is_err_and(is_connection_error) {
+            *secondary = None;
+        }
+        result
+    }
+
+    pub(crate) async fn execute_query_scalar_value(
+        &self,
+        query_type: i32,
+        joined_string: String,
+    ) -> Result<ScalarValue> {
+        let mut secondary = self.connect().await?;
+        let Some((client, statements)) = secondary.as_mut() else {
+            return Err(LakeSoulMetaDataError::Internal("secondary not connected".to_string()));
+        };
+        debug!("stale read of query {} from the secondary", query_type);
+        let result = execute_query_scalar_with_context(
+            client,
+            &mut statements.prepared,
+            &statements.context,
+            query_type,
+            joined_string,
+        )
+        .await;
+        if result.as_ref().is_err_and(is_connection_error) {
+            *secondary = None;
+        }
+        result
+    }
+
+    /// Connect to the secondary and prepare its statements ahead of the first failover.
+    pub(crate) async fn warmup(&self) -> Result<()> {
+        let mut secondary = self.connect().await?;
+        let Some((client, statements)) = secondary.as_mut() else {
+            return Err(LakeSoulMetaDataError::Internal("secondary not connected".to_string()));
+        };
+        let result = prepare_all_statements_with_context(client, &mut statements.prepared, &statements.context).await;
+        if result.as_ref().is_err_and(is_connection_error) {
+            *secondary = None;
+        }
+        result.map(|_| ())
+    }
+
+    /// Cancel the query running on the secondary, if any.
+    pub(crate) async fn cancel_query(&self) -> Result<()> {
+        let cancel_token = self
+            .secondary_handle
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|handle| handle.cancel_token.clone());
+        if let Some(cancel_token) = cancel_token {
+            cancel_token.cancel_query(NoTls).await?;
+        }
+        Ok(())
+    }
+
+    /// End the connection to the secondary once its query is done, and connect to it no more.
+    pub(crate) async fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        // dropping the client ends the connection
+        self.secondary.lock().await.take();
+        let task = self
+            .secondary_handle
+            .lock()
+            .unwrap()
+            .take()
+            .and_then(|handle| handle.task);
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+    }
+
+    async fn connect(&self) -> Result<MutexGuard<'_, Option<(Client, ConnectionStatements)>>> {
+        let mut secondary = self.secondary.lock().await;
+        if self.closed.load(Ordering::Acquire) {
+            return Err(LakeSoulMetaDataError::ClientClosed(
+                "secondary metadata database connection is closed".to_string(),
+            ));
+        }
+        if secondary.is_none() {
+            let (client, handle) = connect_on(self.config.clone(), &self.runtime_handle).await?;
+            let column_fallbacks = probe_column_fallbacks(&client).await?;
+            *secondary = Some((client, ConnectionStatements::new(column_fallbacks)));
+            *self.secondary_handle.lock().unwrap() = Some(handle);
+        }
+        Ok(secondary)
+    }
+}
+
+/// Check the primary every `interval`: fail reads over once its connection is lost, and reconnect to it once it
+/// is back, replacing the connection, prepared statements and connection handle of the client.
+pub(crate) fn spawn_health_check(
+    failover: Arc<Failover>,
+    config: String,
+    client: Arc<Mutex<Client>>,
+    prepared: Arc<Mutex<ConnectionStatements>>,
+    connection: Arc<std::sync::Mutex<ConnectionHandle>>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let runtime_handle = failover.runtime_handle.clone();
+    runtime_handle.spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if !failover.is_primary_down() {
+                let primary = client.lock().await;
+                let checked = primary
+                    .simple_query("SELECT 1")
+                    .await
+                    .map_err(LakeSoulMetaDataError::from);
+                match checked {
+                    Err(err) if is_connection_error(&err) => failover.primary_failed(&err),
+                    Ok(_) if seeks_writable_host(&config) && is_read_only(&primary).await.unwrap_or(false) => {
+                        let err = LakeSoulMetaDataError::ReadOnlyDatabase("primary turned read-only".to_string());
+                        failover.primary_failed(&err);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            match connect_on(config.clone(), &failover.runtime_handle).await {
+                Ok((primary, handle)) => {
+                    // in the order of the queries, which must not see the new connection with the old statements
+                    let mut client = client.lock().await;
+                    let mut prepared = prepared.lock().await;
+                    *connection.lock().unwrap() = handle;
+                    *client = primary;
+                    prepared.reset();
+                    failover.primary_down.store(false, Ordering::Release);
+                    info!("primary metadata database is back, reads are served by the primary again");
+                }
+                Err(err) => debug!("primary metadata database still unreachable: {}", err),
+            }
+        }
+    })
+}
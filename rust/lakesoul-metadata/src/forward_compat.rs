@@ -0,0 +1,192 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rows written by newer clients, e.g. during a rolling upgrade, are passed through rather than rejected:
+//! a commit op unknown to this client keeps its name through every copy of its partition info and data commit,
+//! and table property keys unknown to this client are kept as they are when properties are merged. Anything
+//! passed through is recorded by the client which passed it through, see
+//! [`crate::MetaDataClient::compatibility_warnings`].
+//!
+//! An unknown commit op read from the database is given a code of its own below [`UNKNOWN_COMMIT_OP_CODE`], under
+//! which it is written back by its name. A commit op code neither in the proto nor given out here, e.g. decoded
+//! from the protobuf of a newer client, has no name to be written under and is rejected on write.
+
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use futures::stream::{self, BoxStream, StreamExt};
+use proto::proto::entity::CommitOp;
+use tracing::warn;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+use crate::transfusion::config::{
+    CHANGE_TRACKING_ENABLED, DROPPED_COLUMN, HASH_BUCKET_NUM, LAST_TABLE_SCHEMA_CHANGE_TIME, PARTITION_STATS_ENABLED,
+};
+
+/// Code of the first unknown commit op, the following ones counting down from it.
+pub const UNKNOWN_COMMIT_OP_CODE: i32 = -1000;
+
+/// Table property keys this client interprets, the others are passed through.
+const KNOWN_PROPERTY_KEYS: [&str; 5] = [
+    HASH_BUCKET_NUM,
+    CHANGE_TRACKING_ENABLED,
+    PARTITION_STATS_ENABLED,
+    DROPPED_COLUMN,
+    LAST_TABLE_SCHEMA_CHANGE_TIME,
+];
+
+/// Names of the unknown commit ops, by their code counting down from [`UNKNOWN_COMMIT_OP_CODE`]. Shared by all
+/// clients of the process, so that a code means the same commit op whichever client decoded it.
+static UNKNOWN_COMMIT_OPS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Warnings logged so far, each being logged once per process.
+static LOGGED: Mutex<BTreeSet<CompatibilityWarning>> = Mutex::new(BTreeSet::new());
+
+tokio::task_local! {
+    static RECORDING: CompatibilityWarnings;
+}
+
+/// Something a newer client wrote which this client passed through without understanding it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CompatibilityWarning {
+    /// Commit op read from the database, given a code from [`UNKNOWN_COMMIT_OP_CODE`] down.
+    UnknownCommitOp(String),
+    /// Table property key kept as is when merging properties.
+    UnknownPropertyKey(String),
+}
+
+/// Warnings of a client, recorded while it runs an operation in [`CompatibilityWarnings::record`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompatibilityWarnings(Arc<Mutex<BTreeSet<CompatibilityWarning>>>);
+
+impl CompatibilityWarnings {
+    /// Run `f`, recording what it passes through.
+    pub(crate) async fn record<F: Future>(&self, f: F) -> F::Output {
+        RECORDING.scope(self.clone(), f).await
+    }
+
+    /// Record what `stream` passes through while it is polled.
+    pub(crate) fn record_stream<T: Send + 'static>(&self, stream: BoxStream<'static, T>) -> BoxStream<'static, T> {
+        let warnings = self.clone();
+        let mut stream = stream;
+        stream::poll_fn(move |cx| RECORDING.sync_scope(warnings.clone(), || stream.poll_next_unpin(cx))).boxed()
+    }
+
+    /// Record the keys of `properties` this client does not interpret, which are kept as they are.
+    pub(crate) fn pass_through_properties(&self, properties: &serde_json::Map<String, serde_json::Value>) {
+        RECORDING.sync_scope(self.clone(), || pass_through_properties(properties))
+    }
+
+    /// Warnings recorded so far, in order.
+    pub(crate) fn list(&self) -> Vec<CompatibilityWarning> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+fn warn_once(warning: CompatibilityWarning) {
+    let _ = RECORDING.try_with(|warnings| warnings.0.lock().unwrap().insert(warning.clone()));
+    if LOGGED.lock().unwrap().insert(warning.clone()) {
+        warn!("passing through {:?} unknown to this client", warning);
+    }
+}
+
+/// Code of the commit op named `name` in the database, an unknown one being given a code of its own.
+pub(crate) fn decode_commit_op(name: &str) -> i32 {
+    if let Some(commit_op) = CommitOp::from_str_name(name) {
+        return commit_op as i32;
+    }
+    let index = {
+        let mut unknown = UNKNOWN_COMMIT_OPS.lock().unwrap();
+        match unknown.iter().position(|known| known == name) {
+            Some(index) => index,
+            None => {
+                unknown.push(name.to_string());
+                unknown.len() - 1
+            }
+        }
+    };
+    warn_once(CompatibilityWarning::UnknownCommitOp(name.to_string()));
+    UNKNOWN_COMMIT_OP_CODE - index as i32
+}
+
+/// Name the commit op of code `code` is written under to the database.
+/// Fails with InvalidInput for a code neither in the proto nor given out by [`decode_commit_op`].
+pub(crate) fn encode_commit_op(code: i32) -> Result<String> {
+    if let Ok(commit_op) = CommitOp::try_from(code) {
+        return Ok(commit_op.as_str_name().to_string());
+    }
+    usize::try_from(UNKNOWN_COMMIT_OP_CODE as i64 - code as i64)
+        .ok()
+        .and_then(|index| UNKNOWN_COMMIT_OPS.lock().unwrap().get(index).cloned())
+        .ok_or_else(|| LakeSoulMetaDataError::InvalidInput(format!("unknown commit_op {}", code)))
+}
+
+fn pass_through_properties(properties: &serde_json::Map<String, serde_json::Value>) {
+    properties
+        .keys()
+        .filter(|key| !KNOWN_PROPERTY_KEYS.contains(&key.as_str()))
+        .for_each(|key| warn_once(CompatibilityWarning::UnknownPropertyKey(key.clone())));
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use proto::proto::entity;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_commit_ops() {
+        assert_eq!(decode_commit_op("CompactionCommit"), CommitOp::CompactionCommit as i32);
+        assert_eq!(encode_commit_op(CommitOp::MergeCommit as i32).unwrap(), "MergeCommit");
+
+        let (warnings, other) = (CompatibilityWarnings::default(), CompatibilityWarnings::default());
+        let code = warnings.record(async { decode_commit_op("FutureCommit") }).await;
+        assert!(code <= UNKNOWN_COMMIT_OP_CODE);
+        assert_eq!(decode_commit_op("FutureCommit"), code);
+        assert_eq!(encode_commit_op(code).unwrap(), "FutureCommit");
+        assert_eq!(
+            warnings.list(),
+            vec![CompatibilityWarning::UnknownCommitOp("FutureCommit".to_string())]
+        );
+        // recorded by the client which decoded it only, under the code given out already
+        assert!(other.list().is_empty());
+        assert_eq!(other.record(async { decode_commit_op("FutureCommit") }).await, code);
+        assert_eq!(other.list(), warnings.list());
+
+        // a newer client's PartitionInfo with a field and a commit op added after this client was built
+        let mut bytes = entity::PartitionInfo {
+            table_id: "table_future".to_string(),
+            partition_desc: "-5".to_string(),
+            version: 3,
+            ..Default::default()
+        }
+        .encode_to_vec();
+        bytes.extend([0x20, 0x2a]); // commit_op = 42
+        bytes.extend([0xf8, 0x06, 0x01]); // field 111, varint 1
+        let partition_info = entity::PartitionInfo::decode(bytes.as_slice()).unwrap();
+        assert_eq!((partition_info.version, partition_info.commit_op), (3, 42));
+        let copied = entity::PartitionInfo {
+            version: partition_info.version + 1,
+            ..partition_info.clone()
+        };
+        assert_eq!(copied.commit_op, 42);
+        // rejected on write rather than written as the default commit op
+        assert!(matches!(
+            encode_commit_op(copied.commit_op),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_property_keys() {
+        let properties =
+            serde_json::from_str(r#"{"hashBucketNum": "2", "future.option": {"nested": [1, 2]}}"#).unwrap();
+        let warnings = CompatibilityWarnings::default();
+        warnings.pass_through_properties(&properties);
+        let warnings = warnings.list();
+        assert!(warnings.contains(&CompatibilityWarning::UnknownPropertyKey("future.option".to_string())));
+        assert!(!warnings.contains(&CompatibilityWarning::UnknownPropertyKey(HASH_BUCKET_NUM.to_string())));
+    }
+}
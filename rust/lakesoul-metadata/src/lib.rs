@@ -2,24 +2,68 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use std::borrow::Cow;
 use std::str::FromStr;
+use std::sync::LazyLock;
 use std::{collections::HashMap, io::ErrorKind};
 
-use postgres_types::{FromSql, ToSql};
+use futures::stream::{BoxStream, StreamExt};
+use postgres_types::{FromSql, ToSql, Type};
 use prost::Message;
+use regex::Regex;
 pub use tokio::runtime::{Builder, Runtime};
-use tokio::spawn;
+use tokio::task::JoinHandle;
+use tokio::{runtime, spawn};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{CancelToken, Config, Error, GenericClient, Row, Transaction};
 pub use tokio_postgres::{Client, NoTls, Statement};
-use tokio_postgres::{Error, Row};
 
+pub use catalog_export::{ConflictPolicy, ExportManifest, ImportSummary};
+pub use conflict_policy::{CommitConflictPolicy, ConflictDecision, FailOnConflict, RebaseAppends};
 use error::{LakeSoulMetaDataError, Result};
-pub use metadata_client::{MetaDataClient, MetaDataClientRef};
+pub use error_context::ErrorContext;
+use forward_compat::{decode_commit_op, encode_commit_op};
+pub use forward_compat::{CompatibilityWarning, UNKNOWN_COMMIT_OP_CODE};
+pub use metadata_client::{
+    CloseReport, CommitHint, CommitHook, CommitMetrics, CompactionLock, ConsistencyReport, ConsistencyViolation,
+    CreateTableOutcome, DropProgress, DropSummary, ExternalFile, HealthReport, MetaDataClient, MetaDataClientBuilder,
+    MetaDataClientRef, NamespaceSyncSummary, OperationLogEntry, PartialCommitReport, PartitionFilter, PathMode,
+    ReadSnapshot, ResolvedFileOp, SyncReport, TableInfoUpdate, TableReadSnapshot, TagInfo,
+};
+pub use op_options::OpOptions;
+pub use ops_limiter::LimiterMetrics;
 use proto::proto::entity;
+use schema_compat::{probe_column_fallbacks, ColumnFallbacks};
+pub use schema_validation::{FieldMismatch, SchemaValidation};
+pub use snapshot::Snapshot;
+pub use table_cache::TableCacheMetrics;
+pub use table_properties::{validate_table_properties, PropertyIssue};
+pub use trace_context::{TraceContext, TracePropagation};
+pub use write_limits::WriteLimits;
 
 pub mod transfusion;
 
+#[cfg(feature = "admin")]
+pub mod admin;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod catalog_export;
+pub mod conflict_policy;
 pub mod error;
+mod error_context;
+mod failover;
+mod forward_compat;
 mod metadata_client;
+mod op_options;
+mod ops_limiter;
+mod partition_coercion;
+pub mod schema_compat;
+mod schema_validation;
+mod snapshot;
+mod table_cache;
+mod table_properties;
+mod trace_context;
+mod write_limits;
 
 pub const DAO_TYPE_QUERY_ONE_OFFSET: i32 = 0;
 pub const DAO_TYPE_QUERY_LIST_OFFSET: i32 = 100;
@@ -28,9 +72,110 @@ pub const DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET: i32 = 300;
 pub const DAO_TYPE_QUERY_SCALAR_OFFSET: i32 = 400;
 pub const DAO_TYPE_UPDATE_OFFSET: i32 = 500;
 
-pub const PARAM_DELIM: &str = "__DELIM__";
-pub const PARTITION_DESC_DELIM: &str = "_DELIM_";
+/// Databases [`clean_meta_for_test`] cleans without being told to, see
+/// [`MetaDataClientBuilder::with_clean_meta_database_pattern`].
+pub const DEFAULT_CLEAN_META_DATABASE_PATTERN: &str = "*_test";
+
+const QUERY_PARAM_DELIM: &str = "__DELIM__";
+const PARTITION_DESC_LIST_DELIM: &str = "_DELIM_";
+/// Starts an escape sequence in encoded params, never found in the params of legacy callers.
+const PARAM_ESCAPE: char = '\u{1}';
+
+#[deprecated(note = "use encode_query_params and decode_query_params, which escape delimiters within params")]
+pub const PARAM_DELIM: &str = QUERY_PARAM_DELIM;
+#[deprecated(
+    note = "use encode_partition_desc_list and decode_partition_desc_list, which escape delimiters within descs"
+)]
+pub const PARTITION_DESC_DELIM: &str = PARTITION_DESC_LIST_DELIM;
+
+/// Escape `DELIM` out of a param, so that the delimiters joining params are the only occurrences left.
+fn escape_param(param: &str) -> String {
+    param.replace(PARAM_ESCAPE, "\u{1}0").replace("DELIM", "\u{1}1")
+}
+
+/// Undo [`escape_param`]. Params of legacy callers have no escape sequence and are kept as they are.
+fn unescape_param(param: &str) -> String {
+    if !param.contains(PARAM_ESCAPE) {
+        return param.to_string();
+    }
+    let mut unescaped = String::with_capacity(param.len());
+    let mut chars = param.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            (PARAM_ESCAPE, Some('0')) => {
+                chars.next();
+                unescaped.push(PARAM_ESCAPE);
+            }
+            (PARAM_ESCAPE, Some('1')) => {
+                chars.next();
+                unescaped.push_str("DELIM");
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Join the params of a DAO query or update, escaping the delimiter within params.
+pub fn encode_query_params(params: &[&str]) -> String {
+    params
+        .iter()
+        .map(|param| escape_param(param))
+        .collect::<Vec<String>>()
+        .join(QUERY_PARAM_DELIM)
+}
+
+/// Split params joined by [`encode_query_params`], or by the raw delimiter as legacy callers do.
+pub fn decode_query_params(joined: &str) -> Vec<String> {
+    joined.split(QUERY_PARAM_DELIM).map(unescape_param).collect()
+}
+
+/// Join the partition descs of a list param, escaping the delimiter within descs.
+pub fn encode_partition_desc_list<S: AsRef<str>>(partition_descs: &[S]) -> String {
+    partition_descs
+        .iter()
+        .map(|partition_desc| escape_param(partition_desc.as_ref()))
+        .collect::<Vec<String>>()
+        .join(PARTITION_DESC_LIST_DELIM)
+}
+
+/// Split partition descs joined by [`encode_partition_desc_list`], or by the raw delimiter as legacy callers do.
+pub fn decode_partition_desc_list(joined: &str) -> Vec<String> {
+    joined.split(PARTITION_DESC_LIST_DELIM).map(unescape_param).collect()
+}
+
+/// Upsert the file statistics of the snapshot `$3` of a partition, stamped `$4`, the timestamp of the partition
+/// version, as [`DaoType::RebuildPartitionStatsByTableId`] does.
+fn upsert_partition_stats_of_snapshot() -> String {
+    format!(
+        "insert into partition_stats(table_id, partition_desc, file_count, total_bytes, last_commit_ts)
+        select $1::TEXT, $2::TEXT, count(f.path), coalesce(sum(f.size), 0)::BIGINT, $4::BIGINT
+        from ({}) f
+        on conflict (table_id, partition_desc) do update
+        set file_count = excluded.file_count, total_bytes = excluded.total_bytes,
+            last_commit_ts = excluded.last_commit_ts",
+        live_files_of_snapshot("$1::TEXT", "$2::TEXT", "$3::_UUID")
+    )
+}
+
+/// Query of the path and size of the files live in `snapshot` of a partition, each argument being an SQL expression:
+/// the files whose last op in the order of the commits of the snapshot is an add, as the engines read them.
+fn live_files_of_snapshot(table_id: &str, partition_desc: &str, snapshot: &str) -> String {
+    format!(
+        "select last_op.path, last_op.size
+        from (
+            select distinct on (f.path) f.path, f.file_op, f.size
+            from unnest({snapshot}) with ordinality s(commit_id, commit_ord)
+            join data_commit_info d
+                on d.table_id = {table_id} and d.partition_desc = {partition_desc} and d.commit_id = s.commit_id
+            cross join lateral unnest(d.file_ops) with ordinality f(path, file_op, size, file_exist_cols, op_ord)
+            order by f.path, s.commit_ord desc, f.op_ord desc
+        ) last_op
+        where last_op.file_op = 'add'"
+    )
+}
 
+#[derive(Clone, Copy)]
 enum ResultType {
     Namespace,
     TableInfo,
@@ -40,7 +185,13 @@ enum ResultType {
     DataCommitInfo,
     TablePathIdWithOnlyPath,
     PartitionInfoWithOnlyCommitOp,
+    PartitionInfoWithOnlyDesc,
     PartitionInfoWithoutTimestamp,
+    PartitionStats,
+    FileStats,
+    DataCommitInfoWithStats,
+    CommitProvenance,
+    TableTag,
 }
 
 #[derive(FromSql, ToSql, Debug, PartialEq)]
@@ -71,6 +222,7 @@ impl DataFileOp {
                 .ok_or(LakeSoulMetaDataError::Internal("unknown file_op".into()))? as i32,
             size: self.size,
             file_exist_cols: self.file_exist_cols.clone(),
+            column_stats: HashMap::new(),
         })
     }
 }
@@ -91,11 +243,16 @@ pub enum DaoType {
 
     SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId = DAO_TYPE_QUERY_ONE_OFFSET + 9,
 
+    SelectTableNameIdByTableId = DAO_TYPE_QUERY_ONE_OFFSET + 11,
+
+    SelectPartitionStatsByTableIdAndPartitionDesc = DAO_TYPE_QUERY_ONE_OFFSET + 12,
+
     // ==== Query List ====
     ListNamespaces = DAO_TYPE_QUERY_LIST_OFFSET,
     ListTableNameByNamespace = DAO_TYPE_QUERY_LIST_OFFSET + 1,
     ListAllTablePath = DAO_TYPE_QUERY_LIST_OFFSET + 2,
     ListAllPathTablePathByNamespace = DAO_TYPE_QUERY_LIST_OFFSET + 3,
+    ListTableNameIdByTableIds = DAO_TYPE_QUERY_LIST_OFFSET + 11,
 
     // Query Partition List
     ListPartitionByTableId = DAO_TYPE_QUERY_LIST_OFFSET + 4,
@@ -108,6 +265,18 @@ pub enum DaoType {
     // Query DataCommitInfo List
     ListDataCommitInfoByTableIdAndPartitionDescAndCommitList = DAO_TYPE_QUERY_LIST_OFFSET + 10,
 
+    ListPartitionSnapshotByTableIdAndTimestamp = DAO_TYPE_QUERY_LIST_OFFSET + 12,
+    ListOperationLogByTableId = DAO_TYPE_QUERY_LIST_OFFSET + 13,
+    ListPartitionByTableIdAndFilter = DAO_TYPE_QUERY_LIST_OFFSET + 14,
+    ListFileStatsByTableIdAndPartitionDescAndVersion = DAO_TYPE_QUERY_LIST_OFFSET + 15,
+    ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList = DAO_TYPE_QUERY_LIST_OFFSET + 16,
+    ListOperationLogProvenanceByTableId = DAO_TYPE_QUERY_LIST_OFFSET + 17,
+    ListPartitionByTableIdAndTag = DAO_TYPE_QUERY_LIST_OFFSET + 18,
+    ListTableTagByTableId = DAO_TYPE_QUERY_LIST_OFFSET + 19,
+    ListEmptyTableInfoByNamespace = DAO_TYPE_QUERY_LIST_OFFSET + 20,
+    ListPartitionValueByTableIdAndColumn = DAO_TYPE_QUERY_LIST_OFFSET + 21,
+    ListTableInfoByTableNamesAndNamespaces = DAO_TYPE_QUERY_LIST_OFFSET + 22,
+
     // ==== Insert One ====
     InsertNamespace = DAO_TYPE_INSERT_ONE_OFFSET,
     InsertTablePathId = DAO_TYPE_INSERT_ONE_OFFSET + 1,
@@ -119,12 +288,22 @@ pub enum DaoType {
     // ==== Transaction Insert List ====
     TransactionInsertPartitionInfo = DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET,
     TransactionInsertDataCommitInfo = DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 1,
+    TransactionInsertPartitionInfoWithStats = DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 2,
+    TransactionInsertFileStats = DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 3,
+    TransactionInsertCommitProvenance = DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET + 4,
 
     // ==== Query SCALAR ====
     GetLatestTimestampFromPartitionInfo = DAO_TYPE_QUERY_SCALAR_OFFSET,
     GetLatestTimestampFromPartitionInfoWithoutPartitionDesc = DAO_TYPE_QUERY_SCALAR_OFFSET + 1,
     GetLatestVersionUpToTimeFromPartitionInfo = DAO_TYPE_QUERY_SCALAR_OFFSET + 2,
     GetLatestVersionTimestampUpToTimeFromPartitionInfo = DAO_TYPE_QUERY_SCALAR_OFFSET + 3,
+    GetDbTimestamp = DAO_TYPE_QUERY_SCALAR_OFFSET + 4,
+    CountPartitionByTableIdAndFilter = DAO_TYPE_QUERY_SCALAR_OFFSET + 5,
+    GetTagPartitionCountByTableIdAndTag = DAO_TYPE_QUERY_SCALAR_OFFSET + 6,
+    GetTablePropertyByTableIdAndKey = DAO_TYPE_QUERY_SCALAR_OFFSET + 7,
+    PartitionExistsByTableIdAndPartitionDesc = DAO_TYPE_QUERY_SCALAR_OFFSET + 8,
+    PartitionIsEmptyByTableIdAndPartitionDesc = DAO_TYPE_QUERY_SCALAR_OFFSET + 9,
+    GetTableSchemaByTableIdAndVersion = DAO_TYPE_QUERY_SCALAR_OFFSET + 10,
 
     // ==== Update ====
     // Update Namespace
@@ -151,250 +330,778 @@ pub enum DaoType {
     DeleteDataCommitInfoByTableIdAndPartitionDescAndCommitIdList = DAO_TYPE_UPDATE_OFFSET + 13,
     DeleteDataCommitInfoByTableIdAndPartitionDesc = DAO_TYPE_UPDATE_OFFSET + 14,
     DeleteDataCommitInfoByTableId = DAO_TYPE_UPDATE_OFFSET + 15,
+
+    // Update TableInfo, TableNameId and TablePathId together
+    UpdateTableNamespaceByTableId = DAO_TYPE_UPDATE_OFFSET + 16,
+
+    // Update PartitionStats
+    DeletePartitionStatsByTableId = DAO_TYPE_UPDATE_OFFSET + 17,
+    RebuildPartitionStatsByTableId = DAO_TYPE_UPDATE_OFFSET + 18,
+
+    // Update FileStats
+    DeleteFileStatsByTableId = DAO_TYPE_UPDATE_OFFSET + 19,
+
+    // Update CommitProvenance
+    DeleteCommitProvenanceByTableId = DAO_TYPE_UPDATE_OFFSET + 20,
+
+    // Update TableTag
+    InsertTableTagByTableIdAndTag = DAO_TYPE_UPDATE_OFFSET + 21,
+    DeleteTableTagByTableId = DAO_TYPE_UPDATE_OFFSET + 22,
+    DeleteTableTagByTableIdAndTag = DAO_TYPE_UPDATE_OFFSET + 23,
+
+    // Update TableSchemaHistory
+    DeleteTableSchemaHistoryByTableId = DAO_TYPE_UPDATE_OFFSET + 24,
+
+    // Update PartitionInfo and DataCommitInfo together
+    TruncateTableByTableId = DAO_TYPE_UPDATE_OFFSET + 25,
+    RemoveDanglingSnapshotCommitByTableIdAndPartitionDesc = DAO_TYPE_UPDATE_OFFSET + 27,
+
+    // Update CommitSchemaVersion
+    DeleteCommitSchemaVersionByTableId = DAO_TYPE_UPDATE_OFFSET + 28,
 }
 
 pub type PreparedStatementMap = HashMap<DaoType, Statement>;
 
+/// What the statements of an operation are prepared for besides their DAO.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StatementContext {
+    /// Fallbacks for the optional columns missing from the connected database.
+    pub(crate) column_fallbacks: ColumnFallbacks,
+    /// Comment prefixed to the statements of the running operation, which are then prepared for it alone.
+    pub(crate) statement_comment: Option<String>,
+}
+
+/// Prepared statements of a connection held by a [`MetaDataClient`], with the context they are prepared in.
+#[derive(Default)]
+pub(crate) struct ConnectionStatements {
+    pub(crate) prepared: PreparedStatementMap,
+    pub(crate) context: StatementContext,
+    /// Traceparent the application_name of the connection is tagged with.
+    pub(crate) application_name_trace: Option<String>,
+}
+
+impl ConnectionStatements {
+    pub(crate) fn new(column_fallbacks: ColumnFallbacks) -> Self {
+        Self {
+            context: StatementContext {
+                column_fallbacks,
+                statement_comment: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Forget the statements and the tag of a connection replaced by a new one to the same database.
+    pub(crate) fn reset(&mut self) {
+        self.prepared.clear();
+        self.application_name_trace = None;
+    }
+}
+
+/// How a DAO is executed, given by the offset range of its [`DaoType`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DaoKind {
+    QueryOne,
+    QueryList,
+    QueryScalar,
+    Insert,
+    TransactionInsert,
+    Update,
+}
+
+impl DaoKind {
+    /// Whether the DAO only reads the metadata tables.
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, DaoKind::QueryOne | DaoKind::QueryList | DaoKind::QueryScalar)
+    }
+}
+
+impl DaoType {
+    pub fn kind(&self) -> DaoKind {
+        match *self as i32 {
+            dao_type if dao_type < DAO_TYPE_QUERY_LIST_OFFSET => DaoKind::QueryOne,
+            dao_type if dao_type < DAO_TYPE_INSERT_ONE_OFFSET => DaoKind::QueryList,
+            dao_type if dao_type < DAO_TYPE_TRANSACTION_INSERT_LIST_OFFSET => DaoKind::Insert,
+            dao_type if dao_type < DAO_TYPE_QUERY_SCALAR_OFFSET => DaoKind::TransactionInsert,
+            dao_type if dao_type < DAO_TYPE_UPDATE_OFFSET => DaoKind::QueryScalar,
+            _ => DaoKind::Update,
+        }
+    }
+}
+
+/// The SQL a DAO runs, see [`MetaDataClient::explain_dao`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DaoDescription {
+    pub dao_type: DaoType,
+    pub kind: DaoKind,
+    /// SQL template as prepared on the connected database, None if the statement is built when executed.
+    pub sql: Option<String>,
+    /// Type each parameter `$1`, `$2`, ... is cast to in the template, e.g. `TEXT[]`, empty if not cast.
+    pub params: Vec<String>,
+}
+
+/// A param of a statement, `$1` or `$1::TEXT`.
+static PARAM_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\$(\d+)(?:::(\w+(?:\[\])?))?").unwrap());
+
+/// Describe the statement of a DAO as it is prepared on a database with `column_fallbacks`, without preparing it.
+pub fn describe_dao(column_fallbacks: &ColumnFallbacks, dao_type: DaoType) -> DaoDescription {
+    let statement = dao_statement(&dao_type, column_fallbacks);
+    let sql = (!statement.is_empty()).then(|| statement.into_owned());
+    let mut params = Vec::<String>::new();
+    if let Some(sql) = &sql {
+        for caps in PARAM_REGEX.captures_iter(sql) {
+            let index = caps[1].parse::<usize>().unwrap_or_default();
+            if index > params.len() {
+                params.resize(index, String::new());
+            }
+            if let (Some(index), Some(cast)) = (index.checked_sub(1), caps.get(2)) {
+                if params[index].is_empty() {
+                    params[index] = cast.as_str().to_uppercase();
+                }
+            }
+        }
+    }
+    DaoDescription {
+        dao_type,
+        kind: dao_type.kind(),
+        sql,
+        params,
+    }
+}
+
+/// Columns read into the entities, in the order the rows are read by `row_to_*`, some of them optional, see
+/// [`ColumnFallbacks`].
+const NAMESPACE_COLUMNS: &[&str] = &["namespace", "properties", "comment", "domain"];
+const TABLE_INFO_COLUMNS: &[&str] = &[
+    "table_id",
+    "table_name",
+    "table_path",
+    "table_schema",
+    "properties",
+    "partitions",
+    "table_namespace",
+    "domain",
+];
+const TABLE_NAME_ID_COLUMNS: &[&str] = &["table_name", "table_id", "table_namespace", "domain"];
+const TABLE_PATH_ID_COLUMNS: &[&str] = &["table_path", "table_id", "table_namespace", "domain"];
+const PARTITION_INFO_COLUMNS: &[&str] = &[
+    "table_id",
+    "partition_desc",
+    "version",
+    "commit_op",
+    "snapshot",
+    "timestamp",
+    "expression",
+    "domain",
+    "change_tracking",
+];
+/// Partition versions read without their timestamp and change tracking.
+const PARTITION_VERSION_COLUMNS: &[&str] = &[
+    "table_id",
+    "partition_desc",
+    "version",
+    "commit_op",
+    "snapshot",
+    "expression",
+    "domain",
+];
+const DATA_COMMIT_INFO_COLUMNS: &[&str] = &[
+    "table_id",
+    "partition_desc",
+    "commit_id",
+    "file_ops",
+    "commit_op",
+    "timestamp",
+    "committed",
+    "domain",
+];
+
+/// Select `select_list` from the latest version of each partition of table `$1`, narrowed by the nullable filters
+/// `$2` to `$4`. Shared by the listing and counting statements so that both see the same partitions.
+fn latest_partition_statement(select_list: &str, order_by: &str) -> String {
+    format!(
+        "select {}
+        from partition_info p
+        where table_id = $1::TEXT
+            and version = (
+                select max(version)
+                from partition_info m
+                where m.table_id = p.table_id and m.partition_desc = p.partition_desc)
+            and ($2::TEXT[] is null or partition_desc = ANY($2::TEXT[]))
+            and ($3::BIGINT is null or timestamp >= $3::BIGINT)
+            and ($4::TEXT is null or commit_op = $4::TEXT)
+        {}",
+        select_list, order_by
+    )
+}
+
+/// Insert of a partition version stamped with `$9`, or the database clock if null.
+fn insert_partition_info_statement(column_fallbacks: &ColumnFallbacks) -> String {
+    column_fallbacks.insert(
+        "partition_info",
+        &[
+            "table_id",
+            "partition_desc",
+            "version",
+            "commit_op",
+            "snapshot",
+            "expression",
+            "domain",
+            "change_tracking",
+            "timestamp",
+        ],
+        &[
+            "$1::TEXT",
+            "$2::TEXT",
+            "$3::INT",
+            "$4::TEXT",
+            "$5::_UUID",
+            "$6::TEXT",
+            "$7::TEXT",
+            "$8::BOOL",
+            "coalesce($9::BIGINT, (date_part('epoch', now()) * 1000)::BIGINT)",
+        ],
+    )
+}
+
+/// Insert of a data commit stamped with the database clock if `$9`, else with `$6`.
+fn insert_data_commit_info_statement(column_fallbacks: &ColumnFallbacks) -> String {
+    column_fallbacks.insert(
+        "data_commit_info",
+        DATA_COMMIT_INFO_COLUMNS,
+        &[
+            "$1::TEXT",
+            "$2::TEXT",
+            "$3::UUID",
+            "$4::_data_file_op",
+            "$5::TEXT",
+            "case when $9::BOOL then (date_part('epoch', now()) * 1000)::BIGINT else $6::BIGINT end",
+            "$7::BOOL",
+            "$8::TEXT",
+        ],
+    )
+}
+
+/// SQL template of a DAO on a database with `column_fallbacks`, empty for the DAOs whose statement is built when
+/// executed rather than prepared.
+fn dao_statement(dao_type: &DaoType, column_fallbacks: &ColumnFallbacks) -> Cow<'static, str> {
+    let select_list = |alias, table, columns| column_fallbacks.select_list(alias, table, columns);
+    Cow::Borrowed(match dao_type {
+        // Select Namespace
+        DaoType::SelectNamespaceByNamespace =>
+            return Cow::Owned(format!(
+                "select {}
+                from namespace
+                where namespace = $1::TEXT",
+                select_list("", "namespace", NAMESPACE_COLUMNS)
+            )),
+        DaoType::ListNamespaces =>
+            return Cow::Owned(format!(
+                "select {}
+                from namespace",
+                select_list("", "namespace", NAMESPACE_COLUMNS)
+            )),
+
+        // Select TablePathId
+        DaoType::SelectTablePathIdByTablePath =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_path_id
+                where table_path = $1::TEXT",
+                select_list("", "table_path_id", TABLE_PATH_ID_COLUMNS)
+            )),
+        DaoType::ListAllTablePath =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_path_id",
+                select_list("", "table_path_id", TABLE_PATH_ID_COLUMNS)
+            )),
+        DaoType::ListAllPathTablePathByNamespace =>
+            "select table_path
+            from table_path_id
+            where table_namespace = $1::TEXT ",
+
+        // Select TableNameId
+        DaoType::SelectTableNameIdByTableName =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_name_id
+                where table_name = $1::TEXT and table_namespace = $2::TEXT",
+                select_list("", "table_name_id", TABLE_NAME_ID_COLUMNS)
+            )),
+        DaoType::ListTableNameByNamespace =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_name_id
+                where table_namespace = $1::TEXT",
+                select_list("", "table_name_id", TABLE_NAME_ID_COLUMNS)
+            )),
+        DaoType::SelectTableNameIdByTableId =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_name_id
+                where table_id = $1::TEXT",
+                select_list("", "table_name_id", TABLE_NAME_ID_COLUMNS)
+            )),
+        DaoType::SelectPartitionStatsByTableIdAndPartitionDesc =>
+            "select table_id, partition_desc, file_count, total_bytes, last_commit_ts
+            from partition_stats
+            where table_id = $1::TEXT and partition_desc = $2::TEXT",
+        DaoType::ListTableNameIdByTableIds =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_name_id
+                where table_id = ANY($1::TEXT[])",
+                select_list("", "table_name_id", TABLE_NAME_ID_COLUMNS)
+            )),
+        DaoType::ListTableInfoByTableNamesAndNamespaces =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_info t
+                join unnest($1::TEXT[], $2::TEXT[]) as k(table_name, table_namespace)
+                    on t.table_name = k.table_name and t.table_namespace = k.table_namespace",
+                select_list("t", "table_info", TABLE_INFO_COLUMNS)
+            )),
+
+        // Select TableInfo
+        DaoType::SelectTableInfoByTableId =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_info
+                where table_id = $1::TEXT",
+                select_list("", "table_info", TABLE_INFO_COLUMNS)
+            )),
+        DaoType::SelectTableInfoByTableNameAndNameSpace =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_info
+                where table_name = $1::TEXT and table_namespace=$2::TEXT",
+                select_list("", "table_info", TABLE_INFO_COLUMNS)
+            )),
+        DaoType::SelectTableInfoByTablePath =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_info
+                where table_path = $1::TEXT",
+                select_list("", "table_info", TABLE_INFO_COLUMNS)
+            )),
+        DaoType::SelectTableInfoByIdAndTablePath =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_info
+                where table_id = $1::TEXT and table_path=$2::TEXT",
+                select_list("", "table_info", TABLE_INFO_COLUMNS)
+            )),
+
+        // Select PartitionInfo
+        DaoType::SelectPartitionVersionByTableIdAndDescAndVersion =>
+            return Cow::Owned(format!(
+                "select {}
+                from partition_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT and version = $3::INT",
+                select_list("", "partition_info", PARTITION_VERSION_COLUMNS)
+            )),
+        DaoType::SelectOnePartitionVersionByTableIdAndDesc =>
+            return Cow::Owned(format!(
+                "select m.table_id, t.partition_desc, m.version, m.commit_op, m.snapshot, m.expression, {} from (
+                    select table_id,partition_desc,max(version) from partition_info
+                    where table_id = $1::TEXT and partition_desc = $2::TEXT group by table_id, partition_desc) t
+                    left join partition_info m on t.table_id = m.table_id
+                    and t.partition_desc = m.partition_desc and t.max = m.version",
+                select_list("m", "partition_info", &["domain"])
+            )),
+        DaoType::ListPartitionByTableIdAndDesc =>
+            return Cow::Owned(format!(
+                "select {}
+                from partition_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT ",
+                select_list("", "partition_info", PARTITION_INFO_COLUMNS)
+            )),
+        DaoType::ListPartitionByTableId =>
+            return Cow::Owned(format!(
+                "select m.table_id, t.partition_desc, m.version, m.commit_op, m.snapshot, m.expression, {}
+                from (
+                    select table_id,partition_desc,max(version)
+                    from partition_info
+                    where table_id = $1::TEXT
+                    group by table_id,partition_desc) t
+                left join partition_info m
+                on t.table_id = m.table_id and t.partition_desc = m.partition_desc and t.max = m.version",
+                select_list("m", "partition_info", &["domain"])
+            )),
+        DaoType::ListPartitionVersionByTableIdAndPartitionDescAndTimestampRange =>
+            return Cow::Owned(format!(
+                "select {}
+                from partition_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp >= $3::BIGINT and timestamp < $4::BIGINT",
+                select_list("", "partition_info", PARTITION_INFO_COLUMNS)
+            )),
+        DaoType::ListCommitOpsBetweenVersions =>
+            "select distinct(commit_op)
+            from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT and version between $3::INT and $4::INT",
+        DaoType::ListPartitionVersionByTableIdAndPartitionDescAndVersionRange =>
+            return Cow::Owned(format!(
+                "select {}
+                from partition_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT and version >= $3::INT and version <= $4::INT",
+                select_list("", "partition_info", PARTITION_INFO_COLUMNS)
+            )),
+        // the highest version committed up to the timestamp wins, so skewed or equal timestamps never hide a newer version
+        DaoType::ListPartitionSnapshotByTableIdAndTimestamp =>
+            return Cow::Owned(format!(
+                "select distinct on (partition_desc) {}
+                from partition_info
+                where table_id = $1::TEXT and timestamp <= $2::BIGINT
+                order by partition_desc, version desc",
+                select_list("", "partition_info", PARTITION_INFO_COLUMNS)
+            )),
+        DaoType::ListOperationLogByTableId =>
+            return Cow::Owned(format!(
+                "select {}
+                from partition_info
+                where table_id = $1::TEXT
+                order by timestamp desc, version desc
+                limit $2::BIGINT",
+                select_list("", "partition_info", PARTITION_INFO_COLUMNS)
+            )),
+        DaoType::ListFileStatsByTableIdAndPartitionDescAndVersion =>
+            "select s.commit_id, s.path, s.stats
+            from partition_info p
+            join file_stats s on s.commit_id = ANY(p.snapshot)
+            where p.table_id = $1::TEXT and p.partition_desc = $2::TEXT and p.version = $3::INT
+            order by s.commit_id, s.path",
+        // commits of a version are the ones its snapshot adds to the previous version of the partition,
+        // over the same versions as ListOperationLogByTableId
+        DaoType::ListOperationLogProvenanceByTableId =>
+            "select c.commit_id, c.table_id, c.partition_desc, c.engine, c.job_id, c.user_name, c.checkpoint_id, p.version
+            from (
+                select partition_desc, version, snapshot,
+                    lag(snapshot) over (partition by partition_desc order by version) as previous_snapshot
+                from partition_info
+                where table_id = $1::TEXT
+                order by timestamp desc, version desc
+                limit $2::BIGINT
+            ) p
+            join commit_provenance c
+                on c.table_id = $1::TEXT and c.partition_desc = p.partition_desc and c.commit_id = ANY(p.snapshot)
+                and not c.commit_id = ANY(coalesce(p.previous_snapshot, '{}'::UUID[]))
+            order by p.partition_desc, p.version, c.commit_id",
+        DaoType::ListPartitionByTableIdAndTag =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_tag t
+                cross join lateral unnest(t.partition_descs, t.versions) as v(partition_desc, version)
+                join partition_info p
+                    on p.table_id = t.table_id and p.partition_desc = v.partition_desc and p.version = v.version
+                where t.table_id = $1::TEXT and t.tag = $2::TEXT
+                order by p.partition_desc",
+                select_list("p", "partition_info", PARTITION_INFO_COLUMNS)
+            )),
+        DaoType::ListEmptyTableInfoByNamespace =>
+            return Cow::Owned(format!(
+                "select {}
+                from table_info t
+                where ($1::TEXT = '' or t.table_namespace = $1::TEXT)
+                    and not exists(select 1 from partition_info p where p.table_id = t.table_id)
+                order by table_namespace, table_name",
+                select_list("t", "table_info", TABLE_INFO_COLUMNS)
+            )),
+        // the value is all that follows `$2=` in the desc, so values holding '=' are kept whole
+        DaoType::ListPartitionValueByTableIdAndColumn =>
+            "select distinct substr(kv, length($2::TEXT) + 2) as value
+            from (
+                select distinct string_to_array(partition_desc, ',') as kvs
+                from partition_info
+                where table_id = $1::TEXT
+            ) p
+            cross join lateral unnest(p.kvs) kv
+            where left(kv, length($2::TEXT) + 1) = $2::TEXT || '=' and p.kvs @> $3::TEXT[]
+            order by value",
+        DaoType::ListTableTagByTableId =>
+            "select table_id, tag, timestamp
+            from table_tag
+            where table_id = $1::TEXT
+            order by timestamp, tag",
+        DaoType::ListPartitionByTableIdAndFilter =>
+            return Cow::Owned(latest_partition_statement(
+                &select_list("", "partition_info", PARTITION_INFO_COLUMNS),
+                "order by partition_desc",
+            )),
+
+        // Select DataCommitInfo
+        DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId =>
+            return Cow::Owned(format!(
+                "select {}
+                from data_commit_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT and commit_id = $3::UUID",
+                select_list("", "data_commit_info", DATA_COMMIT_INFO_COLUMNS)
+            )),
+
+
+        // Insert
+        DaoType::InsertNamespace =>
+            return Cow::Owned(column_fallbacks.insert(
+                "namespace",
+                NAMESPACE_COLUMNS,
+                &["$1::TEXT", "$2::JSON", "$3::TEXT", "$4::TEXT"],
+            )),
+        DaoType::InsertTableInfo =>
+            return Cow::Owned(column_fallbacks.insert(
+                "table_info",
+                TABLE_INFO_COLUMNS,
+                &["$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT", "$5::JSON", "$6::TEXT", "$7::TEXT", "$8::TEXT"],
+            )),
+        DaoType::InsertTableNameId =>
+            return Cow::Owned(column_fallbacks.insert(
+                "table_name_id",
+                &["table_id", "table_name", "table_namespace", "domain"],
+                &["$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT"],
+            )),
+        DaoType::InsertTablePathId =>
+            return Cow::Owned(column_fallbacks.insert(
+                "table_path_id",
+                &["table_id", "table_path", "table_namespace", "domain"],
+                &["$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT"],
+            )),
+        DaoType::InsertPartitionInfo =>
+            return Cow::Owned(column_fallbacks.insert(
+                "partition_info",
+                &[
+                    "table_id",
+                    "partition_desc",
+                    "version",
+                    "commit_op",
+                    "snapshot",
+                    "expression",
+                    "domain",
+                    "change_tracking",
+                ],
+                &["$1::TEXT", "$2::TEXT", "$3::INT", "$4::TEXT", "$5::_UUID", "$6::TEXT", "$7::TEXT", "$8::BOOL"],
+            )),
+        DaoType::InsertDataCommitInfo => return Cow::Owned(insert_data_commit_info_statement(column_fallbacks)),
+
+        // Query Scalar
+        DaoType::GetLatestTimestampFromPartitionInfo =>
+            "select max(timestamp) as timestamp
+            from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT",
+        DaoType::GetLatestTimestampFromPartitionInfoWithoutPartitionDesc =>
+            "select max(timestamp) as timestamp
+            from partition_info
+            where table_id = $1::TEXT",
+        DaoType::GetLatestVersionUpToTimeFromPartitionInfo =>
+            "select max(version) as version
+            from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp < $3::BIGINT",
+        DaoType::GetLatestVersionTimestampUpToTimeFromPartitionInfo =>
+            "select max(timestamp) as timestamp
+            from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp < $3::BIGINT",
+        DaoType::GetDbTimestamp =>
+            "select (date_part('epoch', now()) * 1000)::BIGINT as timestamp",
+        DaoType::CountPartitionByTableIdAndFilter =>
+            return Cow::Owned(latest_partition_statement("count(*)", "")),
+        DaoType::GetTagPartitionCountByTableIdAndTag =>
+            "select cardinality(partition_descs)::BIGINT
+            from table_tag
+            where table_id = $1::TEXT and tag = $2::TEXT",
+        DaoType::GetTablePropertyByTableIdAndKey =>
+            return Cow::Owned(format!(
+                "select {}->>$2::TEXT
+                from table_info
+                where table_id = $1::TEXT",
+                column_fallbacks.column("", "table_info", "properties")
+            )),
+        DaoType::PartitionExistsByTableIdAndPartitionDesc =>
+            "select exists(
+                select 1
+                from partition_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT)",
+        DaoType::PartitionIsEmptyByTableIdAndPartitionDesc =>
+            "select not exists(
+                select 1
+                from (
+                    select snapshot
+                    from partition_info
+                    where table_id = $1::TEXT and partition_desc = $2::TEXT
+                    order by version desc
+                    limit 1
+                ) p
+                join data_commit_info d
+                on d.table_id = $1::TEXT and d.partition_desc = $2::TEXT and d.commit_id = any(p.snapshot)
+                where exists(select 1 from unnest(d.file_ops) f where f.file_op = 'add'))",
+        DaoType::GetTableSchemaByTableIdAndVersion =>
+            "select coalesce(h.table_schema, t.table_schema)
+            from table_info t
+            join (
+                select max(timestamp) as timestamp
+                from partition_info
+                where table_id = $1::TEXT and version = $2::INT
+                having count(*) > 0
+            ) v on true
+            left join lateral (
+                select table_schema
+                from table_schema_history
+                where table_id = t.table_id and replaced_at > v.timestamp
+                order by version
+                limit 1
+            ) h on true
+            where t.table_id = $1::TEXT",
+
+        // Update / Delete
+        DaoType::DeleteNamespaceByNamespace =>
+            "delete from namespace
+            where namespace = $1::TEXT ",
+        DaoType::UpdateNamespacePropertiesByNamespace =>
+            "update namespace
+            set properties = $2::JSON where namespace = $1::TEXT",
+
+        DaoType::DeleteTableNameIdByTableNameAndNamespace =>
+            "delete from table_name_id
+            where table_name = $1::TEXT and table_namespace = $2::TEXT",
+        DaoType::DeleteTableNameIdByTableId =>
+            "delete from table_name_id
+            where table_id = $1::TEXT",
+
+        DaoType::DeleteTableInfoByIdAndPath =>
+            "delete from table_info
+            where table_id = $1::TEXT and table_path = $2::TEXT",
+        DaoType::UpdateTableInfoPropertiesById =>
+            "update table_info
+            set properties = $2::JSON where table_id = $1::TEXT",
+
+        DaoType::DeleteTablePathIdByTablePath =>
+            "delete from table_path_id
+            where table_path = $1::TEXT ",
+        DaoType::DeleteTablePathIdByTableId =>
+            "delete from table_path_id
+            where table_id = $1::TEXT ",
+
+        DaoType::DeleteOneDataCommitInfoByTableIdAndPartitionDescAndCommitId =>
+            "delete from data_commit_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT and commit_id = $3::UUID ",
+        DaoType::DeleteDataCommitInfoByTableIdAndPartitionDesc =>
+            "delete from data_commit_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT",
+        DaoType::DeleteDataCommitInfoByTableId =>
+            "delete from data_commit_info
+            where table_id = $1::TEXT",
+
+        DaoType::DeletePartitionInfoByTableId =>
+            "delete from partition_info
+            where table_id = $1::TEXT",
+        DaoType::DeletePartitionInfoByTableIdAndPartitionDesc =>
+            "delete from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT",
+        DaoType::DeletePreviousVersionPartition =>
+            "delete from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp <= $3::BIGINT",
+
+        DaoType::DeletePartitionStatsByTableId =>
+            "delete from partition_stats
+            where table_id = $1::TEXT",
+        DaoType::DeleteFileStatsByTableId =>
+            "delete from file_stats
+            where commit_id in (select commit_id from data_commit_info where table_id = $1::TEXT)",
+        DaoType::DeleteCommitProvenanceByTableId =>
+            "delete from commit_provenance
+            where table_id = $1::TEXT",
+        // the latest version of each partition at the time of tagging, in a single statement
+        DaoType::InsertTableTagByTableIdAndTag =>
+            "insert into table_tag(table_id, tag, partition_descs, versions, timestamp)
+            select $1::TEXT, $2::TEXT,
+                coalesce(array_agg(partition_desc order by partition_desc), '{}'),
+                coalesce(array_agg(version order by partition_desc), '{}'),
+                (date_part('epoch', now()) * 1000)::BIGINT
+            from (
+                select partition_desc, max(version) as version
+                from partition_info
+                where table_id = $1::TEXT
+                group by partition_desc
+            ) p",
+        DaoType::DeleteTableTagByTableId =>
+            "delete from table_tag
+            where table_id = $1::TEXT",
+        DaoType::DeleteTableTagByTableIdAndTag =>
+            "delete from table_tag
+            where table_id = $1::TEXT and tag = $2::TEXT",
+        DaoType::DeleteTableSchemaHistoryByTableId =>
+            "delete from table_schema_history
+            where table_id = $1::TEXT",
+        DaoType::DeleteCommitSchemaVersionByTableId =>
+            "delete from commit_schema_version
+            where table_id = $1::TEXT",
+        // drop the commits without a data_commit_info row from the snapshots, keeping the order of the others
+        DaoType::RemoveDanglingSnapshotCommitByTableIdAndPartitionDesc =>
+            "update partition_info p
+            set snapshot = array(
+                select s.commit_id
+                from unnest(p.snapshot) with ordinality as s(commit_id, position)
+                where exists(
+                    select 1 from data_commit_info d
+                    where d.table_id = p.table_id and d.partition_desc = p.partition_desc
+                        and d.commit_id = s.commit_id)
+                order by s.position)
+            where p.table_id = $1::TEXT and p.partition_desc = $2::TEXT
+                and exists(
+                    select 1 from unnest(p.snapshot) s(commit_id)
+                    where not exists(
+                        select 1 from data_commit_info d
+                        where d.table_id = p.table_id and d.partition_desc = p.partition_desc
+                            and d.commit_id = s.commit_id))",
+        DaoType::RebuildPartitionStatsByTableId =>
+            return Cow::Owned(format!(
+                "insert into partition_stats(table_id, partition_desc, file_count, total_bytes, last_commit_ts)
+                select p.table_id, p.partition_desc, count(f.path), coalesce(sum(f.size), 0)::BIGINT, max(p.timestamp)
+                from (
+                    select distinct on (partition_desc) table_id, partition_desc, snapshot, timestamp
+                    from partition_info
+                    where table_id = $1::TEXT
+                    order by partition_desc, version desc
+                ) p
+                left join lateral ({}) f on true
+                group by p.table_id, p.partition_desc
+                on conflict (table_id, partition_desc) do update
+                set file_count = excluded.file_count, total_bytes = excluded.total_bytes,
+                    last_commit_ts = excluded.last_commit_ts",
+                live_files_of_snapshot("p.table_id", "p.partition_desc", "p.snapshot")
+            )),
+
+
+        // not prepared
+        DaoType::UpdateTableInfoById |
+        DaoType::UpdateTableNamespaceByTableId |
+        DaoType::TruncateTableByTableId |
+        DaoType::TransactionInsertDataCommitInfo |
+        DaoType::TransactionInsertPartitionInfo |
+        DaoType::TransactionInsertPartitionInfoWithStats |
+        DaoType::TransactionInsertFileStats |
+        DaoType::TransactionInsertCommitProvenance |
+        DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList |
+        DaoType::ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList |
+        DaoType::DeleteDataCommitInfoByTableIdAndPartitionDescAndCommitIdList |
+        DaoType::ListPartitionDescByTableIdAndParList => "",
+
+        /* _ => todo!(), */
+    })
+}
+
 async fn get_prepared_statement(
     client: &Client,
     prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
     dao_type: &DaoType,
 ) -> Result<Statement> {
-    if let Some(statement) = prepared.get(dao_type) {
+    if let Some(statement) = prepared.get(dao_type).filter(|_| context.statement_comment.is_none()) {
         Ok(statement.clone())
     } else {
         let result = {
-            let statement = match dao_type {
-                // Select Namespace
-                DaoType::SelectNamespaceByNamespace =>
-                    "select namespace, properties, comment, domain
-                    from namespace
-                    where namespace = $1::TEXT",
-                DaoType::ListNamespaces =>
-                    "select namespace, properties, comment, domain
-                    from namespace",
-
-                // Select TablePathId
-                DaoType::SelectTablePathIdByTablePath =>
-                    "select table_path, table_id, table_namespace, domain
-                    from table_path_id
-                    where table_path = $1::TEXT",
-                DaoType::ListAllTablePath =>
-                    "select table_path, table_id, table_namespace, domain
-                    from table_path_id",
-                DaoType::ListAllPathTablePathByNamespace =>
-                    "select table_path
-                    from table_path_id
-                    where table_namespace = $1::TEXT ",
-
-                // Select TableNameId
-                DaoType::SelectTableNameIdByTableName =>
-                    "select table_name, table_id, table_namespace, domain
-                    from table_name_id
-                    where table_name = $1::TEXT and table_namespace = $2::TEXT",
-                DaoType::ListTableNameByNamespace =>
-                    "select table_name, table_id, table_namespace, domain
-                    from table_name_id
-                    where table_namespace = $1::TEXT",
-
-                // Select TableInfo
-                DaoType::SelectTableInfoByTableId =>
-                    "select table_id, table_name, table_path, table_schema, properties, partitions, table_namespace, domain
-                    from table_info
-                    where table_id = $1::TEXT",
-                DaoType::SelectTableInfoByTableNameAndNameSpace =>
-                    "select table_id, table_name, table_path, table_schema, properties, partitions, table_namespace, domain
-                    from table_info
-                    where table_name = $1::TEXT and table_namespace=$2::TEXT",
-                DaoType::SelectTableInfoByTablePath =>
-                    "select table_id, table_name, table_path, table_schema, properties, partitions, table_namespace, domain
-                    from table_info
-                    where table_path = $1::TEXT",
-                DaoType::SelectTableInfoByIdAndTablePath =>
-                    "select table_id, table_name, table_path, table_schema, properties, partitions, table_namespace, domain
-                    from table_info
-                    where table_id = $1::TEXT and table_path=$2::TEXT",
-
-                // Select PartitionInfo
-                DaoType::SelectPartitionVersionByTableIdAndDescAndVersion =>
-                    "select table_id, partition_desc, version, commit_op, snapshot, expression, domain
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and version = $3::INT",
-                DaoType::SelectOnePartitionVersionByTableIdAndDesc =>
-                    "select m.table_id, t.partition_desc, m.version, m.commit_op, m.snapshot, m.expression, m.domain from (
-                        select table_id,partition_desc,max(version) from partition_info
-                        where table_id = $1::TEXT and partition_desc = $2::TEXT group by table_id, partition_desc) t
-                        left join partition_info m on t.table_id = m.table_id
-                        and t.partition_desc = m.partition_desc and t.max = m.version",
-                DaoType::ListPartitionByTableIdAndDesc =>
-                    "select table_id, partition_desc, version, commit_op, snapshot, timestamp, expression, domain
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT ",
-                DaoType::ListPartitionByTableId =>
-                    "select m.table_id, t.partition_desc, m.version, m.commit_op, m.snapshot, m.expression, m.domain
-                    from (
-                        select table_id,partition_desc,max(version)
-                        from partition_info
-                        where table_id = $1::TEXT
-                        group by table_id,partition_desc) t
-                    left join partition_info m
-                    on t.table_id = m.table_id and t.partition_desc = m.partition_desc and t.max = m.version",
-                DaoType::ListPartitionVersionByTableIdAndPartitionDescAndTimestampRange =>
-                    "select table_id, partition_desc, version, commit_op, snapshot, timestamp, expression, domain
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp >= $3::BIGINT and timestamp < $4::BIGINT",
-                DaoType::ListCommitOpsBetweenVersions =>
-                    "select distinct(commit_op)
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and version between $3::INT and $4::INT",
-                DaoType::ListPartitionVersionByTableIdAndPartitionDescAndVersionRange =>
-                    "select table_id, partition_desc, version, commit_op, snapshot, timestamp, expression, domain
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and version >= $3::INT and version <= $4::INT",
-
-                // Select DataCommitInfo
-                DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId =>
-                    "select table_id, partition_desc, commit_id, file_ops, commit_op, timestamp, committed, domain
-                    from data_commit_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and commit_id = $3::UUID",
-
-
-                // Insert
-                DaoType::InsertNamespace =>
-                    "insert into namespace(
-                        namespace,
-                        properties,
-                        comment,
-                        domain)
-                    values($1::TEXT, $2::JSON, $3::TEXT, $4::TEXT)",
-                DaoType::InsertTableInfo =>
-                    "insert into table_info(
-                        table_id,
-                        table_name,
-                        table_path,
-                        table_schema,
-                        properties,
-                        partitions,
-                        table_namespace,
-                        domain)
-                    values($1::TEXT, $2::TEXT, $3::TEXT, $4::TEXT, $5::JSON, $6::TEXT, $7::TEXT, $8::TEXT)",
-                DaoType::InsertTableNameId =>
-                    "insert into table_name_id(
-                        table_id,
-                        table_name,
-                        table_namespace,
-                        domain)
-                    values($1::TEXT, $2::TEXT, $3::TEXT, $4::TEXT)",
-                DaoType::InsertTablePathId =>
-                    "insert into table_path_id(
-                        table_id,
-                        table_path,
-                        table_namespace,
-                        domain)
-                    values($1::TEXT, $2::TEXT, $3::TEXT, $4::TEXT)",
-                DaoType::InsertPartitionInfo =>
-                    "insert into partition_info(
-                        table_id,
-                        partition_desc,
-                        version,
-                        commit_op,
-                        snapshot,
-                        expression,
-                        domain
-                    )
-                    values($1::TEXT, $2::TEXT, $3::INT, $4::TEXT, $5::_UUID, $6::TEXT, $7::TEXT)",
-                DaoType::InsertDataCommitInfo =>
-                    "insert into data_commit_info(
-                        table_id,
-                        partition_desc,
-                        commit_id,
-                        file_ops,
-                        commit_op,
-                        timestamp,
-                        committed,
-                        domain
-                    )
-                    values($1::TEXT, $2::TEXT, $3::UUID, $4::_data_file_op, $5::TEXT, $6::BIGINT, $7::BOOL, $8::TEXT)",
-
-                // Query Scalar
-                DaoType::GetLatestTimestampFromPartitionInfo =>
-                    "select max(timestamp) as timestamp
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT",
-                DaoType::GetLatestTimestampFromPartitionInfoWithoutPartitionDesc =>
-                    "select max(timestamp) as timestamp
-                    from partition_info
-                    where table_id = $1::TEXT",
-                DaoType::GetLatestVersionUpToTimeFromPartitionInfo =>
-                    "select max(version) as version
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp < $3::BIGINT",
-                DaoType::GetLatestVersionTimestampUpToTimeFromPartitionInfo =>
-                    "select max(timestamp) as timestamp
-                    from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp < $3::BIGINT",
-
-                // Update / Delete
-                DaoType::DeleteNamespaceByNamespace =>
-                    "delete from namespace
-                    where namespace = $1::TEXT ",
-                DaoType::UpdateNamespacePropertiesByNamespace =>
-                    "update namespace
-                    set properties = $2::JSON where namespace = $1::TEXT",
-
-                DaoType::DeleteTableNameIdByTableNameAndNamespace =>
-                    "delete from table_name_id
-                    where table_name = $1::TEXT and table_namespace = $2::TEXT",
-                DaoType::DeleteTableNameIdByTableId =>
-                    "delete from table_name_id
-                    where table_id = $1::TEXT",
-
-                DaoType::DeleteTableInfoByIdAndPath =>
-                    "delete from table_info
-                    where table_id = $1::TEXT and table_path = $2::TEXT",
-                DaoType::UpdateTableInfoPropertiesById =>
-                    "update table_info
-                    set properties = $2::JSON where table_id = $1::TEXT",
-
-                DaoType::DeleteTablePathIdByTablePath =>
-                    "delete from table_path_id
-                    where table_path = $1::TEXT ",
-                DaoType::DeleteTablePathIdByTableId =>
-                    "delete from table_path_id
-                    where table_id = $1::TEXT ",
-
-                DaoType::DeleteOneDataCommitInfoByTableIdAndPartitionDescAndCommitId =>
-                    "delete from data_commit_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and commit_id = $3::UUID ",
-                DaoType::DeleteDataCommitInfoByTableIdAndPartitionDesc =>
-                    "delete from data_commit_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT",
-                DaoType::DeleteDataCommitInfoByTableId =>
-                    "delete from data_commit_info
-                    where table_id = $1::TEXT",
-
-                DaoType::DeletePartitionInfoByTableId =>
-                    "delete from partition_info
-                    where table_id = $1::TEXT",
-                DaoType::DeletePartitionInfoByTableIdAndPartitionDesc =>
-                    "delete from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT",
-                DaoType::DeletePreviousVersionPartition =>
-                    "delete from partition_info
-                    where table_id = $1::TEXT and partition_desc = $2::TEXT and timestamp <= $3::BIGINT",
-
-
-                // not prepared
-                DaoType::UpdateTableInfoById |
-                DaoType::TransactionInsertDataCommitInfo |
-                DaoType::TransactionInsertPartitionInfo |
-                DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList |
-                DaoType::DeleteDataCommitInfoByTableIdAndPartitionDescAndCommitIdList |
-                DaoType::ListPartitionDescByTableIdAndParList => "",
-
-                /* _ => todo!(), */
-            };
-            client.prepare(statement).await
+            let statement = dao_statement(dao_type, &context.column_fallbacks);
+            if let Some(comment) = &context.statement_comment {
+                return Ok(client.prepare(&format!("/* {} */ {}", comment, statement)).await?);
+            }
+            client.prepare(&statement).await
         };
         match result {
             Ok(statement) => {
@@ -406,13 +1113,54 @@ async fn get_prepared_statement(
     }
 }
 
+/// Prepare the statements of all DAOs not prepared yet, returning how many were prepared. A statement failing to
+/// prepare, e.g. on a table missing from an outdated metadata database, is left to fail when executed.
+pub async fn prepare_all_statements(client: &Client, prepared: &mut PreparedStatementMap) -> Result<usize> {
+    prepare_all_statements_with_context(client, prepared, &StatementContext::default()).await
+}
+
+pub(crate) async fn prepare_all_statements_with_context(
+    client: &Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+) -> Result<usize> {
+    let mut count = 0;
+    for dao_type in (DAO_TYPE_QUERY_ONE_OFFSET..DAO_TYPE_UPDATE_OFFSET + 100)
+        .filter_map(|dao_type| DaoType::try_from(dao_type).ok())
+    {
+        let statement = dao_statement(&dao_type, &context.column_fallbacks);
+        if statement.is_empty() || prepared.get(&dao_type).is_some() {
+            continue;
+        }
+        match client.prepare(&statement).await {
+            Ok(statement) => {
+                prepared.insert(dao_type, statement);
+                count += 1;
+            }
+            Err(err) if client.is_closed() => return Err(err.into()),
+            Err(err) => tracing::debug!("statement of {:?} not prepared: {}", dao_type, err),
+        }
+    }
+    Ok(count)
+}
+
+/// The `partition_descs, committed_after, commit_op` params of a partition filter, None when unset.
+type PartitionFilterParams = (Option<Vec<String>>, Option<i64>, Option<String>);
+
+/// Decode the `partition_descs, committed_after, commit_op` params of a partition filter, empty meaning unset.
+fn get_partition_filter_params(params: &[String]) -> Result<PartitionFilterParams> {
+    let partition_descs = (!params[0].is_empty()).then(|| decode_partition_desc_list(&params[0]));
+    let committed_after = if params[1].is_empty() {
+        None
+    } else {
+        Some(i64::from_str(&params[1])?)
+    };
+    let commit_op = (!params[2].is_empty()).then(|| params[2].clone());
+    Ok((partition_descs, committed_after, commit_op))
+}
+
 fn get_params(joined_string: String) -> Vec<String> {
-    joined_string
-        .split(PARAM_DELIM)
-        .collect::<Vec<&str>>()
-        .into_iter()
-        .map(|s| s.to_string())
-        .collect::<Vec<String>>()
+    decode_query_params(&joined_string)
 }
 
 fn separate_uuid(concated_uuid: &str) -> Result<Vec<String>> {
@@ -433,35 +1181,60 @@ pub async fn execute_query(
     prepared: &mut PreparedStatementMap,
     query_type: i32,
     joined_string: String,
+) -> Result<Vec<u8>> {
+    execute_query_with_limit(client, prepared, query_type, joined_string, None).await
+}
+
+/// [`execute_query`] failing with ResultTooLarge once the rows fetched, or the encoded result, exceed
+/// `max_result_bytes`. The rows are fetched one by one, so that the query is aborted before the whole result is
+/// held in memory.
+pub async fn execute_query_with_limit(
+    client: &Client,
+    prepared: &mut PreparedStatementMap,
+    query_type: i32,
+    joined_string: String,
+    max_result_bytes: Option<usize>,
+) -> Result<Vec<u8>> {
+    execute_query_with_context(
+        client,
+        prepared,
+        &StatementContext::default(),
+        query_type,
+        joined_string,
+        max_result_bytes,
+    )
+    .await
+}
+
+pub(crate) async fn execute_query_with_context(
+    client: &Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+    query_type: i32,
+    joined_string: String,
+    max_result_bytes: Option<usize>,
 ) -> Result<Vec<u8>> {
     if query_type >= DAO_TYPE_INSERT_ONE_OFFSET {
         eprintln!("Invalid query_type_index: {:?}", query_type);
         return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
     }
     let query_type = DaoType::try_from(query_type).map_err(|e| LakeSoulMetaDataError::Other(Box::new(e)))?;
-    let statement = get_prepared_statement(client, prepared, &query_type).await?;
+    let statement = get_prepared_statement(client, prepared, context, &query_type).await?;
 
     let params = get_params(joined_string);
 
     let rows = match query_type {
         DaoType::ListNamespaces | DaoType::ListAllTablePath if params.len() == 1 && params[0].is_empty() => {
-            let result = client.query(&statement, &[]).await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            query_rows(client, &statement, &[], max_result_bytes).await?
         }
         DaoType::ListTableNameByNamespace if params.len() == 1 => {
-            let result = client.query(&statement, &[&params[0]]).await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            query_rows(client, &statement, &[&params[0]], max_result_bytes).await?
         }
         DaoType::SelectNamespaceByNamespace
         | DaoType::SelectTableInfoByTableId
         | DaoType::SelectTablePathIdByTablePath
         | DaoType::SelectTableInfoByTablePath
+        | DaoType::SelectTableNameIdByTableId
             if params.len() == 1 =>
         {
             let result = client.query_opt(&statement, &[&params[0]]).await;
@@ -471,25 +1244,45 @@ pub async fn execute_query(
                 Err(e) => return Err(LakeSoulMetaDataError::from(e)),
             }
         }
-        DaoType::ListPartitionByTableId | DaoType::ListAllPathTablePathByNamespace if params.len() == 1 => {
-            let result = client.query(&statement, &[&params[0]]).await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+        DaoType::ListPartitionByTableId
+        | DaoType::ListAllPathTablePathByNamespace
+        | DaoType::ListTableTagByTableId
+        | DaoType::ListEmptyTableInfoByNamespace
+            if params.len() == 1 =>
+        {
+            query_rows(client, &statement, &[&params[0]], max_result_bytes).await?
+        }
+        DaoType::ListPartitionValueByTableIdAndColumn if params.len() == 3 => {
+            let filters = match params[2].as_str() {
+                "" => vec![],
+                filters => decode_partition_desc_list(filters),
+            };
+            query_rows(
+                client,
+                &statement,
+                &[&params[0], &params[1], &filters],
+                max_result_bytes,
+            )
+            .await?
         }
-        DaoType::SelectOnePartitionVersionByTableIdAndDesc | DaoType::ListPartitionByTableIdAndDesc
+        DaoType::ListTableNameIdByTableIds => query_rows(client, &statement, &[&params], max_result_bytes).await?,
+        // params alternate table names and namespaces
+        DaoType::ListTableInfoByTableNamesAndNamespaces if params.len().is_multiple_of(2) => {
+            let table_names = params.iter().step_by(2).collect::<Vec<_>>();
+            let namespaces = params.iter().skip(1).step_by(2).collect::<Vec<_>>();
+            query_rows(client, &statement, &[&table_names, &namespaces], max_result_bytes).await?
+        }
+        DaoType::SelectOnePartitionVersionByTableIdAndDesc
+        | DaoType::ListPartitionByTableIdAndDesc
+        | DaoType::ListPartitionByTableIdAndTag
             if params.len() == 2 =>
         {
-            let result = client.query(&statement, &[&params[0], &params[1]]).await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            query_rows(client, &statement, &[&params[0], &params[1]], max_result_bytes).await?
         }
         DaoType::SelectTableNameIdByTableName
         | DaoType::SelectTableInfoByTableNameAndNameSpace
         | DaoType::SelectTableInfoByIdAndTablePath
+        | DaoType::SelectPartitionStatsByTableIdAndPartitionDesc
             if params.len() == 2 =>
         {
             let result = client.query_opt(&statement, &[&params[0], &params[1]]).await;
@@ -513,103 +1306,112 @@ pub async fn execute_query(
             }
         }
         DaoType::SelectPartitionVersionByTableIdAndDescAndVersion if params.len() == 3 => {
-            let result = client
-                .query(&statement, &[&params[0], &params[1], &i32::from_str(&params[2])?])
-                .await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            query_rows(
+                client,
+                &statement,
+                &[&params[0], &params[1], &i32::from_str(&params[2])?],
+                max_result_bytes,
+            )
+            .await?
         }
         DaoType::ListCommitOpsBetweenVersions
         | DaoType::ListPartitionVersionByTableIdAndPartitionDescAndVersionRange
             if params.len() == 4 =>
         {
-            let result = client
-                .query(
-                    &statement,
-                    &[
-                        &params[0],
-                        &params[1],
-                        &i32::from_str(&params[2])?,
-                        &i32::from_str(&params[3])?,
-                    ],
-                )
-                .await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            query_rows(
+                client,
+                &statement,
+                &[
+                    &params[0],
+                    &params[1],
+                    &i32::from_str(&params[2])?,
+                    &i32::from_str(&params[3])?,
+                ],
+                max_result_bytes,
+            )
+            .await?
         }
         DaoType::ListPartitionDescByTableIdAndParList if params.len() == 2 => {
             let partitions = "'".to_owned()
-                + &params[1]
-                    .replace('\'', "''")
-                    .split(PARTITION_DESC_DELIM)
-                    .collect::<Vec<&str>>()
+                + &decode_partition_desc_list(&params[1])
+                    .iter()
+                    .map(|partition_desc| partition_desc.replace('\'', "''"))
+                    .collect::<Vec<String>>()
                     .join("','")
                 + "'";
-            let statement = format!("select m.table_id, t.partition_desc, m.version, m.commit_op, m.snapshot, m.expression, m.domain from (
+            let statement = format!("select m.table_id, t.partition_desc, m.version, m.commit_op, m.snapshot, m.expression, {} from (
                 select table_id,partition_desc,max(version) from partition_info
                 where table_id = $1::TEXT and partition_desc in ({})
                 group by table_id,partition_desc) t
-                left join partition_info m on t.table_id = m.table_id and t.partition_desc = m.partition_desc and t.max = m.version", partitions);
-            let result = {
-                let statement = client.prepare(&statement).await?;
-                client.query(&statement, &[&params[0]]).await
-            };
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+                left join partition_info m on t.table_id = m.table_id and t.partition_desc = m.partition_desc and t.max = m.version",
+                context.column_fallbacks.select_list("m", "partition_info", &["domain"]), partitions);
+            let statement = client.prepare(&statement).await?;
+            query_rows(client, &statement, &[&params[0]], max_result_bytes).await?
         }
         DaoType::ListPartitionVersionByTableIdAndPartitionDescAndTimestampRange if params.len() == 4 => {
-            let result = client
-                .query(
-                    &statement,
-                    &[
-                        &params[0],
-                        &params[1],
-                        &i64::from_str(&params[2])?,
-                        &i64::from_str(&params[3])?,
-                    ],
-                )
-                .await;
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            query_rows(
+                client,
+                &statement,
+                &[
+                    &params[0],
+                    &params[1],
+                    &i64::from_str(&params[2])?,
+                    &i64::from_str(&params[3])?,
+                ],
+                max_result_bytes,
+            )
+            .await?
         }
-        DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList if params.len() == 3 => {
+        DaoType::ListPartitionSnapshotByTableIdAndTimestamp
+        | DaoType::ListOperationLogByTableId
+        | DaoType::ListOperationLogProvenanceByTableId
+            if params.len() == 2 =>
+        {
+            query_rows(
+                client,
+                &statement,
+                &[&params[0], &i64::from_str(&params[1])?],
+                max_result_bytes,
+            )
+            .await?
+        }
+        DaoType::ListPartitionByTableIdAndFilter if params.len() == 4 => {
+            let (partition_descs, committed_after, commit_op) = get_partition_filter_params(&params[1..])?;
+            query_rows(
+                client,
+                &statement,
+                &[&params[0], &partition_descs, &committed_after, &commit_op],
+                max_result_bytes,
+            )
+            .await?
+        }
+        DaoType::ListFileStatsByTableIdAndPartitionDescAndVersion if params.len() == 3 => {
+            query_rows(
+                client,
+                &statement,
+                &[&params[0], &params[1], &i32::from_str(&params[2])?],
+                max_result_bytes,
+            )
+            .await?
+        }
+        DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList
+        | DaoType::ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList
+            if params.len() == 3 =>
+        {
             let concated_uuid = &params[2];
-            if concated_uuid.len() % 32 != 0 {
+            if !concated_uuid.len().is_multiple_of(32) {
                 eprintln!("Invalid params of query_type={:?}, params={:?}", query_type, params);
                 return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
             }
 
-            let uuid_list = separate_uuid(concated_uuid)?;
-
-            let uuid_str_list = "'".to_owned() + &uuid_list.join("','") + "'";
-
-            let uuid_list_str = uuid_list.join("");
-
-            let statement = format!(
-                "select table_id, partition_desc, commit_id, file_ops, commit_op, timestamp, committed, domain
-                from data_commit_info
-                where table_id = $1::TEXT and partition_desc = $2::TEXT
-                and commit_id in ({})
-                order by position(commit_id::text in '{}')",
-                uuid_str_list, uuid_list_str
-            );
+            let statement = data_commit_info_by_commit_list_statement(
+                &context.column_fallbacks,
+                concated_uuid,
+                query_type == DaoType::ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList,
+            )?;
 
-            let result = {
-                let statement = client.prepare(&statement).await?;
-                client.query(&statement, &[&params[0], &params[1]]).await
-            };
-            match result {
-                Ok(rows) => rows,
-                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
-            }
+            let statement = client.prepare(&statement).await?;
+            query_rows(client, &statement, &[&params[0], &params[1]], max_result_bytes).await?
         }
         _ => {
             eprintln!("Invalid params num of query_type={:?}, params={:?}", query_type, params);
@@ -617,38 +1419,7 @@ pub async fn execute_query(
         }
     };
 
-    let result_type = match query_type {
-        DaoType::SelectNamespaceByNamespace | DaoType::ListNamespaces => ResultType::Namespace,
-
-        DaoType::SelectTableInfoByTableId
-        | DaoType::SelectTableInfoByTableNameAndNameSpace
-        | DaoType::SelectTableInfoByTablePath
-        | DaoType::SelectTableInfoByIdAndTablePath => ResultType::TableInfo,
-
-        DaoType::SelectTablePathIdByTablePath | DaoType::ListAllTablePath => ResultType::TablePathId,
-
-        DaoType::SelectTableNameIdByTableName | DaoType::ListTableNameByNamespace => ResultType::TableNameId,
-
-        DaoType::ListPartitionByTableId
-        | DaoType::ListPartitionDescByTableIdAndParList
-        | DaoType::SelectPartitionVersionByTableIdAndDescAndVersion
-        | DaoType::SelectOnePartitionVersionByTableIdAndDesc => ResultType::PartitionInfoWithoutTimestamp,
-
-        DaoType::ListPartitionByTableIdAndDesc
-        | DaoType::ListPartitionVersionByTableIdAndPartitionDescAndTimestampRange
-        | DaoType::ListPartitionVersionByTableIdAndPartitionDescAndVersionRange => ResultType::PartitionInfo,
-
-        DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId
-        | DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList => ResultType::DataCommitInfo,
-
-        DaoType::ListAllPathTablePathByNamespace => ResultType::TablePathIdWithOnlyPath,
-
-        DaoType::ListCommitOpsBetweenVersions => ResultType::PartitionInfoWithOnlyCommitOp,
-        _ => {
-            eprintln!("Invalid query_type={:?} when parsing query result type", query_type);
-            return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
-        }
-    };
+    let result_type = get_result_type(&query_type)?;
 
     let wrapper = match result_type {
         ResultType::TableNameId => {
@@ -696,34 +1467,14 @@ pub async fn execute_query(
         }
 
         ResultType::Namespace => {
-            let namespace: Vec<entity::Namespace> = rows
-                .iter()
-                .map(|row| entity::Namespace {
-                    namespace: row.get(0),
-                    properties: row.get::<_, serde_json::Value>(1).to_string(),
-                    comment: row.get::<_, Option<String>>(2).unwrap_or(String::from("")),
-                    domain: row.get(3),
-                })
-                .collect();
+            let namespace: Vec<entity::Namespace> = rows.iter().map(row_to_namespace).collect();
             entity::JniWrapper {
                 namespace,
                 ..Default::default()
             }
         }
         ResultType::TableInfo => {
-            let table_info: Vec<entity::TableInfo> = rows
-                .iter()
-                .map(|row| entity::TableInfo {
-                    table_id: row.get(0),
-                    table_name: row.get(1),
-                    table_path: row.get(2),
-                    table_schema: row.get(3),
-                    properties: row.get::<_, serde_json::Value>(4).to_string(),
-                    partitions: row.get(5),
-                    table_namespace: row.get(6),
-                    domain: row.get(7),
-                })
-                .collect();
+            let table_info: Vec<entity::TableInfo> = rows.iter().map(row_to_table_info).collect();
             entity::JniWrapper {
                 table_info,
                 ..Default::default()
@@ -732,20 +1483,7 @@ pub async fn execute_query(
         ResultType::PartitionInfo => {
             let partition_info: Vec<entity::PartitionInfo> = rows
                 .iter()
-                .map(|row| {
-                    Ok(entity::PartitionInfo {
-                        table_id: row.get(0),
-                        partition_desc: row.get(1),
-                        version: row.get::<_, i32>(2),
-                        commit_op: entity::CommitOp::from_str_name(row.get(3))
-                            .ok_or(LakeSoulMetaDataError::Internal("unknown commit_op".into()))?
-                            as i32,
-                        snapshot: row_to_uuid_list(row),
-                        timestamp: row.get::<_, i64>(5),
-                        expression: row.get::<_, Option<String>>(6).unwrap_or(String::from("")),
-                        domain: row.get(7),
-                    })
-                })
+                .map(row_to_partition_info)
                 .collect::<Result<Vec<entity::PartitionInfo>>>()?;
             entity::JniWrapper {
                 partition_info,
@@ -756,20 +1494,7 @@ pub async fn execute_query(
         ResultType::PartitionInfoWithoutTimestamp => {
             let partition_info: Vec<entity::PartitionInfo> = rows
                 .iter()
-                .map(|row| {
-                    Ok(entity::PartitionInfo {
-                        table_id: row.get(0),
-                        partition_desc: row.get(1),
-                        version: row.get::<_, i32>(2),
-                        commit_op: entity::CommitOp::from_str_name(row.get(3))
-                            .ok_or(LakeSoulMetaDataError::Internal("unknown commit_op".into()))?
-                            as i32,
-                        snapshot: row_to_uuid_list(row),
-                        expression: row.get::<_, Option<String>>(5).unwrap_or(String::from("")),
-                        domain: row.get(6),
-                        ..Default::default()
-                    })
-                })
+                .map(row_to_partition_info_without_timestamp)
                 .collect::<Result<Vec<entity::PartitionInfo>>>()?;
             entity::JniWrapper {
                 partition_info,
@@ -781,9 +1506,7 @@ pub async fn execute_query(
                 .iter()
                 .map(|row| {
                     Ok(entity::PartitionInfo {
-                        commit_op: entity::CommitOp::from_str_name(row.get(0))
-                            .ok_or(LakeSoulMetaDataError::Internal("unknown commit_op".into()))?
-                            as i32,
+                        commit_op: decode_commit_op(row.get(0)),
                         ..Default::default()
                     })
                 })
@@ -793,52 +1516,703 @@ pub async fn execute_query(
                 ..Default::default()
             }
         }
-        ResultType::DataCommitInfo => {
-            let data_commit_info: Vec<entity::DataCommitInfo> = rows
+        ResultType::PartitionInfoWithOnlyDesc => {
+            let partition_info: Vec<entity::PartitionInfo> = rows
                 .iter()
-                .map(|row| {
-                    Ok(entity::DataCommitInfo {
-                        table_id: row.get(0),
-                        partition_desc: row.get(1),
-                        commit_id: {
-                            let (high, low) = row.get::<_, uuid::Uuid>(2).as_u64_pair();
-                            Some(entity::Uuid { high, low })
-                        },
-                        file_ops: row
-                            .get::<_, Vec<DataFileOp>>(3)
-                            .iter()
-                            .map(|data_file_op| data_file_op.as_proto_data_file_op())
-                            .collect::<Result<Vec<entity::DataFileOp>>>()?,
-                        commit_op: entity::CommitOp::from_str_name(row.get(4))
-                            .ok_or(LakeSoulMetaDataError::Internal("unknown commit_op".into()))?
-                            as i32,
-                        timestamp: row.get(5),
-                        committed: row.get(6),
-                        domain: row.get(7),
-                    })
+                .map(|row| entity::PartitionInfo {
+                    partition_desc: row.get(0),
+                    ..Default::default()
                 })
-                .collect::<Result<Vec<entity::DataCommitInfo>>>()?;
+                .collect();
             entity::JniWrapper {
-                data_commit_info,
+                partition_info,
                 ..Default::default()
             }
         }
-    };
-    Ok(wrapper.encode_to_vec())
-}
-
-pub async fn execute_insert(
-    client: &mut Client,
-    prepared: &mut PreparedStatementMap,
-    insert_type: i32,
-    wrapper: entity::JniWrapper,
+        ResultType::PartitionStats => {
+            let partition_stats: Vec<entity::PartitionStats> = rows
+                .iter()
+                .map(|row| entity::PartitionStats {
+                    table_id: row.get(0),
+                    partition_desc: row.get(1),
+                    file_count: row.get(2),
+                    total_bytes: row.get(3),
+                    last_commit_ts: row.get(4),
+                })
+                .collect();
+            entity::JniWrapper {
+                partition_stats,
+                ..Default::default()
+            }
+        }
+        ResultType::DataCommitInfo => {
+            let data_commit_info: Vec<entity::DataCommitInfo> = rows
+                .iter()
+                .map(row_to_data_commit_info)
+                .collect::<Result<Vec<entity::DataCommitInfo>>>()?;
+            entity::JniWrapper {
+                data_commit_info,
+                ..Default::default()
+            }
+        }
+        ResultType::DataCommitInfoWithStats => {
+            let data_commit_info: Vec<entity::DataCommitInfo> = rows
+                .iter()
+                .map(row_to_data_commit_info_with_stats)
+                .collect::<Result<Vec<entity::DataCommitInfo>>>()?;
+            entity::JniWrapper {
+                data_commit_info,
+                ..Default::default()
+            }
+        }
+        ResultType::FileStats => entity::JniWrapper {
+            file_stats: rows.iter().map(row_to_file_stats).collect(),
+            ..Default::default()
+        },
+        ResultType::CommitProvenance => entity::JniWrapper {
+            commit_provenance: rows.iter().map(row_to_commit_provenance).collect(),
+            ..Default::default()
+        },
+        ResultType::TableTag => entity::JniWrapper {
+            table_tag: rows
+                .iter()
+                .map(|row| entity::TableTag {
+                    table_id: row.get(0),
+                    tag: row.get(1),
+                    timestamp: row.get(2),
+                })
+                .collect(),
+            ..Default::default()
+        },
+    };
+    check_result_size(wrapper.encoded_len(), max_result_bytes)?;
+    Ok(wrapper.encode_to_vec())
+}
+
+/// Rows of the query, failing with ResultTooLarge as soon as their size exceeds `max_result_bytes`.
+async fn query_rows(
+    client: &Client,
+    statement: &Statement,
+    params: &[&(dyn ToSql + Sync)],
+    max_result_bytes: Option<usize>,
+) -> Result<Vec<Row>> {
+    let Some(max_result_bytes) = max_result_bytes else {
+        return Ok(client.query(statement, params).await?);
+    };
+    let stream = client
+        .query_raw(statement, params.iter().map(|param| *param as &dyn ToSql))
+        .await?;
+    futures::pin_mut!(stream);
+    let mut rows = Vec::new();
+    let mut size = 0;
+    while let Some(row) = stream.next().await {
+        let row = row?;
+        size += row_size(&row);
+        // dropping the stream stops the query
+        check_result_size(size, Some(max_result_bytes))?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Size of a column value as sent by the server, whatever its type.
+struct RawSize(usize);
+
+impl<'a> FromSql<'a> for RawSize {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> std::result::Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawSize(raw.len()))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+fn row_size(row: &Row) -> usize {
+    (0..row.len())
+        .filter_map(|idx| row.try_get::<_, Option<RawSize>>(idx).ok().flatten())
+        .map(|size| size.0)
+        .sum()
+}
+
+fn check_result_size(size: usize, max_result_bytes: Option<usize>) -> Result<()> {
+    match max_result_bytes {
+        Some(max_result_bytes) if size > max_result_bytes => Err(LakeSoulMetaDataError::ResultTooLarge(format!(
+            "result exceeds max_result_bytes={}",
+            max_result_bytes
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn get_result_type(query_type: &DaoType) -> Result<ResultType> {
+    Ok(match query_type {
+        DaoType::SelectNamespaceByNamespace | DaoType::ListNamespaces => ResultType::Namespace,
+
+        DaoType::SelectTableInfoByTableId
+        | DaoType::SelectTableInfoByTableNameAndNameSpace
+        | DaoType::SelectTableInfoByTablePath
+        | DaoType::SelectTableInfoByIdAndTablePath
+        | DaoType::ListEmptyTableInfoByNamespace
+        | DaoType::ListTableInfoByTableNamesAndNamespaces => ResultType::TableInfo,
+
+        DaoType::SelectTablePathIdByTablePath | DaoType::ListAllTablePath => ResultType::TablePathId,
+
+        DaoType::SelectTableNameIdByTableName
+        | DaoType::SelectTableNameIdByTableId
+        | DaoType::ListTableNameByNamespace
+        | DaoType::ListTableNameIdByTableIds => ResultType::TableNameId,
+
+        DaoType::ListPartitionByTableId
+        | DaoType::ListPartitionDescByTableIdAndParList
+        | DaoType::SelectPartitionVersionByTableIdAndDescAndVersion
+        | DaoType::SelectOnePartitionVersionByTableIdAndDesc => ResultType::PartitionInfoWithoutTimestamp,
+
+        DaoType::ListPartitionByTableIdAndDesc
+        | DaoType::ListPartitionVersionByTableIdAndPartitionDescAndTimestampRange
+        | DaoType::ListPartitionVersionByTableIdAndPartitionDescAndVersionRange
+        | DaoType::ListPartitionSnapshotByTableIdAndTimestamp
+        | DaoType::ListOperationLogByTableId
+        | DaoType::ListPartitionByTableIdAndFilter
+        | DaoType::ListPartitionByTableIdAndTag => ResultType::PartitionInfo,
+
+        DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId
+        | DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList => ResultType::DataCommitInfo,
+
+        DaoType::ListAllPathTablePathByNamespace => ResultType::TablePathIdWithOnlyPath,
+
+        DaoType::ListCommitOpsBetweenVersions => ResultType::PartitionInfoWithOnlyCommitOp,
+
+        DaoType::ListPartitionValueByTableIdAndColumn => ResultType::PartitionInfoWithOnlyDesc,
+
+        DaoType::SelectPartitionStatsByTableIdAndPartitionDesc => ResultType::PartitionStats,
+
+        DaoType::ListFileStatsByTableIdAndPartitionDescAndVersion => ResultType::FileStats,
+        DaoType::ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList => {
+            ResultType::DataCommitInfoWithStats
+        }
+        DaoType::ListOperationLogProvenanceByTableId => ResultType::CommitProvenance,
+        DaoType::ListTableTagByTableId => ResultType::TableTag,
+        _ => {
+            eprintln!("Invalid query_type={:?} when parsing query result type", query_type);
+            return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
+        }
+    })
+}
+
+/// With `with_stats`, a ninth column holds the registered file stats of each commit as a json object keyed by path.
+fn data_commit_info_by_commit_list_statement(
+    column_fallbacks: &ColumnFallbacks,
+    concated_uuid: &str,
+    with_stats: bool,
+) -> Result<String> {
+    let uuid_list = separate_uuid(concated_uuid)?;
+
+    let uuid_str_list = "'".to_owned() + &uuid_list.join("','") + "'";
+
+    let uuid_list_str = uuid_list.join("");
+
+    let stats_column = if with_stats {
+        ", (select json_object_agg(s.path, s.stats) from file_stats s where s.commit_id = data_commit_info.commit_id)"
+    } else {
+        ""
+    };
+
+    Ok(format!(
+        "select {}{}
+        from data_commit_info
+        where table_id = $1::TEXT and partition_desc = $2::TEXT
+        and commit_id in ({})
+        order by position(commit_id::text in '{}')",
+        column_fallbacks.select_list("", "data_commit_info", DATA_COMMIT_INFO_COLUMNS),
+        stats_column,
+        uuid_str_list,
+        uuid_list_str
+    ))
+}
+
+fn row_to_namespace(row: &Row) -> entity::Namespace {
+    entity::Namespace {
+        namespace: row.get(0),
+        properties: row.get::<_, serde_json::Value>(1).to_string(),
+        comment: row.get::<_, Option<String>>(2).unwrap_or(String::from("")),
+        domain: row.get(3),
+    }
+}
+
+fn row_to_table_info(row: &Row) -> entity::TableInfo {
+    entity::TableInfo {
+        table_id: row.get(0),
+        table_name: row.get(1),
+        table_path: row.get(2),
+        table_schema: row.get(3),
+        properties: row.get::<_, serde_json::Value>(4).to_string(),
+        partitions: row.get(5),
+        table_namespace: row.get(6),
+        domain: row.get(7),
+    }
+}
+
+fn row_to_partition_info(row: &Row) -> Result<entity::PartitionInfo> {
+    Ok(entity::PartitionInfo {
+        table_id: row.get(0),
+        partition_desc: row.get(1),
+        version: row.get::<_, i32>(2),
+        commit_op: decode_commit_op(row.get(3)),
+        snapshot: row_to_uuid_list(row),
+        timestamp: row.get::<_, i64>(5),
+        expression: row.get::<_, Option<String>>(6).unwrap_or(String::from("")),
+        domain: row.get(7),
+        change_tracking: row.get::<_, Option<bool>>(8).unwrap_or(false),
+    })
+}
+
+fn row_to_partition_info_without_timestamp(row: &Row) -> Result<entity::PartitionInfo> {
+    Ok(entity::PartitionInfo {
+        table_id: row.get(0),
+        partition_desc: row.get(1),
+        version: row.get::<_, i32>(2),
+        commit_op: decode_commit_op(row.get(3)),
+        snapshot: row_to_uuid_list(row),
+        expression: row.get::<_, Option<String>>(5).unwrap_or(String::from("")),
+        domain: row.get(6),
+        ..Default::default()
+    })
+}
+
+/// Column stats are stored as `{"column": {"min": ..., "max": ..., "null_count": ...}}`.
+fn column_stats_to_json(column_stats: &HashMap<String, entity::ColumnStats>) -> serde_json::Value {
+    serde_json::Value::Object(
+        column_stats
+            .iter()
+            .map(|(column, stats)| {
+                (
+                    column.clone(),
+                    serde_json::json!({"min": stats.min, "max": stats.max, "null_count": stats.null_count}),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Stats are only used for skipping, so malformed entries are dropped instead of failing the read.
+fn column_stats_from_json(value: &serde_json::Value) -> HashMap<String, entity::ColumnStats> {
+    value
+        .as_object()
+        .map(|columns| {
+            columns
+                .iter()
+                .filter_map(|(column, stats)| {
+                    Some((
+                        column.clone(),
+                        entity::ColumnStats {
+                            min: stats.get("min")?.as_str()?.to_string(),
+                            max: stats.get("max")?.as_str()?.to_string(),
+                            null_count: stats.get("null_count")?.as_i64()?,
+                        },
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn row_to_file_stats(row: &Row) -> entity::FileStats {
+    let (high, low) = row.get::<_, uuid::Uuid>(0).as_u64_pair();
+    entity::FileStats {
+        commit_id: Some(entity::Uuid { high, low }),
+        path: row.get(1),
+        column_stats: row
+            .get::<_, Option<serde_json::Value>>(2)
+            .map(|stats| column_stats_from_json(&stats))
+            .unwrap_or_default(),
+    }
+}
+
+fn row_to_commit_provenance(row: &Row) -> entity::CommitProvenance {
+    let (high, low) = row.get::<_, uuid::Uuid>(0).as_u64_pair();
+    entity::CommitProvenance {
+        commit_id: Some(entity::Uuid { high, low }),
+        table_id: row.get(1),
+        partition_desc: row.get(2),
+        engine: row.get::<_, Option<String>>(3).unwrap_or_default(),
+        job_id: row.get::<_, Option<String>>(4).unwrap_or_default(),
+        user: row.get::<_, Option<String>>(5).unwrap_or_default(),
+        checkpoint_id: row.get::<_, Option<String>>(6).unwrap_or_default(),
+        version: row.get(7),
+    }
+}
+
+fn row_to_data_commit_info_with_stats(row: &Row) -> Result<entity::DataCommitInfo> {
+    let mut data_commit_info = row_to_data_commit_info(row)?;
+    if let Some(file_stats) = row.get::<_, Option<serde_json::Value>>(8) {
+        for file_op in data_commit_info.file_ops.iter_mut() {
+            if let Some(stats) = file_stats.get(&file_op.path) {
+                file_op.column_stats = column_stats_from_json(stats);
+            }
+        }
+    }
+    Ok(data_commit_info)
+}
+
+fn row_to_data_commit_info(row: &Row) -> Result<entity::DataCommitInfo> {
+    Ok(entity::DataCommitInfo {
+        table_id: row.get(0),
+        partition_desc: row.get(1),
+        commit_id: {
+            let (high, low) = row.get::<_, uuid::Uuid>(2).as_u64_pair();
+            Some(entity::Uuid { high, low })
+        },
+        file_ops: row
+            .get::<_, Vec<DataFileOp>>(3)
+            .iter()
+            .map(|data_file_op| data_file_op.as_proto_data_file_op())
+            .collect::<Result<Vec<entity::DataFileOp>>>()?,
+        commit_op: decode_commit_op(row.get(4)),
+        timestamp: row.get(5),
+        committed: row.get(6),
+        domain: row.get(7),
+    })
+}
+
+/// Item decoded from a single row by [`execute_query_streamed`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamedEntity {
+    PartitionInfo(entity::PartitionInfo),
+    DataCommitInfo(entity::DataCommitInfo),
+}
+
+pub type StreamedEntityStream = BoxStream<'static, Result<StreamedEntity>>;
+
+/// Like [`execute_query`], but decodes PartitionInfo/DataCommitInfo row by row as they arrive from postgres,
+/// without materializing the whole result as an encoded JniWrapper first.
+/// Only partition and data commit listings are supported.
+pub async fn execute_query_streamed(
+    client: &Client,
+    prepared: &mut PreparedStatementMap,
+    query_type: i32,
+    joined_string: String,
+) -> Result<StreamedEntityStream> {
+    execute_query_streamed_with_context(
+        client,
+        prepared,
+        &StatementContext::default(),
+        query_type,
+        joined_string,
+    )
+    .await
+}
+
+pub(crate) async fn execute_query_streamed_with_context(
+    client: &Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+    query_type: i32,
+    joined_string: String,
+) -> Result<StreamedEntityStream> {
+    if query_type >= DAO_TYPE_INSERT_ONE_OFFSET {
+        eprintln!("Invalid query_type_index: {:?}", query_type);
+        return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
+    }
+    let query_type = DaoType::try_from(query_type).map_err(|e| LakeSoulMetaDataError::Other(Box::new(e)))?;
+    let params = get_params(joined_string);
+
+    let (statement, query_params): (Statement, Vec<Box<dyn ToSql + Sync + Send>>) = match query_type {
+        DaoType::ListPartitionByTableId if params.len() == 1 => (
+            get_prepared_statement(client, prepared, context, &query_type).await?,
+            vec![Box::new(params[0].clone())],
+        ),
+        DaoType::ListPartitionByTableIdAndDesc if params.len() == 2 => (
+            get_prepared_statement(client, prepared, context, &query_type).await?,
+            vec![Box::new(params[0].clone()), Box::new(params[1].clone())],
+        ),
+        DaoType::ListPartitionSnapshotByTableIdAndTimestamp if params.len() == 2 => (
+            get_prepared_statement(client, prepared, context, &query_type).await?,
+            vec![Box::new(params[0].clone()), Box::new(i64::from_str(&params[1])?)],
+        ),
+        DaoType::ListPartitionVersionByTableIdAndPartitionDescAndVersionRange if params.len() == 4 => (
+            get_prepared_statement(client, prepared, context, &query_type).await?,
+            vec![
+                Box::new(params[0].clone()),
+                Box::new(params[1].clone()),
+                Box::new(i32::from_str(&params[2])?),
+                Box::new(i32::from_str(&params[3])?),
+            ],
+        ),
+        DaoType::ListPartitionVersionByTableIdAndPartitionDescAndTimestampRange if params.len() == 4 => (
+            get_prepared_statement(client, prepared, context, &query_type).await?,
+            vec![
+                Box::new(params[0].clone()),
+                Box::new(params[1].clone()),
+                Box::new(i64::from_str(&params[2])?),
+                Box::new(i64::from_str(&params[3])?),
+            ],
+        ),
+        DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList if params.len() == 3 => {
+            if !params[2].len().is_multiple_of(32) {
+                eprintln!("Invalid params of query_type={:?}, params={:?}", query_type, params);
+                return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
+            }
+            let statement = data_commit_info_by_commit_list_statement(&context.column_fallbacks, &params[2], false)?;
+            (
+                client.prepare(&statement).await?,
+                vec![Box::new(params[0].clone()), Box::new(params[1].clone())],
+            )
+        }
+        _ => {
+            eprintln!("Invalid params num of query_type={:?}, params={:?}", query_type, params);
+            return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
+        }
+    };
+    let result_type = get_result_type(&query_type)?;
+    let rows = client.query_raw(&statement, query_params).await?;
+
+    Ok(rows
+        .map(move |row| {
+            let row = row?;
+            match result_type {
+                ResultType::PartitionInfo => Ok(StreamedEntity::PartitionInfo(row_to_partition_info(&row)?)),
+                ResultType::PartitionInfoWithoutTimestamp => Ok(StreamedEntity::PartitionInfo(
+                    row_to_partition_info_without_timestamp(&row)?,
+                )),
+                ResultType::DataCommitInfo => Ok(StreamedEntity::DataCommitInfo(row_to_data_commit_info(&row)?)),
+                _ => Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput)),
+            }
+        })
+        .boxed())
+}
+
+/// Each insert type reads exactly one repeated field of the JniWrapper, anything else populated would be silently dropped.
+fn check_insert_wrapper(insert_type: &DaoType, wrapper: &entity::JniWrapper) -> Result<()> {
+    let (expected, allow_empty) = match insert_type {
+        DaoType::InsertNamespace => ("namespace", false),
+        DaoType::InsertTableInfo => ("table_info", false),
+        DaoType::InsertTableNameId => ("table_name_id", false),
+        DaoType::InsertTablePathId => ("table_path_id", false),
+        DaoType::InsertPartitionInfo => ("partition_info", false),
+        DaoType::InsertDataCommitInfo => ("data_commit_info", false),
+        DaoType::TransactionInsertPartitionInfo | DaoType::TransactionInsertPartitionInfoWithStats => {
+            ("partition_info", true)
+        }
+        DaoType::TransactionInsertDataCommitInfo => ("data_commit_info", true),
+        DaoType::TransactionInsertFileStats => ("file_stats", true),
+        DaoType::TransactionInsertCommitProvenance => ("commit_provenance", true),
+        _ => return Ok(()),
+    };
+    let populated = [
+        ("namespace", wrapper.namespace.is_empty()),
+        ("table_info", wrapper.table_info.is_empty()),
+        ("table_path_id", wrapper.table_path_id.is_empty()),
+        ("table_name_id", wrapper.table_name_id.is_empty()),
+        ("partition_info", wrapper.partition_info.is_empty()),
+        ("data_commit_info", wrapper.data_commit_info.is_empty()),
+        ("partition_stats", wrapper.partition_stats.is_empty()),
+        ("file_stats", wrapper.file_stats.is_empty()),
+        ("commit_provenance", wrapper.commit_provenance.is_empty()),
+    ]
+    .into_iter()
+    .filter_map(|(field, is_empty)| if is_empty { None } else { Some(field) })
+    .collect::<Vec<&str>>();
+    if populated.iter().any(|field| *field != expected) || (!allow_empty && populated.is_empty()) {
+        return Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "{:?} expects only field '{}' of JniWrapper to be populated, but got {:?}",
+            insert_type, expected, populated
+        )));
+    }
+    Ok(())
+}
+
+/// Insert one data commit by the statement of [`insert_data_commit_info_statement`].
+async fn insert_data_commit_info_row(
+    client: &impl GenericClient,
+    statement: &Statement,
+    data_commit_info: &entity::DataCommitInfo,
+    server_side_timestamp: bool,
+) -> Result<u64> {
+    let file_ops = data_commit_info
+        .file_ops
+        .iter()
+        .map(DataFileOp::from_proto_data_file_op)
+        .collect::<Result<Vec<DataFileOp>>>()?;
+    let commit_id = data_commit_info
+        .commit_id
+        .as_ref()
+        .ok_or(LakeSoulMetaDataError::Internal("commit_id missing".into()))?;
+    let _uuid = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low);
+
+    Ok(client
+        .execute(
+            statement,
+            &[
+                &data_commit_info.table_id,
+                &data_commit_info.partition_desc,
+                &_uuid,
+                &file_ops,
+                &encode_commit_op(data_commit_info.commit_op)?,
+                &data_commit_info.timestamp,
+                &data_commit_info.committed,
+                &data_commit_info.domain,
+                &server_side_timestamp,
+            ],
+        )
+        .await?)
+}
+
+/// Upsert the column stats of files of their data commits within `transaction`.
+async fn insert_file_stats(transaction: &Transaction<'_>, file_stats_list: &[entity::FileStats]) -> Result<()> {
+    // nothing to prepare, also for a database without the stats table
+    if file_stats_list.is_empty() {
+        return Ok(());
+    }
+    let statement = transaction
+        .prepare(
+            "insert into file_stats(commit_id, path, stats)
+            values($1::UUID, $2::TEXT, $3::JSON)
+            on conflict (commit_id, path) do update set stats = excluded.stats",
+        )
+        .await?;
+    for file_stats in file_stats_list {
+        let commit_id = file_stats
+            .commit_id
+            .as_ref()
+            .ok_or(LakeSoulMetaDataError::InvalidInput("commit_id missing".to_string()))?;
+        transaction
+            .execute(
+                &statement,
+                &[
+                    &uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low),
+                    &file_stats.path,
+                    &column_stats_to_json(&file_stats.column_stats),
+                ],
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Insert the provenance of data commits within `transaction`.
+async fn insert_commit_provenance(
+    transaction: &Transaction<'_>,
+    provenance_list: &[entity::CommitProvenance],
+) -> Result<()> {
+    if provenance_list.is_empty() {
+        return Ok(());
+    }
+    // the provenance of a commit is written once, a retried commit keeps the original writer
+    let statement = transaction
+        .prepare(
+            "insert into commit_provenance(commit_id, table_id, partition_desc, engine, job_id, user_name, checkpoint_id)
+            values($1::UUID, $2::TEXT, $3::TEXT, $4::TEXT, $5::TEXT, $6::TEXT, $7::TEXT)
+            on conflict (table_id, commit_id) do nothing",
+        )
+        .await?;
+    for provenance in provenance_list {
+        let commit_id = provenance
+            .commit_id
+            .as_ref()
+            .ok_or(LakeSoulMetaDataError::InvalidInput("commit_id missing".to_string()))?;
+        transaction
+            .execute(
+                &statement,
+                &[
+                    &uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low),
+                    &provenance.table_id,
+                    &provenance.partition_desc,
+                    &provenance.engine,
+                    &provenance.job_id,
+                    &provenance.user,
+                    &provenance.checkpoint_id,
+                ],
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Insert a data commit along with the column stats of its files and its provenance in one transaction, so that a
+/// data commit is never left behind without them, to be committed as it is by a retry.
+pub(crate) async fn insert_data_commit_info_with_stats(
+    client: &mut Client,
+    context: &StatementContext,
+    data_commit_info: &entity::DataCommitInfo,
+    file_stats: &[entity::FileStats],
+    commit_provenance: &[entity::CommitProvenance],
+    server_side_timestamp: bool,
+) -> Result<u64> {
+    let transaction = client.transaction().await?;
+    let statement = transaction
+        .prepare(&insert_data_commit_info_statement(&context.column_fallbacks))
+        .await?;
+    let inserted =
+        insert_data_commit_info_row(&transaction, &statement, data_commit_info, server_side_timestamp).await?;
+    insert_file_stats(&transaction, file_stats).await?;
+    insert_commit_provenance(&transaction, commit_provenance).await?;
+    transaction.commit().await?;
+    Ok(inserted)
+}
+
+/// Insert with the timestamps of DataCommitInfo taken from the database clock, as [`MetaDataClient`] does by default.
+/// See [`execute_insert_with_timestamp_mode`] for the legacy client-clock mode keeping the supplied timestamps.
+pub async fn execute_insert(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    insert_type: i32,
+    wrapper: entity::JniWrapper,
+) -> Result<i32> {
+    execute_insert_with_timestamp_mode(client, prepared, insert_type, wrapper, true).await
+}
+
+/// When `server_side_timestamp` is set, the timestamp of inserted DataCommitInfo is `now()` of the database instead of
+/// the value supplied by the client, so that clock skew between writers cannot reorder the commit history.
+/// PartitionInfo timestamps are filled by the database clock on the database side.
+pub async fn execute_insert_with_timestamp_mode(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    insert_type: i32,
+    wrapper: entity::JniWrapper,
+    server_side_timestamp: bool,
+) -> Result<i32> {
+    execute_insert_with_commit_timestamp(client, prepared, insert_type, wrapper, server_side_timestamp, None).await
+}
+
+/// Like [`execute_insert_with_timestamp_mode`], but PartitionInfo versions inserted in a transaction are stamped with
+/// `commit_timestamp` when it is given. The caller is responsible for validating it against the database clock.
+pub async fn execute_insert_with_commit_timestamp(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    insert_type: i32,
+    wrapper: entity::JniWrapper,
+    server_side_timestamp: bool,
+    commit_timestamp: Option<i64>,
+) -> Result<i32> {
+    execute_insert_with_context(
+        client,
+        prepared,
+        &StatementContext::default(),
+        insert_type,
+        wrapper,
+        server_side_timestamp,
+        commit_timestamp,
+    )
+    .await
+}
+
+pub(crate) async fn execute_insert_with_context(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+    insert_type: i32,
+    wrapper: entity::JniWrapper,
+    server_side_timestamp: bool,
+    commit_timestamp: Option<i64>,
 ) -> Result<i32> {
     if !(DAO_TYPE_INSERT_ONE_OFFSET..DAO_TYPE_QUERY_SCALAR_OFFSET).contains(&insert_type) {
         eprintln!("Invalid insert_type_index: {:?}", insert_type);
         return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
     }
     let insert_type = DaoType::try_from(insert_type).map_err(|e| LakeSoulMetaDataError::Other(Box::new(e)))?;
-    let statement = get_prepared_statement(client, prepared, &insert_type).await?;
+    check_insert_wrapper(&insert_type, &wrapper)?;
+    let statement = get_prepared_statement(client, prepared, context, &insert_type).await?;
 
     let result = match insert_type {
         DaoType::InsertNamespace if wrapper.namespace.len() == 1 => {
@@ -912,60 +2286,43 @@ pub async fn execute_insert(
                         &partition_info.table_id,
                         &partition_info.partition_desc,
                         &partition_info.version,
-                        &partition_info.commit_op().as_str_name(),
+                        &encode_commit_op(partition_info.commit_op)?,
                         &snapshot,
                         &partition_info.expression,
                         &partition_info.domain,
+                        &partition_info.change_tracking,
                     ],
                 )
                 .await
         }
         DaoType::InsertDataCommitInfo if wrapper.data_commit_info.len() == 1 => {
             let data_commit_info = wrapper.data_commit_info.first().unwrap();
-            let file_ops = data_commit_info
-                .file_ops
-                .iter()
-                .map(DataFileOp::from_proto_data_file_op)
-                .collect::<Result<Vec<DataFileOp>>>()?;
-            let commit_id = data_commit_info
-                .commit_id
-                .as_ref()
-                .ok_or(LakeSoulMetaDataError::Internal("commit_id missing".into()))?;
-            let _uuid = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low);
-
-            client
-                .execute(
-                    &statement,
-                    &[
-                        &data_commit_info.table_id,
-                        &data_commit_info.partition_desc,
-                        &_uuid,
-                        &file_ops,
-                        &data_commit_info.commit_op().as_str_name(),
-                        &data_commit_info.timestamp,
-                        &data_commit_info.committed,
-                        &data_commit_info.domain,
-                    ],
-                )
-                .await
+            Ok(insert_data_commit_info_row(&*client, &statement, data_commit_info, server_side_timestamp).await?)
         }
-        DaoType::TransactionInsertPartitionInfo => {
+        DaoType::TransactionInsertPartitionInfo | DaoType::TransactionInsertPartitionInfoWithStats => {
             let partition_info_list = wrapper.partition_info;
             let result = {
                 let transaction = client.transaction().await?;
+                let stats_statement = if insert_type == DaoType::TransactionInsertPartitionInfoWithStats {
+                    // file statistics of the new snapshot, upserted in the same transaction as the partition version
+                    let prepared = transaction.prepare(&upsert_partition_stats_of_snapshot()).await;
+                    match prepared {
+                        Ok(statement) => Some(statement),
+                        Err(e) => return Err(LakeSoulMetaDataError::from(e)),
+                    }
+                } else {
+                    None
+                };
+                if let Some(commit_timestamp) = commit_timestamp {
+                    check_commit_timestamp_order(&transaction, &partition_info_list, commit_timestamp).await?;
+                }
+                let commit_timestamp = if stats_statement.is_some() {
+                    Some(resolve_commit_timestamp(&transaction, commit_timestamp).await?)
+                } else {
+                    commit_timestamp
+                };
                 let prepared = transaction
-                    .prepare(
-                        "insert into partition_info(
-                        table_id,
-                        partition_desc,
-                        version,
-                        commit_op,
-                        snapshot,
-                        expression,
-                        domain
-                    )
-                    values($1::TEXT, $2::TEXT, $3::INT, $4::TEXT, $5::_UUID, $6::TEXT, $7::TEXT)",
-                    )
+                    .prepare(&insert_partition_info_statement(&context.column_fallbacks))
                     .await;
                 let statement = match prepared {
                     Ok(statement) => statement,
@@ -986,10 +2343,12 @@ pub async fn execute_insert(
                                 &partition_info.table_id,
                                 &partition_info.partition_desc,
                                 &partition_info.version,
-                                &partition_info.commit_op().as_str_name(),
+                                &encode_commit_op(partition_info.commit_op)?,
                                 &snapshot,
                                 &partition_info.expression,
                                 &partition_info.domain,
+                                &partition_info.change_tracking,
+                                &commit_timestamp,
                             ],
                         )
                         .await;
@@ -1018,6 +2377,26 @@ pub async fn execute_insert(
                             };
                         }
                     }
+
+                    if let Some(stats_statement) = &stats_statement {
+                        let result = transaction
+                            .execute(
+                                stats_statement,
+                                &[
+                                    &partition_info.table_id,
+                                    &partition_info.partition_desc,
+                                    &snapshot,
+                                    &commit_timestamp,
+                                ],
+                            )
+                            .await;
+
+                        // the partition version is not committed without its stats
+                        if let Err(e) = result {
+                            transaction.rollback().await?;
+                            return Err(LakeSoulMetaDataError::from(e));
+                        }
+                    }
                 }
                 match transaction.commit().await {
                     Ok(()) => Ok(partition_info_list.len() as u64),
@@ -1034,19 +2413,7 @@ pub async fn execute_insert(
             let result = {
                 let transaction = client.transaction().await?;
                 let prepared = transaction
-                    .prepare(
-                        "insert into data_commit_info(
-                        table_id,
-                        partition_desc,
-                        commit_id,
-                        file_ops,
-                        commit_op,
-                        timestamp,
-                        committed,
-                        domain
-                    )
-                    values($1::TEXT, $2::TEXT, $3::UUID, $4::_data_file_op, $5::TEXT, $6::BIGINT, $7::BOOL, $8::TEXT)",
-                    )
+                    .prepare(&insert_data_commit_info_statement(&context.column_fallbacks))
                     .await;
                 let statement = match prepared {
                     Ok(statement) => statement,
@@ -1073,10 +2440,11 @@ pub async fn execute_insert(
                                 &data_commit_info.partition_desc,
                                 &_uuid,
                                 &file_ops,
-                                &data_commit_info.commit_op().as_str_name(),
+                                &encode_commit_op(data_commit_info.commit_op)?,
                                 &data_commit_info.timestamp,
                                 &data_commit_info.committed,
                                 &data_commit_info.domain,
+                                &server_side_timestamp,
                             ],
                         )
                         .await;
@@ -1098,6 +2466,46 @@ pub async fn execute_insert(
                 Err(e) => return Err(LakeSoulMetaDataError::from(e)),
             }
         }
+        DaoType::TransactionInsertFileStats => {
+            let file_stats_list = wrapper.file_stats;
+            let result = {
+                let transaction = client.transaction().await?;
+                // no stats are registered if any fails, e.g. for a file of an unknown data commit
+                if let Err(e) = insert_file_stats(&transaction, &file_stats_list).await {
+                    transaction.rollback().await?;
+                    return Err(e);
+                }
+                match transaction.commit().await {
+                    Ok(()) => Ok(file_stats_list.len() as u64),
+                    Err(e) => Err(e),
+                }
+            };
+            match result {
+                Ok(count) => Ok(count),
+                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
+            }
+        }
+        DaoType::TransactionInsertCommitProvenance => {
+            let provenance_list = wrapper.commit_provenance;
+            let result = {
+                let transaction = client.transaction().await?;
+                if let Err(e) = insert_commit_provenance(&transaction, &provenance_list).await {
+                    eprintln!("transaction insert error, err = {:?}", e);
+                    return match transaction.rollback().await {
+                        Ok(()) => Ok(0i32),
+                        Err(e) => Err(LakeSoulMetaDataError::from(e)),
+                    };
+                }
+                match transaction.commit().await {
+                    Ok(()) => Ok(provenance_list.len() as u64),
+                    Err(e) => Err(e),
+                }
+            };
+            match result {
+                Ok(count) => Ok(count),
+                Err(e) => return Err(LakeSoulMetaDataError::from(e)),
+            }
+        }
         _ => {
             eprintln!("InvalidInput of type={:?}: {:?}", insert_type, wrapper);
             return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
@@ -1114,25 +2522,44 @@ pub async fn execute_update(
     prepared: &mut PreparedStatementMap,
     update_type: i32,
     joined_string: String,
+) -> Result<i32> {
+    execute_update_with_context(
+        client,
+        prepared,
+        &StatementContext::default(),
+        update_type,
+        joined_string,
+    )
+    .await
+}
+
+pub(crate) async fn execute_update_with_context(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+    update_type: i32,
+    joined_string: String,
 ) -> Result<i32> {
     if update_type < DAO_TYPE_UPDATE_OFFSET {
         eprintln!("Invalid update_type_index: {:?}", update_type);
         return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
     }
     let update_type = DaoType::try_from(update_type).map_err(|e| LakeSoulMetaDataError::Other(Box::new(e)))?;
-    let statement = get_prepared_statement(client, prepared, &update_type).await?;
+    let statement = get_prepared_statement(client, prepared, context, &update_type).await?;
 
-    let params = joined_string
-        .split(PARAM_DELIM)
-        .collect::<Vec<&str>>()
-        .iter()
-        .map(|str| str.to_string())
-        .collect::<Vec<String>>();
+    let params = get_params(joined_string);
 
     let result = match update_type {
         DaoType::DeleteNamespaceByNamespace
         | DaoType::DeletePartitionInfoByTableId
         | DaoType::DeleteDataCommitInfoByTableId
+        | DaoType::DeletePartitionStatsByTableId
+        | DaoType::RebuildPartitionStatsByTableId
+        | DaoType::DeleteFileStatsByTableId
+        | DaoType::DeleteCommitProvenanceByTableId
+        | DaoType::DeleteTableTagByTableId
+        | DaoType::DeleteTableSchemaHistoryByTableId
+        | DaoType::DeleteCommitSchemaVersionByTableId
         | DaoType::DeleteTableNameIdByTableId
         | DaoType::DeleteTablePathIdByTableId
         | DaoType::DeleteTablePathIdByTablePath
@@ -1144,10 +2571,23 @@ pub async fn execute_update(
         | DaoType::DeleteTableNameIdByTableNameAndNamespace
         | DaoType::DeletePartitionInfoByTableIdAndPartitionDesc
         | DaoType::DeleteDataCommitInfoByTableIdAndPartitionDesc
+        | DaoType::DeleteTableTagByTableIdAndTag
+        | DaoType::RemoveDanglingSnapshotCommitByTableIdAndPartitionDesc
             if params.len() == 2 =>
         {
             client.execute(&statement, &[&params[0], &params[1]]).await
         }
+        DaoType::InsertTableTagByTableIdAndTag if params.len() == 2 => {
+            match client.execute(&statement, &[&params[0], &params[1]]).await {
+                Err(e) if e.code() == Some(&SqlState::UNIQUE_VIOLATION) => {
+                    return Err(LakeSoulMetaDataError::AlreadyExists(format!(
+                        "Tag '{}' already exists for table '{}'",
+                        params[1], params[0]
+                    )))
+                }
+                result => result,
+            }
+        }
         DaoType::UpdateTableInfoPropertiesById | DaoType::UpdateNamespacePropertiesByNamespace if params.len() == 2 => {
             let properties: serde_json::Value = serde_json::from_str(&params[1])?;
             client.execute(&statement, &[&params[0], &properties]).await
@@ -1204,9 +2644,71 @@ pub async fn execute_update(
                 _ => todo!(),
             }
         }
+        DaoType::UpdateTableNamespaceByTableId if params.len() == 2 => {
+            let transaction = client.transaction().await?;
+            let table_name: String = match transaction
+                .query_opt(
+                    "select table_name from table_info where table_id = $1::TEXT",
+                    &[&params[0]],
+                )
+                .await?
+            {
+                Some(row) => row.get(0),
+                None => {
+                    return Err(LakeSoulMetaDataError::NotFound(format!(
+                        "Table '{}' not found",
+                        params[0]
+                    )))
+                }
+            };
+            let conflict = transaction
+                .query_opt(
+                    "select table_id from table_name_id
+                    where table_name = $1::TEXT and table_namespace = $2::TEXT and table_id <> $3::TEXT",
+                    &[&table_name, &params[1], &params[0]],
+                )
+                .await?;
+            if conflict.is_some() {
+                return Err(LakeSoulMetaDataError::AlreadyExists(format!(
+                    "Table '{}' already exists in namespace '{}'",
+                    table_name, params[1]
+                )));
+            }
+            let mut count = 0;
+            for statement in [
+                "update table_info set table_namespace = $2::TEXT where table_id = $1::TEXT",
+                "update table_name_id set table_namespace = $2::TEXT where table_id = $1::TEXT",
+                "update table_path_id set table_namespace = $2::TEXT where table_id = $1::TEXT",
+            ] {
+                count += transaction.execute(statement, &[&params[0], &params[1]]).await?;
+            }
+            transaction.commit().await.map(|_| count)
+        }
+        // data commits not committed yet belong to commits in flight, which add their partition versions later
+        DaoType::TruncateTableByTableId if params.len() == 1 => {
+            let transaction = client.transaction().await?;
+            if transaction
+                .query_opt(
+                    "select 1 from table_info where table_id = $1::TEXT for update",
+                    &[&params[0]],
+                )
+                .await?
+                .is_none()
+            {
+                return Err(LakeSoulMetaDataError::NotFound(format!(
+                    "Table '{}' not found",
+                    params[0]
+                )));
+            }
+            let mut count = 0;
+            for statement in table_data_delete_statements(&context.column_fallbacks) {
+                count += transaction.execute(statement, &[&params[0]]).await?;
+            }
+            transaction.commit().await.map(|_| count)
+        }
         DaoType::DeleteDataCommitInfoByTableIdAndPartitionDescAndCommitIdList if params.len() == 3 => {
             let concated_uuid = &params[2];
-            if concated_uuid.len() % 32 != 0 {
+            if !concated_uuid.len().is_multiple_of(32) {
                 eprintln!("Invalid params of update_type={:?}, params={:?}", update_type, params);
                 return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
             }
@@ -1235,69 +2737,195 @@ pub async fn execute_update(
     }
 }
 
-fn ts_string(res: Result<Option<Row>, Error>) -> Result<Option<String>> {
-    match res {
-        Ok(Some(row)) => {
-            let ts = row.get::<_, Option<i64>>(0);
-            match ts {
-                Some(ts) => Ok(Some(format!("{}", ts))),
-                None => Ok(None),
-            }
+/// Statements deleting the partition versions and committed data commits of table `$1` with their stats,
+/// provenance and tags, skipping the optional tables missing. The data commits of commits in flight are kept.
+fn table_data_delete_statements(column_fallbacks: &ColumnFallbacks) -> Vec<&'static str> {
+    let mut statements = vec![];
+    if !column_fallbacks.is_table_missing("file_stats") {
+        statements.push(
+            "delete from file_stats
+            where commit_id in (select commit_id from data_commit_info where table_id = $1::TEXT and committed)",
+        );
+    }
+    if !column_fallbacks.is_table_missing("commit_provenance") {
+        statements.push(
+            "delete from commit_provenance
+            where table_id = $1::TEXT and commit_id in (
+                select commit_id from data_commit_info where table_id = $1::TEXT and committed)",
+        );
+    }
+    if !column_fallbacks.is_table_missing("commit_schema_version") {
+        statements.push(
+            "delete from commit_schema_version
+            where table_id = $1::TEXT and commit_id in (
+                select commit_id from data_commit_info where table_id = $1::TEXT and committed)",
+        );
+    }
+    if !column_fallbacks.is_table_missing("partition_stats") {
+        statements.push("delete from partition_stats where table_id = $1::TEXT");
+    }
+    if !column_fallbacks.is_table_missing("table_tag") {
+        statements.push("delete from table_tag where table_id = $1::TEXT");
+    }
+    statements.push("delete from data_commit_info where table_id = $1::TEXT and committed");
+    statements.push("delete from partition_info where table_id = $1::TEXT");
+    statements
+}
+
+/// Run prepared update DAOs, given as `(update_type, joined_string)` with TEXT params only such as the deletes by
+/// table id, in one transaction, so that a failure leaves nothing changed. Returns the number of updated rows.
+pub(crate) async fn execute_updates_in_transaction(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+    updates: Vec<(DaoType, String)>,
+) -> Result<i32> {
+    let mut statements = Vec::with_capacity(updates.len());
+    for (update_type, joined_string) in updates {
+        let statement = get_prepared_statement(client, prepared, context, &update_type).await?;
+        statements.push((statement, get_params(joined_string)));
+    }
+    let transaction = client.transaction().await?;
+    let mut count = 0;
+    for (statement, params) in &statements {
+        let params = params
+            .iter()
+            .map(|param| param as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+        count += transaction.execute(statement, &params).await?;
+    }
+    transaction.commit().await?;
+    Ok(count as i32)
+}
+
+/// Outcome of a scalar query, keeping a NULL value and a query without rows apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScalarValue {
+    NoRows,
+    Null,
+    Value(String),
+}
+
+impl ScalarValue {
+    /// The value if there is one, NULL and no rows both give None.
+    pub fn into_option(self) -> Option<String> {
+        match self {
+            ScalarValue::Value(value) => Some(value),
+            ScalarValue::Null | ScalarValue::NoRows => None,
         }
-        Err(e) => Err(LakeSoulMetaDataError::from(e)),
-        Ok(None) => Ok(None),
     }
 }
 
+fn scalar_value<T>(res: Result<Option<Row>, Error>) -> Result<ScalarValue>
+where
+    T: for<'a> FromSql<'a> + ToString,
+{
+    match res? {
+        Some(row) => Ok(row
+            .get::<_, Option<T>>(0)
+            .map_or(ScalarValue::Null, |value| ScalarValue::Value(value.to_string()))),
+        None => Ok(ScalarValue::NoRows),
+    }
+}
+
+/// NULL and no rows both give None, see [`execute_query_scalar_value`] to tell them apart.
 pub async fn execute_query_scalar(
     client: &mut Client,
     prepared: &mut PreparedStatementMap,
     query_type: i32,
     joined_string: String,
 ) -> Result<Option<String>, LakeSoulMetaDataError> {
+    execute_query_scalar_value(client, prepared, query_type, joined_string)
+        .await
+        .map(ScalarValue::into_option)
+}
+
+pub async fn execute_query_scalar_value(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    query_type: i32,
+    joined_string: String,
+) -> Result<ScalarValue, LakeSoulMetaDataError> {
+    execute_query_scalar_with_context(
+        client,
+        prepared,
+        &StatementContext::default(),
+        query_type,
+        joined_string,
+    )
+    .await
+}
+
+pub(crate) async fn execute_query_scalar_with_context(
+    client: &mut Client,
+    prepared: &mut PreparedStatementMap,
+    context: &StatementContext,
+    query_type: i32,
+    joined_string: String,
+) -> Result<ScalarValue, LakeSoulMetaDataError> {
     if !(DAO_TYPE_QUERY_SCALAR_OFFSET..DAO_TYPE_UPDATE_OFFSET).contains(&query_type) {
         eprintln!("Invalid update_scalar_type_index: {:?}", query_type);
         return Err(LakeSoulMetaDataError::from(ErrorKind::InvalidInput));
     }
     let query_type = DaoType::try_from(query_type).map_err(|e| LakeSoulMetaDataError::Other(Box::new(e)))?;
-    let statement = get_prepared_statement(client, prepared, &query_type).await?;
+    let statement = get_prepared_statement(client, prepared, context, &query_type).await?;
 
     let params = get_params(joined_string);
 
     match query_type {
+        DaoType::GetDbTimestamp if params.len() == 1 && params[0].is_empty() => {
+            let result = client.query_opt(&statement, &[]).await;
+            scalar_value::<i64>(result)
+        }
+        DaoType::CountPartitionByTableIdAndFilter if params.len() == 4 => {
+            let (partition_descs, committed_after, commit_op) = get_partition_filter_params(&params[1..])?;
+            let result = client
+                .query_opt(
+                    &statement,
+                    &[&params[0], &partition_descs, &committed_after, &commit_op],
+                )
+                .await;
+            scalar_value::<i64>(result)
+        }
+        DaoType::GetTagPartitionCountByTableIdAndTag if params.len() == 2 => {
+            let result = client.query_opt(&statement, &[&params[0], &params[1]]).await;
+            scalar_value::<i64>(result)
+        }
+        DaoType::GetTablePropertyByTableIdAndKey if params.len() == 2 => {
+            let result = client.query_opt(&statement, &[&params[0], &params[1]]).await;
+            scalar_value::<String>(result)
+        }
+        DaoType::PartitionExistsByTableIdAndPartitionDesc | DaoType::PartitionIsEmptyByTableIdAndPartitionDesc
+            if params.len() == 2 =>
+        {
+            let result = client.query_opt(&statement, &[&params[0], &params[1]]).await;
+            scalar_value::<bool>(result)
+        }
+        DaoType::GetTableSchemaByTableIdAndVersion if params.len() == 2 => {
+            let result = client
+                .query_opt(&statement, &[&params[0], &i32::from_str(&params[1])?])
+                .await;
+            scalar_value::<String>(result)
+        }
         DaoType::GetLatestTimestampFromPartitionInfoWithoutPartitionDesc if params.len() == 1 => {
             let result = client.query_opt(&statement, &[&params[0]]).await;
-            ts_string(result)
+            scalar_value::<i64>(result)
         }
         DaoType::GetLatestTimestampFromPartitionInfo if params.len() == 2 => {
             let result = client.query_opt(&statement, &[&params[0], &params[1]]).await;
-            match result {
-                Ok(Some(row)) => Ok(Some(format!("{}", row.get::<_, i64>(0)))),
-                Ok(None) => Ok(None),
-                Err(e) => Err(LakeSoulMetaDataError::from(e)),
-            }
+            scalar_value::<i64>(result)
         }
         DaoType::GetLatestVersionUpToTimeFromPartitionInfo if params.len() == 3 => {
             let result = client
                 .query_opt(&statement, &[&params[0], &params[1], &i64::from_str(&params[2])?])
                 .await;
-            match result {
-                Ok(Some(row)) => {
-                    let ts = row.get::<_, Option<i32>>(0);
-                    match ts {
-                        Some(ts) => Ok(Some(format!("{}", ts))),
-                        None => Ok(None),
-                    }
-                }
-                Err(e) => Err(LakeSoulMetaDataError::from(e)),
-                Ok(None) => Ok(None),
-            }
+            scalar_value::<i32>(result)
         }
         DaoType::GetLatestVersionTimestampUpToTimeFromPartitionInfo if params.len() == 3 => {
             let result = client
                 .query_opt(&statement, &[&params[0], &params[1], &i64::from_str(&params[2])?])
                 .await;
-            ts_string(result)
+            scalar_value::<i64>(result)
         }
 
         _ => {
@@ -1307,26 +2935,759 @@ pub async fn execute_query_scalar(
     }
 }
 
-pub async fn clean_meta_for_test(client: &Client) -> Result<i32> {
-    let result = client
-        .batch_execute(
-            "delete from namespace;
-            delete from data_commit_info;
-            delete from table_info;
-            delete from table_path_id;
-            delete from table_name_id;
-            delete from partition_info;",
+/// All namespaces with the number of tables in each, namespaces without tables counted as 0.
+pub async fn list_namespaces_with_table_counts(
+    client: &Client,
+    column_fallbacks: &ColumnFallbacks,
+) -> Result<Vec<(entity::Namespace, i64)>> {
+    let statement = client
+        .prepare(&format!(
+            "select {}, coalesce(c.table_count, 0)
+            from namespace n
+            left join (
+                select table_namespace, count(*) as table_count
+                from table_info
+                group by table_namespace
+            ) c on c.table_namespace = n.namespace
+            order by n.namespace",
+            column_fallbacks.select_list("n", "namespace", NAMESPACE_COLUMNS)
+        ))
+        .await?;
+    Ok(client
+        .query(&statement, &[])
+        .await?
+        .iter()
+        .map(|row| (row_to_namespace(row), row.get::<_, i64>(4)))
+        .collect())
+}
+
+/// Activity of a namespace as shown by a catalog overview, see [`MetaDataClient::get_catalog_overview`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamespaceOverview {
+    pub namespace: String,
+    pub table_count: i64,
+    /// Partitions of the tables of the namespace, each counted once whatever its number of versions.
+    pub partition_count: i64,
+    /// Committed data commits of the tables of the namespace.
+    pub data_commit_count: i64,
+    /// Timestamp of the latest commit to a table of the namespace, None if none has been committed to.
+    pub last_updated: Option<i64>,
+}
+
+/// Overview of every namespace computed by a single aggregate statement, in order of namespace.
+pub async fn get_catalog_overview(client: &Client) -> Result<Vec<NamespaceOverview>> {
+    let statement = client
+        .prepare(
+            "select n.namespace, coalesce(t.table_count, 0), coalesce(p.partition_count, 0),
+                coalesce(d.data_commit_count, 0), p.last_updated
+            from namespace n
+            left join (
+                select table_namespace, count(*) as table_count
+                from table_info
+                group by table_namespace
+            ) t on t.table_namespace = n.namespace
+            left join (
+                select i.table_namespace, count(distinct (p.table_id, p.partition_desc)) as partition_count,
+                    max(p.timestamp) as last_updated
+                from partition_info p
+                join table_info i on i.table_id = p.table_id
+                group by i.table_namespace
+            ) p on p.table_namespace = n.namespace
+            left join (
+                select i.table_namespace, count(*) as data_commit_count
+                from data_commit_info d
+                join table_info i on i.table_id = d.table_id
+                where d.committed
+                group by i.table_namespace
+            ) d on d.table_namespace = n.namespace
+            order by n.namespace",
         )
-        .await;
-    match result {
-        Ok(_) => Ok(0i32),
-        Err(e) => Err(LakeSoulMetaDataError::from(e)),
+        .await?;
+    Ok(client
+        .query(&statement, &[])
+        .await?
+        .iter()
+        .map(|row| NamespaceOverview {
+            namespace: row.get(0),
+            table_count: row.get(1),
+            partition_count: row.get(2),
+            data_commit_count: row.get(3),
+            last_updated: row.get(4),
+        })
+        .collect())
+}
+
+/// Versions of a partition from the latest down, at most `limit` if given, as listed by DESCRIBE HISTORY.
+pub async fn get_partition_history(
+    client: &Client,
+    table_id: &str,
+    partition_desc: &str,
+    limit: Option<i64>,
+) -> Result<Vec<entity::PartitionVersionEntry>> {
+    let statement = client
+        .prepare(
+            "select partition_desc, version, commit_op, timestamp, cardinality(snapshot),
+                (select count(*) from unnest(snapshot) s(commit_id)
+                where not s.commit_id = ANY(coalesce(previous_snapshot, '{}')))::INT
+            from (
+                select partition_desc, version, commit_op, timestamp, snapshot,
+                    lag(snapshot) over (order by version) as previous_snapshot
+                from partition_info
+                where table_id = $1::TEXT and partition_desc = $2::TEXT
+            ) p
+            order by version desc
+            limit $3::BIGINT",
+        )
+        .await?;
+    client
+        .query(&statement, &[&table_id, &partition_desc, &limit])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(entity::PartitionVersionEntry {
+                partition_desc: row.get(0),
+                version: row.get(1),
+                commit_op: decode_commit_op(row.get(2)),
+                timestamp: row.get(3),
+                commit_count: row.get(4),
+                added_commit_count: row.get(5),
+            })
+        })
+        .collect()
+}
+
+/// File statistics of the latest version of each partition of a table summed up, as listed by DESCRIBE DETAIL.
+/// Computed from the data commits, so that they are available without partition stats being maintained.
+pub async fn get_table_stats(client: &Client, table_id: &str) -> Result<entity::TableStats> {
+    let statement = client
+        .prepare(&format!(
+            "select count(distinct p.partition_desc), count(f.path), coalesce(sum(f.size), 0)::BIGINT,
+                coalesce(max(p.timestamp), 0)
+            from (
+                select distinct on (partition_desc) partition_desc, snapshot, timestamp
+                from partition_info
+                where table_id = $1::TEXT
+                order by partition_desc, version desc
+            ) p
+            left join lateral ({}) f on true",
+            live_files_of_snapshot("$1::TEXT", "p.partition_desc", "p.snapshot")
+        ))
+        .await?;
+    let row = client.query_one(&statement, &[&table_id]).await?;
+    Ok(entity::TableStats {
+        table_id: table_id.to_string(),
+        partition_count: row.get(0),
+        file_count: row.get(1),
+        total_bytes: row.get(2),
+        last_commit_ts: row.get(3),
+    })
+}
+
+/// Up to `limit` tables with the bytes of the files of the latest version of their partitions, summed up as by
+/// [`get_table_stats`], the largest first. Tables without files count 0 bytes, ties are ordered by table_id.
+pub async fn list_tables_by_size(
+    client: &Client,
+    column_fallbacks: &ColumnFallbacks,
+    limit: i64,
+) -> Result<Vec<(entity::TableInfo, i64)>> {
+    if limit < 0 {
+        return Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "limit {} is negative",
+            limit
+        )));
     }
+    let statement = client
+        .prepare(&format!(
+            "select {}, coalesce(s.total_bytes, 0)::BIGINT as total_bytes
+            from table_info t
+            left join (
+                select p.table_id, sum(f.size) as total_bytes
+                from (
+                    select distinct on (table_id, partition_desc) table_id, partition_desc, snapshot
+                    from partition_info
+                    order by table_id, partition_desc, version desc
+                ) p
+                join lateral ({}) f on true
+                group by p.table_id
+            ) s on s.table_id = t.table_id
+            order by total_bytes desc, t.table_id
+            limit $1::BIGINT",
+            column_fallbacks.select_list("t", "table_info", TABLE_INFO_COLUMNS),
+            live_files_of_snapshot("p.table_id", "p.partition_desc", "p.snapshot")
+        ))
+        .await?;
+    Ok(client
+        .query(&statement, &[&limit])
+        .await?
+        .iter()
+        .map(|row| (row_to_table_info(row), row.get(8)))
+        .collect())
+}
+
+/// Up to `sample_size` paths of the files of the latest version of each partition of a table, in no particular
+/// order. The rows are cut off by a LIMIT rather than sampled by TABLESAMPLE, which would draw whole data commits.
+pub async fn sample_file_paths(client: &Client, table_id: &str, sample_size: i64) -> Result<Vec<String>> {
+    if sample_size < 0 {
+        return Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "sample size {} is negative",
+            sample_size
+        )));
+    }
+    let statement = client
+        .prepare(
+            "select f.path
+            from (
+                select distinct on (partition_desc) partition_desc, snapshot
+                from partition_info
+                where table_id = $1::TEXT
+                order by partition_desc, version desc
+            ) p
+            join data_commit_info d
+                on d.table_id = $1::TEXT and d.partition_desc = p.partition_desc and d.commit_id = ANY(p.snapshot)
+            cross join lateral unnest(d.file_ops) f
+            where f.file_op = 'add'
+            limit $2::BIGINT",
+        )
+        .await?;
+    Ok(client
+        .query(&statement, &[&table_id, &sample_size])
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect())
+}
+
+/// Distinct domains of the tables in the catalog, in order.
+pub async fn list_domains(client: &Client, column_fallbacks: &ColumnFallbacks) -> Result<Vec<String>> {
+    let statement = client
+        .prepare(&format!(
+            "select distinct {} as domain
+            from table_info
+            order by domain",
+            column_fallbacks.column("", "table_info", "domain")
+        ))
+        .await?;
+    Ok(client
+        .query(&statement, &[])
+        .await?
+        .iter()
+        .map(|row| row.get::<_, String>(0))
+        .collect())
+}
+
+/// Page of at most `limit` tables of the domain, across namespaces, in the order of their table_id after
+/// `after_table_id`, from the start if None.
+pub async fn list_table_info_by_domain(
+    client: &Client,
+    column_fallbacks: &ColumnFallbacks,
+    domain: &str,
+    after_table_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<entity::TableInfo>> {
+    let statement = client
+        .prepare(&format!(
+            "select {}
+            from table_info
+            where {} = $1::TEXT and ($2::TEXT is null or table_id > $2::TEXT)
+            order by table_id
+            limit $3::BIGINT",
+            column_fallbacks.select_list("", "table_info", TABLE_INFO_COLUMNS),
+            column_fallbacks.column("", "table_info", "domain")
+        ))
+        .await?;
+    Ok(client
+        .query(&statement, &[&domain, &after_table_id, &limit])
+        .await?
+        .iter()
+        .map(row_to_table_info)
+        .collect())
+}
+
+/// Partitions of a table whose latest version holds no live file, each file added by its snapshot being deleted by
+/// it as well, in order.
+pub async fn list_empty_partitions(client: &Client, table_id: &str) -> Result<Vec<String>> {
+    let statement = client
+        .prepare(
+            "select p.partition_desc
+            from (
+                select distinct on (partition_desc) partition_desc, snapshot
+                from partition_info
+                where table_id = $1::TEXT
+                order by partition_desc, version desc
+            ) p
+            where not exists(
+                select 1
+                from data_commit_info d, unnest(d.file_ops) f
+                where d.table_id = $1::TEXT and d.partition_desc = p.partition_desc
+                    and d.commit_id = any(p.snapshot) and f.file_op = 'add'
+                    and not exists(
+                        select 1
+                        from data_commit_info e, unnest(e.file_ops) g
+                        where e.table_id = $1::TEXT and e.partition_desc = p.partition_desc
+                            and e.commit_id = any(p.snapshot) and g.file_op = 'del' and g.path = f.path
+                    )
+            )
+            order by p.partition_desc",
+        )
+        .await?;
+    Ok(client
+        .query(&statement, &[&table_id])
+        .await?
+        .iter()
+        .map(|row| row.get::<_, String>(0))
+        .collect())
+}
+
+/// Schema of each of the tables of `table_ids`, missing tables left out.
+pub async fn get_schemas_by_table_ids(client: &Client, table_ids: &[String]) -> Result<HashMap<String, String>> {
+    let statement = client
+        .prepare(
+            "select table_id, table_schema
+            from table_info
+            where table_id = ANY($1::TEXT[])",
+        )
+        .await?;
+    Ok(client
+        .query(&statement, &[&table_ids])
+        .await?
+        .iter()
+        .map(|row| (row.get::<_, String>(0), row.get::<_, String>(1)))
+        .collect())
+}
+
+/// Schemas of a table by version, in order: the replaced ones kept by `table_schema_history` followed by the
+/// current one, each with the time it took effect, 0 if not recorded. Empty if there is no such table.
+pub async fn get_schema_history(client: &Client, table_id: &str) -> Result<Vec<(i32, i64, String)>> {
+    let statement = client
+        .prepare(
+            "select version, coalesce(lag(replaced_at) over (order by version), 0), table_schema
+            from table_schema_history
+            where table_id = $1::TEXT
+            union all
+            select coalesce(max(h.version) + 1, 0), coalesce(max(h.replaced_at), 0), t.table_schema
+            from table_info t
+            left join table_schema_history h on h.table_id = t.table_id
+            where t.table_id = $1::TEXT
+            group by t.table_schema
+            order by 1",
+        )
+        .await?;
+    Ok(client
+        .query(&statement, &[&table_id])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect())
+}
+
+/// Version of the schema a data commit was written with, along with the schema unless it is past the retention
+/// of the schema history. None if no version was recorded for the commit.
+pub async fn get_commit_schema(
+    client: &Client,
+    table_id: &str,
+    commit_id: uuid::Uuid,
+) -> Result<Option<(i32, Option<String>)>> {
+    let statement = client
+        .prepare(
+            "select c.schema_version, coalesce(h.table_schema, case
+                when c.schema_version = coalesce(
+                    (select max(version) + 1 from table_schema_history where table_id = $1::TEXT), 0)
+                then t.table_schema end)
+            from commit_schema_version c
+            join table_info t on t.table_id = c.table_id
+            left join table_schema_history h on h.table_id = c.table_id and h.version = c.schema_version
+            where c.table_id = $1::TEXT and c.commit_id = $2::UUID",
+        )
+        .await?;
+    Ok(client
+        .query_opt(&statement, &[&table_id, &commit_id])
+        .await?
+        .map(|row| (row.get(0), row.get(1))))
+}
+
+/// Delete the replaced schemas of a table but the latest `retained` ones, returning the number deleted.
+pub async fn prune_schema_history(client: &Client, table_id: &str, retained: i32) -> Result<u64> {
+    let statement = client
+        .prepare(
+            "delete from table_schema_history
+            where table_id = $1::TEXT
+                and version <= (select max(version) from table_schema_history where table_id = $1::TEXT) - $2::INT",
+        )
+        .await?;
+    Ok(client.execute(&statement, &[&table_id, &retained]).await?)
+}
+
+/// Number of committed data commits of a table by partition, partitions without any left out.
+pub async fn count_data_commits_by_table_id(client: &Client, table_id: &str) -> Result<Vec<(String, i64)>> {
+    let statement = client
+        .prepare(
+            "select partition_desc, count(*)
+            from data_commit_info
+            where table_id = $1::TEXT and committed
+            group by partition_desc",
+        )
+        .await?;
+    Ok(client
+        .query(&statement, &[&table_id])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get::<_, i64>(1)))
+        .collect())
+}
+
+/// Number of partition versions of a table by commit op code, commit ops never used left out.
+pub async fn count_commit_ops_by_table_id(client: &Client, table_id: &str) -> Result<Vec<(i32, i64)>> {
+    let statement = client
+        .prepare(
+            "select commit_op, count(*)
+            from partition_info
+            where table_id = $1::TEXT
+            group by commit_op",
+        )
+        .await?;
+    Ok(client
+        .query(&statement, &[&table_id])
+        .await?
+        .iter()
+        .map(|row| (decode_commit_op(row.get(0)), row.get::<_, i64>(1)))
+        .collect())
+}
+
+/// Insert the table_name_id, table_path_id and table_info of a new table in one transaction, unless there is a
+/// table of the same name in the namespace already, returning whether the table was inserted. Fails with
+/// InvalidInput, inserting nothing, if the path or the id of the table belongs to another table.
+pub async fn insert_table_if_not_exists(
+    client: &mut Client,
+    column_fallbacks: &ColumnFallbacks,
+    table_info: &entity::TableInfo,
+) -> Result<bool> {
+    let properties: serde_json::Value = serde_json::from_str(&table_info.properties)?;
+    let transaction = client.transaction().await?;
+    let inserted = transaction
+        .execute(
+            &transaction
+                .prepare(&format!(
+                    "{} on conflict do nothing",
+                    column_fallbacks.insert(
+                        "table_name_id",
+                        &["table_id", "table_name", "table_namespace", "domain"],
+                        &["$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT"],
+                    )
+                ))
+                .await?,
+            &[
+                &table_info.table_id,
+                &table_info.table_name,
+                &table_info.table_namespace,
+                &table_info.domain,
+            ],
+        )
+        .await?;
+    if inserted == 0 {
+        return Ok(false);
+    }
+    let inserted = transaction
+        .execute(
+            &transaction
+                .prepare(&format!(
+                    "{} on conflict do nothing",
+                    column_fallbacks.insert(
+                        "table_path_id",
+                        &["table_id", "table_path", "table_namespace", "domain"],
+                        &["$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT"],
+                    )
+                ))
+                .await?,
+            &[
+                &table_info.table_id,
+                &table_info.table_path,
+                &table_info.table_namespace,
+                &table_info.domain,
+            ],
+        )
+        .await?;
+    if inserted == 0 {
+        return Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "table path {} belongs to another table",
+            table_info.table_path
+        )));
+    }
+    let inserted = transaction
+        .execute(
+            &transaction
+                .prepare(&format!(
+                    "{} on conflict do nothing",
+                    column_fallbacks.insert(
+                        "table_info",
+                        TABLE_INFO_COLUMNS,
+                        &[
+                            "$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT", "$5::JSON", "$6::TEXT", "$7::TEXT",
+                            "$8::TEXT"
+                        ],
+                    )
+                ))
+                .await?,
+            &[
+                &table_info.table_id,
+                &table_info.table_name,
+                &table_info.table_path,
+                &table_info.table_schema,
+                &properties,
+                &table_info.partitions,
+                &table_info.table_namespace,
+                &table_info.domain,
+            ],
+        )
+        .await?;
+    if inserted == 0 {
+        return Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "table id {} belongs to another table",
+            table_info.table_id
+        )));
+    }
+    transaction.commit().await?;
+    Ok(true)
+}
+
+/// `commit_timestamp`, or the database clock if None, for the partition versions and their stats to carry the same
+/// timestamp.
+async fn resolve_commit_timestamp(transaction: &Transaction<'_>, commit_timestamp: Option<i64>) -> Result<i64> {
+    match commit_timestamp {
+        Some(commit_timestamp) => Ok(commit_timestamp),
+        None => Ok(transaction
+            .query_one(
+                dao_statement(&DaoType::GetDbTimestamp, &ColumnFallbacks::default()).as_ref(),
+                &[],
+            )
+            .await?
+            .get(0)),
+    }
+}
+
+/// Fail with InvalidInput if the `commit_timestamp` given by the caller is earlier than the latest version of any of
+/// the partitions, which the new versions would precede in time travel.
+async fn check_commit_timestamp_order(
+    transaction: &Transaction<'_>,
+    partition_info_list: &[entity::PartitionInfo],
+    commit_timestamp: i64,
+) -> Result<()> {
+    let statement = transaction
+        .prepare(
+            "select max(timestamp) from partition_info
+            where table_id = $1::TEXT and partition_desc = $2::TEXT",
+        )
+        .await?;
+    for partition_info in partition_info_list {
+        let latest: Option<i64> = transaction
+            .query_one(&statement, &[&partition_info.table_id, &partition_info.partition_desc])
+            .await?
+            .get(0);
+        if let Some(latest) = latest.filter(|latest| commit_timestamp < *latest) {
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "commit timestamp {} is earlier than {} of the latest version of partition '{}' of table '{}'",
+                commit_timestamp, latest, partition_info.partition_desc, partition_info.table_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Insert partition versions in one transaction, e.g. the first version of partitions expected not to exist yet
+/// without reading the current versions. Partitions which turn out to have the version already are skipped, their
+/// partition_descs are returned for the caller to commit them the usual way.
+pub async fn insert_new_partition_info(
+    client: &mut Client,
+    column_fallbacks: &ColumnFallbacks,
+    partition_info_list: &[entity::PartitionInfo],
+    with_stats: bool,
+    commit_timestamp: Option<i64>,
+) -> Result<Vec<String>> {
+    let transaction = client.transaction().await?;
+    if let Some(commit_timestamp) = commit_timestamp {
+        check_commit_timestamp_order(&transaction, partition_info_list, commit_timestamp).await?;
+    }
+    let statement = transaction
+        .prepare(&format!(
+            "{} on conflict do nothing",
+            insert_partition_info_statement(column_fallbacks)
+        ))
+        .await?;
+    let (stats_statement, commit_timestamp) = if with_stats {
+        (
+            Some(transaction.prepare(&upsert_partition_stats_of_snapshot()).await?),
+            Some(resolve_commit_timestamp(&transaction, commit_timestamp).await?),
+        )
+    } else {
+        (None, commit_timestamp)
+    };
+    let mut existing = Vec::new();
+    for partition_info in partition_info_list {
+        let snapshot = partition_info
+            .snapshot
+            .iter()
+            .map(|commit_id| uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low))
+            .collect::<Vec<uuid::Uuid>>();
+        let inserted = transaction
+            .execute(
+                &statement,
+                &[
+                    &partition_info.table_id,
+                    &partition_info.partition_desc,
+                    &partition_info.version,
+                    &encode_commit_op(partition_info.commit_op)?,
+                    &snapshot,
+                    &partition_info.expression,
+                    &partition_info.domain,
+                    &partition_info.change_tracking,
+                    &commit_timestamp,
+                ],
+            )
+            .await?;
+        if inserted == 0 {
+            existing.push(partition_info.partition_desc.clone());
+            continue;
+        }
+        transaction
+            .execute(
+                "update data_commit_info set committed = 'true' where commit_id = ANY($1::_UUID)",
+                &[&snapshot],
+            )
+            .await?;
+        if let Some(stats_statement) = &stats_statement {
+            transaction
+                .execute(
+                    stats_statement,
+                    &[
+                        &partition_info.table_id,
+                        &partition_info.partition_desc,
+                        &snapshot,
+                        &commit_timestamp,
+                    ],
+                )
+                .await?;
+        }
+    }
+    transaction.commit().await?;
+    Ok(existing)
+}
+
+/// Append file ops and the column stats of the appended files to a data commit not committed yet, returning the
+/// number of updated data commits.
+pub async fn append_data_file_ops(
+    client: &mut Client,
+    table_id: &str,
+    partition_desc: &str,
+    commit_id: uuid::Uuid,
+    file_ops: &[entity::DataFileOp],
+    file_stats: &[entity::FileStats],
+) -> Result<u64> {
+    let file_ops = file_ops
+        .iter()
+        .map(DataFileOp::from_proto_data_file_op)
+        .collect::<Result<Vec<DataFileOp>>>()?;
+    let transaction = client.transaction().await?;
+    let statement = transaction
+        .prepare(
+            "update data_commit_info
+            set file_ops = file_ops || $4::_data_file_op
+            where table_id = $1::TEXT and partition_desc = $2::TEXT and commit_id = $3::UUID and not committed",
+        )
+        .await?;
+    let updated = transaction
+        .execute(&statement, &[&table_id, &partition_desc, &commit_id, &file_ops])
+        .await?;
+    // the stats of the appended files are registered with them
+    if updated > 0 {
+        insert_file_stats(&transaction, file_stats).await?;
+    }
+    transaction.commit().await?;
+    Ok(updated)
+}
+
+/// Tables emptied by [`clean_meta_for_test`].
+const CLEANED_META_TABLES: [&str; 12] = [
+    "namespace",
+    "data_commit_info",
+    "table_info",
+    "table_path_id",
+    "table_name_id",
+    "partition_info",
+    "partition_stats",
+    "file_stats",
+    "commit_provenance",
+    "table_tag",
+    "table_schema_history",
+    "commit_schema_version",
+];
+
+/// Whether `name` matches the glob `pattern`, in which `*` stands for any characters and `?` for one.
+fn matches_database_pattern(pattern: &str, name: &str) -> bool {
+    let regex = regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", ".");
+    Regex::new(&format!("^{}$", regex)).is_ok_and(|regex| regex.is_match(name))
+}
+
+/// Empty every metadata table in one transaction, returning the number of deleted rows, each table's logged. The
+/// optional tables missing from the database are skipped.
+///
+/// Refuses with InvalidInput unless the name of the connected database matches `database_pattern`, a glob such as
+/// [`DEFAULT_CLEAN_META_DATABASE_PATTERN`], or `i_know_what_i_am_doing` is set.
+pub async fn clean_meta_for_test(
+    client: &mut Client,
+    database_pattern: &str,
+    i_know_what_i_am_doing: bool,
+) -> Result<i32> {
+    let database: String = client.query_one("select current_database()", &[]).await?.get(0);
+    if !i_know_what_i_am_doing && !matches_database_pattern(database_pattern, &database) {
+        return Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "refusing to clean the metadata of database {}, which does not match {}",
+            database, database_pattern
+        )));
+    }
+    let column_fallbacks = probe_column_fallbacks(client).await?;
+    let transaction = client.transaction().await?;
+    let mut deleted = Vec::with_capacity(CLEANED_META_TABLES.len());
+    for table in CLEANED_META_TABLES
+        .into_iter()
+        .filter(|table| !column_fallbacks.is_table_missing(table))
+    {
+        let count = transaction.execute(&format!("delete from {}", table), &[]).await?;
+        deleted.push((table, count));
+    }
+    transaction.commit().await?;
+    for (table, count) in &deleted {
+        tracing::info!("cleaned {} rows of {} in database {}", count, table, database);
+    }
+    Ok(deleted.iter().map(|(_, count)| *count as i32).sum())
 }
 
 ///  Create a pg connection, return pg client
+/// Connect with a pg config, which may name the `schema` holding the catalog, `public` by default.
+/// The connection is driven by a task of the current runtime.
 pub async fn create_connection(config: String) -> Result<Client> {
-    let (client, connection) = match tokio_postgres::connect(config.as_str(), NoTls).await {
+    create_connection_on(config, &runtime::Handle::current()).await
+}
+
+/// [`create_connection`] driving the connection by a task of `runtime`, which must outlive the client.
+pub async fn create_connection_on(config: String, runtime: &runtime::Handle) -> Result<Client> {
+    Ok(connect_on(config, runtime).await?.0)
+}
+
+/// The means of ending a connection besides its client: cancelling its query and awaiting the task driving it.
+pub(crate) struct ConnectionHandle {
+    pub(crate) cancel_token: CancelToken,
+    /// Taken by the first caller ending the connection.
+    pub(crate) task: Option<JoinHandle<()>>,
+}
+
+/// [`create_connection_on`] along with the handle of the connection.
+pub(crate) async fn connect_on(config: String, runtime: &runtime::Handle) -> Result<(Client, ConnectionHandle)> {
+    let (config, schema) = parse_config(&config)?;
+    // connected on `runtime` too, the socket is registered with the driver of the runtime it is made on
+    let connected = runtime
+        .spawn(async move { config.connect(NoTls).await })
+        .await
+        .map_err(|e| LakeSoulMetaDataError::Internal(e.to_string()))?;
+    let (client, connection) = match connected {
         Ok((client, connection)) => (client, connection),
         Err(e) => {
             eprintln!("{}", e);
@@ -1334,23 +3695,101 @@ pub async fn create_connection(config: String) -> Result<Client> {
         }
     };
 
-    spawn(async move {
+    let task = runtime.spawn(async move {
         if let Err(e) = connection.await {
             eprintln!("connection error: {}", e);
         }
     });
 
-    Ok(client)
+    if let Some(schema) = schema {
+        set_search_path(&client, &schema).await?;
+    }
+    let handle = ConnectionHandle {
+        cancel_token: client.cancel_token(),
+        task: Some(task),
+    };
+    Ok((client, handle))
+}
+
+/// Parse a pg config into the connection settings, keepalives included, and the `schema` of the catalog.
+pub(crate) fn parse_config(config: &str) -> Result<(Config, Option<String>)> {
+    let (config, schema) = split_schema(config);
+    let config = Config::from_str(&config).map_err(|e| LakeSoulMetaDataError::InvalidConfig(e.to_string()))?;
+    Ok((config, schema))
 }
 
-fn row_to_uuid_list(row: &Row) -> Vec<entity::Uuid> {
-    row.get::<_, Vec<uuid::Uuid>>(4)
-        .iter()
-        .map(|uuid| {
-            let (high, low) = uuid.as_u64_pair();
-            entity::Uuid { high, low }
+/// Split the `schema` property off a pg config, as libpq does not know it.
+fn split_schema(config: &str) -> (String, Option<String>) {
+    let mut schema = None;
+    let config = metadata_client::config_properties(config)
+        .into_iter()
+        .filter(|property| match property.strip_prefix("schema=") {
+            Some(value) => {
+                schema = Some(metadata_client::unquote_config_value(value)).filter(|value| !value.is_empty());
+                false
+            }
+            None => true,
         })
-        .collect()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (config, schema)
+}
+
+/// Make the unqualified names of the queries of `client` resolve in `schema`, which must exist.
+pub(crate) async fn set_search_path(client: &Client, schema: &str) -> Result<()> {
+    if client
+        .query_opt("select 1 from pg_namespace where nspname = $1", &[&schema])
+        .await?
+        .is_none()
+    {
+        return Err(LakeSoulMetaDataError::InvalidConfig(format!(
+            "schema {} does not exist",
+            schema
+        )));
+    }
+    client
+        .batch_execute(&format!("SET search_path TO \"{}\"", schema.replace('"', "\"\"")))
+        .await?;
+    Ok(())
+}
+
+/// Check a pg config string without keeping a connection around.
+/// Each failing stage is reported with its own error: InvalidConfig, AuthenticationFailed or ConnectionFailed.
+pub async fn validate_config(config: &str) -> Result<()> {
+    let (config, schema) = split_schema(config);
+    let config = Config::from_str(&config).map_err(|e| LakeSoulMetaDataError::InvalidConfig(e.to_string()))?;
+    let (client, connection) = config.connect(NoTls).await.map_err(|e| match e.code() {
+        Some(code) if *code == SqlState::INVALID_PASSWORD || *code == SqlState::INVALID_AUTHORIZATION_SPECIFICATION => {
+            LakeSoulMetaDataError::AuthenticationFailed(e.to_string())
+        }
+        _ => LakeSoulMetaDataError::ConnectionFailed(e.to_string()),
+    })?;
+    let connection = spawn(connection);
+    let result = match client.simple_query("SELECT 1").await {
+        Ok(_) => match &schema {
+            Some(schema) => set_search_path(&client, schema).await,
+            None => Ok(()),
+        },
+        Err(e) => Err(LakeSoulMetaDataError::ConnectionFailed(e.to_string())),
+    };
+    drop(client);
+    let _ = connection.await;
+    result
+}
+
+/// Snapshot of a partition row, without the duplicated commit ids older clients may have written.
+fn row_to_uuid_list(row: &Row) -> Vec<entity::Uuid> {
+    let (snapshot, dropped) = Snapshot::dedup(row.get::<_, Vec<uuid::Uuid>>(4));
+    if dropped > 0 {
+        tracing::warn!(
+            "dropped {} duplicated commit ids from version {} of partition '{}' of table '{}'",
+            dropped,
+            row.get::<_, i32>(2),
+            row.get::<_, String>(1),
+            row.get::<_, String>(0)
+        );
+    }
+    snapshot.to_proto()
 }
 
 #[cfg(test)]
@@ -1359,6 +3798,233 @@ mod tests {
 
     use proto::proto::entity;
 
+    use crate::error::LakeSoulMetaDataError;
+    use crate::schema_compat::ColumnFallbacks;
+    use crate::{
+        check_insert_wrapper, decode_partition_desc_list, decode_query_params, describe_dao,
+        encode_partition_desc_list, encode_query_params, matches_database_pattern, split_schema, validate_config,
+        DaoKind, DaoType, DEFAULT_CLEAN_META_DATABASE_PATTERN,
+    };
+
+    #[test]
+    fn test_query_params_escaping() {
+        let params = ["a__DELIM__b", "c_", "_DELIM_d", "\u{1}1", "DELIMITER", ""];
+        let encoded = encode_query_params(&params);
+        assert_eq!(encoded.matches("__DELIM__").count(), params.len() - 1);
+        assert_eq!(decode_query_params(&encoded), params);
+        // params joined by legacy callers
+        assert_eq!(decode_query_params("t1__DELIM__DELIMITER"), ["t1", "DELIMITER"]);
+        assert_eq!(decode_query_params(""), [""]);
+
+        let partition_descs = ["date=a_", "_DELIM_", "date=b_DELIM"];
+        let encoded_list = encode_partition_desc_list(&partition_descs);
+        let decoded = decode_query_params(&encode_query_params(&["table_id", &encoded_list]));
+        assert_eq!(decode_partition_desc_list(&decoded[1]), partition_descs);
+        assert_eq!(decode_partition_desc_list("a_DELIM_b"), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_describe_dao() {
+        let column_fallbacks = ColumnFallbacks::default();
+        let description = describe_dao(&column_fallbacks, DaoType::ListPartitionByTableIdAndFilter);
+        assert_eq!(description.kind, DaoKind::QueryList);
+        assert!(description.sql.unwrap().contains("from partition_info"));
+        assert_eq!(description.params, ["TEXT", "TEXT[]", "BIGINT", "TEXT"]);
+
+        let description = describe_dao(&column_fallbacks, DaoType::DeleteNamespaceByNamespace);
+        assert_eq!(description.kind, DaoKind::Update);
+        assert!(!description.kind.is_read_only());
+        assert_eq!(description.params, ["TEXT"]);
+
+        // built when executed
+        let description = describe_dao(&column_fallbacks, DaoType::TransactionInsertDataCommitInfo);
+        assert_eq!(description.kind, DaoKind::TransactionInsert);
+        assert_eq!((description.sql, description.params), (None, vec![]));
+    }
+
+    #[test]
+    fn test_matches_database_pattern() {
+        let matches = |name| matches_database_pattern(DEFAULT_CLEAN_META_DATABASE_PATTERN, name);
+        assert!(matches("lakesoul_test"));
+        assert!(!matches("lakesoul_staging"));
+        assert!(!matches("lakesoul_test_backup"));
+        assert!(matches_database_pattern("ci_?", "ci_1"));
+        assert!(!matches_database_pattern("ci.?", "ci_1"));
+    }
+
+    #[test]
+    fn test_split_schema() {
+        assert_eq!(
+            split_schema("host=127.0.0.1 schema=tenant_a dbname=lakesoul_test"),
+            (
+                "host=127.0.0.1 dbname=lakesoul_test".to_string(),
+                Some("tenant_a".to_string())
+            )
+        );
+        assert_eq!(
+            split_schema("schema='tenant \\'b\\'' user=lakesoul_test"),
+            ("user=lakesoul_test".to_string(), Some("tenant 'b'".to_string()))
+        );
+        assert_eq!(
+            split_schema("user=lakesoul_test"),
+            ("user=lakesoul_test".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_check_insert_wrapper() {
+        let namespace = entity::Namespace {
+            namespace: "default".to_owned(),
+            properties: "{}".to_owned(),
+            comment: "".to_owned(),
+            domain: "public".to_owned(),
+        };
+        let wrapper = entity::JniWrapper {
+            namespace: vec![namespace.clone()],
+            ..Default::default()
+        };
+        check_insert_wrapper(&DaoType::InsertNamespace, &wrapper).unwrap();
+
+        let wrapper = entity::JniWrapper {
+            namespace: vec![namespace],
+            table_info: vec![entity::TableInfo::default()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_insert_wrapper(&DaoType::InsertNamespace, &wrapper),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            check_insert_wrapper(&DaoType::InsertTableInfo, &entity::JniWrapper::default()),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        check_insert_wrapper(&DaoType::TransactionInsertPartitionInfo, &entity::JniWrapper::default()).unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_validate_config() {
+        assert!(matches!(
+            validate_config("host=127.0.0.1 port=not_a_port").await,
+            Err(LakeSoulMetaDataError::InvalidConfig(_))
+        ));
+        let config = crate::MetaDataClientBuilder::from_env().unwrap().config;
+        validate_config(&config).await.unwrap();
+        // a server checking passwords refuses a connection without one as well, one trusting its clients takes any
+        let without_password = config
+            .split_whitespace()
+            .filter(|property| !property.starts_with("password="))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let checks_password = validate_config(&without_password).await.is_err();
+        let wrong_password = validate_config(&format!("{} password=wrong_password", without_password)).await;
+        if checks_password {
+            assert!(matches!(
+                wrong_password,
+                Err(LakeSoulMetaDataError::AuthenticationFailed(_))
+            ));
+        } else {
+            wrong_password.unwrap();
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_insert_timestamp_mode() {
+        use crate::{
+            create_connection, encode_query_params, execute_insert, execute_insert_with_timestamp_mode, execute_query,
+            execute_query_scalar, execute_update, DaoType, PreparedStatementMap,
+        };
+
+        let mut client = create_connection(
+            "host=127.0.0.1 port=5432 dbname=lakesoul_test user=lakesoul_test password=lakesoul_test".to_string(),
+        )
+        .await
+        .unwrap();
+        let mut prepared = PreparedStatementMap::new();
+        let table_id = format!("table_{}", uuid::Uuid::new_v4());
+        let data_commit_info = |timestamp: i64| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            entity::DataCommitInfo {
+                table_id: table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                timestamp,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+        let commit_id_string = |data_commit_info: &entity::DataCommitInfo| {
+            let commit_id = data_commit_info.commit_id.clone().unwrap();
+            uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string()
+        };
+
+        // the FFI insert takes the timestamp from the database clock unless told to keep the one set by the client
+        let client_clock = data_commit_info(1);
+        let server_clock = data_commit_info(1);
+        for (data_commit_info, server_side_timestamp) in [(&client_clock, false), (&server_clock, true)] {
+            let wrapper = entity::JniWrapper {
+                data_commit_info: vec![data_commit_info.clone()],
+                ..Default::default()
+            };
+            if server_side_timestamp {
+                execute_insert(
+                    &mut client,
+                    &mut prepared,
+                    DaoType::InsertDataCommitInfo as i32,
+                    wrapper,
+                )
+                .await
+                .unwrap();
+            } else {
+                execute_insert_with_timestamp_mode(
+                    &mut client,
+                    &mut prepared,
+                    DaoType::InsertDataCommitInfo as i32,
+                    wrapper,
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+        }
+        let db_time = execute_query_scalar(
+            &mut client,
+            &mut prepared,
+            DaoType::GetDbTimestamp as i32,
+            String::new(),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .parse::<i64>()
+        .unwrap();
+        let mut timestamps = Vec::new();
+        for data_commit_info in [&client_clock, &server_clock] {
+            let encoded = execute_query(
+                &client,
+                &mut prepared,
+                DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId as i32,
+                encode_query_params(&[table_id.as_str(), "-5", commit_id_string(data_commit_info).as_str()]),
+            )
+            .await
+            .unwrap();
+            let wrapper = entity::JniWrapper::decode(prost::bytes::Bytes::from(encoded)).unwrap();
+            timestamps.push(wrapper.data_commit_info[0].timestamp);
+        }
+        assert_eq!(timestamps[0], 1);
+        assert!(1 < timestamps[1] && timestamps[1] <= db_time);
+
+        for data_commit_info in [&client_clock, &server_clock] {
+            execute_update(
+                &mut client,
+                &mut prepared,
+                DaoType::DeleteOneDataCommitInfoByTableIdAndPartitionDescAndCommitId as i32,
+                encode_query_params(&[table_id.as_str(), "-5", commit_id_string(data_commit_info).as_str()]),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
     #[test]
     fn test_entity() -> std::io::Result<()> {
         let namespace = entity::Namespace {
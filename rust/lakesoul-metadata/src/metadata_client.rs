@@ -2,31 +2,189 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter};
-use std::ops::DerefMut;
-use std::sync::Arc;
-use std::{collections::HashMap, env, fs, vec};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{self, AtomicBool, AtomicU64};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs, vec,
+};
 
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
 use prost::Message;
-use tokio::sync::Mutex;
-use tokio_postgres::Client;
-use tracing::debug;
+use tokio::runtime;
+use tokio::sync::{oneshot, Mutex, MutexGuard};
+use tokio::task::JoinHandle;
+use tokio_postgres::{Client, NoTls};
+use tracing::{debug, warn};
 use url::Url;
 
 use proto::proto::entity::{
-    self, CommitOp, DataCommitInfo, JniWrapper, MetaInfo, Namespace, PartitionInfo, TableInfo, TableNameId, TablePathId,
+    self, CommitOp, CommitProvenance, DataCommitInfo, FileOp, FileStats, JniWrapper, MetaInfo, Namespace,
+    PartitionInfo, PartitionStats, TableInfo, TableNameId, TablePathId,
 };
 
+use crate::catalog_export::{
+    CatalogReader, CatalogRecord, CatalogWriter, ConflictPolicy, ExportManifest, ImportSummary,
+};
+use crate::conflict_policy::{builtin_conflict_policies, CommitConflictPolicy, ConflictDecision};
 use crate::error::{LakeSoulMetaDataError, Result};
+use crate::error_context::{in_context, note_attempt, ErrorContext};
+use crate::failover::{is_connection_error, is_read_only, spawn_health_check, Failover, DEFAULT_FAILBACK_INTERVAL};
+use crate::forward_compat::{encode_commit_op, CompatibilityWarning, CompatibilityWarnings};
+use crate::op_options::{current_max_retry, OpOptions};
+use crate::ops_limiter::{LimiterMetrics, OpsLimiter};
+use crate::partition_coercion::PartitionValueCoercion;
+use crate::schema_compat::{probe_column_fallbacks, ColumnFallbacks};
+use crate::schema_validation::{validate_schema, SchemaValidation};
+use crate::snapshot::Snapshot;
+use crate::table_cache::{
+    cache_source, listen_notifications, CacheKey, CachedEntity, TableCache, TableCacheMetrics,
+    DEFAULT_TABLE_CACHE_GRACE_PERIOD, DEFAULT_TABLE_CACHE_TTL,
+};
+use crate::table_properties::validate_table_properties;
+use crate::trace_context::{propagate, TracePropagation};
+use crate::transfusion::config::{
+    CHANGE_TRACKING_ENABLED, COMMIT_CONFLICT_POLICY, LAKESOUL_NULL_STRING, LAKESOUL_PARTITION_DESC_KV_DELIM,
+    LAKESOUL_RANGE_PARTITION_SPLITTER, PARTITION_STATS_ENABLED,
+};
+use crate::transfusion::{canonical_partition_desc, parse_table_info_partitions, range_partition_keys};
+use crate::write_limits::WriteLimits;
 use crate::{
-    clean_meta_for_test, create_connection, execute_insert, execute_query, execute_update, DaoType,
-    PreparedStatementMap, PARAM_DELIM, PARTITION_DESC_DELIM,
+    append_data_file_ops, clean_meta_for_test, connect_on, count_commit_ops_by_table_id,
+    count_data_commits_by_table_id, create_connection_on, describe_dao, encode_partition_desc_list,
+    encode_query_params, execute_insert_with_context, execute_query_scalar_with_context,
+    execute_query_streamed_with_context, execute_query_with_context, execute_update_with_context,
+    execute_updates_in_transaction, get_catalog_overview, get_commit_schema, get_schema_history,
+    get_schemas_by_table_ids, insert_data_commit_info_with_stats, insert_new_partition_info,
+    insert_table_if_not_exists, list_domains, list_empty_partitions, list_namespaces_with_table_counts,
+    list_table_info_by_domain, list_tables_by_size, prepare_all_statements_with_context, prune_schema_history,
+    sample_file_paths, ConnectionHandle, ConnectionStatements, DaoDescription, DaoType, NamespaceOverview, ScalarValue,
+    StreamedEntity, StreamedEntityStream, DEFAULT_CLEAN_META_DATABASE_PATTERN,
 };
 
+pub const DEFAULT_PG_CONFIG: &str =
+    "host=127.0.0.1 port=5432 dbname=lakesoul_test user=lakesoul_test password=lakesoul_test";
+pub const DEFAULT_NAMESPACE: &str = "default";
+pub const DEFAULT_DOMAIN: &str = "public";
+/// How far a user-supplied commit timestamp may be ahead of the database clock.
+pub const COMMIT_TIMESTAMP_MAX_SKEW_MS: i64 = 5_000;
+/// Prefix of the tags pinning read snapshots, which are left out of [`MetaDataClient::list_tags`].
+const READ_SNAPSHOT_TAG_PREFIX: &str = "__read_snapshot_";
+/// Names looked up per query by [`MetaDataClient::get_table_infos`].
+const TABLE_INFO_BATCH_SIZE: usize = 500;
+/// Tables fetched per query by [`MetaDataClient::get_all_table_info_by_domain`].
+const TABLE_INFO_PAGE_SIZE: i64 = 1000;
+/// Tables analyzed after a bulk commit, see [`MetaDataClientBuilder::with_analyze_after_commit_rows`].
+const ANALYZED_AFTER_COMMIT: [&str; 2] = ["partition_info", "data_commit_info"];
+/// Prefix of the namespace properties inherited by the tables created in the namespace, see
+/// [`MetaDataClient::create_table`].
+const NAMESPACE_TABLE_DEFAULT_PREFIX: &str = "table.default.";
+
+/// Callback run after a successful commit, see [`MetaDataClient::register_commit_hook`].
+pub type CommitHook = Arc<dyn Fn(&MetaInfo, CommitOp) + Send + Sync>;
+
 pub struct MetaDataClient {
-    client: Arc<Mutex<Client>>,
-    prepared: Arc<Mutex<PreparedStatementMap>>,
+    config: String,
+    pub(crate) client: Arc<Mutex<Client>>,
+    /// Replaced along with the connection when it is reopened by the failover health check.
+    connection: Arc<std::sync::Mutex<ConnectionHandle>>,
+    prepared: Arc<Mutex<ConnectionStatements>>,
     max_retry: usize,
+    server_side_timestamp: bool,
+    default_namespace: String,
+    default_domain: String,
+    default_provenance: Option<CommitProvenance>,
+    limiter: OpsLimiter,
+    table_cache: Option<Arc<TableCache>>,
+    table_cache_listener: std::sync::Mutex<Option<JoinHandle<()>>>,
+    write_limits: WriteLimits,
+    compaction_lock_wait: bool,
+    clean_meta_database_pattern: String,
+    max_result_bytes: Option<usize>,
+    /// Append and merge commits to a partition whose commits it holds already, see [`CommitMetrics`].
+    retried_commits: AtomicU64,
+    duplicate_commit_ids: AtomicU64,
+    /// Resolves the version conflicts of commits to tables not naming a policy, see [`crate::conflict_policy`].
+    conflict_policy: Option<Arc<dyn CommitConflictPolicy>>,
+    /// Policies by name, selectable by the tables.
+    conflict_policies: HashMap<String, Arc<dyn CommitConflictPolicy>>,
+    commit_hooks: RwLock<Vec<CommitHook>>,
+    failover: Option<Arc<Failover>>,
+    failover_health_check: std::sync::Mutex<Option<JoinHandle<()>>>,
+    trace_propagation: TracePropagation,
+    path_mode: PathMode,
+    /// Property issues fail the update instead of being logged.
+    strict_table_properties: bool,
+    analyze_after_commit_rows: Option<usize>,
+    /// Replaced schemas kept per table, all if None.
+    schema_history_retention: Option<usize>,
+    /// Set while an ANALYZE after a bulk commit runs, so that commits meanwhile do not start another one.
+    analyze_running: Arc<AtomicBool>,
+    analyzes_after_commit: Arc<AtomicU64>,
+    compatibility_warnings: CompatibilityWarnings,
+    /// Runtime driving the connections and the background tasks of the client.
+    runtime_handle: runtime::Handle,
+    /// Runtime started for a client built without one, dropped last.
+    runtime: Option<ClientRuntime>,
+}
+
+/// Current-thread runtime driving the connections of a client built by [`MetaDataClientBuilder::build_blocking`]
+/// without a runtime, run by a thread of its own until the client is dropped.
+struct ClientRuntime {
+    handle: runtime::Handle,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl ClientRuntime {
+    fn start() -> Result<Self> {
+        let runtime = runtime::Builder::new_current_thread().enable_all().build()?;
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        std::thread::Builder::new()
+            .name("lakesoul-metadata-client".to_string())
+            .spawn(move || {
+                let _ = runtime.block_on(stopped);
+            })?;
+        Ok(Self {
+            handle,
+            shutdown: Some(shutdown),
+        })
+    }
+}
+
+impl Drop for ClientRuntime {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+impl Drop for MetaDataClient {
+    fn drop(&mut self) {
+        for task in [&mut self.table_cache_listener, &mut self.failover_health_check] {
+            if let Some(task) = task.get_mut().unwrap().take() {
+                task.abort();
+            }
+        }
+        if !self.limiter.is_closed() {
+            match self.limiter.in_flight() {
+                0 => debug!("metadata client dropped without being closed"),
+                in_flight => warn!(
+                    "metadata client dropped without being closed, {} operations in flight are abandoned",
+                    in_flight
+                ),
+            }
+        }
+    }
 }
 
 impl Debug for MetaDataClient {
@@ -34,14 +192,382 @@ impl Debug for MetaDataClient {
         f.debug_struct("MetaDataClient")
             .field("client", &"{pg_client}")
             .field("max_retry", &self.max_retry)
+            .field("server_side_timestamp", &self.server_side_timestamp)
+            .field("default_namespace", &self.default_namespace)
+            .field("default_domain", &self.default_domain)
+            .field("default_provenance", &self.default_provenance)
+            .field("limiter", &self.limiter)
+            .field("table_cache", &self.table_cache)
+            .field("write_limits", &self.write_limits)
+            .field("analyze_after_commit_rows", &self.analyze_after_commit_rows)
             .finish()
     }
 }
 
 pub type MetaDataClientRef = Arc<MetaDataClient>;
 
-impl MetaDataClient {
-    pub async fn from_env() -> Result<Self> {
+/// Outcome of [`MetaDataClient::close`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloseReport {
+    /// Operations in flight when the client was closed, a commit counting as one.
+    pub in_flight: usize,
+    /// Operations still in flight at the timeout, whose queries were cancelled.
+    pub cancelled: usize,
+    /// Whether this call closed the client, false if it was closed already.
+    pub closed: bool,
+}
+
+/// Reported by [`MetaDataClient::drop_all_tables_in_namespace`] once a table is handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropProgress {
+    pub table_id: String,
+    pub table_name: String,
+    /// Whether the table was deleted, it is left in place otherwise.
+    pub dropped: bool,
+    /// Tables handled so far, this one included.
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Outcome of [`MetaDataClient::commit_data_partial`], partition descs are in canonical form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartialCommitReport {
+    pub committed: Vec<String>,
+    /// Partitions left as they were because another commit got to them first.
+    pub conflicted: Vec<String>,
+}
+
+/// Commits deduplicated against the current snapshot of their partition, see [`MetaDataClient::commit_metrics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitMetrics {
+    /// Partitions of append and merge commits whose commits the partition held already, e.g. a commit retried
+    /// after a timeout although it went through, which left the current version in place.
+    pub retried_commits: u64,
+    /// Commit ids dropped from append and merge commits because their partition held them already.
+    pub duplicate_commit_ids: u64,
+}
+
+/// Outcome of [`MetaDataClient::create_table_with_outcome`] and [`MetaDataClient::create_table_if_not_exists`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateTableOutcome {
+    /// False if the table existed already.
+    pub created: bool,
+    /// Effective properties of the table, with the defaults of its namespace merged in.
+    pub properties: String,
+}
+
+/// Outcome of [`MetaDataClient::drop_all_tables_in_namespace`].
+#[derive(Debug, Default)]
+pub struct DropSummary {
+    /// Names of the deleted tables.
+    pub dropped: Vec<String>,
+    /// Names of the tables left in place, with the error of their deletion.
+    pub failed: Vec<(String, LakeSoulMetaDataError)>,
+}
+
+/// Outcome of [`MetaDataClient::sync_table_metadata`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub table_id: String,
+    /// True if the table has been created on the target.
+    pub table_created: bool,
+    /// Number of partition versions copied to the target.
+    pub partition_versions: usize,
+    /// Number of data commits copied to the target.
+    pub data_commits: usize,
+    /// Partitions whose versions on the target are missing here or differ, left as they are on the target.
+    pub diverged_partitions: Vec<String>,
+}
+
+/// Outcome of [`MetaDataClient::sync_namespace_metadata`].
+#[derive(Debug, Default)]
+pub struct NamespaceSyncSummary {
+    /// Reports of the synced tables, in completion order.
+    pub synced: Vec<SyncReport>,
+    /// Ids of the tables failing to sync, with their error.
+    pub failed: Vec<(String, LakeSoulMetaDataError)>,
+}
+
+/// What the committer knows about the partitions of a commit, see [`MetaDataClient::commit_data_with_hint`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitHint {
+    /// Read the current versions of the partitions to commit on top of them.
+    #[default]
+    Default,
+    /// The partitions are brand new, e.g. in a backfill: their first version is written without reading the
+    /// current versions, partitions existing after all are committed on top of their current version.
+    NewPartitionsOnly,
+}
+
+/// One partition version bump of a table, see [`MetaDataClient::get_table_operation_log`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationLogEntry {
+    pub partition_desc: String,
+    pub version: i32,
+    /// Code of the commit op, below [`crate::UNKNOWN_COMMIT_OP_CODE`] for the ones unknown to this client.
+    pub commit_op: i32,
+    pub timestamp: i64,
+    /// Recorded provenance of the data commits added by this version, commits without one are left out.
+    pub provenance: Vec<CommitProvenance>,
+}
+
+impl OperationLogEntry {
+    /// The commit op, the default one if it is unknown to this client.
+    pub fn commit_op(&self) -> CommitOp {
+        CommitOp::try_from(self.commit_op).unwrap_or_default()
+    }
+}
+
+/// A file op of a data commit with its flag decoded, see [`MetaDataClient::get_resolved_file_ops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedFileOp {
+    /// Qualified path of the file, with `.`, `..` and repeated separators resolved.
+    pub path: String,
+    pub file_op: FileOp,
+    pub size: i64,
+    pub file_exist_cols: String,
+}
+
+/// A file written outside of LakeSoul, see [`MetaDataClient::register_external_files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalFile {
+    pub path: String,
+    pub size: i64,
+}
+
+/// How the paths of the file ops committed by [`MetaDataClient::commit_data_commit_info`] are stored, see
+/// [`MetaDataClientBuilder::with_normalize_file_paths`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathMode {
+    /// Store the paths as given, absolute or relative to the table path. Read as stored.
+    #[default]
+    KeepAbsolute,
+    /// Store the paths under the table path relative to it and others qualified, see
+    /// [`ResolvedFileOp::path`]. Read qualified.
+    MakeRelative,
+    /// Store all paths qualified, relative ones resolved against the table path. Read qualified.
+    MakeAbsolute,
+}
+
+impl TryFrom<&str> for PathMode {
+    type Error = LakeSoulMetaDataError;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "" | "keep_absolute" => Ok(Self::KeepAbsolute),
+            "make_relative" => Ok(Self::MakeRelative),
+            "make_absolute" => Ok(Self::MakeAbsolute),
+            _ => Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "unknown path mode {}, expected keep_absolute, make_relative or make_absolute",
+                value
+            ))),
+        }
+    }
+}
+
+/// A broken invariant of a table, see [`MetaDataClient::check_table_consistency`]. Commit ids are hyphenated uuids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsistencyViolation {
+    /// The snapshot of a partition version references a data commit which does not exist.
+    MissingCommit {
+        partition_desc: String,
+        version: i32,
+        commit_id: String,
+    },
+    /// The snapshot of a partition version references a data commit not marked as committed.
+    UncommittedCommit {
+        partition_desc: String,
+        version: i32,
+        commit_id: String,
+    },
+    /// The versions of a partition between `from` and `to`, both excluded, are missing.
+    VersionGap { partition_desc: String, from: i32, to: i32 },
+    /// The table_name_id entry of the table name is missing, or is the one of `table_id`.
+    TableNameIdMismatch {
+        table_name: String,
+        table_namespace: String,
+        table_id: Option<String>,
+    },
+    /// The table_path_id entry of the table path is missing, or is the one of `table_id`.
+    TablePathIdMismatch {
+        table_path: String,
+        table_id: Option<String>,
+    },
+    /// The table schema is not valid json.
+    InvalidSchema { error: String },
+    /// A file is added by the current snapshots of several partitions.
+    OverlappingFile { path: String, partition_descs: Vec<String> },
+}
+
+/// Outcome of [`MetaDataClient::check_table_consistency`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsistencyReport {
+    pub table_id: String,
+    /// Violations found, before any fix.
+    pub violations: Vec<ConsistencyViolation>,
+    /// Partition versions rid of their missing commits by [`MetaDataClient::check_table_consistency_with_fix`].
+    pub fixed_versions: usize,
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A tag of a table, see [`MetaDataClient::list_tags`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagInfo {
+    pub tag: String,
+    /// Creation timestamp of the tag in milliseconds.
+    pub timestamp: i64,
+}
+
+/// Fields of a table to change with [`MetaDataClient::update_table_info`], unset fields are left as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableInfoUpdate {
+    pub table_name: Option<String>,
+    pub table_path: Option<String>,
+    pub table_schema: Option<String>,
+}
+
+impl TableInfoUpdate {
+    fn is_empty(&self) -> bool {
+        self.table_name.is_none() && self.table_path.is_none() && self.table_schema.is_none()
+    }
+
+    fn joined_params(&self, table_id: &str) -> String {
+        encode_query_params(&[
+            table_id,
+            self.table_name.as_deref().unwrap_or_default(),
+            self.table_path.as_deref().unwrap_or_default(),
+            self.table_schema.as_deref().unwrap_or_default(),
+        ])
+    }
+}
+
+/// Filter over the latest version of each partition of a table, unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartitionFilter {
+    /// Only these partitions, all partitions when empty.
+    pub partition_descs: Vec<String>,
+    /// Only partitions whose latest version was committed at or after this timestamp.
+    pub committed_after: Option<i64>,
+    /// Only partitions whose latest version was committed by this operation.
+    pub commit_op: Option<CommitOp>,
+}
+
+impl PartitionFilter {
+    fn joined_params(&self, table_id: &str) -> String {
+        encode_query_params(&[
+            table_id,
+            &encode_partition_desc_list(&self.partition_descs),
+            &self.committed_after.map(|ts| ts.to_string()).unwrap_or_default(),
+            self.commit_op
+                .map(|commit_op| commit_op.as_str_name())
+                .unwrap_or_default(),
+        ])
+    }
+}
+
+/// Health of a [`MetaDataClient`], see [`MetaDataClient::health_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    pub connected: bool,
+    /// Whether the database only serves reads, e.g. a standby, failing writes with ReadOnlyDatabase.
+    pub read_only: bool,
+    /// Optional columns missing from the metadata database, as `table.column`, read as their defaults.
+    pub missing_optional_columns: Vec<String>,
+    /// Optional tables missing from the metadata database, the features backed by them are unavailable.
+    pub missing_optional_tables: Vec<String>,
+    /// Caps enforced on the writes of the client.
+    pub write_limits: WriteLimits,
+}
+
+impl HealthReport {
+    pub fn is_degraded(&self) -> bool {
+        !self.missing_optional_columns.is_empty() || !self.missing_optional_tables.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetaDataClientBuilder {
+    pub(crate) config: String,
+    max_retry: usize,
+    server_side_timestamp: bool,
+    default_namespace: String,
+    default_domain: String,
+    default_provenance: Option<CommitProvenance>,
+    max_concurrent_ops: Option<usize>,
+    rate_limit_ops_per_sec: Option<u32>,
+    fail_fast_when_limited: bool,
+    table_cache_ttl: Option<Duration>,
+    table_cache_dir: Option<PathBuf>,
+    table_cache_grace_period: Duration,
+    table_cache_notifications: bool,
+    write_limits: WriteLimits,
+    compaction_lock_wait: bool,
+    clean_meta_database_pattern: String,
+    max_result_bytes: Option<usize>,
+    secondary_config: Option<String>,
+    failback_interval: Duration,
+    trace_propagation: TracePropagation,
+    path_mode: PathMode,
+    strict_table_properties: bool,
+    warmup: bool,
+    conflict_policy: Option<Arc<dyn CommitConflictPolicy>>,
+    conflict_policies: HashMap<String, Arc<dyn CommitConflictPolicy>>,
+    analyze_after_commit_rows: Option<usize>,
+    schema_history_retention: Option<usize>,
+    runtime_handle: Option<runtime::Handle>,
+}
+
+impl Default for MetaDataClientBuilder {
+    fn default() -> Self {
+        Self {
+            config: DEFAULT_PG_CONFIG.to_string(),
+            max_retry: 3,
+            server_side_timestamp: true,
+            default_namespace: DEFAULT_NAMESPACE.to_string(),
+            default_domain: DEFAULT_DOMAIN.to_string(),
+            default_provenance: None,
+            max_concurrent_ops: None,
+            rate_limit_ops_per_sec: None,
+            fail_fast_when_limited: false,
+            table_cache_ttl: None,
+            table_cache_dir: None,
+            table_cache_grace_period: DEFAULT_TABLE_CACHE_GRACE_PERIOD,
+            table_cache_notifications: false,
+            write_limits: WriteLimits::default(),
+            compaction_lock_wait: false,
+            clean_meta_database_pattern: DEFAULT_CLEAN_META_DATABASE_PATTERN.to_string(),
+            max_result_bytes: None,
+            secondary_config: None,
+            failback_interval: DEFAULT_FAILBACK_INTERVAL,
+            trace_propagation: TracePropagation::Disabled,
+            path_mode: PathMode::default(),
+            strict_table_properties: false,
+            warmup: false,
+            conflict_policy: None,
+            conflict_policies: builtin_conflict_policies()
+                .into_iter()
+                .map(|policy| (policy.name().to_string(), policy))
+                .collect(),
+            analyze_after_commit_rows: None,
+            schema_history_retention: None,
+            runtime_handle: None,
+        }
+    }
+}
+
+impl MetaDataClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the pg connection and schema, the default namespace/domain, the operation limits and the table cache
+    /// from the properties file pointed by `lakesoul_home`.
+    pub fn from_env() -> Result<Self> {
         match env::var("lakesoul_home") {
             Ok(config_path) => {
                 let config = fs::read_to_string(&config_path)
@@ -50,606 +576,10578 @@ impl MetaDataClient {
                     .split('\n')
                     .filter_map(|property| property.find('=').map(|idx| property.split_at(idx + 1)))
                     .collect::<HashMap<_, _>>();
-                let url = Url::parse(
-                    &config_map
+                let config = pg_config_from_jdbc_url(
+                    config_map
                         .get("lakesoul.pg.url=")
-                        .unwrap_or(&"jdbc:postgresql://127.0.0.1:5432/lakesoul_test?stringtype=unspecified")[5..],
-                )?;
-                Self::from_config(format!(
-                    "host={} port={} dbname={} user={} password={}",
-                    url.host_str()
-                        .ok_or(LakeSoulMetaDataError::Internal("url host missing".to_string()))?,
-                    url.port()
-                        .ok_or(LakeSoulMetaDataError::Internal("url port missing".to_string()))?,
-                    url.path_segments()
-                        .ok_or(LakeSoulMetaDataError::Internal("url path missing".to_string()))?
-                        .next()
-                        .ok_or(LakeSoulMetaDataError::Internal("url path missing".to_string()))?,
+                        .unwrap_or(&"jdbc:postgresql://127.0.0.1:5432/lakesoul_test?stringtype=unspecified")
+                        .trim(),
                     config_map.get("lakesoul.pg.username=").unwrap_or(&"lakesoul_test"),
-                    config_map.get("lakesoul.pg.password=").unwrap_or(&"lakesoul_test")
-                ))
-                .await
-            }
-            Err(_) => {
-                Self::from_config(
-                    "host=127.0.0.1 port=5432 dbname=lakesoul_test user=lakesoul_test password=lakesoul_test"
-                        .to_string(),
-                )
-                .await
+                    config_map.get("lakesoul.pg.password=").unwrap_or(&"lakesoul_test"),
+                )?;
+                let number = |key: &str| {
+                    config_map
+                        .get(key)
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                        .map(|value| {
+                            value.parse::<u64>().map_err(|_| {
+                                LakeSoulMetaDataError::InvalidConfig(format!("{}{} is not a number", key, value))
+                            })
+                        })
+                        .transpose()
+                };
+                let mut builder = Self::new();
+                if let Some(max_concurrent_ops) = number("lakesoul.meta.max.concurrent.ops=")? {
+                    builder = builder.with_max_concurrent_ops(max_concurrent_ops as usize);
+                }
+                if let Some(ops_per_sec) = number("lakesoul.meta.rate.limit.ops.per.sec=")? {
+                    builder = builder.with_rate_limit(ops_per_sec as u32);
+                }
+                if let Some(fail_fast) = config_map.get("lakesoul.meta.limit.fail.fast=") {
+                    builder = builder.with_fail_fast_when_limited(fail_fast.trim() == "true");
+                }
+                if let Some(ttl_ms) = number("lakesoul.meta.cache.ttl.ms=")? {
+                    builder = builder.with_table_cache(Duration::from_millis(ttl_ms));
+                }
+                if let Some(cache_dir) = config_map
+                    .get("lakesoul.meta.cache.dir=")
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                {
+                    builder = builder.with_table_cache_dir(cache_dir);
+                }
+                if let Some(grace_period_ms) = number("lakesoul.meta.cache.grace.period.ms=")? {
+                    builder = builder.with_table_cache_grace_period(Duration::from_millis(grace_period_ms));
+                }
+                if let Some(notifications) = config_map.get("lakesoul.meta.cache.notify=") {
+                    builder = builder.with_table_cache_notifications(notifications.trim() == "true");
+                }
+                if let Some(wait) = config_map.get("lakesoul.meta.compaction.lock.wait=") {
+                    builder = builder.with_compaction_lock_wait(wait.trim() == "true");
+                }
+                if let Some(pattern) = config_map.get("lakesoul.meta.clean.database.pattern=") {
+                    builder = builder.with_clean_meta_database_pattern(pattern.trim().to_string());
+                }
+                if let Some(max_result_bytes) = number("lakesoul.meta.max.result.bytes=")? {
+                    builder = builder.with_max_result_bytes(max_result_bytes as usize);
+                }
+                if let Some(propagation) = config_map.get("lakesoul.meta.trace.propagation=") {
+                    builder = builder.with_trace_propagation(TracePropagation::try_from(propagation.trim())?);
+                }
+                if let Some(path_mode) = config_map.get("lakesoul.meta.file.path.mode=") {
+                    builder = builder.with_normalize_file_paths(PathMode::try_from(path_mode.trim())?);
+                }
+                if let Some(strict) = config_map.get("lakesoul.meta.table.properties.strict=") {
+                    builder = builder.with_strict_table_properties(strict.trim() == "true");
+                }
+                if let Some(warmup) = config_map.get("lakesoul.meta.warmup=") {
+                    builder = builder.with_warmup(warmup.trim() == "true");
+                }
+                if let Some(name) = config_map.get("lakesoul.meta.commit.conflict.policy=") {
+                    let policy = builder.conflict_policies.get(name.trim()).cloned().ok_or_else(|| {
+                        LakeSoulMetaDataError::InvalidInput(format!("unknown commit conflict policy {}", name.trim()))
+                    })?;
+                    builder = builder.with_conflict_policy(policy);
+                }
+                if let Some(rows) = number("lakesoul.meta.analyze.after.commit.rows=")? {
+                    builder = builder.with_analyze_after_commit_rows(rows as usize);
+                }
+                if let Some(versions) = number("lakesoul.meta.schema.history.retention=")? {
+                    builder = builder.with_schema_history_retention(versions as usize);
+                }
+                let mut write_limits = WriteLimits::default();
+                for (key, limit) in [
+                    (
+                        "lakesoul.meta.limit.max.name.length=",
+                        &mut write_limits.max_name_length,
+                    ),
+                    (
+                        "lakesoul.meta.limit.max.partition.desc.length=",
+                        &mut write_limits.max_partition_desc_length,
+                    ),
+                    (
+                        "lakesoul.meta.limit.max.properties.size=",
+                        &mut write_limits.max_properties_size,
+                    ),
+                    (
+                        "lakesoul.meta.limit.max.schema.size=",
+                        &mut write_limits.max_schema_size,
+                    ),
+                    (
+                        "lakesoul.meta.limit.max.files.per.commit=",
+                        &mut write_limits.max_files_per_commit,
+                    ),
+                ] {
+                    if let Some(value) = number(key)? {
+                        *limit = value as usize;
+                    }
+                }
+                builder = builder.with_write_limits(write_limits).with_config(config);
+                if let Some(keepalives) = config_map.get("lakesoul.pg.keepalives=") {
+                    builder = builder.with_keepalives(parse_keepalives(keepalives)?);
+                }
+                if let Some(idle_secs) = number("lakesoul.pg.keepalives.idle.secs=")? {
+                    builder = builder.with_keepalives_idle(Duration::from_secs(idle_secs));
+                }
+                if let Some(secondary_url) = config_map
+                    .get("lakesoul.pg.secondary.url=")
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                {
+                    builder = builder.with_secondary_config(pg_config_from_jdbc_url(
+                        secondary_url,
+                        config_map.get("lakesoul.pg.username=").unwrap_or(&"lakesoul_test"),
+                        config_map.get("lakesoul.pg.password=").unwrap_or(&"lakesoul_test"),
+                    )?);
+                }
+                if let Some(schema) = config_map
+                    .get("lakesoul.pg.schema=")
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                {
+                    builder = builder.with_schema(schema);
+                }
+                Ok(builder
+                    .with_default_namespace(config_map.get("lakesoul.current.namespace=").unwrap_or(&"").trim())
+                    .with_default_domain(config_map.get("lakesoul.current.domain=").unwrap_or(&"").trim()))
             }
+            Err(_) => Ok(Self::new()),
         }
     }
 
-    pub async fn from_config(config: String) -> Result<Self> {
-        Self::from_config_and_max_retry(config, 3).await
+    pub fn with_config(mut self, config: String) -> Self {
+        self.config = config;
+        self
     }
 
-    pub async fn from_config_and_max_retry(config: String, max_retry: usize) -> Result<Self> {
-        let client = Arc::new(Mutex::new(create_connection(config).await?));
-        let prepared = Arc::new(Mutex::new(PreparedStatementMap::new()));
-        Ok(Self {
-            client,
-            prepared,
-            max_retry,
-        })
+    /// Connect to `host`, a name or an IPv4 or IPv6 address, the latter with or without brackets.
+    pub fn with_host(mut self, host: &str, port: u16) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        self.config = set_config_property(&self.config, "host", host);
+        self.config = set_config_property(&self.config, "port", &port.to_string());
+        self
     }
 
-    pub async fn create_namespace(&self, namespace: Namespace) -> Result<()> {
-        self.insert_namespace(&namespace).await?;
-        Ok(())
+    /// Keep the catalog in the pg schema `schema` instead of `public`, e.g. one schema per tenant.
+    /// The schema must exist and hold the tables of `meta_init.sql`.
+    pub fn with_schema(mut self, schema: &str) -> Self {
+        self.config = set_config_property(&self.config, "schema", schema);
+        self
     }
 
-    pub async fn create_table(&self, table_info: TableInfo) -> Result<()> {
-        self.insert_table_path_id(&table_path_id_from_table_info(&table_info))
-            .await?;
-        self.insert_table_name_id(&table_name_id_from_table_info(&table_info))
-            .await?;
-        self.insert_table_info(&table_info).await?;
-        Ok(())
+    /// Have the OS probe the connections after `idle` without traffic, so that connections dropped by a load
+    /// balancer while idle are detected before the next query. Probes start after two hours by default.
+    pub fn with_keepalives_idle(mut self, idle: Duration) -> Self {
+        self.config = set_config_property(&self.config, "keepalives", "1");
+        self.config = set_config_property(&self.config, "keepalives_idle", &idle.as_secs().max(1).to_string());
+        self
     }
 
-    pub async fn delete_namespace_by_namespace(&self, namespace: &str) -> Result<()> {
-        debug!("delete namespace {}", namespace);
-        self.execute_update(
-            DaoType::DeleteNamespaceByNamespace as i32,
-            [namespace].join(PARAM_DELIM),
-        )
-        .await?;
-        Ok(())
+    /// Turn the TCP keepalives of the connections on or off, on by default.
+    pub fn with_keepalives(mut self, keepalives: bool) -> Self {
+        self.config = set_config_property(&self.config, "keepalives", if keepalives { "1" } else { "0" });
+        self
     }
 
-    // Use transaction?
-    pub async fn delete_table_by_table_info_cascade(&self, table_info: &TableInfo) -> Result<()> {
-        self.delete_table_name_id_by_table_id(&table_info.table_id).await?;
-        self.delete_table_path_id_by_table_id(&table_info.table_id).await?;
-        self.delete_partition_info_by_table_id(&table_info.table_id).await?;
-        self.delete_data_commit_info_by_table_id(&table_info.table_id).await?;
-        self.delete_table_info_by_id_and_path(&table_info.table_id, &table_info.table_path)
-            .await?;
-        Ok(())
+    /// Connect through the Unix socket of the server in `socket_dir`, e.g. `/var/run/postgresql`.
+    /// The socket file is named after the port, which is kept.
+    pub fn with_unix_socket(mut self, socket_dir: &str) -> Self {
+        self.config = set_config_property(&self.config, "host", socket_dir);
+        self
     }
 
-    pub async fn delete_table_path_id_by_table_id(&self, table_id: &str) -> Result<i32> {
-        self.execute_update(DaoType::DeleteTablePathIdByTableId as i32, [table_id].join(PARAM_DELIM))
-            .await
+    pub fn with_max_retry(mut self, max_retry: usize) -> Self {
+        self.max_retry = max_retry;
+        self
     }
 
-    pub async fn delete_table_name_id_by_table_id(&self, table_id: &str) -> Result<i32> {
-        self.execute_update(DaoType::DeleteTableNameIdByTableId as i32, [table_id].join(PARAM_DELIM))
-            .await
+    /// Commit timestamps are taken from the database clock by default.
+    /// Pass `false` to restore the legacy behavior of trusting the timestamp set by the committing client.
+    pub fn with_server_side_timestamp(mut self, server_side_timestamp: bool) -> Self {
+        self.server_side_timestamp = server_side_timestamp;
+        self
     }
 
-    pub async fn delete_partition_info_by_table_id(&self, table_id: &str) -> Result<i32> {
-        self.execute_update(
-            DaoType::DeletePartitionInfoByTableId as i32,
-            [table_id].join(PARAM_DELIM),
-        )
-        .await
+    /// Namespace used when a caller passes an empty one, empty input keeps the current value.
+    pub fn with_default_namespace(mut self, default_namespace: &str) -> Self {
+        if !default_namespace.is_empty() {
+            self.default_namespace = default_namespace.to_string();
+        }
+        self
     }
-    pub async fn delete_data_commit_info_by_table_id(&self, table_id: &str) -> Result<i32> {
-        self.execute_update(
-            DaoType::DeleteDataCommitInfoByTableId as i32,
-            [table_id].join(PARAM_DELIM),
-        )
-        .await
+
+    /// Domain used when a caller passes an empty one, empty input keeps the current value.
+    pub fn with_default_domain(mut self, default_domain: &str) -> Self {
+        if !default_domain.is_empty() {
+            self.default_domain = default_domain.to_string();
+        }
+        self
     }
 
-    pub async fn delete_table_info_by_id_and_path(&self, id: &str, path: &str) -> Result<i32> {
-        self.execute_update(DaoType::DeleteTableInfoByIdAndPath as i32, [id, path].join(PARAM_DELIM))
-            .await
+    /// Provenance recorded for every data commit of the client, fields left empty by
+    /// [`MetaDataClient::commit_data_commit_info_with_provenance`] are taken from it.
+    pub fn with_default_provenance(mut self, default_provenance: CommitProvenance) -> Self {
+        self.default_provenance = Some(default_provenance);
+        self
     }
 
-    async fn execute_insert(&self, insert_type: i32, wrapper: JniWrapper) -> Result<i32> {
-        for times in 0..self.max_retry as i64 {
-            match execute_insert(
-                self.client.lock().await.deref_mut(),
-                self.prepared.lock().await.deref_mut(),
-                insert_type,
-                wrapper.clone(),
-            )
-            .await
-            {
-                Ok(count) => return Ok(count),
-                Err(_) if times < self.max_retry as i64 - 1 => continue,
-                Err(e) => return Err(e),
-            };
-        }
-        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    /// Cap on the metadata operations of the client in flight at once, unlimited by default.
+    pub fn with_max_concurrent_ops(mut self, max_concurrent_ops: usize) -> Self {
+        self.max_concurrent_ops = Some(max_concurrent_ops);
+        self
     }
 
-    async fn execute_update(&self, update_type: i32, joined_string: String) -> Result<i32> {
-        for times in 0..self.max_retry as i64 {
-            match execute_update(
-                self.client.lock().await.deref_mut(),
-                self.prepared.lock().await.deref_mut(),
-                update_type,
-                joined_string.clone(),
-            )
-            .await
-            {
-                Ok(count) => return Ok(count),
-                Err(_) if times < self.max_retry as i64 - 1 => continue,
-                Err(e) => return Err(e),
-            };
-        }
-        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    /// Cap on the metadata operations of the client per second, unlimited by default.
+    pub fn with_rate_limit(mut self, ops_per_sec: u32) -> Self {
+        self.rate_limit_ops_per_sec = Some(ops_per_sec);
+        self
     }
 
-    async fn execute_query(&self, query_type: i32, joined_string: String) -> Result<JniWrapper> {
-        for times in 0..self.max_retry as i64 {
-            match execute_query(
-                self.client.lock().await.deref_mut(),
-                self.prepared.lock().await.deref_mut(),
-                query_type,
-                joined_string.clone(),
-            )
-            .await
-            {
-                Ok(encoded) => return Ok(JniWrapper::decode(prost::bytes::Bytes::from(encoded))?),
-                Err(_) if times < self.max_retry as i64 - 1 => continue,
-                Err(e) => return Err(e),
-            };
-        }
-        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    /// Fail with QueueFull instead of waiting when a limit is reached, for latency-sensitive callers.
+    pub fn with_fail_fast_when_limited(mut self, fail_fast: bool) -> Self {
+        self.fail_fast_when_limited = fail_fast;
+        self
     }
 
-    async fn insert_namespace(&self, namespace: &Namespace) -> Result<i32> {
-        self.execute_insert(
-            DaoType::InsertNamespace as i32,
-            JniWrapper {
-                namespace: vec![namespace.clone()],
-                ..Default::default()
-            },
-        )
-        .await
+    /// Cache fetched TableInfo and TableNameId entries for `ttl`, they are dropped earlier by writes and commits
+    /// to their table through this client. Disabled by default.
+    pub fn with_table_cache(mut self, ttl: Duration) -> Self {
+        self.table_cache_ttl = Some(ttl);
+        self
     }
 
-    async fn insert_table_info(&self, table_info: &TableInfo) -> Result<i32> {
-        self.execute_insert(
-            DaoType::InsertTableInfo as i32,
-            JniWrapper {
-                table_info: vec![table_info.clone()],
-                ..Default::default()
-            },
-        )
-        .await
+    /// Persist the table cache to a file under `dir`, so that later clients start warm.
+    /// Enables the table cache with a ttl of one minute if not enabled yet.
+    pub fn with_table_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.table_cache_dir = Some(dir.into());
+        self
     }
 
-    async fn insert_table_name_id(&self, table_name_id: &TableNameId) -> Result<i32> {
-        self.execute_insert(
-            DaoType::InsertTableNameId as i32,
-            JniWrapper {
-                table_name_id: vec![table_name_id.clone()],
-                ..Default::default()
-            },
-        )
-        .await
+    /// How long entries loaded from the cache file are served while they are refreshed in the background.
+    /// One minute by default.
+    pub fn with_table_cache_grace_period(mut self, grace_period: Duration) -> Self {
+        self.table_cache_grace_period = grace_period;
+        self
     }
 
-    async fn insert_table_path_id(&self, table_path_id: &TablePathId) -> Result<i32> {
-        self.execute_insert(
-            DaoType::InsertTablePathId as i32,
-            JniWrapper {
-                table_path_id: vec![table_path_id.clone()],
-                ..Default::default()
-            },
-        )
-        .await
+    /// Subscribe to the notifications sent by the database on every table write, so that writes of other
+    /// processes drop their cached entries too instead of being seen once the ttl expires.
+    /// Takes one more connection, and only applies if the table cache is enabled.
+    pub fn with_table_cache_notifications(mut self, notifications: bool) -> Self {
+        self.table_cache_notifications = notifications;
+        self
     }
 
-    async fn insert_data_commit_info(&self, data_commit_info: &DataCommitInfo) -> Result<i32> {
-        self.execute_insert(
-            DaoType::InsertDataCommitInfo as i32,
-            JniWrapper {
-                data_commit_info: vec![data_commit_info.clone()],
-                ..Default::default()
-            },
-        )
-        .await
+    /// Caps on the names, partition descs, properties, schemas and commits written by the client, writes over
+    /// them fail with LimitExceeded. Generous by default.
+    pub fn with_write_limits(mut self, write_limits: WriteLimits) -> Self {
+        self.write_limits = write_limits;
+        self
     }
 
-    async fn transaction_insert_partition_info(&self, partition_info_list: Vec<PartitionInfo>) -> Result<i32> {
-        self.execute_insert(
-            DaoType::TransactionInsertPartitionInfo as i32,
-            JniWrapper {
-                partition_info: partition_info_list,
-                ..Default::default()
-            },
-        )
-        .await
+    /// Have [`MetaDataClient::lock_partition_for_compaction`] wait until the partition is unlocked instead of
+    /// failing with Locked.
+    pub fn with_compaction_lock_wait(mut self, wait: bool) -> Self {
+        self.compaction_lock_wait = wait;
+        self
     }
 
-    pub async fn meta_cleanup(&self) -> Result<i32> {
-        clean_meta_for_test(self.client.lock().await.deref_mut()).await?;
-        self.insert_namespace(&Namespace {
-            namespace: "default".to_string(),
-            properties: "{}".to_string(),
-            comment: "".to_string(),
-            domain: "public".to_string(),
-        })
-        .await
+    /// Glob the name of the database must match for [`MetaDataClient::meta_cleanup`] to empty it,
+    /// [`DEFAULT_CLEAN_META_DATABASE_PATTERN`] by default.
+    pub fn with_clean_meta_database_pattern(mut self, pattern: String) -> Self {
+        self.clean_meta_database_pattern = pattern;
+        self
     }
 
-    pub async fn commit_data(&self, meta_info: MetaInfo, commit_op: CommitOp) -> Result<()> {
-        let table_info = meta_info
-            .table_info
-            .ok_or(LakeSoulMetaDataError::Internal("table info missing".to_string()))?;
+    /// Fail queries whose result exceeds `max_result_bytes` with ResultTooLarge, aborting them before the whole
+    /// result is fetched, e.g. listing the data commits of a huge table in a shared service. Unlimited by default.
+    pub fn with_max_result_bytes(mut self, max_result_bytes: usize) -> Self {
+        self.max_result_bytes = Some(max_result_bytes);
+        self
+    }
 
-        if !table_info.table_name.is_empty() {
-            // todo: updateTableShortName
-        }
-        // todo: updateTableProperties
+    /// Serve reads from the database of `secondary_config`, e.g. a replica in another region, while the primary
+    /// cannot be reached. Such reads may be stale, see [`MetaDataClient::is_serving_stale_reads`]. Writes always
+    /// require the primary, which is checked periodically to serve reads again once it is back.
+    pub fn with_secondary_config(mut self, secondary_config: String) -> Self {
+        self.secondary_config = Some(secondary_config);
+        self
+    }
 
-        // conflict handling
-        let _raw_map = meta_info
-            .list_partition
-            .iter()
-            .map(|partition_info| (partition_info.partition_desc.clone(), partition_info.clone()))
-            .collect::<HashMap<String, PartitionInfo>>();
+    /// How often the primary is checked when a secondary is configured, ten seconds by default.
+    pub fn with_failback_interval(mut self, failback_interval: Duration) -> Self {
+        self.failback_interval = failback_interval;
+        self
+    }
 
-        let partition_desc_list = meta_info
-            .list_partition
-            .iter()
-            .map(|partition_info| partition_info.partition_desc.clone())
-            .collect::<Vec<String>>();
+    /// Propagate the trace context the operations run in, see [`crate::TraceContext::scope`], to the metadata
+    /// database. Disabled by default, when the trace context is not even looked up.
+    pub fn with_trace_propagation(mut self, trace_propagation: TracePropagation) -> Self {
+        self.trace_propagation = trace_propagation;
+        self
+    }
 
-        let _snapshot_list = meta_info
-            .list_partition
-            .iter()
-            .flat_map(|partition_info| partition_info.snapshot.clone())
-            .collect::<Vec<entity::Uuid>>();
+    /// Store the paths of committed file ops as `path_mode` demands, so that engines writing them absolute and
+    /// relative to the table path agree. The data file listings, e.g. [`MetaDataClient::get_data_files_by_table_name`],
+    /// resolve the paths stored relative unless the mode is [`PathMode::KeepAbsolute`], the default. File ops
+    /// deleting a file name it as stored, so the mode is best set before a table is first written.
+    pub fn with_normalize_file_paths(mut self, path_mode: PathMode) -> Self {
+        self.path_mode = path_mode;
+        self
+    }
 
-        // conflict handling
-        let cur_map = self
-            .get_cur_partition_map(&table_info.table_id, &partition_desc_list)
-            .await?;
+    /// Fail creating tables and updating table properties with InvalidInput when
+    /// [`crate::validate_table_properties`] reports issues, instead of logging them as warnings. Off by default.
+    pub fn with_strict_table_properties(mut self, strict: bool) -> Self {
+        self.strict_table_properties = strict;
+        self
+    }
 
-        match commit_op {
-            CommitOp::AppendCommit | CommitOp::MergeCommit => {
-                let new_partition_list = meta_info
-                    .list_partition
-                    .iter()
-                    .map(|partition_info| {
-                        let partition_desc = &partition_info.partition_desc;
-                        match cur_map.get(partition_desc) {
-                            Some(cur_partition_info) => {
-                                let mut cur_partition_info = cur_partition_info.clone();
-                                cur_partition_info.domain = self.get_table_domain(&table_info.table_id)?;
-                                cur_partition_info
-                                    .snapshot
-                                    .extend_from_slice(&partition_info.snapshot[..]);
-                                cur_partition_info.version += 1;
-                                cur_partition_info.commit_op = commit_op as i32;
-                                cur_partition_info.expression = partition_info.expression.clone();
-                                Ok(cur_partition_info)
-                            }
-                            None => Ok(PartitionInfo {
-                                table_id: table_info.table_id.clone(),
-                                partition_desc: partition_desc.clone(),
-                                version: 0,
-                                snapshot: Vec::from(&partition_info.snapshot[..]),
-                                domain: self.get_table_domain(&table_info.table_id)?,
-                                commit_op: commit_op as i32,
-                                expression: partition_info.expression.clone(),
-                                ..Default::default()
-                            }),
-                        }
-                    })
-                    .collect::<Result<Vec<PartitionInfo>>>()?;
-                let val = self.transaction_insert_partition_info(new_partition_list).await?;
-                let vec = self.get_all_partition_info(table_info.table_id.as_str()).await?;
-                debug!("val = {val} ,get partition list after finished: {:?}", vec);
-                Ok(())
+    /// Have [`Self::build`] call [`MetaDataClient::warmup`], so that the first operations of the client do not
+    /// pay for preparing their statements. Off by default.
+    pub fn with_warmup(mut self, warmup: bool) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Resolve the version conflicts of commits by `policy` unless the table names another one by its
+    /// `commitConflictPolicy` property, see [`crate::conflict_policy`]. The policy can be named by tables as well.
+    pub fn with_conflict_policy(mut self, policy: Arc<dyn CommitConflictPolicy>) -> Self {
+        self.conflict_policy = Some(policy.clone());
+        self.register_conflict_policy(policy)
+    }
+
+    /// Make `policy` selectable by the `commitConflictPolicy` table property, along with the built-in ones.
+    pub fn register_conflict_policy(mut self, policy: Arc<dyn CommitConflictPolicy>) -> Self {
+        self.conflict_policies.insert(policy.name().to_string(), policy);
+        self
+    }
+
+    /// After a commit writing more than `rows` partition versions, e.g. a backfill or a bulk import, run ANALYZE on
+    /// the partition and commit tables in the background on a connection of its own, so that the planner does not
+    /// work from statistics gone stale. A commit while an ANALYZE runs does not start another one. Off by default.
+    pub fn with_analyze_after_commit_rows(mut self, rows: usize) -> Self {
+        self.analyze_after_commit_rows = Some(rows);
+        self
+    }
+
+    /// Keep the latest `versions` replaced schemas of a table when its schema changes, at least one so that schema
+    /// versions keep counting up. Commits written with an older schema can then no longer be read with it, see
+    /// [`MetaDataClient::get_schema_of_commit`]. All are kept by default.
+    pub fn with_schema_history_retention(mut self, versions: usize) -> Self {
+        self.schema_history_retention = Some(versions.max(1));
+        self
+    }
+
+    /// Drive the connections and the background tasks of the client, i.e. the table cache refresh and
+    /// notifications, by tasks of `runtime_handle` instead of the runtime current at [`Self::build`].
+    /// The runtime must outlive the client, whose operations fail once it is shut down.
+    pub fn with_runtime_handle(mut self, runtime_handle: runtime::Handle) -> Self {
+        self.runtime_handle = Some(runtime_handle);
+        self
+    }
+
+    pub async fn build(mut self) -> Result<MetaDataClient> {
+        self.config = prefer_writable_host(&self.config);
+        let runtime_handle = self.runtime_handle.unwrap_or_else(runtime::Handle::current);
+        let (client, connection) = connect_on(self.config.clone(), &runtime_handle).await?;
+        let column_fallbacks = probe_column_fallbacks(&client).await?;
+        if column_fallbacks.is_degraded() {
+            warn!(
+                "metadata schema is outdated, running in degraded mode with defaults for missing columns: {:?}, \
+                missing tables: {:?}",
+                column_fallbacks.missing_columns(),
+                column_fallbacks.missing_tables()
+            );
+        }
+        let connection = Arc::new(std::sync::Mutex::new(connection));
+        let client = Arc::new(Mutex::new(client));
+        let prepared = Arc::new(Mutex::new(ConnectionStatements::new(column_fallbacks)));
+        let failover = self
+            .secondary_config
+            .map(|secondary_config| Arc::new(Failover::new(secondary_config, runtime_handle.clone())));
+        let failover_health_check = failover.as_ref().map(|failover| {
+            spawn_health_check(
+                failover.clone(),
+                self.config.clone(),
+                client.clone(),
+                prepared.clone(),
+                connection.clone(),
+                self.failback_interval,
+            )
+        });
+        let table_cache = match (self.table_cache_dir, self.table_cache_ttl) {
+            (Some(dir), ttl) => Some(Arc::new(TableCache::open(
+                &dir,
+                cache_source(&self.config),
+                ttl.unwrap_or(DEFAULT_TABLE_CACHE_TTL),
+                self.table_cache_grace_period,
+            ))),
+            (None, Some(ttl)) => Some(Arc::new(TableCache::in_memory(ttl))),
+            (None, None) => None,
+        };
+        if let Some(table_cache) = &table_cache {
+            if !table_cache.table_ids().is_empty() {
+                runtime_handle.spawn(refresh_table_cache(
+                    table_cache.clone(),
+                    client.clone(),
+                    prepared.clone(),
+                ));
+            }
+        }
+        let table_cache_listener = match &table_cache {
+            Some(table_cache) if self.table_cache_notifications => {
+                Some(listen_notifications(table_cache, self.config.clone(), &runtime_handle))
+            }
+            _ => None,
+        };
+        let client = MetaDataClient {
+            config: self.config,
+            client,
+            connection,
+            prepared,
+            max_retry: self.max_retry,
+            server_side_timestamp: self.server_side_timestamp,
+            default_namespace: self.default_namespace,
+            default_domain: self.default_domain,
+            default_provenance: self.default_provenance,
+            limiter: OpsLimiter::new(
+                self.max_concurrent_ops,
+                self.rate_limit_ops_per_sec,
+                self.fail_fast_when_limited,
+            ),
+            table_cache,
+            table_cache_listener: std::sync::Mutex::new(table_cache_listener),
+            write_limits: self.write_limits,
+            compaction_lock_wait: self.compaction_lock_wait,
+            clean_meta_database_pattern: self.clean_meta_database_pattern,
+            max_result_bytes: self.max_result_bytes,
+            retried_commits: AtomicU64::new(0),
+            duplicate_commit_ids: AtomicU64::new(0),
+            conflict_policy: self.conflict_policy,
+            conflict_policies: self.conflict_policies,
+            commit_hooks: RwLock::new(vec![]),
+            failover,
+            failover_health_check: std::sync::Mutex::new(failover_health_check),
+            trace_propagation: self.trace_propagation,
+            path_mode: self.path_mode,
+            strict_table_properties: self.strict_table_properties,
+            analyze_after_commit_rows: self.analyze_after_commit_rows,
+            schema_history_retention: self.schema_history_retention,
+            analyze_running: Arc::new(AtomicBool::new(false)),
+            analyzes_after_commit: Arc::new(AtomicU64::new(0)),
+            compatibility_warnings: CompatibilityWarnings::default(),
+            runtime_handle,
+            runtime: None,
+        };
+        if self.warmup {
+            client.warmup().await?;
+        }
+        Ok(client)
+    }
+
+    /// Build the client from a synchronous context, blocking the thread. Must not be called from within a runtime.
+    ///
+    /// The client is built on `runtime` if given, otherwise on a current-thread runtime started along with the
+    /// client on a thread of its own and stopped when the client is dropped. Either way that runtime drives the
+    /// database connections of the client afterward, so a given runtime must outlive the client. Operations of the
+    /// client may then be awaited from any tokio runtime.
+    pub fn build_blocking(self, runtime: Option<runtime::Handle>) -> Result<MetaDataClient> {
+        match runtime {
+            Some(runtime) => runtime.block_on(self.with_runtime_handle(runtime.clone()).build()),
+            None => {
+                let runtime = ClientRuntime::start()?;
+                let mut client = runtime
+                    .handle
+                    .block_on(self.with_runtime_handle(runtime.handle.clone()).build())?;
+                client.runtime = Some(runtime);
+                Ok(client)
             }
+        }
+    }
+}
+
+/// Format the pg config of a JDBC url such as `jdbc:postgresql://127.0.0.1:5432/lakesoul_test`.
+/// IPv6 hosts are given in brackets, `jdbc:postgresql://[::1]:5432/lakesoul_test`, and a Unix socket directory in
+/// the `host` query parameter as libpq does, `jdbc:postgresql:///lakesoul_test?host=/var/run/postgresql`.
+/// The port defaults to 5432.
+fn pg_config_from_jdbc_url(jdbc_url: &str, user: &str, password: &str) -> Result<String> {
+    let url = Url::parse(jdbc_url.strip_prefix("jdbc:").unwrap_or(jdbc_url))?;
+    let host = match url.query_pairs().find(|(key, _)| key == "host") {
+        Some((_, host)) => host.into_owned(),
+        None => match url.host() {
+            Some(url::Host::Ipv6(address)) => address.to_string(),
+            Some(host) if !host.to_string().is_empty() => host.to_string(),
             _ => {
-                todo!()
+                return Err(LakeSoulMetaDataError::InvalidConfig(format!(
+                    "{} has no host",
+                    jdbc_url
+                )))
+            }
+        },
+    };
+    let dbname = url
+        .path_segments()
+        .and_then(|mut segments| segments.next())
+        .filter(|dbname| !dbname.is_empty())
+        .ok_or_else(|| LakeSoulMetaDataError::InvalidConfig(format!("{} has no database", jdbc_url)))?;
+    let port = url.port().unwrap_or(5432).to_string();
+    Ok([
+        ("host", host.as_str()),
+        ("port", port.as_str()),
+        ("dbname", dbname),
+        ("user", user),
+        ("password", password),
+    ]
+    .iter()
+    .map(|(key, value)| format!("{}={}", key, config_value(value)))
+    .collect::<Vec<_>>()
+    .join(" "))
+}
+
+/// Quote a pg config value if needed, e.g. a socket path with spaces.
+fn config_value(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '\\' || c == '\'') {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Read back a pg config value written by [`config_value`].
+pub(crate) fn unquote_config_value(value: &str) -> String {
+    match value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+        Some(value) => {
+            let mut unquoted = String::new();
+            let mut chars = value.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
             }
+            unquoted
         }
+        None => value.to_string(),
+    }
+}
+
+/// The `lakesoul.pg.keepalives` property, `1` and `true` or `0` and `false` like the `keepalives` of libpq.
+fn parse_keepalives(value: &str) -> Result<bool> {
+    match value.trim() {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        value => Err(LakeSoulMetaDataError::InvalidConfig(format!(
+            "lakesoul.pg.keepalives={} is none of 0, 1, true and false",
+            value
+        ))),
     }
+}
 
-    async fn get_cur_partition_map(
+/// Replace the `key` property of a pg config, or add it.
+fn set_config_property(config: &str, key: &str, value: &str) -> String {
+    let prefix = format!("{}=", key);
+    config_properties(config)
+        .into_iter()
+        .filter(|property| !property.starts_with(&prefix))
+        .chain([format!("{}{}", prefix, config_value(value))])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Ask for the writable host of a pg config listing several hosts, e.g. the primary and its standbys, unless it
+/// sets `target_session_attrs` already. Writes to a standby fail with ReadOnlyDatabase.
+fn prefer_writable_host(config: &str) -> String {
+    let properties = config_properties(config);
+    let several_hosts = properties
+        .iter()
+        .any(|property| property.strip_prefix("host=").is_some_and(|hosts| hosts.contains(',')));
+    if several_hosts
+        && !properties
+            .iter()
+            .any(|property| property.starts_with("target_session_attrs="))
+    {
+        set_config_property(config, "target_session_attrs", "read-write")
+    } else {
+        config.to_string()
+    }
+}
+
+/// Split a pg config into its `key=value` properties, keeping quoted values whole.
+pub(crate) fn config_properties(config: &str) -> Vec<String> {
+    let mut properties = vec![];
+    let mut property = String::new();
+    let (mut quoted, mut escaped) = (false, false);
+    for c in config.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !property.is_empty() {
+                    properties.push(std::mem::take(&mut property));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        property.push(c);
+    }
+    if !property.is_empty() {
+        properties.push(property);
+    }
+    properties
+}
+
+impl MetaDataClient {
+    pub async fn from_env() -> Result<Self> {
+        MetaDataClientBuilder::from_env()?.build().await
+    }
+
+    pub async fn from_config(config: String) -> Result<Self> {
+        Self::from_config_and_max_retry(config, 3).await
+    }
+
+    pub async fn from_config_and_max_retry(config: String, max_retry: usize) -> Result<Self> {
+        MetaDataClientBuilder::new()
+            .with_config(config)
+            .with_max_retry(max_retry)
+            .build()
+            .await
+    }
+
+    /// [`MetaDataClient::from_config`] for synchronous contexts, see [`MetaDataClientBuilder::build_blocking`] for
+    /// the runtime driving the client.
+    pub fn from_config_blocking(config: String, runtime: Option<runtime::Handle>) -> Result<Self> {
+        MetaDataClientBuilder::new().with_config(config).build_blocking(runtime)
+    }
+
+    pub fn default_namespace(&self) -> &str {
+        &self.default_namespace
+    }
+
+    pub fn default_domain(&self) -> &str {
+        &self.default_domain
+    }
+
+    /// Attempts of each query, the `max_retry` of the client unless overridden by the options of the call.
+    fn max_retry(&self) -> usize {
+        current_max_retry(self.max_retry)
+    }
+
+    /// Run `operation`, made of calls to this client, with `options` overriding the retries and timeout of the
+    /// client, e.g. `client.run_with_options(options, client.get_table_info_by_table_id(table_id))`.
+    pub async fn run_with_options<T>(
         &self,
-        table_id: &str,
-        partition_desc_list: &[String],
-    ) -> Result<HashMap<String, PartitionInfo>> {
-        Ok(self
-            .get_partition_info_by_table_id_and_partition_list(table_id, partition_desc_list)
+        options: OpOptions,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        options.scope(operation).await?
+    }
+
+    /// Operations held back by the concurrency and rate limits of the client so far.
+    pub fn limiter_metrics(&self) -> LimiterMetrics {
+        self.limiter.metrics()
+    }
+
+    /// Lookups served by the table cache so far, all zero if it is disabled.
+    pub fn table_cache_metrics(&self) -> TableCacheMetrics {
+        self.table_cache
+            .as_ref()
+            .map(|table_cache| table_cache.metrics())
+            .unwrap_or_default()
+    }
+
+    /// Append and merge commits deduplicated against the current snapshot of their partitions so far.
+    pub fn commit_metrics(&self) -> CommitMetrics {
+        CommitMetrics {
+            retried_commits: self.retried_commits.load(atomic::Ordering::Relaxed),
+            duplicate_commit_ids: self.duplicate_commit_ids.load(atomic::Ordering::Relaxed),
+        }
+    }
+
+    /// ANALYZE runs completed after bulk commits so far, see [`MetaDataClientBuilder::with_analyze_after_commit_rows`].
+    pub fn analyzes_after_commit(&self) -> u64 {
+        self.analyzes_after_commit.load(atomic::Ordering::Relaxed)
+    }
+
+    /// The SQL template and parameter types of a DAO as prepared on the database of the client, for debugging.
+    pub async fn explain_dao(&self, dao: DaoType) -> Result<DaoDescription> {
+        Ok(describe_dao(&self.prepared.lock().await.context.column_fallbacks, dao))
+    }
+
+    /// Drop the cached entries of `table_id`, for callers learning of a write by another process.
+    pub fn invalidate_table_cache(&self, table_id: &str) {
+        self.invalidate_cached_table(Some(table_id), None, None);
+    }
+
+    /// Drop all the cached entries.
+    pub fn invalidate_all_cache(&self) {
+        if let Some(table_cache) = &self.table_cache {
+            table_cache.invalidate_all();
+        }
+    }
+
+    /// Close the client before shutting down: new operations fail with ClientClosed from now on, while those in
+    /// flight, commits as a whole, are waited for up to `timeout`. The queries still running then are cancelled on
+    /// the primary and the secondary database, which rolls back their transactions. Changes of the table cache not
+    /// saved yet are written to its file. Then the table cache notifications are stopped, and the connections to
+    /// the primary and the secondary ended and awaited. Closing again only reports the operations in flight.
+    pub async fn close(&self, timeout: Duration) -> Result<CloseReport> {
+        let closed = self.limiter.close();
+        // the health check would otherwise reopen the connection ended below
+        for task in [&self.table_cache_listener, &self.failover_health_check] {
+            let task = task.lock().unwrap().take();
+            if let Some(task) = task {
+                task.abort();
+                let _ = task.await;
+            }
+        }
+        if let Some(table_cache) = &self.table_cache {
+            table_cache.flush().await;
+        }
+        let in_flight = self.limiter.in_flight();
+        let mut cancelled = 0;
+        if !self.limiter.drain(timeout).await {
+            cancelled = self.limiter.in_flight();
+            warn!(
+                "{} metadata operations still in flight after {:?}, cancelling them",
+                cancelled, timeout
+            );
+            let cancel_token = self.connection.lock().unwrap().cancel_token.clone();
+            cancel_token.cancel_query(NoTls).await?;
+            if let Some(failover) = &self.failover {
+                failover.cancel_query().await?;
+            }
+            // the cancelled operations fail right away, unless retried on a connection being lost
+            self.limiter.drain(timeout).await;
+        }
+        if let Some(failover) = &self.failover {
+            failover.close().await;
+        }
+        // the client of the primary is shared with the background tasks, the task driving the connection is ended
+        let task = self.connection.lock().unwrap().task.take();
+        if let Some(task) = task {
+            task.abort();
+            let _ = task.await;
+        }
+        Ok(CloseReport {
+            in_flight,
+            cancelled,
+            closed,
+        })
+    }
+
+    /// Whether reads are served by the secondary database since the primary cannot be reached, so they may miss
+    /// the latest writes. Always false without a secondary, see [`MetaDataClientBuilder::with_secondary_config`].
+    pub fn is_serving_stale_reads(&self) -> bool {
+        self.failover
+            .as_ref()
+            .is_some_and(|failover| failover.is_primary_down())
+    }
+
+    /// What this client passed through without understanding it, e.g. the commit ops and table property keys
+    /// added by newer clients during a rolling upgrade, since it was built.
+    pub fn compatibility_warnings(&self) -> Vec<CompatibilityWarning> {
+        self.compatibility_warnings.list()
+    }
+
+    pub async fn health_report(&self) -> HealthReport {
+        let client = self.client.lock().await;
+        let connected = client.simple_query("SELECT 1").await.is_ok();
+        let read_only = connected && is_read_only(&client).await.unwrap_or(false);
+        drop(client);
+        let prepared = self.prepared.lock().await;
+        let column_fallbacks = &prepared.context.column_fallbacks;
+        HealthReport {
+            connected,
+            read_only,
+            missing_optional_columns: column_fallbacks
+                .missing_columns()
+                .iter()
+                .map(|missing| format!("{}.{}", missing.table, missing.column))
+                .collect(),
+            missing_optional_tables: column_fallbacks.missing_tables().to_vec(),
+            write_limits: self.write_limits.clone(),
+        }
+    }
+
+    /// Validate the connection and prepare the statements of all DAOs, which are otherwise prepared by the first
+    /// operation running them. A secondary database is connected to and prepared as well, failing to reach it
+    /// is only logged since it is needed while the primary is down alone.
+    pub async fn warmup(&self) -> Result<()> {
+        let _permit = self.limiter.acquire().await?;
+        let client = self.client.lock().await;
+        client.simple_query("SELECT 1").await?;
+        let mut statements = self.prepared.lock().await;
+        let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+        let count = prepare_all_statements_with_context(client.deref(), prepared, context).await?;
+        debug!(
+            "warmed up the metadata connection, {} statements prepared, {} in total",
+            count,
+            prepared.len()
+        );
+        drop(statements);
+        drop(client);
+        if let Some(failover) = &self.failover {
+            if let Err(e) = failover.warmup().await {
+                warn!("secondary metadata database not warmed up: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a read-only view of the catalog at the current point in time, on a connection of its own so the
+    /// client stays usable meanwhile. All reads of the snapshot observe the same catalog state.
+    pub async fn begin_read_snapshot(&self) -> Result<ReadSnapshot> {
+        let client = create_connection_on(self.config.clone(), &self.runtime_handle).await?;
+        // a repeatable read transaction takes its snapshot at the first statement, not at begin
+        client
+            .batch_execute("begin isolation level repeatable read read only; select 1;")
+            .await?;
+        let column_fallbacks = self.prepared.lock().await.context.column_fallbacks.clone();
+        Ok(ReadSnapshot {
+            client,
+            prepared: Mutex::new(ConnectionStatements::new(column_fallbacks)),
+            default_namespace: self.default_namespace.clone(),
+            max_result_bytes: self.max_result_bytes,
+            path_mode: self.path_mode,
+            compatibility_warnings: self.compatibility_warnings.clone(),
+        })
+    }
+
+    /// Capture the current version of each partition of the table, to resolve the files of a query from the same
+    /// versions at plan and at execution time. See [`Self::open_pinned_read_snapshot`] to keep the versions from
+    /// being expired meanwhile.
+    pub async fn open_read_snapshot(&self, table_id: &str) -> Result<TableReadSnapshot<'_>> {
+        let partition_info = self.get_all_partition_info(table_id).await?;
+        Ok(TableReadSnapshot {
+            client: self,
+            snapshot: entity::TableReadSnapshot {
+                table_id: table_id.to_string(),
+                partition_info,
+                pin_tag: String::new(),
+            },
+        })
+    }
+
+    /// Like [`Self::open_read_snapshot`], with the captured versions pinned by a tag so that
+    /// [`Self::expire_partition_versions`] keeps them until [`TableReadSnapshot::release`].
+    pub async fn open_pinned_read_snapshot(&self, table_id: &str) -> Result<TableReadSnapshot<'_>> {
+        let pin_tag = format!("{}{}", READ_SNAPSHOT_TAG_PREFIX, uuid::Uuid::new_v4().simple());
+        self.create_tag(table_id, &pin_tag).await?;
+        // the tag records the latest versions atomically, which are read back as the snapshot
+        let partition_info = self.read_at_tag(table_id, &pin_tag).await?;
+        Ok(TableReadSnapshot {
+            client: self,
+            snapshot: entity::TableReadSnapshot {
+                table_id: table_id.to_string(),
+                partition_info,
+                pin_tag,
+            },
+        })
+    }
+
+    /// Rebuild a read snapshot encoded by [`TableReadSnapshot::encode_to_vec`], e.g. on an executor.
+    pub fn decode_read_snapshot(&self, bytes: &[u8]) -> Result<TableReadSnapshot<'_>> {
+        Ok(TableReadSnapshot {
+            client: self,
+            snapshot: entity::TableReadSnapshot::decode(bytes)?,
+        })
+    }
+
+    /// Versions of the partition pinned by read snapshots.
+    async fn read_snapshot_pinned_versions(&self, table_id: &str, partition_desc: &str) -> Result<HashSet<i32>> {
+        if self.is_table_missing("table_tag").await {
+            return Ok(HashSet::new());
+        }
+        let mut pinned = HashSet::new();
+        let table_tags = self
+            .execute_query(DaoType::ListTableTagByTableId as i32, table_id.to_string())
             .await?
-            .iter()
-            .map(|partition_info| (partition_info.partition_desc.clone(), partition_info.clone()))
-            .collect())
+            .table_tag;
+        for table_tag in table_tags {
+            if !table_tag.tag.starts_with(READ_SNAPSHOT_TAG_PREFIX) {
+                continue;
+            }
+            let partition_info = self
+                .execute_query(
+                    DaoType::ListPartitionByTableIdAndTag as i32,
+                    encode_query_params(&[table_id, table_tag.tag.as_str()]),
+                )
+                .await?
+                .partition_info;
+            pinned.extend(
+                partition_info
+                    .iter()
+                    .filter(|partition_info| partition_info.partition_desc == partition_desc)
+                    .map(|partition_info| partition_info.version),
+            );
+        }
+        Ok(pinned)
     }
 
-    pub async fn commit_data_commit_info(&self, data_commit_info: DataCommitInfo) -> Result<()> {
-        let table_id = &data_commit_info.table_id;
-        let partition_desc = &data_commit_info.partition_desc;
-        let commit_op = data_commit_info.commit_op;
-        let commit_id = &data_commit_info
-            .commit_id
-            .clone()
-            .ok_or(LakeSoulMetaDataError::Internal("commit_id missing".to_string()))?;
-        let commit_id_str = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string();
-        match self
-            .get_single_data_commit_info(table_id, partition_desc, &commit_id_str)
+    fn namespace_or_default<'a>(&'a self, namespace: &'a str) -> &'a str {
+        if namespace.is_empty() {
+            &self.default_namespace
+        } else {
+            namespace
+        }
+    }
+
+    pub async fn create_namespace(&self, mut namespace: Namespace) -> Result<()> {
+        if namespace.domain.is_empty() {
+            namespace.domain = self.default_domain.clone();
+        }
+        self.write_limits.check_name(&namespace.namespace)?;
+        self.write_limits.check_properties(&namespace.properties)?;
+        self.insert_namespace(&namespace).await?;
+        Ok(())
+    }
+
+    /// Create the table, inheriting the properties of its namespace prefixed by `table.default.`, e.g.
+    /// `table.default.hashBucketNum`, unless the table sets them itself. A table created without a domain belongs
+    /// to the domain of its namespace. See [`Self::create_table_with_outcome`] for the effective properties.
+    pub async fn create_table(&self, table_info: TableInfo) -> Result<()> {
+        self.create_table_with_outcome(table_info).await?;
+        Ok(())
+    }
+
+    /// [`Self::create_table`] returning the effective properties of the table, e.g. for callers to log them.
+    pub async fn create_table_with_outcome(&self, table_info: TableInfo) -> Result<CreateTableOutcome> {
+        let table_info = self.new_table_info(table_info).await?;
+        let result = async {
+            self.insert_table_path_id(&table_path_id_from_table_info(&table_info))
+                .await?;
+            self.insert_table_name_id(&table_name_id_from_table_info(&table_info))
+                .await?;
+            self.insert_table_info(&table_info).await
+        }
+        .await;
+        // a cached table of the same name or path has been dropped by someone else
+        self.invalidate_new_table(&table_info);
+        result?;
+        Ok(CreateTableOutcome {
+            created: true,
+            properties: table_info.properties,
+        })
+    }
+
+    /// Like [`Self::create_table`], leaving the table of the same name in the namespace as is if there is one.
+    /// The table is inserted in one transaction, so that concurrent calls create it once.
+    pub async fn create_table_if_not_exists(&self, table_info: TableInfo) -> Result<CreateTableOutcome> {
+        let table_info = self.new_table_info(table_info).await?;
+        let created = self.insert_table_if_not_exists(&table_info).await;
+        self.invalidate_new_table(&table_info);
+        if created? {
+            return Ok(CreateTableOutcome {
+                created: true,
+                properties: table_info.properties,
+            });
+        }
+        let existing = self
+            .existing_table_outcome(&table_info.table_name, &table_info.table_namespace)
+            .await?;
+        // dropped by someone else meanwhile
+        existing.ok_or_else(|| {
+            LakeSoulMetaDataError::NotFound(format!(
+                "Table '{}' in '{}' dropped while being created",
+                table_info.table_name, table_info.table_namespace
+            ))
+        })
+    }
+
+    /// The table to create from `table_info`, with the defaults of the client and of its namespace filled in.
+    async fn new_table_info(&self, mut table_info: TableInfo) -> Result<TableInfo> {
+        if table_info.table_id.is_empty() || table_info.table_name.is_empty() || table_info.table_path.is_empty() {
+            return Err(LakeSoulMetaDataError::InvalidInput(
+                "table_id, table_name and table_path of a new table must not be empty".to_string(),
+            ));
+        }
+        table_info.table_namespace = self.namespace_or_default(&table_info.table_namespace).to_string();
+        let namespace = self
+            .execute_query(
+                DaoType::SelectNamespaceByNamespace as i32,
+                encode_query_params(&[table_info.table_namespace.as_str()]),
+            )
             .await?
-        {
-            Some(data_commit_info) if data_commit_info.committed => {
-                return Ok(());
+            .namespace
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        // a table without a domain of its own belongs to the domain of its namespace
+        if table_info.domain.is_empty() {
+            table_info.domain = if namespace.domain.is_empty() {
+                self.default_domain.clone()
+            } else {
+                namespace.domain
+            };
+        }
+        table_info.properties = merge_namespace_table_defaults(
+            &namespace.properties,
+            &table_info.properties,
+            &self.compatibility_warnings,
+        )?;
+        self.write_limits.check_table_info(&table_info)?;
+        self.check_table_properties(&table_info.table_name, &table_info.properties)?;
+        Ok(table_info)
+    }
+
+    /// Drop the cached tables of the same name or path as the new table, which have been dropped by someone else.
+    fn invalidate_new_table(&self, table_info: &TableInfo) {
+        self.invalidate_cached_table(
+            Some(&table_info.table_id),
+            Some((table_info.table_namespace.as_str(), table_info.table_name.as_str())),
+            Some(table_info.table_path.as_str()),
+        );
+    }
+
+    async fn existing_table_outcome(&self, table_name: &str, namespace: &str) -> Result<Option<CreateTableOutcome>> {
+        match self.fetch_table_info_by_table_name(table_name, namespace).await {
+            Ok(table_info) => Ok(Some(CreateTableOutcome {
+                created: false,
+                properties: table_info.properties,
+            })),
+            Err(LakeSoulMetaDataError::NotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn delete_namespace_by_namespace(&self, namespace: &str) -> Result<()> {
+        debug!("delete namespace {}", namespace);
+        self.execute_update(
+            DaoType::DeleteNamespaceByNamespace as i32,
+            encode_query_params(&[namespace]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Delete a table with all its partition versions, data commits, stats, provenance, tags and schema history in
+    /// one transaction, so that a failure leaves the table in place.
+    pub async fn delete_table_by_table_info_cascade(&self, table_info: &TableInfo) -> Result<()> {
+        let result = {
+            let (mut client, mut statements) = self.lock_connection().await;
+            self.delete_table_cascade_on(
+                &mut client,
+                &mut statements,
+                &table_info.table_id,
+                &table_info.table_path,
+            )
+            .await
+        };
+        self.invalidate_cached_table(
+            Some(&table_info.table_id),
+            Some((&table_info.table_name, &table_info.table_namespace)),
+            Some(&table_info.table_path),
+        );
+        result?;
+        Ok(())
+    }
+
+    /// Delete all the tables of `namespace` as [`Self::delete_table_by_table_info_cascade`] does, up to
+    /// `parallelism` at once on connections of their own. `progress` is called once each table is handled, and
+    /// the tables failing to be deleted are reported in the summary, left in place, rather than stopping the others.
+    pub async fn drop_all_tables_in_namespace(
+        &self,
+        namespace: &str,
+        parallelism: usize,
+        progress: Option<Box<dyn Fn(DropProgress) + Send>>,
+    ) -> Result<DropSummary> {
+        let tables = self.get_all_table_name_id_by_namespace(namespace).await?;
+        let names = tables
+            .iter()
+            .map(|table| (table.table_name.clone(), table.table_namespace.clone()))
+            .collect::<Vec<_>>();
+        let mut table_infos = self.get_table_infos(&names).await?;
+        // a table gone meanwhile has no path, leaving only its other rows to delete
+        let tables = tables
+            .into_iter()
+            .map(|table| {
+                let table_path = table_infos
+                    .remove(&(table.table_name.clone(), table.table_namespace.clone()))
+                    .map(|table_info| table_info.table_path)
+                    .unwrap_or_default();
+                (table, table_path)
+            })
+            .collect::<Vec<_>>();
+        let total = tables.len();
+        let column_fallbacks = self.prepared.lock().await.context.column_fallbacks.clone();
+        let mut connections = Vec::with_capacity(parallelism.max(1).min(total));
+        for _ in 0..parallelism.max(1).min(total) {
+            connections.push(create_connection_on(self.config.clone(), &self.runtime_handle).await?);
+        }
+        let tables = &Mutex::new(tables.into_iter());
+        let summary = Mutex::new(DropSummary::default());
+        // a mutex makes the callback shareable among the workers
+        let progress = &progress.map(std::sync::Mutex::new);
+        let workers = connections.into_iter().map(|mut client| {
+            let mut statements = ConnectionStatements::new(column_fallbacks.clone());
+            let summary = &summary;
+            async move {
+                loop {
+                    let Some((table, table_path)) = tables.lock().await.next() else {
+                        return;
+                    };
+                    let result = self
+                        .delete_table_cascade_on(&mut client, &mut statements, &table.table_id, &table_path)
+                        .await;
+                    self.invalidate_cached_table(
+                        Some(&table.table_id),
+                        Some((&table.table_name, &table.table_namespace)),
+                        Some(&table_path),
+                    );
+                    let dropped = result.is_ok();
+                    let done = {
+                        let mut summary = summary.lock().await;
+                        match result {
+                            Ok(_) => summary.dropped.push(table.table_name.clone()),
+                            Err(e) => summary.failed.push((table.table_name.clone(), e)),
+                        }
+                        summary.dropped.len() + summary.failed.len()
+                    };
+                    if let Some(progress) = progress {
+                        (progress.lock().unwrap())(DropProgress {
+                            table_id: table.table_id,
+                            table_name: table.table_name,
+                            dropped,
+                            done,
+                            total,
+                        });
+                    }
+                }
+            }
+        });
+        futures::future::join_all(workers).await;
+        Ok(summary.into_inner())
+    }
+
+    /// The transaction of [`Self::delete_table_by_table_info_cascade`] on a connection of the caller.
+    async fn delete_table_cascade_on(
+        &self,
+        client: &mut Client,
+        statements: &mut ConnectionStatements,
+        table_id: &str,
+        table_path: &str,
+    ) -> Result<i32> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match execute_updates_in_transaction(
+                client,
+                &mut statements.prepared,
+                &statements.context,
+                table_cascade_deletes(&statements.context.column_fallbacks, table_id, table_path),
+            )
+            .await
+            {
+                Ok(count) => return Ok(count),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Remove all the partition versions and committed data commits of a table in one transaction, with their
+    /// stats, provenance and tags, keeping the table itself. Fails with NotFound if the table does not exist.
+    /// Data files are left to the caller.
+    pub async fn truncate_table(&self, table_id: &str) -> Result<()> {
+        self.execute_update(DaoType::TruncateTableByTableId as i32, encode_query_params(&[table_id]))
+            .await?;
+        Ok(())
+    }
+
+    /// Move a table into `new_namespace`, updating table_info, table_name_id and table_path_id in one transaction.
+    /// Fails with AlreadyExists if the target namespace has a table of the same name.
+    pub async fn move_table_to_namespace(&self, table_id: &str, new_namespace: &str) -> Result<()> {
+        self.write_limits.check_name(new_namespace)?;
+        let result = self
+            .execute_update(
+                DaoType::UpdateTableNamespaceByTableId as i32,
+                encode_query_params(&[table_id, new_namespace]),
+            )
+            .await;
+        self.invalidate_cached_table(Some(table_id), None, None);
+        result?;
+        Ok(())
+    }
+
+    pub async fn delete_table_path_id_by_table_id(&self, table_id: &str) -> Result<i32> {
+        let result = self
+            .execute_update(
+                DaoType::DeleteTablePathIdByTableId as i32,
+                encode_query_params(&[table_id]),
+            )
+            .await;
+        self.invalidate_cached_table(Some(table_id), None, None);
+        result
+    }
+
+    pub async fn delete_table_name_id_by_table_id(&self, table_id: &str) -> Result<i32> {
+        let result = self
+            .execute_update(
+                DaoType::DeleteTableNameIdByTableId as i32,
+                encode_query_params(&[table_id]),
+            )
+            .await;
+        self.invalidate_cached_table(Some(table_id), None, None);
+        result
+    }
+
+    pub async fn delete_partition_info_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeletePartitionInfoByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+    pub async fn delete_data_commit_info_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeleteDataCommitInfoByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_partition_stats_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeletePartitionStatsByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_file_stats_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeleteFileStatsByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_commit_provenance_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeleteCommitProvenanceByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_table_tag_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeleteTableTagByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_table_schema_history_by_table_id(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeleteTableSchemaHistoryByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_table_info_by_id_and_path(&self, id: &str, path: &str) -> Result<i32> {
+        let result = self
+            .execute_update(
+                DaoType::DeleteTableInfoByIdAndPath as i32,
+                encode_query_params(&[id, path]),
+            )
+            .await;
+        self.invalidate_cached_table(Some(id), None, Some(path));
+        result
+    }
+
+    pub async fn update_table_properties(&self, table_id: &str, properties: &str) -> Result<i32> {
+        self.write_limits.check_properties(properties)?;
+        self.check_table_properties(table_id, properties)?;
+        let result = self
+            .execute_update(
+                DaoType::UpdateTableInfoPropertiesById as i32,
+                encode_query_params(&[table_id, properties]),
+            )
+            .await;
+        self.invalidate_cached_table(Some(table_id), None, None);
+        result
+    }
+
+    /// Log the issues [`validate_table_properties`] finds in the properties json of `table`, or fail with them
+    /// if the client is strict. Properties that are not a json object are left to the other checks.
+    fn check_table_properties(&self, table: &str, properties: &str) -> Result<()> {
+        let Ok(properties) = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(properties) else {
+            return Ok(());
+        };
+        let properties = properties
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            })
+            .collect::<HashMap<String, String>>();
+        let issues = validate_table_properties(&properties)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+        if issues.is_empty() {
+            return Ok(());
+        }
+        if self.strict_table_properties {
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "properties of table {}: {}",
+                table,
+                issues.join("; ")
+            )));
+        }
+        warn!("properties of table {}: {}", table, issues.join("; "));
+        Ok(())
+    }
+
+    /// Change the name, path or schema of the table in table_info only, table_name_id and table_path_id are left
+    /// to the caller. Nothing is updated if `update` sets no field.
+    pub async fn update_table_info(&self, table_id: &str, update: &TableInfoUpdate) -> Result<i32> {
+        if update.is_empty() {
+            return Ok(0);
+        }
+        if let Some(table_name) = &update.table_name {
+            self.write_limits.check_name(table_name)?;
+        }
+        if let Some(table_schema) = &update.table_schema {
+            self.write_limits.check_schema(table_schema)?;
+        }
+        let result = self
+            .execute_update(DaoType::UpdateTableInfoById as i32, update.joined_params(table_id))
+            .await;
+        self.invalidate_cached_table(Some(table_id), None, update.table_path.as_deref());
+        if let (Ok(_), Some(_), Some(retained)) = (&result, &update.table_schema, self.schema_history_retention) {
+            if !self.is_table_missing("table_schema_history").await {
+                self.prune_schema_history(table_id, retained).await?;
+            }
+        }
+        result
+    }
+
+    /// Delete the replaced schemas of the table but the latest `retained` ones.
+    async fn prune_schema_history(&self, table_id: &str, retained: usize) -> Result<u64> {
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match prune_schema_history(self.client.lock().await.deref(), table_id, retained as i32).await {
+                Ok(deleted) => return Ok(deleted),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Change the schema of the table, the replaced schema is kept for [`Self::get_schema_at_version`] and
+    /// [`Self::get_schema_history`] within the retention of the client.
+    pub async fn update_table_schema(&self, table_id: &str, table_schema: &str) -> Result<i32> {
+        self.update_table_info(
+            table_id,
+            &TableInfoUpdate {
+                table_schema: Some(table_schema.to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    pub async fn update_namespace_properties(&self, namespace: &str, properties: &str) -> Result<i32> {
+        self.write_limits.check_properties(properties)?;
+        self.execute_update(
+            DaoType::UpdateNamespacePropertiesByNamespace as i32,
+            encode_query_params(&[namespace, properties]),
+        )
+        .await
+    }
+
+    pub async fn delete_table_path_id_by_table_path(&self, table_path: &str) -> Result<i32> {
+        let result = self
+            .execute_update(
+                DaoType::DeleteTablePathIdByTablePath as i32,
+                encode_query_params(&[table_path]),
+            )
+            .await;
+        self.invalidate_cached_table(None, None, Some(table_path));
+        result
+    }
+
+    pub async fn delete_table_name_id_by_table_name(&self, table_name: &str, namespace: &str) -> Result<i32> {
+        let namespace = self.namespace_or_default(namespace);
+        let result = self
+            .execute_update(
+                DaoType::DeleteTableNameIdByTableNameAndNamespace as i32,
+                encode_query_params(&[table_name, namespace]),
+            )
+            .await;
+        self.invalidate_cached_table(None, Some((namespace, table_name)), None);
+        result
+    }
+
+    pub async fn delete_partition_info_by_table_id_and_partition_desc(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+    ) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeletePartitionInfoByTableIdAndPartitionDesc as i32,
+            encode_query_params(&[table_id, partition_desc]),
+        )
+        .await
+    }
+
+    /// Delete the versions of the partition committed at or before `timestamp`, the latest one included.
+    pub async fn delete_previous_version_partition(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        timestamp: i64,
+    ) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeletePreviousVersionPartition as i32,
+            encode_query_params(&[table_id, partition_desc, &timestamp.to_string()]),
+        )
+        .await
+    }
+
+    pub async fn delete_single_data_commit_info(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        commit_id: &entity::Uuid,
+    ) -> Result<i32> {
+        let commit_id = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string();
+        self.execute_update(
+            DaoType::DeleteOneDataCommitInfoByTableIdAndPartitionDescAndCommitId as i32,
+            encode_query_params(&[table_id, partition_desc, &commit_id]),
+        )
+        .await
+    }
+
+    pub async fn delete_data_commit_info_by_commit_ids(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        commit_ids: &[entity::Uuid],
+    ) -> Result<i32> {
+        if commit_ids.is_empty() {
+            return Ok(0);
+        }
+        // the DAO takes the ids as concatenated 32 hex digit strings
+        let concated_uuid = commit_ids
+            .iter()
+            .map(|commit_id| format!("{:016x}{:016x}", commit_id.high, commit_id.low))
+            .collect::<String>();
+        self.execute_update(
+            DaoType::DeleteDataCommitInfoByTableIdAndPartitionDescAndCommitIdList as i32,
+            encode_query_params(&[table_id, partition_desc, &concated_uuid]),
+        )
+        .await
+    }
+
+    pub async fn delete_data_commit_info_by_table_id_and_partition_desc(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+    ) -> Result<i32> {
+        self.execute_update(
+            DaoType::DeleteDataCommitInfoByTableIdAndPartitionDesc as i32,
+            encode_query_params(&[table_id, partition_desc]),
+        )
+        .await
+    }
+
+    /// Drop every version and data commit of the partition.
+    /// File stats and provenance of its commits stay until the table is deleted.
+    pub async fn delete_partition_cascade(&self, table_id: &str, partition_desc: &str) -> Result<()> {
+        self.delete_partition_info_by_table_id_and_partition_desc(table_id, partition_desc)
+            .await?;
+        self.delete_data_commit_info_by_table_id_and_partition_desc(table_id, partition_desc)
+            .await?;
+        Ok(())
+    }
+
+    /// See [`Self::expire_partition_versions`] and [`Self::run_with_options`].
+    pub async fn expire_partition_versions_with_options(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        timestamp: i64,
+        options: OpOptions,
+    ) -> Result<i32> {
+        self.run_with_options(
+            options,
+            self.expire_partition_versions(table_id, partition_desc, timestamp),
+        )
+        .await
+    }
+
+    /// Drop the versions of the partition committed at or before `timestamp` except the latest one, together with
+    /// the data commits no remaining version refers to. Versions pinned by read snapshots are kept, along with the
+    /// later ones. Returns the number of dropped versions.
+    pub async fn expire_partition_versions(&self, table_id: &str, partition_desc: &str, timestamp: i64) -> Result<i32> {
+        let versions = self
+            .execute_query(
+                DaoType::ListPartitionByTableIdAndDesc as i32,
+                encode_query_params(&[table_id, partition_desc]),
+            )
+            .await?
+            .partition_info;
+        let Some(latest) = versions.iter().max_by_key(|partition_info| partition_info.version) else {
+            return Ok(0);
+        };
+        let pinned = self.read_snapshot_pinned_versions(table_id, partition_desc).await?;
+        let timestamp = versions
+            .iter()
+            .filter(|partition_info| pinned.contains(&partition_info.version))
+            .map(|partition_info| partition_info.timestamp - 1)
+            .fold(timestamp.min(latest.timestamp - 1), i64::min);
+        let (expired, remaining): (Vec<_>, Vec<_>) = versions
+            .iter()
+            .partition(|partition_info| partition_info.timestamp <= timestamp);
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        let referenced = remaining
+            .iter()
+            .flat_map(|partition_info| partition_info.snapshot.iter())
+            .map(|commit_id| (commit_id.high, commit_id.low))
+            .collect::<HashSet<_>>();
+        let mut unreferenced = HashSet::new();
+        let dropped_commits = expired
+            .iter()
+            .flat_map(|partition_info| partition_info.snapshot.iter())
+            .filter(|commit_id| {
+                !referenced.contains(&(commit_id.high, commit_id.low))
+                    && unreferenced.insert((commit_id.high, commit_id.low))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let count = self
+            .delete_previous_version_partition(table_id, partition_desc, timestamp)
+            .await?;
+        self.delete_data_commit_info_by_commit_ids(table_id, partition_desc, &dropped_commits)
+            .await?;
+        Ok(count)
+    }
+
+    async fn set_table_property(&self, table_id: &str, key: &str, value: String) -> Result<()> {
+        // not from the table cache, which may be behind other writers
+        let table_info = self.fetch_table_info_by_table_id(table_id).await?;
+        let mut properties: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&table_info.properties)?;
+        self.compatibility_warnings.pass_through_properties(&properties);
+        properties.insert(key.to_string(), serde_json::Value::String(value));
+        self.update_table_properties(table_id, &serde_json::to_string(&properties)?)
+            .await?;
+        Ok(())
+    }
+
+    /// Value of `key` in the table properties, None if the key is absent.
+    /// Fails with NotFound if there is no such table.
+    pub async fn get_table_property(&self, table_id: &str, key: &str) -> Result<Option<String>> {
+        match self
+            .execute_query_scalar_value(
+                DaoType::GetTablePropertyByTableIdAndKey as i32,
+                encode_query_params(&[table_id, key]),
+            )
+            .await?
+        {
+            ScalarValue::Value(value) => Ok(Some(value)),
+            ScalarValue::Null => Ok(None),
+            ScalarValue::NoRows => Err(LakeSoulMetaDataError::NotFound(format!(
+                "Table '{}' not found",
+                table_id
+            ))),
+        }
+    }
+
+    pub async fn set_change_tracking_enabled(&self, table_id: &str, enabled: bool) -> Result<()> {
+        self.set_table_property(table_id, CHANGE_TRACKING_ENABLED, enabled.to_string())
+            .await
+    }
+
+    pub async fn is_change_tracking_enabled(&self, table_id: &str) -> Result<bool> {
+        let table_info = self.get_table_info_by_table_id(table_id).await?;
+        Ok(change_tracking_enabled(&table_info))
+    }
+
+    /// Opt the table in or out of maintaining [`PartitionStats`] on every commit.
+    /// Enabling it does not backfill existing partitions, see [`MetaDataClient::rebuild_partition_stats`].
+    pub async fn set_partition_stats_enabled(&self, table_id: &str, enabled: bool) -> Result<()> {
+        self.set_table_property(table_id, PARTITION_STATS_ENABLED, enabled.to_string())
+            .await
+    }
+
+    /// File statistics of the latest version of the partition, `None` if they are not maintained for it.
+    pub async fn get_partition_stats_fast(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+    ) -> Result<Option<PartitionStats>> {
+        Ok(self
+            .execute_query(
+                DaoType::SelectPartitionStatsByTableIdAndPartitionDesc as i32,
+                encode_query_params(&[table_id, partition_desc]),
+            )
+            .await?
+            .partition_stats
+            .pop())
+    }
+
+    /// Recompute the [`PartitionStats`] of every partition of the table from its data commits.
+    pub async fn rebuild_partition_stats(&self, table_id: &str) -> Result<i32> {
+        self.execute_update(
+            DaoType::RebuildPartitionStatsByTableId as i32,
+            encode_query_params(&[table_id]),
+        )
+        .await
+    }
+
+    fn provenance_or_default(&self, provenance: CommitProvenance) -> CommitProvenance {
+        let Some(default_provenance) = &self.default_provenance else {
+            return provenance;
+        };
+        let or_default = |value: String, default_value: &str| {
+            if value.is_empty() {
+                default_value.to_string()
+            } else {
+                value
+            }
+        };
+        CommitProvenance {
+            engine: or_default(provenance.engine, &default_provenance.engine),
+            job_id: or_default(provenance.job_id, &default_provenance.job_id),
+            user: or_default(provenance.user, &default_provenance.user),
+            checkpoint_id: or_default(provenance.checkpoint_id, &default_provenance.checkpoint_id),
+            ..provenance
+        }
+    }
+
+    /// Serve `key` from the table cache if enabled, otherwise run `fetch` and cache its result.
+    async fn cached<T: CachedEntity>(&self, key: CacheKey, fetch: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(table_cache) = &self.table_cache else {
+            return fetch.await;
+        };
+        if let Some(value) = table_cache.get(&key) {
+            return Ok(value);
+        }
+        // the fetch has not started yet, a write racing with it bumps the generation
+        let generation = table_cache.generation();
+        let value = fetch.await?;
+        table_cache.put(generation, value.clone());
+        Ok(value)
+    }
+
+    fn invalidate_cached_table(
+        &self,
+        table_id: Option<&str>,
+        table_name: Option<(&str, &str)>,
+        table_path: Option<&str>,
+    ) {
+        if let Some(table_cache) = &self.table_cache {
+            table_cache.invalidate(table_id, table_name, table_path);
+        }
+    }
+
+    async fn is_table_missing(&self, table: &str) -> bool {
+        self.prepared
+            .lock()
+            .await
+            .context
+            .column_fallbacks
+            .is_table_missing(table)
+    }
+
+    async fn execute_insert(&self, insert_type: i32, wrapper: JniWrapper) -> Result<i32> {
+        self.execute_insert_with_commit_timestamp(insert_type, wrapper, None)
+            .await
+    }
+
+    async fn execute_insert_with_commit_timestamp(
+        &self,
+        insert_type: i32,
+        wrapper: JniWrapper,
+        commit_timestamp: Option<i64>,
+    ) -> Result<i32> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (mut client, mut statements) = self.lock_connection().await;
+            let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+            match execute_insert_with_context(
+                client.deref_mut(),
+                prepared,
+                context,
+                insert_type,
+                wrapper.clone(),
+                self.server_side_timestamp,
+                commit_timestamp,
+            )
+            .await
+            {
+                Ok(count) => return Ok(count),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    async fn execute_update(&self, update_type: i32, joined_string: String) -> Result<i32> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (mut client, mut statements) = self.lock_connection().await;
+            let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+            match execute_update_with_context(
+                client.deref_mut(),
+                prepared,
+                context,
+                update_type,
+                joined_string.clone(),
+            )
+            .await
+            {
+                Ok(count) => return Ok(count),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Reads fail over to the secondary if any, see [`MetaDataClientBuilder::with_secondary_config`].
+    async fn execute_query(&self, query_type: i32, joined_string: String) -> Result<JniWrapper> {
+        self.compatibility_warnings
+            .record(self.execute_query_recorded(query_type, joined_string))
+            .await
+    }
+
+    async fn execute_query_recorded(&self, query_type: i32, joined_string: String) -> Result<JniWrapper> {
+        let _permit = self.limiter.acquire().await?;
+        if let Some(failover) = self.failover.as_ref().filter(|failover| failover.is_primary_down()) {
+            let encoded = failover
+                .execute_query(query_type, joined_string, self.max_result_bytes)
+                .await?;
+            return Ok(JniWrapper::decode(prost::bytes::Bytes::from(encoded))?);
+        }
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (client, mut statements) = self.lock_connection().await;
+            let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+            match execute_query_with_context(
+                client.deref(),
+                prepared,
+                context,
+                query_type,
+                joined_string.clone(),
+                self.max_result_bytes,
+            )
+            .await
+            {
+                Ok(encoded) => return Ok(JniWrapper::decode(prost::bytes::Bytes::from(encoded))?),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => match &self.failover {
+                    Some(failover) if is_connection_error(&e) => {
+                        failover.primary_failed(&e);
+                        let encoded = failover
+                            .execute_query(query_type, joined_string, self.max_result_bytes)
+                            .await?;
+                        return Ok(JniWrapper::decode(prost::bytes::Bytes::from(encoded))?);
+                    }
+                    _ => return Err(e),
+                },
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    async fn execute_query_scalar(&self, query_type: i32, joined_string: String) -> Result<Option<String>> {
+        self.execute_query_scalar_value(query_type, joined_string)
+            .await
+            .map(ScalarValue::into_option)
+    }
+
+    async fn execute_query_scalar_value(&self, query_type: i32, joined_string: String) -> Result<ScalarValue> {
+        let _permit = self.limiter.acquire().await?;
+        if let Some(failover) = self.failover.as_ref().filter(|failover| failover.is_primary_down()) {
+            return failover.execute_query_scalar_value(query_type, joined_string).await;
+        }
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (mut client, mut statements) = self.lock_connection().await;
+            let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+            match execute_query_scalar_with_context(
+                client.deref_mut(),
+                prepared,
+                context,
+                query_type,
+                joined_string.clone(),
+            )
+            .await
+            {
+                Ok(scalar) => return Ok(scalar),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => match &self.failover {
+                    Some(failover) if is_connection_error(&e) => {
+                        failover.primary_failed(&e);
+                        return failover.execute_query_scalar_value(query_type, joined_string).await;
+                    }
+                    _ => return Err(e),
+                },
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Lock the connection and its prepared statements for an operation, tagged with the trace context of the
+    /// caller if propagated, see [`MetaDataClientBuilder::with_trace_propagation`].
+    async fn lock_connection(&self) -> (MutexGuard<'_, Client>, MutexGuard<'_, ConnectionStatements>) {
+        let client = self.client.lock().await;
+        let mut statements = self.prepared.lock().await;
+        propagate(&client, &mut statements, self.trace_propagation).await;
+        (client, statements)
+    }
+
+    /// Streams are not retried, since rows may already have been handed to the caller.
+    async fn execute_query_streamed(&self, query_type: i32, joined_string: String) -> Result<StreamedEntityStream> {
+        let (client, mut statements) = self.lock_connection().await;
+        let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+        let stream =
+            execute_query_streamed_with_context(client.deref(), prepared, context, query_type, joined_string).await?;
+        Ok(self.compatibility_warnings.record_stream(stream))
+    }
+
+    async fn insert_namespace(&self, namespace: &Namespace) -> Result<i32> {
+        self.execute_insert(
+            DaoType::InsertNamespace as i32,
+            JniWrapper {
+                namespace: vec![namespace.clone()],
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn insert_table_info(&self, table_info: &TableInfo) -> Result<i32> {
+        self.execute_insert(
+            DaoType::InsertTableInfo as i32,
+            JniWrapper {
+                table_info: vec![table_info.clone()],
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn insert_table_name_id(&self, table_name_id: &TableNameId) -> Result<i32> {
+        self.execute_insert(
+            DaoType::InsertTableNameId as i32,
+            JniWrapper {
+                table_name_id: vec![table_name_id.clone()],
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn insert_table_path_id(&self, table_path_id: &TablePathId) -> Result<i32> {
+        self.execute_insert(
+            DaoType::InsertTablePathId as i32,
+            JniWrapper {
+                table_path_id: vec![table_path_id.clone()],
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Insert the data commit along with the column stats carried by its file ops, see
+    /// [`Self::column_stats_of_file_ops`], and the provenance if any.
+    async fn insert_data_commit_info_with_stats(
+        &self,
+        data_commit_info: &DataCommitInfo,
+        provenance: Option<CommitProvenance>,
+    ) -> Result<u64> {
+        let file_stats = match &data_commit_info.commit_id {
+            Some(commit_id) => {
+                self.column_stats_of_file_ops(commit_id, &data_commit_info.file_ops)
+                    .await
+            }
+            None => vec![],
+        };
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (mut client, statements) = self.lock_connection().await;
+            match insert_data_commit_info_with_stats(
+                client.deref_mut(),
+                &statements.context,
+                data_commit_info,
+                &file_stats,
+                provenance.as_slice(),
+                self.server_side_timestamp,
+            )
+            .await
+            {
+                Ok(count) => return Ok(count),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    async fn transaction_insert_partition_info(
+        &self,
+        partition_info_list: Vec<PartitionInfo>,
+        with_stats: bool,
+        commit_timestamp: Option<i64>,
+    ) -> Result<i32> {
+        let insert_type = if with_stats {
+            DaoType::TransactionInsertPartitionInfoWithStats
+        } else {
+            DaoType::TransactionInsertPartitionInfo
+        };
+        self.execute_insert_with_commit_timestamp(
+            insert_type as i32,
+            JniWrapper {
+                partition_info: partition_info_list,
+                ..Default::default()
+            },
+            commit_timestamp,
+        )
+        .await
+    }
+
+    async fn insert_table_if_not_exists(&self, table_info: &TableInfo) -> Result<bool> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (mut client, statements) = self.lock_connection().await;
+            match insert_table_if_not_exists(client.deref_mut(), &statements.context.column_fallbacks, table_info).await
+            {
+                Ok(created) => return Ok(created),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    async fn insert_new_partition_info(
+        &self,
+        partition_info_list: &[PartitionInfo],
+        with_stats: bool,
+        commit_timestamp: Option<i64>,
+    ) -> Result<Vec<String>> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let (mut client, statements) = self.lock_connection().await;
+            match insert_new_partition_info(
+                client.deref_mut(),
+                &statements.context.column_fallbacks,
+                partition_info_list,
+                with_stats,
+                commit_timestamp,
+            )
+            .await
+            {
+                Ok(existing) => return Ok(existing),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Empty the metadata tables and recreate the default namespace, for tests. Refuses with InvalidInput unless the
+    /// database matches [`MetaDataClientBuilder::with_clean_meta_database_pattern`].
+    pub async fn meta_cleanup(&self) -> Result<i32> {
+        clean_meta_for_test(
+            self.client.lock().await.deref_mut(),
+            &self.clean_meta_database_pattern,
+            false,
+        )
+        .await?;
+        self.insert_namespace(&Namespace {
+            namespace: self.default_namespace.clone(),
+            properties: "{}".to_string(),
+            comment: "".to_string(),
+            domain: self.default_domain.clone(),
+        })
+        .await
+    }
+
+    /// See [`Self::meta_cleanup`] and [`Self::run_with_options`].
+    pub async fn meta_cleanup_with_options(&self, options: OpOptions) -> Result<i32> {
+        self.run_with_options(options, self.meta_cleanup()).await
+    }
+
+    pub async fn commit_data(&self, meta_info: MetaInfo, commit_op: CommitOp) -> Result<()> {
+        self.commit_data_with_timestamp(meta_info, commit_op, None).await
+    }
+
+    /// See [`Self::commit_data`] and [`Self::run_with_options`]. A commit timing out may still have gone through,
+    /// retrying it is safe as the commits a partition holds already are not appended again.
+    pub async fn commit_data_with_options(
+        &self,
+        meta_info: MetaInfo,
+        commit_op: CommitOp,
+        options: OpOptions,
+    ) -> Result<()> {
+        self.run_with_options(options, self.commit_data(meta_info, commit_op))
+            .await
+    }
+
+    /// Commit with the new partition versions stamped `commit_timestamp` instead of the database clock, e.g. for
+    /// backfills. Timestamps more than [`COMMIT_TIMESTAMP_MAX_SKEW_MS`] ahead of the database clock are rejected,
+    /// since they would hide later commits from time travel, and so are timestamps earlier than the latest version
+    /// of a partition committed to, which the new version would precede.
+    pub async fn commit_data_with_timestamp(
+        &self,
+        meta_info: MetaInfo,
+        commit_op: CommitOp,
+        commit_timestamp: Option<i64>,
+    ) -> Result<()> {
+        let context = ErrorContext::new("commit_data");
+        let context = match &meta_info.table_info {
+            Some(table_info) => context.with_table_id(&table_info.table_id),
+            None => context,
+        };
+        in_context(
+            context,
+            self.limiter
+                .admit(self.commit_data_admitted(meta_info, commit_op, commit_timestamp, CommitHint::Default)),
+        )
+        .await?
+    }
+
+    /// Commit with a hint on the partitions committed to. With [`CommitHint::NewPartitionsOnly`] the first versions
+    /// of new partitions are written in one transaction, and those of the partitions found to exist already in a
+    /// second one.
+    pub async fn commit_data_with_hint(
+        &self,
+        meta_info: MetaInfo,
+        commit_op: CommitOp,
+        hint: CommitHint,
+    ) -> Result<()> {
+        self.limiter
+            .admit(self.commit_data_admitted(meta_info, commit_op, None, hint))
+            .await?
+    }
+
+    async fn commit_data_admitted(
+        &self,
+        meta_info: MetaInfo,
+        commit_op: CommitOp,
+        commit_timestamp: Option<i64>,
+        hint: CommitHint,
+    ) -> Result<()> {
+        self.check_commit_timestamp(commit_timestamp).await?;
+        let hooked_meta_info = (!self.commit_hooks.read().unwrap().is_empty()).then(|| meta_info.clone());
+        let table_info = meta_info.table_info.clone();
+        let conflict_policy = match (&table_info, hint) {
+            (Some(table_info), CommitHint::Default) => self.conflict_policy_of(table_info)?,
+            _ => None,
+        };
+        let resolve_meta_info = conflict_policy.as_ref().map(|_| meta_info.clone());
+        let (table_id, new_partition_list, with_stats) =
+            self.new_partition_versions(meta_info, commit_op, hint).await?;
+        if new_partition_list.is_empty() {
+            debug!("commit to table {} holds no new commits", table_id);
+            return Ok(());
+        }
+        let rows = new_partition_list.len();
+        let val = match hint {
+            CommitHint::Default => match (conflict_policy, resolve_meta_info) {
+                (Some(conflict_policy), Some(meta_info)) => {
+                    self.insert_resolving_conflicts(
+                        meta_info,
+                        commit_op,
+                        new_partition_list,
+                        with_stats,
+                        commit_timestamp,
+                        conflict_policy.as_ref(),
+                    )
+                    .await?
+                }
+                _ => {
+                    self.transaction_insert_partition_info(new_partition_list, with_stats, commit_timestamp)
+                        .await?
+                }
+            },
+            CommitHint::NewPartitionsOnly => {
+                let existing = self
+                    .insert_new_partition_info(&new_partition_list, with_stats, commit_timestamp)
+                    .await?;
+                let val = (new_partition_list.len() - existing.len()) as i32;
+                if !existing.is_empty() {
+                    debug!("{} partitions of table {} exist already", existing.len(), table_id);
+                    let existing = existing.into_iter().collect::<HashSet<String>>();
+                    let meta_info = MetaInfo {
+                        table_info,
+                        list_partition: new_partition_list
+                            .into_iter()
+                            .filter(|partition_info| existing.contains(&partition_info.partition_desc))
+                            .collect(),
+                        ..Default::default()
+                    };
+                    let (_, partition_list, _) = self
+                        .new_partition_versions(meta_info, commit_op, CommitHint::Default)
+                        .await?;
+                    if !partition_list.is_empty() {
+                        self.transaction_insert_partition_info(partition_list, with_stats, commit_timestamp)
+                            .await?;
+                    }
+                }
+                val
+            }
+        };
+        self.analyze_after_commit(rows);
+        if let Some(meta_info) = hooked_meta_info {
+            self.run_commit_hooks(&meta_info, commit_op);
+        }
+        if matches!(commit_op, CommitOp::AppendCommit | CommitOp::MergeCommit) {
+            let vec = self.get_all_partition_info(&table_id).await?;
+            debug!("val = {val} ,get partition list after finished: {:?}", vec);
+        }
+        Ok(())
+    }
+
+    /// Run `hook` after each successful [`Self::commit_data`] of the client, including the commits built on it such
+    /// as [`Self::commit_data_commit_info`], with the MetaInfo and the commit op as given, e.g. to update a search
+    /// index. Commits writing no new version, such as a retried commit that went through already, do not run the
+    /// hooks. Hooks run in the order they were registered on the task committing, a panicking hook is logged and
+    /// does not affect the commit or the other hooks.
+    pub fn register_commit_hook(&self, hook: CommitHook) {
+        self.commit_hooks.write().unwrap().push(hook);
+    }
+
+    fn run_commit_hooks(&self, meta_info: &MetaInfo, commit_op: CommitOp) {
+        // hooks run without the lock held, so that they may register hooks themselves
+        let hooks = self.commit_hooks.read().unwrap().clone();
+        for hook in hooks {
+            if panic::catch_unwind(AssertUnwindSafe(|| hook(meta_info, commit_op))).is_err() {
+                warn!(
+                    "commit hook panicked after a {:?} to table {:?}",
+                    commit_op,
+                    meta_info.table_info.as_ref().map(|table_info| &table_info.table_id)
+                );
+            }
+        }
+    }
+
+    /// The policy resolving the version conflicts of commits to the table: the one named by its
+    /// `commitConflictPolicy` property, otherwise the one of the client, if any.
+    fn conflict_policy_of(&self, table_info: &TableInfo) -> Result<Option<Arc<dyn CommitConflictPolicy>>> {
+        let name = serde_json::from_str::<serde_json::Value>(&table_info.properties)
+            .ok()
+            .and_then(|properties| properties[COMMIT_CONFLICT_POLICY].as_str().map(str::to_string));
+        match name {
+            Some(name) => self.conflict_policies.get(&name).cloned().map(Some).ok_or_else(|| {
+                LakeSoulMetaDataError::InvalidConfig(format!(
+                    "table '{}' names unknown commit conflict policy {}",
+                    table_info.table_id, name
+                ))
+            }),
+            None => Ok(self.conflict_policy.clone()),
+        }
+    }
+
+    /// Insert `partition_list`, the new versions computed for `meta_info`, resolving the conflicts with the
+    /// versions committed meanwhile by `policy` as described in [`crate::conflict_policy`]. Returns the number of
+    /// partitions inserted.
+    async fn insert_resolving_conflicts(
+        &self,
+        meta_info: MetaInfo,
+        commit_op: CommitOp,
+        mut partition_list: Vec<PartitionInfo>,
+        with_stats: bool,
+        commit_timestamp: Option<i64>,
+        policy: &dyn CommitConflictPolicy,
+    ) -> Result<i32> {
+        let table_info = meta_info
+            .table_info
+            .as_ref()
+            .ok_or(LakeSoulMetaDataError::Internal("table info missing".to_string()))?;
+        let table_id = table_info.table_id.clone();
+        // the commits added to each partition, the snapshots the versions were based on left out
+        let range_keys = range_partition_keys(&table_info.partitions);
+        let mut added = HashMap::<String, Snapshot>::new();
+        for partition_info in &meta_info.list_partition {
+            added
+                .entry(canonical_partition_desc(&partition_info.partition_desc, &range_keys))
+                .or_default()
+                .merge_append(&Snapshot::try_from_proto(&partition_info.snapshot)?);
+        }
+        for _ in 0..self.max_retry() {
+            let expected = partition_list.len() as i32;
+            let inserted = self
+                .transaction_insert_partition_info(partition_list.clone(), with_stats, commit_timestamp)
+                .await?;
+            // a conflicting insert rolls back the transaction, reporting no partition inserted
+            if inserted == expected {
+                return Ok(inserted);
+            }
+            let partition_descs = partition_list
+                .iter()
+                .map(|partition_info| partition_info.partition_desc.clone())
+                .collect::<Vec<_>>();
+            let cur_map = self.get_cur_partition_map(&table_id, &partition_descs).await?;
+            let mut retry_read = false;
+            for partition_info in &mut partition_list {
+                let current = match cur_map.get(&partition_info.partition_desc) {
+                    Some(current) if current.version >= partition_info.version => current,
+                    _ => continue,
+                };
+                match policy.resolve(current, partition_info, commit_op) {
+                    ConflictDecision::Merge => {
+                        let mut snapshot = Snapshot::try_from_proto(&current.snapshot)?;
+                        if let Some(added) = added.get(&partition_info.partition_desc) {
+                            snapshot.merge_append(added);
+                        }
+                        partition_info.snapshot = snapshot.to_proto();
+                        partition_info.version = current.version + 1;
+                    }
+                    ConflictDecision::RetryRead => retry_read = true,
+                    ConflictDecision::Fail => {
+                        return Err(LakeSoulMetaDataError::ConcurrentCommit(format!(
+                            "commit to partition '{}' of table '{}' conflicted with version {} committed meanwhile, \
+                            failed by conflict policy {}",
+                            partition_info.partition_desc,
+                            table_id,
+                            current.version,
+                            policy.name()
+                        )));
+                    }
+                }
+            }
+            if retry_read {
+                partition_list = self
+                    .new_partition_versions(meta_info.clone(), commit_op, CommitHint::Default)
+                    .await?
+                    .1;
+                if partition_list.is_empty() {
+                    return Ok(0);
+                }
+            }
+        }
+        Err(LakeSoulMetaDataError::ConcurrentCommit(format!(
+            "commit to table '{}' still conflicted after {} attempts",
+            table_id,
+            self.max_retry()
+        )))
+    }
+
+    /// Commit each partition of `meta_info` on its own instead of all or none, e.g. for idempotent backfills which
+    /// retry the partitions reported as conflicted. A partition conflicts if it has been committed to since the
+    /// version listed in `read_partition_info`, or while committing.
+    pub async fn commit_data_partial(&self, meta_info: MetaInfo, commit_op: CommitOp) -> Result<PartialCommitReport> {
+        self.limiter
+            .admit(self.commit_data_partial_admitted(meta_info, commit_op))
+            .await?
+    }
+
+    async fn commit_data_partial_admitted(
+        &self,
+        mut meta_info: MetaInfo,
+        commit_op: CommitOp,
+    ) -> Result<PartialCommitReport> {
+        let range_keys = range_partition_keys(
+            &meta_info
+                .table_info
+                .as_ref()
+                .ok_or(LakeSoulMetaDataError::Internal("table info missing".to_string()))?
+                .partitions,
+        );
+        let read_versions = std::mem::take(&mut meta_info.read_partition_info)
+            .into_iter()
+            .map(|partition_info| {
+                (
+                    canonical_partition_desc(&partition_info.partition_desc, &range_keys),
+                    partition_info.version,
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let partition_descs = meta_info
+            .list_partition
+            .iter()
+            .map(|partition_info| canonical_partition_desc(&partition_info.partition_desc, &range_keys))
+            .collect::<Vec<_>>();
+        let (_, new_partition_list, with_stats) = self
+            .new_partition_versions(meta_info, commit_op, CommitHint::Default)
+            .await?;
+        // partitions holding all of their commits already were committed by an earlier attempt
+        let mut committed = partition_descs
+            .into_iter()
+            .filter(|partition_desc| {
+                !new_partition_list
+                    .iter()
+                    .any(|partition_info| &partition_info.partition_desc == partition_desc)
+            })
+            .collect::<Vec<_>>();
+        let (unchanged, conflicted): (Vec<_>, Vec<_>) = new_partition_list.into_iter().partition(|partition_info| {
+            match read_versions.get(&partition_info.partition_desc) {
+                Some(read_version) => partition_info.version == read_version + 1,
+                None => true,
+            }
+        });
+        let mut conflicted = conflicted
+            .into_iter()
+            .map(|partition_info| partition_info.partition_desc)
+            .collect::<Vec<_>>();
+        let raced = if unchanged.is_empty() {
+            vec![]
+        } else {
+            self.insert_new_partition_info(&unchanged, with_stats, None).await?
+        };
+        self.analyze_after_commit(unchanged.len() - raced.len());
+        committed.extend(
+            unchanged
+                .into_iter()
+                .map(|partition_info| partition_info.partition_desc)
+                .filter(|partition_desc| !raced.contains(partition_desc)),
+        );
+        conflicted.extend(raced);
+        Ok(PartialCommitReport { committed, conflicted })
+    }
+
+    /// Commit to several tables in a single transaction, e.g. a fact table along with its dimension tables: either
+    /// all tables advance or none does. A conflict on any table, such as a concurrent commit to one of the
+    /// partitions or a partition listed twice, rolls back the whole commit and fails it with InvalidInput.
+    pub async fn commit_multi_table(&self, commits: Vec<(MetaInfo, CommitOp)>) -> Result<()> {
+        self.limiter.admit(self.commit_multi_table_admitted(commits)).await?
+    }
+
+    async fn commit_multi_table_admitted(&self, commits: Vec<(MetaInfo, CommitOp)>) -> Result<()> {
+        let mut partition_list = Vec::new();
+        let mut any_with_stats = false;
+        let mut table_ids = Vec::new();
+        for (meta_info, commit_op) in commits {
+            let (table_id, new_partition_list, with_stats) = self
+                .new_partition_versions(meta_info, commit_op, CommitHint::Default)
+                .await?;
+            partition_list.extend(new_partition_list);
+            // statistics are kept for every table of the transaction if one of them needs them
+            any_with_stats |= with_stats;
+            table_ids.push(table_id);
+        }
+        if partition_list.is_empty() {
+            return Ok(());
+        }
+        let expected = partition_list.len() as i32;
+        let inserted = self
+            .transaction_insert_partition_info(partition_list, any_with_stats, None)
+            .await?;
+        // a conflicting insert rolls back the transaction, reporting no partition inserted
+        if inserted != expected {
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "multi-table commit to tables {:?} conflicted and was rolled back",
+                table_ids
+            )));
+        }
+        self.analyze_after_commit(inserted as usize);
+        Ok(())
+    }
+
+    /// Start an ANALYZE of the partition and commit tables in the background if a commit wrote more than
+    /// `analyze_after_commit_rows` partition versions and none is running already.
+    fn analyze_after_commit(&self, rows: usize) {
+        if self.analyze_after_commit_rows.is_none_or(|threshold| rows <= threshold)
+            || self.analyze_running.swap(true, atomic::Ordering::AcqRel)
+        {
+            return;
+        }
+        let config = self.config.clone();
+        let runtime_handle = self.runtime_handle.clone();
+        let running = self.analyze_running.clone();
+        let analyzes = self.analyzes_after_commit.clone();
+        self.runtime_handle.spawn(async move {
+            let analyzed = async {
+                let client = create_connection_on(config, &runtime_handle).await?;
+                for table in ANALYZED_AFTER_COMMIT {
+                    client.batch_execute(&format!("analyze {}", table)).await?;
+                }
+                Ok::<_, LakeSoulMetaDataError>(())
+            }
+            .await;
+            match analyzed {
+                Ok(()) => {
+                    debug!("analyzed {:?} after a bulk commit", ANALYZED_AFTER_COMMIT);
+                    analyzes.fetch_add(1, atomic::Ordering::Relaxed);
+                }
+                Err(e) => warn!(
+                    "failed to analyze {:?} after a bulk commit: {}",
+                    ANALYZED_AFTER_COMMIT, e
+                ),
+            }
+            running.store(false, atomic::Ordering::Release);
+        });
+    }
+
+    /// Replace the whole content of the table, as INSERT OVERWRITE TABLE does: each partition of `new_partitions`
+    /// gets a version holding just its data commits, and every other partition of the table an empty version. All
+    /// versions are written in a single transaction, so that readers see either the old or the new content. Fails
+    /// with ConcurrentCommit, leaving the table as it was, if one of the partitions is committed to meanwhile.
+    pub async fn overwrite_table(
+        &self,
+        table_id: &str,
+        new_partitions: Vec<(String, Vec<DataCommitInfo>)>,
+    ) -> Result<()> {
+        self.limiter
+            .admit(self.overwrite_table_admitted(table_id, new_partitions))
+            .await?
+    }
+
+    async fn overwrite_table_admitted(
+        &self,
+        table_id: &str,
+        new_partitions: Vec<(String, Vec<DataCommitInfo>)>,
+    ) -> Result<()> {
+        let table_info = self
+            .find_table_info_by_table_id(table_id)
+            .await?
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_id)))?;
+        self.invalidate_cached_table(Some(table_id), None, None);
+        let range_keys = range_partition_keys(&table_info.partitions);
+        let domain = self.domain_of(&table_info);
+        // partition descs given in another key order end up in the same version
+        let mut grouped = BTreeMap::<String, Vec<DataCommitInfo>>::new();
+        for (partition_desc, data_commit_info) in new_partitions {
+            let partition_desc = canonical_partition_desc(&partition_desc, &range_keys);
+            self.write_limits.check_partition_desc(&partition_desc)?;
+            grouped.entry(partition_desc).or_default().extend(data_commit_info);
+        }
+
+        let mut data_commit_info = Vec::new();
+        let mut snapshots = Vec::with_capacity(grouped.len());
+        for (partition_desc, commits) in grouped {
+            let mut snapshot = Snapshot::new();
+            for mut commit in commits {
+                let commit_id = commit
+                    .commit_id
+                    .clone()
+                    .ok_or(LakeSoulMetaDataError::InvalidInput("commit_id missing".to_string()))?;
+                if !snapshot.insert(uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low)) {
+                    return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                        "commit {} is listed twice for partition '{}'",
+                        uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low),
+                        partition_desc
+                    )));
+                }
+                commit.table_id = table_id.to_string();
+                commit.partition_desc = partition_desc.clone();
+                commit.commit_op = CommitOp::UpdateCommit as i32;
+                commit.committed = false;
+                if commit.domain.is_empty() {
+                    commit.domain = domain.clone();
+                }
+                self.write_limits.check_data_commit_info(&commit)?;
+                data_commit_info.push(commit);
+            }
+            snapshots.push((partition_desc, snapshot));
+        }
+        if !data_commit_info.is_empty() {
+            self.execute_insert(
+                DaoType::TransactionInsertDataCommitInfo as i32,
+                JniWrapper {
+                    data_commit_info,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        }
+
+        let cur_versions = self
+            .get_all_partition_info(table_id)
+            .await?
+            .into_iter()
+            .map(|partition_info| (partition_info.partition_desc, partition_info.version))
+            .collect();
+        self.insert_overwrite_versions(&table_info, snapshots, cur_versions)
+            .await
+    }
+
+    /// Write the versions overwriting the table, following `cur_versions` read beforehand.
+    async fn insert_overwrite_versions(
+        &self,
+        table_info: &TableInfo,
+        snapshots: Vec<(String, Snapshot)>,
+        mut cur_versions: BTreeMap<String, i32>,
+    ) -> Result<()> {
+        let table_id = table_info.table_id.as_str();
+        let domain = self.domain_of(table_info);
+        let change_tracking = change_tracking_enabled(table_info);
+        let new_version = |partition_desc: String, snapshot: &Snapshot, cur_version: Option<i32>| PartitionInfo {
+            table_id: table_id.to_string(),
+            partition_desc,
+            version: cur_version.map_or(0, |version| version + 1),
+            commit_op: CommitOp::UpdateCommit as i32,
+            snapshot: snapshot.to_proto(),
+            domain: domain.clone(),
+            change_tracking,
+            ..Default::default()
+        };
+        let mut partition_list = snapshots
+            .iter()
+            .map(|(partition_desc, snapshot)| {
+                new_version(partition_desc.clone(), snapshot, cur_versions.remove(partition_desc))
+            })
+            .collect::<Vec<_>>();
+        // the partitions left out of the new content are emptied
+        partition_list.extend(
+            cur_versions
+                .into_iter()
+                .map(|(partition_desc, version)| new_version(partition_desc, &Snapshot::new(), Some(version))),
+        );
+        if partition_list.is_empty() {
+            return Ok(());
+        }
+        let expected = partition_list.len() as i32;
+        let inserted = self
+            .transaction_insert_partition_info(partition_list, partition_stats_enabled(table_info), None)
+            .await?;
+        // a conflicting insert rolls back the transaction, reporting no partition inserted
+        if inserted != expected {
+            return Err(LakeSoulMetaDataError::ConcurrentCommit(format!(
+                "overwrite of table '{}' conflicted with a concurrent commit and was rolled back",
+                table_id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn check_commit_timestamp(&self, commit_timestamp: Option<i64>) -> Result<()> {
+        if let Some(commit_timestamp) = commit_timestamp {
+            if commit_timestamp < 0 {
+                return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                    "commit timestamp {} is negative",
+                    commit_timestamp
+                )));
+            }
+            let db_time = self.get_db_time().await?;
+            if commit_timestamp > db_time + COMMIT_TIMESTAMP_MAX_SKEW_MS {
+                return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                    "commit timestamp {} is ahead of the database clock {} by more than {}ms",
+                    commit_timestamp, db_time, COMMIT_TIMESTAMP_MAX_SKEW_MS
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The new versions of the partitions committed by `meta_info`, along with the table id and whether the
+    /// table keeps partition statistics.
+    async fn new_partition_versions(
+        &self,
+        mut meta_info: MetaInfo,
+        commit_op: CommitOp,
+        hint: CommitHint,
+    ) -> Result<(String, Vec<PartitionInfo>, bool)> {
+        let table_info = meta_info
+            .table_info
+            .ok_or(LakeSoulMetaDataError::Internal("table info missing".to_string()))?;
+        // engines evolve the table schema along with their commits
+        self.invalidate_cached_table(Some(&table_info.table_id), None, None);
+
+        // logically equal partition descs are committed to the same partition
+        let range_keys = range_partition_keys(&table_info.partitions);
+        for partition_info in &mut meta_info.list_partition {
+            partition_info.partition_desc = canonical_partition_desc(&partition_info.partition_desc, &range_keys);
+            self.write_limits.check_partition_desc(&partition_info.partition_desc)?;
+        }
+
+        if !table_info.table_name.is_empty() {
+            // todo: updateTableShortName
+        }
+        // todo: updateTableProperties
+
+        // conflict handling
+        let _raw_map = meta_info
+            .list_partition
+            .iter()
+            .map(|partition_info| (partition_info.partition_desc.clone(), partition_info.clone()))
+            .collect::<HashMap<String, PartitionInfo>>();
+
+        let partition_desc_list = meta_info
+            .list_partition
+            .iter()
+            .map(|partition_info| partition_info.partition_desc.clone())
+            .collect::<Vec<String>>();
+
+        let _snapshot_list = meta_info
+            .list_partition
+            .iter()
+            .flat_map(|partition_info| partition_info.snapshot.clone())
+            .collect::<Vec<entity::Uuid>>();
+
+        // conflict handling
+        let cur_map = match hint {
+            CommitHint::Default => {
+                self.get_cur_partition_map(&table_info.table_id, &partition_desc_list)
+                    .await?
+            }
+            // new partitions have no current version, existing ones are caught when inserting
+            CommitHint::NewPartitionsOnly => HashMap::new(),
+        };
+
+        let change_tracking = change_tracking_enabled(&table_info);
+        let with_stats = partition_stats_enabled(&table_info);
+
+        match commit_op {
+            CommitOp::AppendCommit | CommitOp::MergeCommit => {
+                let new_partition_list = meta_info
+                    .list_partition
+                    .iter()
+                    .map(|partition_info| {
+                        let partition_desc = &partition_info.partition_desc;
+                        match cur_map.get(partition_desc) {
+                            Some(cur_partition_info) => {
+                                let mut cur_partition_info = cur_partition_info.clone();
+                                cur_partition_info.domain = self.domain_of(&table_info);
+                                // commits the partition holds already, e.g. of a retried commit, are not listed twice
+                                let mut snapshot = Snapshot::dedup(
+                                    cur_partition_info
+                                        .snapshot
+                                        .iter()
+                                        .map(|commit_id| uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low)),
+                                )
+                                .0;
+                                let committed = Snapshot::try_from_proto(&partition_info.snapshot)?;
+                                let appended = snapshot.merge_append(&committed);
+                                self.duplicate_commit_ids
+                                    .fetch_add((committed.len() - appended) as u64, atomic::Ordering::Relaxed);
+                                if appended == 0 && !committed.is_empty() {
+                                    // a retry of a commit which went through already, the current version stays
+                                    self.retried_commits.fetch_add(1, atomic::Ordering::Relaxed);
+                                    return Ok(None);
+                                }
+                                cur_partition_info.snapshot = snapshot.to_proto();
+                                cur_partition_info.version += 1;
+                                cur_partition_info.commit_op = commit_op as i32;
+                                cur_partition_info.expression = partition_info.expression.clone();
+                                cur_partition_info.change_tracking = change_tracking;
+                                Ok(Some(cur_partition_info))
+                            }
+                            None => Ok(Some(PartitionInfo {
+                                table_id: table_info.table_id.clone(),
+                                partition_desc: partition_desc.clone(),
+                                version: 0,
+                                snapshot: Snapshot::try_from_proto(&partition_info.snapshot)?.to_proto(),
+                                domain: self.domain_of(&table_info),
+                                commit_op: commit_op as i32,
+                                expression: partition_info.expression.clone(),
+                                change_tracking,
+                                ..Default::default()
+                            })),
+                        }
+                    })
+                    .filter_map(Result::transpose)
+                    .collect::<Result<Vec<PartitionInfo>>>()?;
+                Ok((table_info.table_id, new_partition_list, with_stats))
+            }
+            CommitOp::RollbackCommit => {
+                // a rollback overwrites: the new version only contains the restored snapshot
+                let new_partition_list = meta_info
+                    .list_partition
+                    .iter()
+                    .map(|partition_info| {
+                        let partition_desc = &partition_info.partition_desc;
+                        Ok(PartitionInfo {
+                            table_id: table_info.table_id.clone(),
+                            partition_desc: partition_desc.clone(),
+                            version: cur_map
+                                .get(partition_desc)
+                                .map_or(0, |cur_partition_info| cur_partition_info.version + 1),
+                            snapshot: Snapshot::try_from_proto(&partition_info.snapshot)?.to_proto(),
+                            domain: self.domain_of(&table_info),
+                            commit_op: commit_op as i32,
+                            expression: partition_info.expression.clone(),
+                            change_tracking,
+                            ..Default::default()
+                        })
+                    })
+                    .collect::<Result<Vec<PartitionInfo>>>()?;
+                Ok((table_info.table_id, new_partition_list, with_stats))
+            }
+            _ => {
+                todo!()
+            }
+        }
+    }
+
+    /// Roll a partition back to `target_version` by committing a new version with the snapshot of the target
+    /// version, recorded as a RollbackCommit, so that the versions in between stay in the history.
+    /// Fails with NotFound if the partition has no such version.
+    pub async fn rollback_partition(&self, table_id: &str, partition_desc: &str, target_version: i32) -> Result<()> {
+        let table_info = self.fetch_table_info_by_table_id(table_id).await?;
+        let partition_desc = canonical_partition_desc(partition_desc, &range_partition_keys(&table_info.partitions));
+        let target = self
+            .execute_query(
+                DaoType::SelectPartitionVersionByTableIdAndDescAndVersion as i32,
+                encode_query_params(&[table_id, partition_desc.as_str(), target_version.to_string().as_str()]),
+            )
+            .await?
+            .partition_info
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                LakeSoulMetaDataError::NotFound(format!(
+                    "version {} of partition '{}' of table '{}' not found",
+                    target_version, partition_desc, table_id
+                ))
+            })?;
+        self.commit_data(
+            MetaInfo {
+                table_info: Some(table_info),
+                list_partition: vec![target],
+                ..Default::default()
+            },
+            CommitOp::RollbackCommit,
+        )
+        .await
+    }
+
+    /// Consolidate the snapshot of the latest version of a partition into a single data commit, see
+    /// [`Self::compact_partition_snapshot_with_gc`]. The consolidated data commits are kept for the earlier versions.
+    pub async fn compact_partition_snapshot(&self, table_id: &str, partition_desc: &str) -> Result<i32> {
+        self.compact_partition_snapshot_with_gc(table_id, partition_desc, false)
+            .await
+    }
+
+    /// Consolidate the snapshot of the latest version of a partition into a single data commit holding the file ops
+    /// of its commits in order, committed as a new version recorded as a CompactionCommit. No file is rewritten, the
+    /// point is to read one data commit instead of many. With `gc` the earlier versions are expired along with the
+    /// data commits only they refer to, as by [`Self::expire_partition_versions`].
+    ///
+    /// Returns the number of data commits consolidated, 0 if the snapshot has less than two.
+    /// Fails with InvalidInput if the partition is committed to meanwhile, leaving it as it was.
+    pub async fn compact_partition_snapshot_with_gc(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        gc: bool,
+    ) -> Result<i32> {
+        self.limiter
+            .admit(self.compact_partition_snapshot_admitted(table_id, partition_desc, gc))
+            .await?
+    }
+
+    async fn compact_partition_snapshot_admitted(&self, table_id: &str, partition_desc: &str, gc: bool) -> Result<i32> {
+        let table_info = self.fetch_table_info_by_table_id(table_id).await?;
+        let partition_desc = canonical_partition_desc(partition_desc, &range_partition_keys(&table_info.partitions));
+        let Some(current) = self
+            .get_cur_partition_map(table_id, std::slice::from_ref(&partition_desc))
+            .await?
+            .remove(&partition_desc)
+        else {
+            return Ok(0);
+        };
+        if current.snapshot.len() < 2 {
+            return Ok(0);
+        }
+        let data_commit_info_list = self
+            .get_stored_data_commit_info_with_stats_of_single_partition(&current)
+            .await?;
+        if data_commit_info_list.len() != current.snapshot.len() {
+            return Err(LakeSoulMetaDataError::Internal(format!(
+                "snapshot of version {} of partition '{}' of table '{}' refers to missing data commits",
+                current.version, partition_desc, table_id
+            )));
+        }
+
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        let commit_id = entity::Uuid { high, low };
+        let data_commit_info = DataCommitInfo {
+            table_id: table_id.to_string(),
+            partition_desc: partition_desc.clone(),
+            commit_id: Some(commit_id.clone()),
+            file_ops: data_commit_info_list
+                .iter()
+                .flat_map(|data_commit_info| data_commit_info.file_ops.iter().cloned())
+                .collect(),
+            commit_op: CommitOp::CompactionCommit as i32,
+            timestamp: data_commit_info_list
+                .iter()
+                .map(|data_commit_info| data_commit_info.timestamp)
+                .max()
+                .unwrap_or_default(),
+            committed: false,
+            domain: current.domain.clone(),
+        };
+        self.write_limits.check_data_commit_info(&data_commit_info)?;
+        self.insert_data_commit_info_with_stats(&data_commit_info, None).await?;
+
+        let compacted = PartitionInfo {
+            version: current.version + 1,
+            snapshot: vec![commit_id.clone()],
+            commit_op: CommitOp::CompactionCommit as i32,
+            change_tracking: change_tracking_enabled(&table_info),
+            ..current.clone()
+        };
+        let raced = self
+            .insert_new_partition_info(&[compacted], partition_stats_enabled(&table_info), None)
+            .await?;
+        if !raced.is_empty() {
+            self.delete_single_data_commit_info(table_id, &partition_desc, &commit_id)
+                .await?;
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "partition '{}' of table '{}' has been committed to since version {}",
+                partition_desc, table_id, current.version
+            )));
+        }
+        if gc {
+            self.expire_partition_versions(table_id, &partition_desc, i64::MAX)
+                .await?;
+        }
+        Ok(current.snapshot.len() as i32)
+    }
+
+    /// File stats of the column stats carried by the file ops of the data commit `commit_id`, none if the database
+    /// lacks the stats table.
+    async fn column_stats_of_file_ops(
+        &self,
+        commit_id: &entity::Uuid,
+        file_ops: &[entity::DataFileOp],
+    ) -> Vec<FileStats> {
+        if self.is_table_missing("file_stats").await {
+            return vec![];
+        }
+        file_ops
+            .iter()
+            .filter(|file_op| !file_op.column_stats.is_empty())
+            .map(|file_op| FileStats {
+                commit_id: Some(commit_id.clone()),
+                path: file_op.path.clone(),
+                column_stats: file_op.column_stats.clone(),
+            })
+            .collect()
+    }
+
+    async fn get_cur_partition_map(
+        &self,
+        table_id: &str,
+        partition_desc_list: &[String],
+    ) -> Result<HashMap<String, PartitionInfo>> {
+        Ok(self
+            .get_partition_info_by_table_id_and_partition_list(table_id, partition_desc_list)
+            .await?
+            .iter()
+            .map(|partition_info| (partition_info.partition_desc.clone(), partition_info.clone()))
+            .collect())
+    }
+
+    pub async fn commit_data_commit_info(&self, data_commit_info: DataCommitInfo) -> Result<()> {
+        self.commit_data_commit_info_with_provenance(data_commit_info, CommitProvenance::default())
+            .await
+    }
+
+    /// Commit the data commit recording who wrote it, empty fields of `provenance` are taken from the
+    /// default provenance of the client. Nothing is recorded if all fields end up empty, or if the database
+    /// has no room for provenance.
+    pub async fn commit_data_commit_info_with_provenance(
+        &self,
+        data_commit_info: DataCommitInfo,
+        provenance: CommitProvenance,
+    ) -> Result<()> {
+        in_context(
+            ErrorContext::new("commit_data_commit_info").with_table_id(&data_commit_info.table_id),
+            self.limiter
+                .admit(self.commit_data_commit_info_admitted(data_commit_info, provenance)),
+        )
+        .await?
+    }
+
+    async fn commit_data_commit_info_admitted(
+        &self,
+        mut data_commit_info: DataCommitInfo,
+        provenance: CommitProvenance,
+    ) -> Result<()> {
+        // the commit follows the current table properties, not cached ones
+        let table_info = self.fetch_table_info_by_table_id(&data_commit_info.table_id).await?;
+        data_commit_info.partition_desc = canonical_partition_desc(
+            &data_commit_info.partition_desc,
+            &range_partition_keys(&table_info.partitions),
+        );
+        if data_commit_info.domain.is_empty() {
+            data_commit_info.domain = self.domain_of(&table_info);
+        }
+        self.store_file_paths(&table_info, &mut data_commit_info.file_ops)?;
+        self.write_limits.check_data_commit_info(&data_commit_info)?;
+        let table_id = &data_commit_info.table_id;
+        let partition_desc = &data_commit_info.partition_desc;
+        // the conflicts of a commit op passed through from a newer client are resolved by rules this client lacks
+        let commit_op = match CommitOp::try_from(data_commit_info.commit_op) {
+            Ok(commit_op) => commit_op,
+            Err(_) => {
+                return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                    "commit op {} is unknown to this client and cannot be committed",
+                    encode_commit_op(data_commit_info.commit_op)?
+                )))
+            }
+        };
+        let commit_id = &data_commit_info
+            .commit_id
+            .clone()
+            .ok_or(LakeSoulMetaDataError::Internal("commit_id missing".to_string()))?;
+        let commit_id_str = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string();
+        match self
+            .get_single_data_commit_info(table_id, partition_desc, &commit_id_str)
+            .await?
+        {
+            Some(data_commit_info) if data_commit_info.committed => {
+                return Ok(());
             }
             None => {
-                self.insert_data_commit_info(&data_commit_info).await?;
+                let provenance = self.provenance_or_default(provenance);
+                let provenance = (provenance != CommitProvenance::default()
+                    && !self.is_table_missing("commit_provenance").await)
+                    .then(|| CommitProvenance {
+                        commit_id: Some(commit_id.clone()),
+                        table_id: table_id.clone(),
+                        partition_desc: partition_desc.clone(),
+                        ..provenance
+                    });
+                self.insert_data_commit_info_with_stats(&data_commit_info, provenance)
+                    .await?;
+            }
+            _ => {}
+        };
+        let domain = self.domain_of(&table_info);
+        self.commit_data(
+            MetaInfo {
+                table_info: Some(table_info),
+                list_partition: vec![PartitionInfo {
+                    table_id: table_id.clone(),
+                    partition_desc: partition_desc.clone(),
+                    commit_op: commit_op as i32,
+                    domain,
+                    snapshot: vec![commit_id.clone()],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            commit_op,
+        )
+        .await
+    }
+
+    /// Register files written outside of LakeSoul, e.g. an existing directory of parquet files, as a new version of
+    /// a partition, see [`MetaDataClient::register_external_files_by_partition`].
+    pub async fn register_external_files(
+        &self,
+        table_id: &str,
+        files: Vec<ExternalFile>,
+        partition_desc: &str,
+    ) -> Result<()> {
+        self.register_external_files_by_partition(table_id, vec![(partition_desc.to_string(), files)], false)
+            .await
+    }
+
+    /// Register files written outside of LakeSoul, grouped by partition desc, as append commits of one table
+    /// version. The data commits are inserted in a single batch, so that thousands of files take one round trip.
+    /// Fails with InvalidInput if a file is not under the table path, unless `allow_outside_table_path` is set for
+    /// external locations, or if its size is negative.
+    pub async fn register_external_files_by_partition(
+        &self,
+        table_id: &str,
+        files_by_partition: Vec<(String, Vec<ExternalFile>)>,
+        allow_outside_table_path: bool,
+    ) -> Result<()> {
+        let table_info = self.fetch_table_info_by_table_id(table_id).await?;
+        let range_keys = range_partition_keys(&table_info.partitions);
+        let table_path = table_info.table_path.trim_end_matches('/');
+        // partition descs given in another key order end up in the same commit
+        let mut grouped = BTreeMap::<String, Vec<ExternalFile>>::new();
+        for (partition_desc, files) in files_by_partition {
+            for file in &files {
+                if file.size < 0 {
+                    return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                        "external file '{}' has a negative size",
+                        file.path
+                    )));
+                }
+                let under_table_path = file
+                    .path
+                    .strip_prefix(table_path)
+                    .is_some_and(|rest| rest.starts_with('/') && !rest.split('/').any(|segment| segment == ".."));
+                if !allow_outside_table_path && !under_table_path {
+                    return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                        "external file '{}' is not under the path '{}' of table '{}'",
+                        file.path, table_info.table_path, table_id
+                    )));
+                }
+            }
+            grouped
+                .entry(canonical_partition_desc(&partition_desc, &range_keys))
+                .or_default()
+                .extend(files);
+        }
+        grouped.retain(|_, files| !files.is_empty());
+        if grouped.is_empty() {
+            return Ok(());
+        }
+
+        let domain = self.domain_of(&table_info);
+        let mut data_commit_info = Vec::with_capacity(grouped.len());
+        let mut list_partition = Vec::with_capacity(grouped.len());
+        for (partition_desc, files) in grouped {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            let commit_id = entity::Uuid { high, low };
+            let commit = DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: partition_desc.clone(),
+                commit_id: Some(commit_id.clone()),
+                file_ops: files
+                    .into_iter()
+                    .map(|file| entity::DataFileOp {
+                        path: file.path,
+                        file_op: FileOp::Add as i32,
+                        size: file.size,
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: domain.clone(),
+                ..Default::default()
+            };
+            self.write_limits.check_data_commit_info(&commit)?;
+            data_commit_info.push(commit);
+            list_partition.push(PartitionInfo {
+                table_id: table_id.to_string(),
+                partition_desc,
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: domain.clone(),
+                snapshot: vec![commit_id],
+                ..Default::default()
+            });
+        }
+        self.execute_insert(
+            DaoType::TransactionInsertDataCommitInfo as i32,
+            JniWrapper {
+                data_commit_info,
+                ..Default::default()
+            },
+        )
+        .await?;
+        self.commit_data(
+            MetaInfo {
+                table_info: Some(table_info),
+                list_partition,
+                ..Default::default()
+            },
+            CommitOp::AppendCommit,
+        )
+        .await
+    }
+
+    /// Append file ops to a data commit not committed yet, for commits built incrementally before
+    /// [`MetaDataClient::commit_data_commit_info`]. Returns the number of appended file ops.
+    /// Fails with NotFound if there is no such data commit, and with InvalidInput if it is committed already.
+    pub async fn append_file_ops(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        commit_id: &entity::Uuid,
+        mut file_ops: Vec<entity::DataFileOp>,
+    ) -> Result<i32> {
+        let partition_desc = self.normalize_partition_desc(table_id, partition_desc).await?;
+        let table_info = self.fetch_table_info_by_table_id(table_id).await?;
+        self.store_file_paths(&table_info, &mut file_ops)?;
+        let uuid = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low);
+        let committed = || {
+            LakeSoulMetaDataError::InvalidInput(format!(
+                "data commit {} of table '{}' is committed already",
+                uuid, table_id
+            ))
+        };
+        let data_commit_info = self
+            .get_single_data_commit_info(table_id, &partition_desc, &uuid.to_string())
+            .await?
+            .ok_or_else(|| {
+                LakeSoulMetaDataError::NotFound(format!("data commit {} of table '{}' not found", uuid, table_id))
+            })?;
+        if data_commit_info.committed {
+            return Err(committed());
+        }
+        self.write_limits.check_data_commit_info(&DataCommitInfo {
+            file_ops: [data_commit_info.file_ops, file_ops.clone()].concat(),
+            ..data_commit_info
+        })?;
+        let stats = self.column_stats_of_file_ops(commit_id, &file_ops).await;
+        let updated = {
+            let _permit = self.limiter.acquire().await?;
+            append_data_file_ops(
+                self.client.lock().await.deref_mut(),
+                table_id,
+                &partition_desc,
+                uuid,
+                &file_ops,
+                &stats,
+            )
+            .await?
+        };
+        // committed between the check and the update
+        if updated == 0 {
+            return Err(committed());
+        }
+        Ok(file_ops.len() as i32)
+    }
+
+    /// Current time of the metadata database in milliseconds, the same clock used for commit timestamps.
+    /// Write the namespaces, all of them if None, with their tables, every partition version and the data commits
+    /// the versions refer to, to `sink` as length-prefixed protobuf records. Only the versions of one partition are
+    /// held in memory at a time. The export is checkpointed after each table, an export cut short can be continued
+    /// by [`Self::resume_export_catalog`] with the manifest of [`ExportManifest::recover`]. Fails with NotFound for a
+    /// missing namespace.
+    pub async fn export_catalog(&self, namespaces: Option<Vec<String>>, sink: impl Write) -> Result<ExportManifest> {
+        let namespaces = match namespaces {
+            Some(namespaces) => {
+                let mut found = Vec::with_capacity(namespaces.len());
+                for namespace in namespaces {
+                    let found_namespace = self
+                        .execute_query(
+                            DaoType::SelectNamespaceByNamespace as i32,
+                            encode_query_params(&[namespace.as_str()]),
+                        )
+                        .await?
+                        .namespace
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| {
+                            LakeSoulMetaDataError::NotFound(format!("Namespace '{}' not found", namespace))
+                        })?;
+                    found.push(found_namespace);
+                }
+                found
+            }
+            None => self.get_all_namespace().await?,
+        };
+        let mut writer = CatalogWriter::new(sink)?;
+        let mut manifest = ExportManifest::default();
+        for namespace in namespaces {
+            manifest.namespaces.push(namespace.namespace.clone());
+            writer.write(&CatalogRecord::Namespace(namespace))?;
+        }
+        writer.write(&CatalogRecord::Checkpoint(String::new()))?;
+        manifest.resume_offset = writer.offset();
+        manifest.records = writer.records();
+        self.export_tables(&mut writer, manifest).await
+    }
+
+    /// Continue an export cut short, `sink` appending to the export truncated to [`ExportManifest::resume_offset`].
+    /// Tables created in the exported namespaces since are exported as well.
+    pub async fn resume_export_catalog(&self, manifest: ExportManifest, sink: impl Write) -> Result<ExportManifest> {
+        if manifest.complete {
+            return Ok(manifest);
+        }
+        let mut writer = CatalogWriter::resume(sink, &manifest);
+        self.export_tables(&mut writer, manifest).await
+    }
+
+    /// TableInfo of the table bypassing the table cache, None if there is no such table.
+    async fn find_table_info_by_table_id(&self, table_id: &str) -> Result<Option<TableInfo>> {
+        Ok(self
+            .execute_query(DaoType::SelectTableInfoByTableId as i32, table_id.to_string())
+            .await?
+            .table_info
+            .into_iter()
+            .next())
+    }
+
+    /// All versions of the partition in ascending order.
+    async fn list_partition_versions(&self, table_id: &str, partition_desc: &str) -> Result<Vec<PartitionInfo>> {
+        let mut versions = self
+            .execute_query(
+                DaoType::ListPartitionByTableIdAndDesc as i32,
+                encode_query_params(&[table_id, partition_desc]),
+            )
+            .await?
+            .partition_info;
+        versions.sort_by_key(|partition_info| partition_info.version);
+        Ok(versions)
+    }
+
+    async fn export_tables<W: Write>(
+        &self,
+        writer: &mut CatalogWriter<W>,
+        mut manifest: ExportManifest,
+    ) -> Result<ExportManifest> {
+        let exported = manifest.exported_tables.iter().cloned().collect::<HashSet<_>>();
+        for namespace in manifest.namespaces.clone() {
+            for table_name_id in self.get_all_table_name_id_by_namespace(&namespace).await? {
+                if exported.contains(&table_name_id.table_id) {
+                    continue;
+                }
+                // left out if dropped in the meantime
+                let Some(table_info) = self.find_table_info_by_table_id(&table_name_id.table_id).await? else {
+                    continue;
+                };
+                self.export_table(writer, table_info).await?;
+                writer.write(&CatalogRecord::Checkpoint(table_name_id.table_id.clone()))?;
+                manifest.exported_tables.push(table_name_id.table_id);
+                manifest.resume_offset = writer.offset();
+                manifest.records = writer.records();
+            }
+        }
+        writer.write(&CatalogRecord::End)?;
+        manifest.complete = true;
+        Ok(manifest)
+    }
+
+    async fn export_table<W: Write>(&self, writer: &mut CatalogWriter<W>, table_info: TableInfo) -> Result<()> {
+        let table_id = table_info.table_id.clone();
+        writer.write(&CatalogRecord::Table(table_info))?;
+        for latest in self.get_all_partition_info(&table_id).await? {
+            let versions = self.list_partition_versions(&table_id, &latest.partition_desc).await?;
+            let (referenced, _) = Snapshot::dedup(
+                versions
+                    .iter()
+                    .flat_map(|partition_info| partition_info.snapshot.iter())
+                    .map(|commit_id| uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low)),
+            );
+            if !referenced.is_empty() {
+                let referenced = PartitionInfo {
+                    snapshot: referenced.to_proto(),
+                    ..latest
+                };
+                for data_commit_info in self
+                    .get_stored_data_commit_info_with_stats_of_single_partition(&referenced)
+                    .await?
+                {
+                    writer.write(&CatalogRecord::DataCommit(data_commit_info))?;
+                }
+            }
+            for partition_info in versions {
+                writer.write(&CatalogRecord::Partition(partition_info))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the partition versions of the table missing on `target` with the data commits they refer to, creating
+    /// the table of the same id on `target` if absent. Partitions whose versions on `target` are not on this side,
+    /// or differ from them, are reported as diverged and left as they are. Fails with NotFound if there is no such
+    /// table here.
+    pub async fn sync_table_metadata(&self, target: &MetaDataClient, table_id: &str) -> Result<SyncReport> {
+        let table_info = self
+            .find_table_info_by_table_id(table_id)
+            .await?
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_id)))?;
+        let mut report = SyncReport {
+            table_id: table_id.to_string(),
+            ..Default::default()
+        };
+        if target.find_table_info_by_table_id(table_id).await?.is_none() {
+            target.create_table(table_info.clone()).await?;
+            report.table_created = true;
+        }
+        let mut target_latest = target
+            .get_all_partition_info(table_id)
+            .await?
+            .into_iter()
+            .map(|partition_info| (partition_info.partition_desc.clone(), partition_info))
+            .collect::<HashMap<_, _>>();
+        for latest in self.get_all_partition_info(table_id).await? {
+            let partition_desc = latest.partition_desc.clone();
+            let target_current = target_latest.remove(&partition_desc);
+            if target_current
+                .as_ref()
+                .is_some_and(|current| current.version == latest.version && current.snapshot == latest.snapshot)
+            {
+                continue;
+            }
+            let versions = self.list_partition_versions(table_id, &partition_desc).await?;
+            let target_versions = match target_current {
+                Some(_) => target.list_partition_versions(table_id, &partition_desc).await?,
+                None => vec![],
+            };
+            // the versions on the target must be the first versions of this side
+            let diverged = target_versions.iter().any(|target_version| {
+                versions
+                    .iter()
+                    .find(|version| version.version == target_version.version)
+                    .is_none_or(|version| version.snapshot != target_version.snapshot)
+            });
+            if diverged {
+                report.diverged_partitions.push(partition_desc);
+                continue;
+            }
+            let synced_version = target_versions.last().map(|partition_info| partition_info.version);
+            let missing = versions
+                .into_iter()
+                .filter(|partition_info| synced_version.is_none_or(|synced| partition_info.version > synced))
+                .collect::<Vec<_>>();
+
+            let (referenced, _) = Snapshot::dedup(
+                missing
+                    .iter()
+                    .flat_map(|partition_info| partition_info.snapshot.iter())
+                    .map(|commit_id| uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low)),
+            );
+            if !referenced.is_empty() {
+                let referenced = PartitionInfo {
+                    snapshot: referenced.to_proto(),
+                    ..latest
+                };
+                let present = target
+                    .get_data_commit_info_of_single_partition(&referenced)
+                    .await?
+                    .into_iter()
+                    .filter_map(|data_commit_info| data_commit_info.commit_id)
+                    .collect::<Vec<_>>();
+                for data_commit_info in self
+                    .get_stored_data_commit_info_with_stats_of_single_partition(&referenced)
+                    .await?
+                {
+                    if data_commit_info
+                        .commit_id
+                        .as_ref()
+                        .is_some_and(|commit_id| present.contains(commit_id))
+                    {
+                        continue;
+                    }
+                    target
+                        .insert_data_commit_info_with_stats(&data_commit_info, None)
+                        .await?;
+                    report.data_commits += 1;
+                }
+            }
+            for partition_info in missing {
+                let raced = target
+                    .insert_new_partition_info(
+                        std::slice::from_ref(&partition_info),
+                        partition_stats_enabled(&table_info),
+                        Some(partition_info.timestamp),
+                    )
+                    .await?;
+                if !raced.is_empty() {
+                    // committed to on the target meanwhile
+                    report.diverged_partitions.push(partition_desc);
+                    break;
+                }
+                report.partition_versions += 1;
+            }
+        }
+        // partitions of the target only
+        report.diverged_partitions.extend(target_latest.into_keys());
+        report.diverged_partitions.sort();
+        Ok(report)
+    }
+
+    /// [`Self::sync_table_metadata`] of the tables of the namespace, at most `parallelism` at a time, creating the
+    /// namespace on `target` if absent. The syncs share the connection of each client. Tables failing to sync are
+    /// reported with their error, the others are synced regardless.
+    pub async fn sync_namespace_metadata(
+        &self,
+        target: &MetaDataClient,
+        namespace: &str,
+        parallelism: usize,
+    ) -> Result<NamespaceSyncSummary> {
+        let source_namespace = self
+            .execute_query(
+                DaoType::SelectNamespaceByNamespace as i32,
+                encode_query_params(&[self.namespace_or_default(namespace)]),
+            )
+            .await?
+            .namespace
+            .into_iter()
+            .next()
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Namespace '{}' not found", namespace)))?;
+        let target_namespace = target
+            .execute_query(
+                DaoType::SelectNamespaceByNamespace as i32,
+                encode_query_params(&[source_namespace.namespace.as_str()]),
+            )
+            .await?
+            .namespace;
+        if target_namespace.is_empty() {
+            target.create_namespace(source_namespace.clone()).await?;
+        }
+        let tables = self
+            .get_all_table_name_id_by_namespace(&source_namespace.namespace)
+            .await?;
+        let tables = &Mutex::new(tables.into_iter());
+        let summary = Mutex::new(NamespaceSyncSummary::default());
+        let workers = (0..parallelism.max(1)).map(|_| {
+            let summary = &summary;
+            async move {
+                loop {
+                    let Some(table) = tables.lock().await.next() else {
+                        return;
+                    };
+                    let result = self.sync_table_metadata(target, &table.table_id).await;
+                    let mut summary = summary.lock().await;
+                    match result {
+                        Ok(report) => summary.synced.push(report),
+                        Err(e) => summary.failed.push((table.table_id, e)),
+                    }
+                }
+            }
+        });
+        futures::future::join_all(workers).await;
+        Ok(summary.into_inner())
+    }
+
+    /// Import an export of [`Self::export_catalog`] record by record, keeping the timestamps of the partition
+    /// versions and data commits unless the client takes timestamps from the server. Tables conflicting with
+    /// existing ones are handled according to `conflict`. Fails with InvalidInput if the export has been cut short,
+    /// leaving the tables imported completely up to there in place. The table being imported when the import fails
+    /// is deleted again, so that a rerun imports it rather than finding it in the way.
+    pub async fn import_catalog(&self, source: impl Read, conflict: ConflictPolicy) -> Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        // the table whose records are read, with whether they are imported
+        let mut current = None;
+        let result = self.import_records(source, conflict, &mut summary, &mut current).await;
+        if let (Err(err), Some((table_info, true))) = (&result, &current) {
+            // a rerun with ConflictPolicy::Skip would leave the partial table as it is otherwise
+            if let Err(delete_err) = self.delete_table_by_table_info_cascade(table_info).await {
+                warn!(
+                    "failed to delete table '{}' imported partially before failing with {}: {}",
+                    table_info.table_name, err, delete_err
+                );
+            }
+        }
+        result.map(|_| summary)
+    }
+
+    async fn import_records(
+        &self,
+        source: impl Read,
+        conflict: ConflictPolicy,
+        summary: &mut ImportSummary,
+        current: &mut Option<(TableInfo, bool)>,
+    ) -> Result<()> {
+        let mut reader = CatalogReader::new(source)?;
+        while let Some(record) = reader.next()? {
+            match record {
+                CatalogRecord::Namespace(namespace) => {
+                    let exists = !self
+                        .execute_query(
+                            DaoType::SelectNamespaceByNamespace as i32,
+                            encode_query_params(&[namespace.namespace.as_str()]),
+                        )
+                        .await?
+                        .namespace
+                        .is_empty();
+                    if !exists {
+                        self.create_namespace(namespace).await?;
+                        summary.namespaces += 1;
+                    } else if conflict == ConflictPolicy::Replace {
+                        self.update_namespace_properties(&namespace.namespace, &namespace.properties)
+                            .await?;
+                        summary.namespaces += 1;
+                    }
+                }
+                CatalogRecord::Table(table_info) => {
+                    let imported = self.import_table(&table_info, conflict, summary).await?;
+                    *current = Some((table_info, imported));
+                }
+                CatalogRecord::DataCommit(data_commit_info) => {
+                    if imported_table(current, &data_commit_info.table_id)?.is_some() {
+                        self.insert_data_commit_info_with_stats(&data_commit_info, None).await?;
+                        summary.data_commits += 1;
+                    }
+                }
+                CatalogRecord::Partition(partition_info) => {
+                    if let Some(table_info) = imported_table(current, &partition_info.table_id)? {
+                        let existing = self
+                            .insert_new_partition_info(
+                                std::slice::from_ref(&partition_info),
+                                partition_stats_enabled(table_info),
+                                Some(partition_info.timestamp),
+                            )
+                            .await?;
+                        if !existing.is_empty() {
+                            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                                "version {} of partition '{}' of table '{}' is exported twice",
+                                partition_info.version, partition_info.partition_desc, partition_info.table_id
+                            )));
+                        }
+                        summary.partition_versions += 1;
+                    }
+                }
+                CatalogRecord::Checkpoint(_) => *current = None,
+                CatalogRecord::End => return Ok(()),
+            }
+        }
+        Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "catalog export has been cut short after {} records",
+            reader.records()
+        )))
+    }
+
+    /// Create the imported table unless it conflicts with existing ones and is skipped, returning whether it has
+    /// been created.
+    async fn import_table(
+        &self,
+        table_info: &TableInfo,
+        conflict: ConflictPolicy,
+        summary: &mut ImportSummary,
+    ) -> Result<bool> {
+        let mut existing = Vec::from_iter(self.find_table_info_by_table_id(&table_info.table_id).await?);
+        for found in [
+            self.fetch_table_info_by_table_path(&table_info.table_path).await,
+            self.fetch_table_info_by_table_name(&table_info.table_name, &table_info.table_namespace)
+                .await,
+        ] {
+            match found {
+                Ok(found) if existing.iter().all(|table| table.table_id != found.table_id) => existing.push(found),
+                Ok(_) | Err(LakeSoulMetaDataError::NotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if let Some(table) = existing.first() {
+            match conflict {
+                ConflictPolicy::Fail => {
+                    return Err(LakeSoulMetaDataError::AlreadyExists(format!(
+                        "table '{}' conflicts with table '{}' of namespace '{}' at '{}'",
+                        table_info.table_name, table.table_name, table.table_namespace, table.table_path
+                    )))
+                }
+                ConflictPolicy::Skip => {
+                    summary.skipped_tables.push(table_info.table_name.clone());
+                    return Ok(false);
+                }
+                ConflictPolicy::Replace => {
+                    for table in &existing {
+                        self.delete_table_by_table_info_cascade(table).await?;
+                    }
+                }
+            }
+        }
+        self.create_table(table_info.clone()).await?;
+        summary.tables += 1;
+        Ok(true)
+    }
+
+    pub async fn get_db_time(&self) -> Result<i64> {
+        self.execute_query_scalar(DaoType::GetDbTimestamp as i32, String::new())
+            .await?
+            .ok_or(LakeSoulMetaDataError::Internal("db timestamp missing".to_string()))?
+            .parse::<i64>()
+            .map_err(LakeSoulMetaDataError::from)
+    }
+
+    /// Timestamp of the latest commit to the table, None if the table has not been committed to.
+    pub async fn get_table_last_updated(&self, table_id: &str) -> Result<Option<i64>> {
+        self.execute_query_scalar(
+            DaoType::GetLatestTimestampFromPartitionInfoWithoutPartitionDesc as i32,
+            table_id.to_string(),
+        )
+        .await?
+        .map(|timestamp| timestamp.parse::<i64>().map_err(LakeSoulMetaDataError::from))
+        .transpose()
+    }
+
+    /// Partition versions visible at `timestamp`, for each partition the highest version committed up to that time.
+    pub async fn get_table_snapshot_at_timestamp(&self, table_id: &str, timestamp: i64) -> Result<Vec<PartitionInfo>> {
+        self.execute_query(
+            DaoType::ListPartitionSnapshotByTableIdAndTimestamp as i32,
+            encode_query_params(&[table_id, timestamp.to_string().as_str()]),
+        )
+        .await
+        .map(|wrapper| wrapper.partition_info)
+    }
+
+    /// Domain the table was created in, which its partitions and commits are recorded in as well. Fails with
+    /// NotFound if there is no such table.
+    pub async fn get_table_domain(&self, table_id: &str) -> Result<String> {
+        self.get_table_info_by_table_id(table_id)
+            .await
+            .map(|table_info| self.domain_of(&table_info))
+    }
+
+    /// Recorded domain of `table_info`, the default domain for tables created without one.
+    fn domain_of(&self, table_info: &TableInfo) -> String {
+        if table_info.domain.is_empty() {
+            self.default_domain.clone()
+        } else {
+            table_info.domain.clone()
+        }
+    }
+
+    pub async fn get_all_table_name_id_by_namespace(&self, namespace: &str) -> Result<Vec<TableNameId>> {
+        match self
+            .execute_query(
+                DaoType::ListTableNameByNamespace as i32,
+                self.namespace_or_default(namespace).to_string(),
+            )
+            .await
+        {
+            Ok(wrapper) => Ok(wrapper.table_name_id),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// TableInfo of the tables of the namespace, in the order of [`Self::get_all_table_name_id_by_namespace`].
+    /// A table dropped in between is left out.
+    pub async fn get_all_table_info_by_namespace(&self, namespace: &str) -> Result<Vec<TableInfo>> {
+        let names = self
+            .get_all_table_name_id_by_namespace(namespace)
+            .await?
+            .into_iter()
+            .map(|table_name_id| (table_name_id.table_name, table_name_id.table_namespace))
+            .collect::<Vec<_>>();
+        let mut table_infos = self.get_table_infos(&names).await?;
+        Ok(names.iter().filter_map(|name| table_infos.remove(name)).collect())
+    }
+
+    pub async fn get_all_namespace(&self) -> Result<Vec<Namespace>> {
+        self.execute_query(DaoType::ListNamespaces as i32, String::new())
+            .await
+            .map(|wrapper| wrapper.namespace)
+    }
+
+    pub async fn get_namespaces_with_table_counts(&self) -> Result<Vec<(Namespace, i64)>> {
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match list_namespaces_with_table_counts(
+                self.client.lock().await.deref(),
+                &self.prepared.lock().await.context.column_fallbacks,
+            )
+            .await
+            {
+                Ok(namespaces) => return Ok(namespaces),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Table, partition and data commit counts along with the latest commit of every namespace, e.g. for a
+    /// dashboard. Computed by a single aggregate statement whatever the number of tables.
+    pub async fn get_catalog_overview(&self) -> Result<Vec<NamespaceOverview>> {
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match get_catalog_overview(self.client.lock().await.deref()).await {
+                Ok(overview) => return Ok(overview),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Distinct domains the tables of the catalog belong to, in order.
+    pub async fn list_domains(&self) -> Result<Vec<String>> {
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match list_domains(
+                self.client.lock().await.deref(),
+                &self.prepared.lock().await.context.column_fallbacks,
+            )
+            .await
+            {
+                Ok(domains) => return Ok(domains),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Up to `limit` tables of the catalog with the bytes of their live files, the largest first, for capacity
+    /// planning. The sizes are those of [`crate::get_table_stats`]. Fails with InvalidInput if `limit` is negative.
+    pub async fn list_tables_by_size(&self, limit: i64) -> Result<Vec<(TableInfo, i64)>> {
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match list_tables_by_size(
+                self.client.lock().await.deref(),
+                &self.prepared.lock().await.context.column_fallbacks,
+                limit,
+            )
+            .await
+            {
+                Ok(tables) => return Ok(tables),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// TableInfo of the tables of the domain across all namespaces, in the order of their table_id, fetched in pages
+    /// of [`TABLE_INFO_PAGE_SIZE`] tables. The result reflects the domains recorded in the metadata only, not the
+    /// access granted by the storage the tables are in.
+    pub async fn get_all_table_info_by_domain(&self, domain: &str) -> Result<Vec<TableInfo>> {
+        let mut table_infos = vec![];
+        loop {
+            let after_table_id = table_infos
+                .last()
+                .map(|table_info: &TableInfo| table_info.table_id.clone());
+            let page = self
+                .get_table_info_page_by_domain(domain, after_table_id.as_deref(), TABLE_INFO_PAGE_SIZE)
+                .await?;
+            let exhausted = (page.len() as i64) < TABLE_INFO_PAGE_SIZE;
+            table_infos.extend(page);
+            if exhausted {
+                return Ok(table_infos);
+            }
+        }
+    }
+
+    /// Page of at most `limit` tables of [`Self::get_all_table_info_by_domain`], those after the table
+    /// `after_table_id`, from the start if None.
+    pub async fn get_table_info_page_by_domain(
+        &self,
+        domain: &str,
+        after_table_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<TableInfo>> {
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match list_table_info_by_domain(
+                self.client.lock().await.deref(),
+                &self.prepared.lock().await.context.column_fallbacks,
+                domain,
+                after_table_id,
+                limit,
+            )
+            .await
+            {
+                Ok(table_infos) => return Ok(table_infos),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    pub async fn get_namespace_by_namespace(&self, namespace: &str) -> Result<Namespace> {
+        self.execute_query(
+            DaoType::SelectNamespaceByNamespace as i32,
+            encode_query_params(&[self.namespace_or_default(namespace)]),
+        )
+        .await
+        .map(|wrapper| wrapper.namespace[0].clone())
+    }
+
+    pub async fn get_table_name_id_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableNameId> {
+        let namespace = self.namespace_or_default(namespace);
+        self.cached(
+            CacheKey::Name(namespace.to_string(), table_name.to_string()),
+            self.fetch_table_name_id_by_table_name(table_name, namespace),
+        )
+        .await
+    }
+
+    async fn fetch_table_name_id_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableNameId> {
+        match self
+            .execute_query(
+                DaoType::SelectTableNameIdByTableName as i32,
+                encode_query_params(&[table_name, self.namespace_or_default(namespace)]),
+            )
+            .await
+        {
+            Ok(wrapper) => Ok(wrapper.table_name_id[0].clone()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn get_table_name_id_by_table_id(&self, table_id: &str) -> Result<Option<TableNameId>> {
+        let fetch = async {
+            self.execute_query(DaoType::SelectTableNameIdByTableId as i32, table_id.to_string())
+                .await?
+                .table_name_id
+                .into_iter()
+                .next()
+                .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_id)))
+        };
+        match self.cached(CacheKey::Id(table_id.to_string()), fetch).await {
+            Ok(table_name_id) => Ok(Some(table_name_id)),
+            Err(LakeSoulMetaDataError::NotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Bulk reverse lookup of table_id -> TableNameId, ids without a name entry are absent from the map.
+    pub async fn get_table_names_by_table_ids(&self, ids: &[String]) -> Result<HashMap<String, TableNameId>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.execute_query(
+            DaoType::ListTableNameIdByTableIds as i32,
+            encode_query_params(&ids.iter().map(String::as_str).collect::<Vec<&str>>()),
+        )
+        .await
+        .map(|wrapper| {
+            wrapper
+                .table_name_id
+                .into_iter()
+                .map(|table_name_id| (table_name_id.table_id.clone(), table_name_id))
+                .collect()
+        })
+    }
+
+    pub async fn get_table_info_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableInfo> {
+        let namespace = self.namespace_or_default(namespace);
+        self.cached(
+            CacheKey::Name(namespace.to_string(), table_name.to_string()),
+            self.fetch_table_info_by_table_name(table_name, namespace),
+        )
+        .await
+    }
+
+    /// Bulk lookup of (table name, namespace) -> TableInfo, in a query per [`TABLE_INFO_BATCH_SIZE`] names not
+    /// served by the table cache. Missing tables are absent from the map, keyed by the names as given.
+    pub async fn get_table_infos(&self, names: &[(String, String)]) -> Result<HashMap<(String, String), TableInfo>> {
+        let mut table_infos = HashMap::with_capacity(names.len());
+        let mut missed = vec![];
+        for (table_name, namespace) in names {
+            let resolved_namespace = self.namespace_or_default(namespace);
+            let cached = self.table_cache.as_ref().and_then(|table_cache| {
+                table_cache.get::<TableInfo>(&CacheKey::Name(resolved_namespace.to_string(), table_name.clone()))
+            });
+            match cached {
+                Some(table_info) => {
+                    table_infos.insert((table_name.clone(), namespace.clone()), table_info);
+                }
+                None => missed.push((table_name, namespace, resolved_namespace)),
+            }
+        }
+        let generation = self.table_cache.as_ref().map(|table_cache| table_cache.generation());
+        for chunk in missed.chunks(TABLE_INFO_BATCH_SIZE) {
+            let params = chunk
+                .iter()
+                .flat_map(|(table_name, _, resolved_namespace)| [table_name.as_str(), *resolved_namespace])
+                .collect::<Vec<_>>();
+            let fetched = self
+                .execute_query(
+                    DaoType::ListTableInfoByTableNamesAndNamespaces as i32,
+                    encode_query_params(&params),
+                )
+                .await?
+                .table_info;
+            if let (Some(table_cache), Some(generation)) = (&self.table_cache, generation) {
+                table_cache.put_all(generation, fetched.iter().cloned());
+            }
+            let fetched = fetched
+                .into_iter()
+                .map(|table_info| {
+                    (
+                        (table_info.table_name.clone(), table_info.table_namespace.clone()),
+                        table_info,
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            // a table may be asked for under both an empty and the default namespace
+            for (table_name, namespace, resolved_namespace) in chunk {
+                if let Some(table_info) = fetched.get(&(table_name.to_string(), resolved_namespace.to_string())) {
+                    table_infos.insert((table_name.to_string(), namespace.to_string()), table_info.clone());
+                }
+            }
+        }
+        Ok(table_infos)
+    }
+
+    async fn fetch_table_info_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableInfo> {
+        match self
+            .execute_query(
+                DaoType::SelectTableInfoByTableNameAndNameSpace as i32,
+                encode_query_params(&[table_name, self.namespace_or_default(namespace)]),
+            )
+            .await
+        {
+            Ok(wrapper) if wrapper.table_info.is_empty() => Err(crate::error::LakeSoulMetaDataError::NotFound(
+                format!("Table '{}' not found", table_name),
+            )),
+            Ok(wrapper) => Ok(wrapper.table_info[0].clone()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn get_table_info_by_table_path(&self, table_path: &str) -> Result<TableInfo> {
+        self.cached(
+            CacheKey::Path(table_path.to_string()),
+            self.fetch_table_info_by_table_path(table_path),
+        )
+        .await
+    }
+
+    async fn fetch_table_info_by_table_path(&self, table_path: &str) -> Result<TableInfo> {
+        match self
+            .execute_query(DaoType::SelectTableInfoByTablePath as i32, table_path.to_string())
+            .await
+        {
+            Ok(wrapper) if wrapper.table_info.is_empty() => Err(crate::error::LakeSoulMetaDataError::NotFound(
+                format!("Table '{}' not found", table_path),
+            )),
+            Ok(wrapper) => Ok(wrapper.table_info[0].clone()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Spark-formatted schema of the table.
+    pub async fn get_table_schema(&self, table_id: &str) -> Result<String> {
+        Ok(self.get_table_info_by_table_id(table_id).await?.table_schema)
+    }
+
+    /// Schemas of the tables of `table_ids` by table id in a single query, e.g. for planning a query across
+    /// them. Missing tables are absent from the map.
+    pub async fn get_schemas_by_table_ids(&self, table_ids: &[String]) -> Result<HashMap<String, String>> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match get_schemas_by_table_ids(self.client.lock().await.deref(), table_ids).await {
+                Ok(schemas) => return Ok(schemas),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Compare `candidate_schema`, e.g. the schema of the files about to be committed, with the schema of the table
+    /// without writing anything. Both may be spark-formatted or arrow-java-formatted.
+    pub async fn validate_schema_against(&self, table_id: &str, candidate_schema: &str) -> Result<SchemaValidation> {
+        validate_schema(&self.get_table_schema(table_id).await?, candidate_schema)
+    }
+
+    /// Load the TableInfo and TableNameId of `table_ids` into the table cache ahead of their lookups, ids of
+    /// missing tables are skipped. Does nothing if the table cache is disabled.
+    pub async fn prefetch_table_info(&self, table_ids: &[String]) -> Result<()> {
+        let Some(table_cache) = &self.table_cache else {
+            return Ok(());
+        };
+        let generation = table_cache.generation();
+        let table_name_ids = self.get_table_names_by_table_ids(table_ids).await?;
+        for table_id in table_ids {
+            let table_info = self
+                .execute_query(DaoType::SelectTableInfoByTableId as i32, table_id.clone())
+                .await?
+                .table_info;
+            table_cache.refresh(generation, table_id, table_info.into_iter().next());
+            table_cache.refresh(generation, table_id, table_name_ids.get(table_id).cloned());
+        }
+        table_cache.save();
+        Ok(())
+    }
+
+    /// Fails with NotFound if there is no such table.
+    pub async fn get_table_info_by_table_id(&self, table_id: &str) -> Result<TableInfo> {
+        in_context(
+            ErrorContext::new("get_table_info_by_table_id").with_table_id(table_id),
+            self.cached(
+                CacheKey::Id(table_id.to_string()),
+                self.fetch_table_info_by_table_id(table_id),
+            ),
+        )
+        .await
+    }
+
+    async fn fetch_table_info_by_table_id(&self, table_id: &str) -> Result<TableInfo> {
+        self.find_table_info_by_table_id(table_id)
+            .await?
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_id)))
+    }
+
+
+    pub async fn get_data_files_by_table_name(
+        &self,
+        table_name: &str,
+        namespace: &str,
+    ) -> Result<Vec<String>> {
+        let table_info = self.get_table_info_by_table_name(table_name, namespace).await?;
+        debug!("table_info: {:?}", table_info);
+        let partition_list = self.get_all_partition_info(table_info.table_id.as_str()).await?;
+        debug!(
+            "{} 's partition_list: {:?}",
+            table_info.table_id.as_str(),
+            partition_list
+        );
+        self.get_data_files_of_partitions(partition_list).await
+    }
+
+    /// Data files of the latest version of the partitions of the table at `table_path` whose columns have the values
+    /// of `partition_filter`, given as `(column, value)` pairs as in [`Self::get_partition_values_with_filter`], e.g.
+    /// the files of `region=us`. All partitions if the filter is empty. Fails with NotFound if there is no such table.
+    pub async fn get_data_files_by_table_path(
+        &self,
+        table_path: &str,
+        partition_filter: Vec<(&str, &str)>,
+    ) -> Result<Vec<String>> {
+        let table_info = self.get_table_info_by_table_path(table_path).await?;
+        let filters = partition_filter
+            .iter()
+            .map(|(key, value)| [*key, *value].join(LAKESOUL_PARTITION_DESC_KV_DELIM))
+            .collect::<Vec<_>>();
+        let partition_list = self
+            .get_all_partition_info(&table_info.table_id)
+            .await?
+            .into_iter()
+            .filter(|partition_info| {
+                let kvs = partition_info
+                    .partition_desc
+                    .split(LAKESOUL_RANGE_PARTITION_SPLITTER)
+                    .collect::<Vec<_>>();
+                filters.iter().all(|filter| kvs.contains(&filter.as_str()))
+            })
+            .collect();
+        self.get_data_files_of_partitions(partition_list).await
+    }
+
+    pub async fn get_data_files_of_partitions(
+        &self, 
+        partition_list: Vec<PartitionInfo>, 
+    ) -> Result<Vec<String>> {
+        let mut data_commit_info_list = Vec::new();
+        for partition_info in &partition_list {
+            data_commit_info_list.extend(self.get_data_commit_info_of_single_partition(partition_info).await?);
+        }
+        self.resolve_file_paths(&mut data_commit_info_list).await?;
+        Ok(data_commit_info_list
+            .into_iter()
+            .flat_map(|data_commit_info| data_commit_info.file_ops.into_iter().map(|file_op| file_op.path))
+            .collect())
+    }
+
+    pub async fn get_data_files_of_single_partition(
+        &self, 
+        partition_info: &PartitionInfo, 
+    ) -> Result<Vec<String>> {
+        self.get_data_files_of_partitions(vec![partition_info.clone()]).await
+    }
+
+    /// Check the paths of file ops to commit to the table and rewrite them as the path mode of the client demands.
+    /// Fails with InvalidInput on an empty path or one on another file system than the table.
+    fn store_file_paths(&self, table_info: &TableInfo, file_ops: &mut [entity::DataFileOp]) -> Result<()> {
+        for file_op in file_ops {
+            check_file_path(&table_info.table_path, &file_op.path)?;
+            file_op.path = store_file_path(self.path_mode, &table_info.table_path, &file_op.path);
+        }
+        Ok(())
+    }
+
+    /// Resolve the paths of the file ops of data commits stored relative to the path of their table, unless the
+    /// client keeps paths as stored. The path of each table is fetched once.
+    async fn resolve_file_paths(&self, data_commit_info_list: &mut [DataCommitInfo]) -> Result<()> {
+        resolve_stored_file_paths(self.path_mode, data_commit_info_list, |table_id| async move {
+            Ok(self.get_table_info_by_table_id(&table_id).await?.table_path)
+        })
+        .await
+    }
+
+    /// Path of the table to resolve the paths of its data files stored relative against, None if the client keeps
+    /// paths as stored or there is no such table.
+    async fn table_path_to_resolve(&self, table_id: &str) -> Result<Option<String>> {
+        match self.path_mode {
+            PathMode::KeepAbsolute => Ok(None),
+            _ => Ok(self
+                .find_table_info_by_table_id(table_id)
+                .await?
+                .map(|table_info| table_info.table_path)),
+        }
+    }
+
+
+    async fn get_data_commit_info_of_single_partition(
+        &self,
+        partition_info: &PartitionInfo,
+    ) -> Result<Vec<DataCommitInfo>> {
+        match self
+            .execute_query(
+                DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList as i32,
+                joined_snapshot_query_string(partition_info),
+            )
+            .await
+        {
+            Ok(wrapper) => Ok(wrapper.data_commit_info),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Data commits of the snapshot of a partition version, with the column stats of each file op
+    /// filled in from the registered file stats. Files without stats, or a database without the stats table,
+    /// just come back without them. Paths stored relative are resolved as by
+    /// [`Self::get_data_files_of_single_partition`].
+    pub async fn get_data_commit_info_with_stats_of_single_partition(
+        &self,
+        partition_info: &PartitionInfo,
+    ) -> Result<Vec<DataCommitInfo>> {
+        let mut data_commit_info_list = self
+            .get_stored_data_commit_info_with_stats_of_single_partition(partition_info)
+            .await?;
+        self.resolve_file_paths(&mut data_commit_info_list).await?;
+        Ok(data_commit_info_list)
+    }
+
+    /// [`Self::get_data_commit_info_with_stats_of_single_partition`] with the paths as stored, for copying the data
+    /// commits.
+    async fn get_stored_data_commit_info_with_stats_of_single_partition(
+        &self,
+        partition_info: &PartitionInfo,
+    ) -> Result<Vec<DataCommitInfo>> {
+        if self.is_table_missing("file_stats").await {
+            return self.get_data_commit_info_of_single_partition(partition_info).await;
+        }
+        Ok(self
+            .execute_query(
+                DaoType::ListDataCommitInfoWithStatsByTableIdAndPartitionDescAndCommitList as i32,
+                joined_snapshot_query_string(partition_info),
+            )
+            .await?
+            .data_commit_info)
+    }
+
+    /// Record column stats of files added by the data commit `commit_id`, replacing stats registered before.
+    pub async fn register_file_stats(&self, commit_id: &entity::Uuid, stats: Vec<FileStats>) -> Result<()> {
+        if stats.is_empty() {
+            return Ok(());
+        }
+        self.execute_insert(
+            DaoType::TransactionInsertFileStats as i32,
+            JniWrapper {
+                file_stats: stats
+                    .into_iter()
+                    .map(|file_stats| FileStats {
+                        commit_id: Some(commit_id.clone()),
+                        ..file_stats
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Registered column stats of the files in the snapshot of one partition version.
+    pub async fn get_file_stats_for_snapshot(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        version: i32,
+    ) -> Result<Vec<FileStats>> {
+        if self.is_table_missing("file_stats").await {
+            return Ok(vec![]);
+        }
+        Ok(self
+            .execute_query(
+                DaoType::ListFileStatsByTableIdAndPartitionDescAndVersion as i32,
+                encode_query_params(&[table_id, partition_desc, &version.to_string()]),
+            )
+            .await?
+            .file_stats)
+    }
+
+    /// The schema in effect when partition version `version` of the table was committed, to read the data of
+    /// that version. For range partitioned tables the latest commit of a partition reaching `version` is taken.
+    /// Fails with NotFound if there is no such table or version.
+    pub async fn get_schema_at_version(&self, table_id: &str, version: i32) -> Result<String> {
+        if self.is_table_missing("table_schema_history").await {
+            return Err(LakeSoulMetaDataError::IncompatibleSchema(
+                "schema history needs the table_schema_history table in the metadata database".to_string(),
+            ));
+        }
+        match self
+            .execute_query_scalar_value(
+                DaoType::GetTableSchemaByTableIdAndVersion as i32,
+                encode_query_params(&[table_id, &version.to_string()]),
+            )
+            .await?
+        {
+            ScalarValue::Value(table_schema) => Ok(table_schema),
+            ScalarValue::Null | ScalarValue::NoRows => Err(LakeSoulMetaDataError::NotFound(format!(
+                "Version {} of table '{}' not found",
+                version, table_id
+            ))),
+        }
+    }
+
+    /// Schemas the table has had as `(schema_version, effective_at, table_schema)`, oldest first and ending with
+    /// the current one, within the retention of [`MetaDataClientBuilder::with_schema_history_retention`].
+    /// `effective_at` is the time in milliseconds the schema replaced the previous one, 0 for the schema the table
+    /// was created with or one replacing a schema past the retention. Fails with NotFound if there is no such table.
+    pub async fn get_schema_history(&self, table_id: &str) -> Result<Vec<(i32, i64, String)>> {
+        if self.is_table_missing("table_schema_history").await {
+            return Err(LakeSoulMetaDataError::IncompatibleSchema(
+                "schema history needs the table_schema_history table in the metadata database".to_string(),
+            ));
+        }
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match get_schema_history(self.client.lock().await.deref(), table_id).await {
+                Ok(history) if history.is_empty() => {
+                    return Err(LakeSoulMetaDataError::NotFound(format!(
+                        "Table '{}' not found",
+                        table_id
+                    )))
+                }
+                Ok(history) => return Ok(history),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// The version of the schema the data commit was written with and that schema, as recorded when the commit was
+    /// inserted, to read the commit as it was written. Fails with NotFound if no version was recorded for the
+    /// commit, e.g. one written before the metadata database recorded them, or if its schema is past the retention
+    /// of the schema history.
+    pub async fn get_schema_of_commit(&self, table_id: &str, commit_id: &entity::Uuid) -> Result<(i32, String)> {
+        if self.is_table_missing("commit_schema_version").await {
+            return Err(LakeSoulMetaDataError::IncompatibleSchema(
+                "commit schema versions need the commit_schema_version table in the metadata database".to_string(),
+            ));
+        }
+        let commit_id = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low);
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match get_commit_schema(self.client.lock().await.deref(), table_id, commit_id).await {
+                Ok(Some((schema_version, Some(table_schema)))) => return Ok((schema_version, table_schema)),
+                Ok(Some((schema_version, None))) => {
+                    return Err(LakeSoulMetaDataError::NotFound(format!(
+                        "Schema version {} of commit {} of table '{}' is past the retention of the schema history",
+                        schema_version, commit_id, table_id
+                    )))
+                }
+                Ok(None) => {
+                    return Err(LakeSoulMetaDataError::NotFound(format!(
+                        "No schema version recorded for commit {} of table '{}'",
+                        commit_id, table_id
+                    )))
+                }
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Tables without any partition version, i.e. created but never written to, in `namespace` or in all
+    /// namespaces if None. Ordered by namespace and table name.
+    pub async fn list_empty_tables(&self, namespace: Option<&str>) -> Result<Vec<TableInfo>> {
+        let namespace = namespace.map_or("", |namespace| self.namespace_or_default(namespace));
+        Ok(self
+            .execute_query(DaoType::ListEmptyTableInfoByNamespace as i32, namespace.to_string())
+            .await?
+            .table_info)
+    }
+
+    /// Distinct values of the range partition column `column` among the partitions of a table, sorted, e.g. all
+    /// the dates of a `date` column. Null values are listed as [`LAKESOUL_NULL_STRING`].
+    pub async fn get_partition_values(&self, table_id: &str, column: &str) -> Result<Vec<String>> {
+        self.get_partition_values_with_filter(table_id, column, &[]).await
+    }
+
+    /// [`Self::get_partition_values`] among the partitions whose other columns have the values of `filters`,
+    /// given as `(column, value)` pairs, e.g. the dates of `region=us`.
+    pub async fn get_partition_values_with_filter(
+        &self,
+        table_id: &str,
+        column: &str,
+        filters: &[(&str, &str)],
+    ) -> Result<Vec<String>> {
+        let filters = filters
+            .iter()
+            .map(|(key, value)| [*key, *value].join(LAKESOUL_PARTITION_DESC_KV_DELIM))
+            .collect::<Vec<_>>();
+        Ok(self
+            .execute_query(
+                DaoType::ListPartitionValueByTableIdAndColumn as i32,
+                encode_query_params(&[table_id, column, &encode_partition_desc_list(&filters)]),
+            )
+            .await?
+            .partition_info
+            .into_iter()
+            .map(|partition_info| partition_info.partition_desc)
+            .collect())
+    }
+
+    pub async fn get_schema_by_table_name(&self, table_name: &str, namespace: &str) -> Result<String> {
+        let table_info = self.get_table_info_by_table_name(table_name, namespace).await?;
+        Ok(table_info.table_schema)
+    }
+
+    pub async fn get_all_partition_info(&self, table_id: &str) -> Result<Vec<PartitionInfo>> {
+        in_context(
+            ErrorContext::new("get_all_partition_info").with_table_id(table_id),
+            async {
+                Ok(self
+                    .execute_query(DaoType::ListPartitionByTableId as i32, table_id.to_string())
+                    .await?
+                    .partition_info)
+            },
+        )
+        .await
+    }
+
+    /// See [`Self::get_all_partition_info`] and [`Self::run_with_options`].
+    pub async fn get_all_partition_info_with_options(
+        &self,
+        table_id: &str,
+        options: OpOptions,
+    ) -> Result<Vec<PartitionInfo>> {
+        self.run_with_options(options, self.get_all_partition_info(table_id))
+            .await
+    }
+
+    /// The latest version of each partition of the table as a json array, for consumers which do not decode
+    /// protobuf. Commit ops are named as in the database, commit ids are hyphenated uuids.
+    pub async fn get_partitions_as_json(&self, table_id: &str) -> Result<String> {
+        let partitions = self
+            .get_all_partition_info(table_id)
+            .await?
+            .into_iter()
+            .map(|partition_info| {
+                Ok(serde_json::json!({
+                    "table_id": partition_info.table_id,
+                    "partition_desc": partition_info.partition_desc,
+                    "version": partition_info.version,
+                    "commit_op": encode_commit_op(partition_info.commit_op)?,
+                    "timestamp": partition_info.timestamp,
+                    "snapshot": partition_info
+                        .snapshot
+                        .iter()
+                        .map(|commit_id| uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string())
+                        .collect::<Vec<_>>(),
+                    "expression": partition_info.expression,
+                    "domain": partition_info.domain,
+                    "change_tracking": partition_info.change_tracking,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(serde_json::to_string(&partitions)?)
+    }
+
+    /// See [`Self::get_partitions_as_json`] and [`Self::run_with_options`].
+    pub async fn get_partitions_as_json_with_options(&self, table_id: &str, options: OpOptions) -> Result<String> {
+        self.run_with_options(options, self.get_partitions_as_json(table_id))
+            .await
+    }
+
+    /// Latest version of the partitions of the table matching `filter`, ordered by partition_desc. The values of
+    /// the partition descs of the filter are compared by the type of their column, e.g. the date `2023-01-01`
+    /// matches the partition `date=2023-1-1`, failing with InvalidInput on a value not of the type.
+    pub async fn get_partitions_by_filter(
+        &self,
+        table_id: &str,
+        filter: &PartitionFilter,
+    ) -> Result<Vec<PartitionInfo>> {
+        let Some(coercion) = self.partition_value_coercion(table_id, filter).await? else {
+            return self.query_partitions_by_filter(table_id, filter).await;
+        };
+        let partition_descs = filter
+            .partition_descs
+            .iter()
+            .map(|partition_desc| coercion.normalize(partition_desc))
+            .collect::<Result<HashSet<_>>>()?;
+        let filter = PartitionFilter {
+            partition_descs: vec![],
+            ..filter.clone()
+        };
+        Ok(self
+            .query_partitions_by_filter(table_id, &filter)
+            .await?
+            .into_iter()
+            .filter(|partition_info| {
+                partition_descs.contains(&coercion.normalize_recorded(&partition_info.partition_desc))
+            })
+            .collect())
+    }
+
+    /// Coercion of the partition values of the table if `filter` lists partition descs to be compared by type.
+    async fn partition_value_coercion(
+        &self,
+        table_id: &str,
+        filter: &PartitionFilter,
+    ) -> Result<Option<PartitionValueCoercion>> {
+        if filter.partition_descs.is_empty() {
+            return Ok(None);
+        }
+        Ok(self
+            .find_table_info_by_table_id(table_id)
+            .await?
+            .and_then(|table_info| PartitionValueCoercion::for_table(&table_info)))
+    }
+
+    async fn query_partitions_by_filter(&self, table_id: &str, filter: &PartitionFilter) -> Result<Vec<PartitionInfo>> {
+        Ok(self
+            .execute_query(
+                DaoType::ListPartitionByTableIdAndFilter as i32,
+                filter.joined_params(table_id),
+            )
+            .await?
+            .partition_info)
+    }
+
+    /// Number of partitions [`MetaDataClient::get_partitions_by_filter`] would return, counted without fetching them
+    /// unless partition values are compared by type.
+    pub async fn estimate_partition_count(&self, table_id: &str, filter: &PartitionFilter) -> Result<i64> {
+        if self.partition_value_coercion(table_id, filter).await?.is_some() {
+            return Ok(self.get_partitions_by_filter(table_id, filter).await?.len() as i64);
+        }
+        self.execute_query_scalar(
+            DaoType::CountPartitionByTableIdAndFilter as i32,
+            filter.joined_params(table_id),
+        )
+        .await?
+        .ok_or(LakeSoulMetaDataError::Internal("partition count missing".to_string()))?
+        .parse::<i64>()
+        .map_err(LakeSoulMetaDataError::from)
+    }
+
+    /// Canonical form of a partition_desc of the table, the one commits are recorded under, for lookups of
+    /// partitions named by callers with their columns in any order.
+    pub async fn normalize_partition_desc(&self, table_id: &str, partition_desc: &str) -> Result<String> {
+        let table_info = self.get_table_info_by_table_id(table_id).await?;
+        Ok(canonical_partition_desc(
+            partition_desc,
+            &range_partition_keys(&table_info.partitions),
+        ))
+    }
+
+    /// Lock the partition so that a single compactor rewrites it at a time, until the returned lock is released
+    /// or dropped. Fails with Locked if another compactor holds the lock, or waits for it if the client is built
+    /// [`MetaDataClientBuilder::with_compaction_lock_wait`].
+    pub async fn lock_partition_for_compaction(&self, table_id: &str, partition_desc: &str) -> Result<CompactionLock> {
+        let partition_desc = self.normalize_partition_desc(table_id, partition_desc).await?;
+        // advisory locks belong to the session, so each lock takes a connection of its own which is closed on drop
+        let client = create_connection_on(self.config.clone(), &self.runtime_handle).await?;
+        let locked = if self.compaction_lock_wait {
+            client
+                .execute(
+                    "select pg_advisory_lock(hashtext($1), hashtext($2))",
+                    &[&table_id, &partition_desc],
+                )
+                .await?;
+            true
+        } else {
+            client
+                .query_one(
+                    "select pg_try_advisory_lock(hashtext($1), hashtext($2))",
+                    &[&table_id, &partition_desc],
+                )
+                .await?
+                .get::<_, bool>(0)
+        };
+        if !locked {
+            return Err(LakeSoulMetaDataError::Locked(format!(
+                "partition '{}' of table '{}' is locked by another compactor",
+                partition_desc, table_id
+            )));
+        }
+        Ok(CompactionLock {
+            client,
+            table_id: table_id.to_string(),
+            partition_desc,
+        })
+    }
+
+    /// Whether the table has any version of the partition, false for a partition never committed to.
+    pub async fn partition_exists(&self, table_id: &str, partition_desc: &str) -> Result<bool> {
+        self.query_partition_flag(
+            DaoType::PartitionExistsByTableIdAndPartitionDesc,
+            table_id,
+            partition_desc,
+        )
+        .await
+    }
+
+    /// Whether the latest version of the partition has no added files, true for a partition never committed to.
+    pub async fn partition_is_empty(&self, table_id: &str, partition_desc: &str) -> Result<bool> {
+        self.query_partition_flag(
+            DaoType::PartitionIsEmptyByTableIdAndPartitionDesc,
+            table_id,
+            partition_desc,
+        )
+        .await
+    }
+
+    /// Partitions of the table whose latest version holds no live file, as all of their files were deleted, e.g.
+    /// candidates for removal by GC. Unlike [`Self::partition_is_empty`], added files deleted again do not count.
+    pub async fn list_empty_partitions(&self, table_id: &str) -> Result<Vec<String>> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match list_empty_partitions(self.client.lock().await.deref(), table_id).await {
+                Ok(partition_descs) => return Ok(partition_descs),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    async fn query_partition_flag(&self, query_type: DaoType, table_id: &str, partition_desc: &str) -> Result<bool> {
+        match self
+            .execute_query_scalar_value(query_type as i32, encode_query_params(&[table_id, partition_desc]))
+            .await?
+        {
+            ScalarValue::Value(value) => Ok(value == "true"),
+            _ => Err(LakeSoulMetaDataError::Internal(format!(
+                "{:?} returned no value",
+                query_type
+            ))),
+        }
+    }
+
+    /// Return the latest version of every partition of the table, ordered by the value of range partition column `key`.
+    /// Numeric and date/timestamp columns are compared by value according to the table schema, others lexicographically.
+    pub async fn get_partitions_sorted(
+        &self,
+        table_id: &str,
+        key: &str,
+        ascending: bool,
+    ) -> Result<Vec<PartitionInfo>> {
+        let table_info = self.get_table_info_by_table_id(table_id).await?;
+        let (range_keys, _) = parse_table_info_partitions(&table_info.partitions);
+        if !range_keys.iter().any(|range_key| range_key == key) {
+            return Err(LakeSoulMetaDataError::NotFound(format!(
+                "Range partition column '{}' not found in table '{}'",
+                key, table_id
+            )));
+        }
+        let ordering = PartitionValueOrdering::from_table_schema(&table_info.table_schema, key);
+        let mut partition_list = self.get_all_partition_info(table_id).await?;
+        sort_partitions_by_key(&mut partition_list, key, ordering, ascending);
+        Ok(partition_list)
+    }
+
+    /// Streaming counterpart of [`Self::get_all_partition_info`] for tables with a huge number of partitions.
+    pub async fn stream_all_partition_info(&self, table_id: &str) -> Result<BoxStream<'static, Result<PartitionInfo>>> {
+        Ok(self
+            .execute_query_streamed(DaoType::ListPartitionByTableId as i32, table_id.to_string())
+            .await?
+            .map(|item| match item? {
+                StreamedEntity::PartitionInfo(partition_info) => Ok(partition_info),
+                other => Err(LakeSoulMetaDataError::Internal(format!(
+                    "unexpected item {:?} in partition info stream",
+                    other
+                ))),
+            })
+            .boxed())
+    }
+
+    /// Streaming counterpart of [`Self::get_data_files_of_single_partition`], yielding the DataCommitInfo of the snapshot.
+    pub async fn stream_data_commit_info_of_single_partition(
+        &self,
+        partition_info: &PartitionInfo,
+    ) -> Result<BoxStream<'static, Result<DataCommitInfo>>> {
+        let table_path = self.table_path_to_resolve(&partition_info.table_id).await?;
+        Ok(self
+            .stream_stored_data_commit_info_of_single_partition(partition_info)
+            .await?
+            .map_ok(move |mut data_commit_info| {
+                if let Some(table_path) = &table_path {
+                    resolve_file_op_paths(table_path, &mut data_commit_info);
+                }
+                data_commit_info
+            })
+            .boxed())
+    }
+
+    /// [`Self::stream_data_commit_info_of_single_partition`] with the paths as stored.
+    async fn stream_stored_data_commit_info_of_single_partition(
+        &self,
+        partition_info: &PartitionInfo,
+    ) -> Result<BoxStream<'static, Result<DataCommitInfo>>> {
+        Ok(self
+            .execute_query_streamed(
+                DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList as i32,
+                joined_snapshot_query_string(partition_info),
+            )
+            .await?
+            .map(|item| match item? {
+                StreamedEntity::DataCommitInfo(data_commit_info) => Ok(data_commit_info),
+                other => Err(LakeSoulMetaDataError::Internal(format!(
+                    "unexpected item {:?} in data commit info stream",
+                    other
+                ))),
+            })
+            .boxed())
+    }
+
+    /// File ops of the latest version of each partition of the table, e.g. for storage GC over tables with millions
+    /// of files. The data commits are read a partition at a time by a streamed query as the stream is polled, so
+    /// only the partition list is held in memory. Paths stored relative are resolved as by
+    /// [`Self::get_data_files_of_single_partition`].
+    pub fn file_op_stream(&self, table_id: &str) -> BoxStream<'_, Result<entity::DataFileOp>> {
+        let table_id = table_id.to_string();
+        futures::stream::once(async move {
+            let partition_list = self.get_all_partition_info(&table_id).await?;
+            let table_path = self.table_path_to_resolve(&table_id).await?.map(Arc::new);
+            Ok::<_, LakeSoulMetaDataError>((partition_list, table_path))
+        })
+        .map_ok(|(partition_list, table_path)| {
+            futures::stream::iter(
+                partition_list
+                    .into_iter()
+                    .filter(|partition_info| !partition_info.snapshot.is_empty())
+                    .map(move |partition_info| Ok((partition_info, table_path.clone()))),
+            )
+        })
+        .try_flatten()
+        .and_then(move |(partition_info, table_path)| async move {
+            self.stream_stored_data_commit_info_of_single_partition(&partition_info)
+                .await
+                .map(|data_commit_infos| {
+                    data_commit_infos.map_ok(move |data_commit_info| (data_commit_info, table_path.clone()))
+                })
+        })
+        .try_flatten()
+        .map_ok(|(mut data_commit_info, table_path)| {
+            if let Some(table_path) = table_path.as_deref() {
+                resolve_file_op_paths(table_path, &mut data_commit_info);
+            }
+            futures::stream::iter(data_commit_info.file_ops.into_iter().map(Ok))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Number of partition versions of a table by commit op code, over all its partitions, the commit ops unknown
+    /// to this client counted under codes below [`crate::UNKNOWN_COMMIT_OP_CODE`].
+    /// Commit ops the table never used are left out.
+    pub async fn get_commit_op_distribution(&self, table_id: &str) -> Result<HashMap<i32, i64>> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let counted = self
+                .compatibility_warnings
+                .record(count_commit_ops_by_table_id(self.client.lock().await.deref(), table_id))
+                .await;
+            match counted {
+                Ok(counts) => return Ok(counts.into_iter().collect()),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Number of committed data commits of a table by partition, a cheap measure of the write activity of each
+    /// partition. Partitions without any commit are left out.
+    pub async fn get_partition_commit_counts(&self, table_id: &str) -> Result<HashMap<String, i64>> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            match count_data_commits_by_table_id(self.client.lock().await.deref(), table_id).await {
+                Ok(counts) => return Ok(counts.into_iter().collect()),
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// Up to `sample_size` paths of the current data files of a table, for sampling without fetching them all.
+    /// Paths stored relative are resolved as by [`Self::get_data_files_of_single_partition`]. Fails with InvalidInput
+    /// if `sample_size` is negative.
+    pub async fn sample_file_paths(&self, table_id: &str, sample_size: i64) -> Result<Vec<String>> {
+        let _permit = self.limiter.acquire().await?;
+        for times in 0..self.max_retry() as i64 {
+            note_attempt(times as usize + 1);
+            let sampled = sample_file_paths(self.client.lock().await.deref(), table_id, sample_size).await;
+            match sampled {
+                Ok(paths) if paths.iter().all(|path| is_absolute_path(path)) => return Ok(paths),
+                Ok(paths) => {
+                    let Some(table_path) = self.table_path_to_resolve(table_id).await? else {
+                        return Ok(paths);
+                    };
+                    return Ok(paths.iter().map(|path| resolve_file_path(&table_path, path)).collect());
+                }
+                Err(e) if times < self.max_retry() as i64 - 1 && e.is_retryable() => continue,
+                Err(e) => return Err(e),
+            };
+        }
+        Err(LakeSoulMetaDataError::Internal("unreachable".to_string()))
+    }
+
+    /// The `limit` latest partition versions of a table whatever their partition, newest first.
+    pub async fn get_recent_partition_versions(&self, table_id: &str, limit: i64) -> Result<Vec<PartitionInfo>> {
+        Ok(self
+            .execute_query(
+                DaoType::ListOperationLogByTableId as i32,
+                encode_query_params(&[table_id, limit.to_string().as_str()]),
+            )
+            .await?
+            .partition_info)
+    }
+
+    /// Check the invariants of the metadata of a table: the commits referenced by the snapshots exist and are
+    /// committed, the versions of each partition are contiguous, the name and path entries point at the table, the
+    /// schema is valid json and no file is added by the current snapshots of two partitions.
+    pub async fn check_table_consistency(&self, table_id: &str) -> Result<ConsistencyReport> {
+        self.check_table_consistency_with_fix(table_id, false).await
+    }
+
+    /// [`Self::check_table_consistency`], removing with `fix` the missing commits from the snapshots referencing
+    /// them. The other violations are left to the operator.
+    pub async fn check_table_consistency_with_fix(&self, table_id: &str, fix: bool) -> Result<ConsistencyReport> {
+        let table_info = self.fetch_table_info_by_table_id(table_id).await?;
+        let mut violations = vec![];
+        if let Err(e) = serde_json::from_str::<serde_json::Value>(&table_info.table_schema) {
+            violations.push(ConsistencyViolation::InvalidSchema { error: e.to_string() });
+        }
+        let table_name_id = self
+            .execute_query(
+                DaoType::SelectTableNameIdByTableName as i32,
+                encode_query_params(&[table_info.table_name.as_str(), table_info.table_namespace.as_str()]),
+            )
+            .await?
+            .table_name_id
+            .into_iter()
+            .next();
+        if table_name_id
+            .as_ref()
+            .map(|table_name_id| table_name_id.table_id.as_str())
+            != Some(table_id)
+        {
+            violations.push(ConsistencyViolation::TableNameIdMismatch {
+                table_name: table_info.table_name.clone(),
+                table_namespace: table_info.table_namespace.clone(),
+                table_id: table_name_id.map(|table_name_id| table_name_id.table_id),
+            });
+        }
+        let table_path_id = self
+            .execute_query(
+                DaoType::SelectTablePathIdByTablePath as i32,
+                table_info.table_path.clone(),
+            )
+            .await?
+            .table_path_id
+            .into_iter()
+            .next();
+        if table_path_id
+            .as_ref()
+            .map(|table_path_id| table_path_id.table_id.as_str())
+            != Some(table_id)
+        {
+            violations.push(ConsistencyViolation::TablePathIdMismatch {
+                table_path: table_info.table_path.clone(),
+                table_id: table_path_id.map(|table_path_id| table_path_id.table_id),
+            });
+        }
+
+        let mut versions_by_partition = BTreeMap::<String, Vec<PartitionInfo>>::new();
+        for partition_info in self.get_recent_partition_versions(table_id, i64::MAX).await? {
+            versions_by_partition
+                .entry(partition_info.partition_desc.clone())
+                .or_default()
+                .push(partition_info);
+        }
+        let mut partitions_by_path = BTreeMap::<String, Vec<String>>::new();
+        let mut dangling_partitions = vec![];
+        for (partition_desc, mut versions) in versions_by_partition {
+            versions.sort_by_key(|partition_info| partition_info.version);
+            for pair in versions.windows(2) {
+                if pair[1].version != pair[0].version + 1 {
+                    violations.push(ConsistencyViolation::VersionGap {
+                        partition_desc: partition_desc.clone(),
+                        from: pair[0].version,
+                        to: pair[1].version,
+                    });
+                }
+            }
+            let mut referenced = HashSet::new();
+            let snapshot = versions
+                .iter()
+                .flat_map(|partition_info| partition_info.snapshot.iter())
+                .filter(|commit_id| referenced.insert((commit_id.high, commit_id.low)))
+                .cloned()
+                .collect::<Vec<_>>();
+            let data_commits = if snapshot.is_empty() {
+                HashMap::new()
+            } else {
+                self.get_data_commit_info_of_single_partition(&PartitionInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.clone(),
+                    snapshot,
+                    ..Default::default()
+                })
+                .await?
+                .into_iter()
+                .filter_map(|data_commit_info| {
+                    let commit_id = data_commit_info.commit_id.as_ref()?;
+                    Some(((commit_id.high, commit_id.low), data_commit_info))
+                })
+                .collect::<HashMap<_, _>>()
+            };
+            let mut dangling = false;
+            for partition_info in &versions {
+                for commit_id in &partition_info.snapshot {
+                    let (partition_desc, version) = (partition_desc.clone(), partition_info.version);
+                    let uuid = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string();
+                    match data_commits.get(&(commit_id.high, commit_id.low)) {
+                        None => {
+                            dangling = true;
+                            violations.push(ConsistencyViolation::MissingCommit {
+                                partition_desc,
+                                version,
+                                commit_id: uuid,
+                            });
+                        }
+                        Some(data_commit_info) if !data_commit_info.committed => {
+                            violations.push(ConsistencyViolation::UncommittedCommit {
+                                partition_desc,
+                                version,
+                                commit_id: uuid,
+                            });
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            if dangling {
+                dangling_partitions.push(partition_desc.clone());
+            }
+            let Some(latest) = versions.last() else {
+                continue;
+            };
+            for commit_id in &latest.snapshot {
+                let Some(data_commit_info) = data_commits.get(&(commit_id.high, commit_id.low)) else {
+                    continue;
+                };
+                for file_op in &data_commit_info.file_ops {
+                    if file_op.file_op != FileOp::Add as i32 {
+                        continue;
+                    }
+                    let partition_descs = partitions_by_path.entry(file_op.path.clone()).or_default();
+                    if partition_descs.last() != Some(&partition_desc) {
+                        partition_descs.push(partition_desc.clone());
+                    }
+                }
+            }
+        }
+        violations.extend(
+            partitions_by_path
+                .into_iter()
+                .filter(|(_, partition_descs)| partition_descs.len() > 1)
+                .map(|(path, partition_descs)| ConsistencyViolation::OverlappingFile { path, partition_descs }),
+        );
+
+        let mut fixed_versions = 0;
+        if fix {
+            for partition_desc in dangling_partitions {
+                fixed_versions += self
+                    .execute_update(
+                        DaoType::RemoveDanglingSnapshotCommitByTableIdAndPartitionDesc as i32,
+                        encode_query_params(&[table_id, partition_desc.as_str()]),
+                    )
+                    .await? as usize;
+            }
+        }
+        Ok(ConsistencyReport {
+            table_id: table_id.to_string(),
+            violations,
+            fixed_versions,
+        })
+    }
+
+    /// Flat timeline of the partition versions of a table, newest first, with the provenance of their data commits.
+    pub async fn get_table_operation_log(&self, table_id: &str, limit: i64) -> Result<Vec<OperationLogEntry>> {
+        let joined_params = encode_query_params(&[table_id, limit.to_string().as_str()]);
+        let mut provenance_by_version = HashMap::<(String, i32), Vec<CommitProvenance>>::new();
+        if !self.is_table_missing("commit_provenance").await {
+            for provenance in self
+                .execute_query(
+                    DaoType::ListOperationLogProvenanceByTableId as i32,
+                    joined_params.clone(),
+                )
+                .await?
+                .commit_provenance
+            {
+                provenance_by_version
+                    .entry((provenance.partition_desc.clone(), provenance.version))
+                    .or_default()
+                    .push(provenance);
+            }
+        }
+        Ok(self
+            .execute_query(DaoType::ListOperationLogByTableId as i32, joined_params)
+            .await?
+            .partition_info
+            .into_iter()
+            .map(|partition_info| OperationLogEntry {
+                commit_op: partition_info.commit_op,
+                provenance: provenance_by_version
+                    .remove(&(partition_info.partition_desc.clone(), partition_info.version))
+                    .unwrap_or_default(),
+                partition_desc: partition_info.partition_desc,
+                version: partition_info.version,
+                timestamp: partition_info.timestamp,
+            })
+            .collect())
+    }
+
+    pub async fn get_single_data_commit_info(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        commit_id: &str,
+    ) -> Result<Option<DataCommitInfo>> {
+        match self
+            .execute_query(
+                DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId as i32,
+                encode_query_params(&[table_id, partition_desc, commit_id]),
+            )
+            .await
+        {
+            Ok(wrapper) => Ok(if wrapper.data_commit_info.is_empty() {
+                None
+            } else {
+                Some(wrapper.data_commit_info[0].clone())
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check that the data commit belongs to the partition of the table before operating on it, as operations given
+    /// a commit of another table or partition silently find nothing. Fails with NotFound otherwise.
+    pub async fn assert_commit_belongs(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        commit_id: &entity::Uuid,
+    ) -> Result<()> {
+        let commit_id = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string();
+        match self
+            .get_single_data_commit_info(table_id, partition_desc, &commit_id)
+            .await?
+        {
+            Some(_) => Ok(()),
+            None => Err(LakeSoulMetaDataError::NotFound(format!(
+                "data commit {} does not belong to partition '{}' of table '{}'",
+                commit_id, partition_desc, table_id
+            ))),
+        }
+    }
+
+    /// Record the latest version of each partition of the table under `tag`, to be read later by [`Self::read_at_tag`].
+    /// Fails with AlreadyExists if the table has a tag of the same name.
+    pub async fn create_tag(&self, table_id: &str, tag: &str) -> Result<()> {
+        if self.is_table_missing("table_tag").await {
+            return Err(LakeSoulMetaDataError::IncompatibleSchema(
+                "table tags need the table_tag table in the metadata database".to_string(),
+            ));
+        }
+        self.execute_update(
+            DaoType::InsertTableTagByTableIdAndTag as i32,
+            encode_query_params(&[table_id, tag]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// The partition versions recorded under `tag`, ordered by partition_desc.
+    /// Fails with NotFound if there is no such tag, or if some of its versions have been cleaned up since.
+    pub async fn read_at_tag(&self, table_id: &str, tag: &str) -> Result<Vec<PartitionInfo>> {
+        let joined_params = encode_query_params(&[table_id, tag]);
+        let partition_count = self
+            .execute_query_scalar(
+                DaoType::GetTagPartitionCountByTableIdAndTag as i32,
+                joined_params.clone(),
+            )
+            .await?
+            .ok_or_else(|| {
+                LakeSoulMetaDataError::NotFound(format!("Tag '{}' not found for table '{}'", tag, table_id))
+            })?
+            .parse::<usize>()?;
+        let partition_info = self
+            .execute_query(DaoType::ListPartitionByTableIdAndTag as i32, joined_params)
+            .await?
+            .partition_info;
+        if partition_info.len() != partition_count {
+            return Err(LakeSoulMetaDataError::NotFound(format!(
+                "Versions of tag '{}' of table '{}' have been cleaned up",
+                tag, table_id
+            )));
+        }
+        Ok(partition_info)
+    }
+
+    /// Tags of the table, oldest first.
+    pub async fn list_tags(&self, table_id: &str) -> Result<Vec<TagInfo>> {
+        if self.is_table_missing("table_tag").await {
+            return Ok(vec![]);
+        }
+        Ok(self
+            .execute_query(DaoType::ListTableTagByTableId as i32, table_id.to_string())
+            .await?
+            .table_tag
+            .into_iter()
+            .filter(|table_tag| !table_tag.tag.starts_with(READ_SNAPSHOT_TAG_PREFIX))
+            .map(|table_tag| TagInfo {
+                tag: table_tag.tag,
+                timestamp: table_tag.timestamp,
+            })
+            .collect())
+    }
+
+    /// Remove `tag` from the table, the tagged partition versions are left as they are.
+    /// Fails with NotFound if the table has no such tag.
+    pub async fn delete_tag(&self, table_id: &str, tag: &str) -> Result<()> {
+        let deleted = self
+            .execute_update(
+                DaoType::DeleteTableTagByTableIdAndTag as i32,
+                encode_query_params(&[table_id, tag]),
+            )
+            .await?;
+        if deleted == 0 {
+            return Err(LakeSoulMetaDataError::NotFound(format!(
+                "Tag '{}' not found for table '{}'",
+                tag, table_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// File ops of one data commit with decoded flags and normalized paths.
+    /// Paths without a scheme are local files, relative ones are resolved against the table path.
+    pub async fn get_resolved_file_ops(
+        &self,
+        table_id: &str,
+        partition_desc: &str,
+        commit_id: &str,
+    ) -> Result<Vec<ResolvedFileOp>> {
+        let data_commit_info = self
+            .get_single_data_commit_info(table_id, partition_desc, commit_id)
+            .await?
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("data commit '{}' not found", commit_id)))?;
+        let table_path = if data_commit_info
+            .file_ops
+            .iter()
+            .any(|file_op| !is_absolute_path(&file_op.path))
+        {
+            self.get_table_info_by_table_id(table_id).await?.table_path
+        } else {
+            String::new()
+        };
+        data_commit_info
+            .file_ops
+            .into_iter()
+            .map(|file_op| {
+                Ok(ResolvedFileOp {
+                    file_op: FileOp::try_from(file_op.file_op)
+                        .map_err(|_| LakeSoulMetaDataError::Internal(format!("unknown file_op {}", file_op.file_op)))?,
+                    path: normalize_file_path(&table_path, &file_op.path),
+                    size: file_op.size,
+                    file_exist_cols: file_op.file_exist_cols,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_partition_info_by_table_id_and_partition_list(
+        &self,
+        table_id: &str,
+        partition_desc_list: &[String],
+    ) -> Result<Vec<PartitionInfo>> {
+        match self
+            .execute_query(
+                DaoType::ListPartitionDescByTableIdAndParList as i32,
+                encode_query_params(&[table_id, &encode_partition_desc_list(partition_desc_list)]),
+            )
+            .await
+        {
+            Ok(wrapper) => Ok(wrapper.partition_info),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Consistent point-in-time view of the catalog, see [`MetaDataClient::begin_read_snapshot`].
+///
+/// Its reads mirror those of [`MetaDataClient`] without retries, since a failed statement aborts the transaction.
+/// The transaction is released when the snapshot is dropped, closing its connection.
+pub struct ReadSnapshot {
+    client: Client,
+    prepared: Mutex<ConnectionStatements>,
+    default_namespace: String,
+    max_result_bytes: Option<usize>,
+    path_mode: PathMode,
+    compatibility_warnings: CompatibilityWarnings,
+}
+
+impl Debug for ReadSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadSnapshot")
+            .field("client", &"{pg_client}")
+            .field("default_namespace", &self.default_namespace)
+            .finish()
+    }
+}
+
+/// Exclusive lock of a partition taken by [`MetaDataClient::lock_partition_for_compaction`]. Dropping it closes
+/// its connection, which releases the lock once the server notices, see [`Self::release`] to release it at once.
+pub struct CompactionLock {
+    client: Client,
+    table_id: String,
+    partition_desc: String,
+}
+
+impl Debug for CompactionLock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompactionLock")
+            .field("client", &"{pg_client}")
+            .field("table_id", &self.table_id)
+            .field("partition_desc", &self.partition_desc)
+            .finish()
+    }
+}
+
+impl CompactionLock {
+    pub fn table_id(&self) -> &str {
+        &self.table_id
+    }
+
+    /// The partition desc in canonical form.
+    pub fn partition_desc(&self) -> &str {
+        &self.partition_desc
+    }
+
+    pub async fn release(self) -> Result<()> {
+        self.client
+            .execute(
+                "select pg_advisory_unlock(hashtext($1), hashtext($2))",
+                &[&self.table_id, &self.partition_desc],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+impl ReadSnapshot {
+    async fn execute_query(&self, query_type: DaoType, joined_string: String) -> Result<JniWrapper> {
+        let mut statements = self.prepared.lock().await;
+        let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+        let encoded = self
+            .compatibility_warnings
+            .record(execute_query_with_context(
+                &self.client,
+                prepared,
+                context,
+                query_type as i32,
+                joined_string,
+                self.max_result_bytes,
+            ))
+            .await?;
+        Ok(JniWrapper::decode(prost::bytes::Bytes::from(encoded))?)
+    }
+
+    fn namespace_or_default<'a>(&'a self, namespace: &'a str) -> &'a str {
+        if namespace.is_empty() {
+            &self.default_namespace
+        } else {
+            namespace
+        }
+    }
+
+    pub async fn get_all_namespace(&self) -> Result<Vec<Namespace>> {
+        Ok(self
+            .execute_query(DaoType::ListNamespaces, String::new())
+            .await?
+            .namespace)
+    }
+
+    pub async fn get_all_table_name_id_by_namespace(&self, namespace: &str) -> Result<Vec<TableNameId>> {
+        Ok(self
+            .execute_query(
+                DaoType::ListTableNameByNamespace,
+                self.namespace_or_default(namespace).to_string(),
+            )
+            .await?
+            .table_name_id)
+    }
+
+    pub async fn get_table_info_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableInfo> {
+        self.execute_query(
+            DaoType::SelectTableInfoByTableNameAndNameSpace,
+            encode_query_params(&[table_name, self.namespace_or_default(namespace)]),
+        )
+        .await?
+        .table_info
+        .into_iter()
+        .next()
+        .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_name)))
+    }
+
+    pub async fn get_table_info_by_table_path(&self, table_path: &str) -> Result<TableInfo> {
+        self.execute_query(DaoType::SelectTableInfoByTablePath, table_path.to_string())
+            .await?
+            .table_info
+            .into_iter()
+            .next()
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_path)))
+    }
+
+    pub async fn get_table_info_by_table_id(&self, table_id: &str) -> Result<TableInfo> {
+        self.execute_query(DaoType::SelectTableInfoByTableId, table_id.to_string())
+            .await?
+            .table_info
+            .into_iter()
+            .next()
+            .ok_or_else(|| LakeSoulMetaDataError::NotFound(format!("Table '{}' not found", table_id)))
+    }
+
+    pub async fn get_all_partition_info(&self, table_id: &str) -> Result<Vec<PartitionInfo>> {
+        Ok(self
+            .execute_query(DaoType::ListPartitionByTableId, table_id.to_string())
+            .await?
+            .partition_info)
+    }
+
+    pub async fn get_partition_info_by_table_id_and_partition_list(
+        &self,
+        table_id: &str,
+        partition_desc_list: &[String],
+    ) -> Result<Vec<PartitionInfo>> {
+        Ok(self
+            .execute_query(
+                DaoType::ListPartitionDescByTableIdAndParList,
+                encode_query_params(&[table_id, &encode_partition_desc_list(partition_desc_list)]),
+            )
+            .await?
+            .partition_info)
+    }
+
+    /// Data commits of the snapshot of a partition version, the paths stored relative resolved as by
+    /// [`MetaDataClient::get_data_files_of_single_partition`].
+    pub async fn get_data_commit_info_of_single_partition(
+        &self,
+        partition_info: &PartitionInfo,
+    ) -> Result<Vec<DataCommitInfo>> {
+        let mut data_commit_info_list = self
+            .execute_query(
+                DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList,
+                joined_snapshot_query_string(partition_info),
+            )
+            .await?
+            .data_commit_info;
+        resolve_stored_file_paths(self.path_mode, &mut data_commit_info_list, |table_id| async move {
+            Ok(self.get_table_info_by_table_id(&table_id).await?.table_path)
+        })
+        .await?;
+        Ok(data_commit_info_list)
+    }
+
+    pub async fn get_data_files_of_single_partition(&self, partition_info: &PartitionInfo) -> Result<Vec<String>> {
+        Ok(self
+            .get_data_commit_info_of_single_partition(partition_info)
+            .await?
+            .into_iter()
+            .flat_map(|data_commit_info| data_commit_info.file_ops.into_iter().map(|file_op| file_op.path))
+            .collect())
+    }
+}
+
+/// Partition versions of a table captured once, see [`MetaDataClient::open_read_snapshot`].
+///
+/// The snapshot is plain data besides the client it reads files with: it can be encoded, shipped to another
+/// process and decoded there by [`MetaDataClient::decode_read_snapshot`].
+#[derive(Debug, Clone)]
+pub struct TableReadSnapshot<'a> {
+    client: &'a MetaDataClient,
+    snapshot: entity::TableReadSnapshot,
+}
+
+impl TableReadSnapshot<'_> {
+    pub fn table_id(&self) -> &str {
+        &self.snapshot.table_id
+    }
+
+    /// The captured version of each partition.
+    pub fn partitions(&self) -> &[PartitionInfo] {
+        &self.snapshot.partition_info
+    }
+
+    pub fn partition(&self, partition_desc: &str) -> Option<&PartitionInfo> {
+        self.snapshot
+            .partition_info
+            .iter()
+            .find(|partition_info| partition_info.partition_desc == partition_desc)
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        !self.snapshot.pin_tag.is_empty()
+    }
+
+    /// Files of the partition at its captured version, none if the partition did not exist then.
+    pub async fn data_files(&self, partition_desc: &str) -> Result<Vec<String>> {
+        match self.partition(partition_desc) {
+            Some(partition_info) => self.client.get_data_files_of_single_partition(partition_info).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn encode_to_vec(&self) -> Vec<u8> {
+        self.snapshot.encode_to_vec()
+    }
+
+    /// Unpin the captured versions, for the last user of a pinned snapshot once the query is done.
+    pub async fn release(self) -> Result<()> {
+        if self.is_pinned() {
+            self.client.delete_tag(self.table_id(), &self.snapshot.pin_tag).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetch again the entries loaded from the table cache file, which are served as they are meanwhile.
+async fn refresh_table_cache(
+    table_cache: Arc<TableCache>,
+    client: Arc<Mutex<Client>>,
+    prepared: Arc<Mutex<ConnectionStatements>>,
+) {
+    let query = |query_type: DaoType, table_id: String| {
+        let client = client.clone();
+        let prepared = prepared.clone();
+        async move {
+            let mut statements = prepared.lock().await;
+            let ConnectionStatements { prepared, context, .. } = statements.deref_mut();
+            let encoded = execute_query_with_context(
+                client.lock().await.deref(),
+                prepared,
+                context,
+                query_type as i32,
+                table_id,
+                None,
+            )
+            .await?;
+            Result::<JniWrapper>::Ok(JniWrapper::decode(prost::bytes::Bytes::from(encoded))?)
+        }
+    };
+    for table_id in table_cache.table_ids() {
+        let generation = table_cache.generation();
+        let fetched = async {
+            let table_info = query(DaoType::SelectTableInfoByTableId, table_id.clone()).await?;
+            let table_name_id = query(DaoType::SelectTableNameIdByTableId, table_id.clone()).await?;
+            Result::<(Vec<TableInfo>, Vec<TableNameId>)>::Ok((table_info.table_info, table_name_id.table_name_id))
+        }
+        .await;
+        match fetched {
+            Ok((table_info, table_name_id)) => {
+                table_cache.refresh(generation, &table_id, table_info.into_iter().next());
+                table_cache.refresh(generation, &table_id, table_name_id.into_iter().next());
+            }
+            Err(e) => {
+                warn!("failed to refresh table cache: {}", e);
+                break;
+            }
+        }
+    }
+    table_cache.save();
+}
+
+pub fn table_path_id_from_table_info(table_info: &TableInfo) -> TablePathId {
+    TablePathId {
+        table_path: table_info.table_path.clone(),
+        table_id: table_info.table_id.clone(),
+        table_namespace: table_info.table_namespace.clone(),
+        domain: table_info.domain.clone(),
+    }
+}
+
+/// Joined query params of table_id, partition_desc and the concatenated hex commit ids of the partition snapshot.
+fn joined_snapshot_query_string(partition_info: &PartitionInfo) -> String {
+    let joined_commit_id = partition_info
+        .snapshot
+        .iter()
+        .map(|commit_id| format!("{:0>16x}{:0>16x}", commit_id.high, commit_id.low))
+        .collect::<Vec<String>>()
+        .join("");
+    encode_query_params(&[
+        partition_info.table_id.as_str(),
+        partition_info.partition_desc.as_str(),
+        joined_commit_id.as_str(),
+    ])
+}
+
+/// Whether the table properties carry `changeTrackingEnabled = "true"`.
+/// The table of an import the record of `table_id` belongs to, None if the table is skipped.
+/// Fails with InvalidInput for a record out of the records of its table.
+fn imported_table<'a>(current: &'a Option<(TableInfo, bool)>, table_id: &str) -> Result<Option<&'a TableInfo>> {
+    match current {
+        Some((table_info, imported)) if table_info.table_id == table_id => Ok(imported.then_some(table_info)),
+        _ => Err(LakeSoulMetaDataError::InvalidInput(format!(
+            "record of table '{}' out of the records of its table",
+            table_id
+        ))),
+    }
+}
+
+pub fn change_tracking_enabled(table_info: &TableInfo) -> bool {
+    serde_json::from_str::<serde_json::Value>(&table_info.properties)
+        .map(|properties| properties[CHANGE_TRACKING_ENABLED] == "true")
+        .unwrap_or(false)
+}
+
+/// Whether the table properties carry `partitionStatsEnabled = "true"`.
+pub fn partition_stats_enabled(table_info: &TableInfo) -> bool {
+    serde_json::from_str::<serde_json::Value>(&table_info.properties)
+        .map(|properties| properties[PARTITION_STATS_ENABLED] == "true")
+        .unwrap_or(false)
+}
+
+/// Merge the namespace properties under [`NAMESPACE_TABLE_DEFAULT_PREFIX`], without the prefix, into the properties
+/// of a new table which win over them. The table properties are kept as is if the namespace has no defaults.
+fn merge_namespace_table_defaults(
+    namespace_properties: &str,
+    table_properties: &str,
+    compatibility_warnings: &CompatibilityWarnings,
+) -> Result<String> {
+    let defaults = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(namespace_properties)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(NAMESPACE_TABLE_DEFAULT_PREFIX)
+                .map(|key| (key.to_string(), value))
+        })
+        .collect::<Vec<_>>();
+    if defaults.is_empty() {
+        return Ok(table_properties.to_string());
+    }
+    let mut properties = match table_properties.trim() {
+        "" => serde_json::Map::new(),
+        table_properties => serde_json::from_str(table_properties).map_err(|e| {
+            LakeSoulMetaDataError::InvalidInput(format!("table properties are not a json object: {}", e))
+        })?,
+    };
+    for (key, value) in defaults {
+        properties.entry(key).or_insert(value);
+    }
+    compatibility_warnings.pass_through_properties(&properties);
+    Ok(serde_json::to_string(&properties)?)
+}
+
+pub fn table_name_id_from_table_info(table_info: &TableInfo) -> TableNameId {
+    TableNameId {
+        table_name: table_info.table_name.clone(),
+        table_id: table_info.table_id.clone(),
+        table_namespace: table_info.table_namespace.clone(),
+        domain: table_info.domain.clone(),
+    }
+}
+
+/// How values of a range partition column compare to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PartitionValueOrdering {
+    Numeric,
+    Temporal,
+    Lexicographic,
+}
+
+impl PartitionValueOrdering {
+    /// Resolve the ordering of column `key` from either a spark-formatted or an arrow-java-formatted schema.
+    fn from_table_schema(table_schema: &str, key: &str) -> Self {
+        let schema: serde_json::Value = match serde_json::from_str(table_schema) {
+            Ok(schema) => schema,
+            Err(_) => return Self::Lexicographic,
+        };
+        let field = schema["fields"]
+            .as_array()
+            .and_then(|fields| fields.iter().find(|field| field["name"] == key));
+        let type_name = match field.map(|field| &field["type"]) {
+            Some(serde_json::Value::String(spark_type)) => spark_type.to_lowercase(),
+            Some(arrow_type) => arrow_type["name"].as_str().unwrap_or_default().to_lowercase(),
+            None => return Self::Lexicographic,
+        };
+        match type_name.as_str() {
+            "byte" | "short" | "integer" | "long" | "float" | "double" | "int" | "floatingpoint" => Self::Numeric,
+            "date" | "timestamp" => Self::Temporal,
+            t if t.starts_with("decimal") => Self::Numeric,
+            _ => Self::Lexicographic,
+        }
+    }
+
+    fn compare(&self, left: &str, right: &str) -> Ordering {
+        match self {
+            Self::Numeric => match (left.parse::<i128>(), right.parse::<i128>()) {
+                (Ok(left), Ok(right)) => left.cmp(&right),
+                _ => match (left.parse::<f64>(), right.parse::<f64>()) {
+                    (Ok(left), Ok(right)) => left.total_cmp(&right),
+                    _ => left.cmp(right),
+                },
+            },
+            // compare digit groups so that e.g. 2023-1-2 < 2023-01-10
+            Self::Temporal => {
+                let components = |value: &str| {
+                    value
+                        .split(|c: char| !c.is_ascii_digit())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.parse::<u64>().unwrap_or(u64::MAX))
+                        .collect::<Vec<u64>>()
+                };
+                components(left).cmp(&components(right))
+            }
+            Self::Lexicographic => left.cmp(right),
+        }
+    }
+}
+
+/// Value of range partition column `key` in a partition_desc like `date=2023-01-01,id=1`, None for null values.
+fn partition_value<'a>(partition_desc: &'a str, key: &str) -> Option<&'a str> {
+    partition_desc
+        .split(LAKESOUL_RANGE_PARTITION_SPLITTER)
+        .filter_map(|kv| kv.split_once(LAKESOUL_PARTITION_DESC_KV_DELIM))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+        .filter(|v| *v != LAKESOUL_NULL_STRING)
+}
+
+/// Scheme of a qualified path like `s3://bucket/key` or `file:/tmp/x`, single letters are taken for windows drives.
+fn path_scheme(path: &str) -> Option<(&str, &str)> {
+    path.split_once(':').filter(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+fn is_absolute_path(path: &str) -> bool {
+    path.starts_with('/') || path_scheme(path).is_some()
+}
+
+/// File system of an absolute path by its scheme, `file` for paths without one, the s3 connectors of hadoop
+/// counting as `s3`. None for paths relative to the table path.
+fn file_system_of(path: &str) -> Option<String> {
+    match path_scheme(path) {
+        Some((scheme, _)) => match scheme.to_ascii_lowercase().as_str() {
+            "s3a" | "s3n" => Some("s3".to_string()),
+            scheme => Some(scheme.to_string()),
+        },
+        None if path.starts_with('/') => Some("file".to_string()),
+        None => None,
+    }
+}
+
+/// Fails with InvalidInput if `path` is empty or on another file system than `table_path`.
+fn check_file_path(table_path: &str, path: &str) -> Result<()> {
+    if path.trim().is_empty() {
+        return Err(LakeSoulMetaDataError::InvalidInput(
+            "file op with an empty path".to_string(),
+        ));
+    }
+    match (file_system_of(table_path), file_system_of(path)) {
+        (Some(table_file_system), Some(file_system)) if table_file_system != file_system => {
+            Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "file '{}' is not on the file system of the table path '{}'",
+                path, table_path
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `path` of a file op of the table at `table_path` as stored in `path_mode`.
+fn store_file_path(path_mode: PathMode, table_path: &str, path: &str) -> String {
+    match path_mode {
+        PathMode::KeepAbsolute => path.to_string(),
+        PathMode::MakeAbsolute => normalize_file_path(table_path, path),
+        PathMode::MakeRelative => {
+            let path = normalize_file_path(table_path, path);
+            let prefix = format!("{}/", normalize_file_path("", table_path).trim_end_matches('/'));
+            match path.strip_prefix(&prefix) {
+                Some(relative) if !relative.is_empty() => relative.to_string(),
+                _ => path,
+            }
+        }
+    }
+}
+
+/// The deletes of [`MetaDataClient::delete_table_by_table_info_cascade`] in order, skipping the optional tables
+/// missing. File stats are found through the data commits of the table, so they go before them.
+fn table_cascade_deletes(
+    column_fallbacks: &ColumnFallbacks,
+    table_id: &str,
+    table_path: &str,
+) -> Vec<(DaoType, String)> {
+    let table_id_deletes = [
+        (None, DaoType::DeleteTableNameIdByTableId),
+        (None, DaoType::DeleteTablePathIdByTableId),
+        (None, DaoType::DeletePartitionInfoByTableId),
+        (Some("file_stats"), DaoType::DeleteFileStatsByTableId),
+        (None, DaoType::DeleteDataCommitInfoByTableId),
+        (Some("partition_stats"), DaoType::DeletePartitionStatsByTableId),
+        (Some("commit_provenance"), DaoType::DeleteCommitProvenanceByTableId),
+        (
+            Some("commit_schema_version"),
+            DaoType::DeleteCommitSchemaVersionByTableId,
+        ),
+        (Some("table_tag"), DaoType::DeleteTableTagByTableId),
+        (Some("table_schema_history"), DaoType::DeleteTableSchemaHistoryByTableId),
+    ];
+    let mut deletes = table_id_deletes
+        .into_iter()
+        .filter(|(table, _)| !table.is_some_and(|table| column_fallbacks.is_table_missing(table)))
+        .map(|(_, delete_type)| (delete_type, encode_query_params(&[table_id])))
+        .collect::<Vec<_>>();
+    deletes.push((
+        DaoType::DeleteTableInfoByIdAndPath,
+        encode_query_params(&[table_id, table_path]),
+    ));
+    deletes
+}
+
+/// `path` of a file op of the table at `table_path` as read, qualified if stored relative to the table path.
+pub(crate) fn resolve_file_path(table_path: &str, path: &str) -> String {
+    match is_absolute_path(path) {
+        true => path.to_string(),
+        false => normalize_file_path(table_path, path),
+    }
+}
+
+/// Resolve against `table_path` the paths of the file ops of a data commit of the table stored relative to it.
+fn resolve_file_op_paths(table_path: &str, data_commit_info: &mut DataCommitInfo) {
+    for file_op in &mut data_commit_info.file_ops {
+        file_op.path = resolve_file_path(table_path, &file_op.path);
+    }
+}
+
+/// Resolve the paths of the file ops of data commits stored relative to the path of their table unless `path_mode`
+/// keeps paths as stored, looking up the path of each table with such paths once by `table_path_of`.
+async fn resolve_stored_file_paths<F, Fut>(
+    path_mode: PathMode,
+    data_commit_info_list: &mut [DataCommitInfo],
+    table_path_of: F,
+) -> Result<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    if path_mode == PathMode::KeepAbsolute {
+        return Ok(());
+    }
+    let mut table_paths = HashMap::<String, String>::new();
+    for data_commit_info in data_commit_info_list {
+        if data_commit_info
+            .file_ops
+            .iter()
+            .all(|file_op| is_absolute_path(&file_op.path))
+        {
+            continue;
+        }
+        if !table_paths.contains_key(&data_commit_info.table_id) {
+            let table_path = table_path_of(data_commit_info.table_id.clone()).await?;
+            table_paths.insert(data_commit_info.table_id.clone(), table_path);
+        }
+        resolve_file_op_paths(&table_paths[&data_commit_info.table_id], data_commit_info);
+    }
+    Ok(())
+}
+
+/// Qualify `path` as `scheme://authority/path`, taking paths without a scheme as local files relative to `table_path`.
+fn normalize_file_path(table_path: &str, path: &str) -> String {
+    let (scheme, rest) = match path_scheme(path) {
+        Some(qualified) => qualified,
+        None if path.starts_with('/') => ("file", path),
+        None => return normalize_file_path("", &format!("{}/{}", table_path.trim_end_matches('/'), path)),
+    };
+    let (authority, path) = match rest.strip_prefix("//") {
+        Some(rest) => rest.split_once('/').unwrap_or((rest, "")),
+        None => ("", rest),
+    };
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("{}://{}/{}", scheme, authority, segments.join("/"))
+}
+
+/// Nulls sort first in ascending order.
+fn sort_partitions_by_key(
+    partition_list: &mut [PartitionInfo],
+    key: &str,
+    ordering: PartitionValueOrdering,
+    ascending: bool,
+) {
+    partition_list.sort_by(|left, right| {
+        let order = match (
+            partition_value(&left.partition_desc, key),
+            partition_value(&right.partition_desc, key),
+        ) {
+            (Some(left), Some(right)) => ordering.compare(left, right),
+            (left, right) => left.is_some().cmp(&right.is_some()),
+        };
+        if ascending {
+            order
+        } else {
+            order.reverse()
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        get_partition_history, get_table_stats, FailOnConflict, RebaseAppends, TraceContext, UNKNOWN_COMMIT_OP_CODE,
+    };
+
+    async fn create_test_table(client: &MetaDataClient, prefix: &str) -> TableInfo {
+        let table_name = format!("{}_{}", prefix, uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: ";".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        table_info
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_change_tracking_flag() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "change_tracking").await;
+        let table_id = table_info.table_id.as_str();
+        assert!(!client.is_change_tracking_enabled(table_id).await.unwrap());
+
+        client.set_change_tracking_enabled(table_id, true).await.unwrap();
+        assert!(client.is_change_tracking_enabled(table_id).await.unwrap());
+
+        client.set_change_tracking_enabled(table_id, false).await.unwrap();
+        assert!(!client.is_change_tracking_enabled(table_id).await.unwrap());
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_partition_stats() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "partition_stats").await;
+        let table_id = table_info.table_id.as_str();
+        let commit = |sizes: Vec<i64>| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: sizes
+                    .into_iter()
+                    .map(|size| entity::DataFileOp {
+                        path: format!("file:///tmp/lakesoul/{}.parquet", uuid::Uuid::new_v4()),
+                        file_op: entity::FileOp::Add as i32,
+                        size,
+                        file_exist_cols: String::new(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+
+        // disabled by default
+        client.commit_data_commit_info(commit(vec![10, 20])).await.unwrap();
+        assert_eq!(client.get_partition_stats_fast(table_id, "-5").await.unwrap(), None);
+
+        client.set_partition_stats_enabled(table_id, true).await.unwrap();
+        let added = commit(vec![30]);
+        client.commit_data_commit_info(added.clone()).await.unwrap();
+        let stats = client.get_partition_stats_fast(table_id, "-5").await.unwrap().unwrap();
+        assert_eq!((stats.file_count, stats.total_bytes), (3, 60));
+
+        // files deleted by a later commit of the snapshot are not counted
+        let mut deleted = commit(vec![30]);
+        deleted.file_ops[0].path = added.file_ops[0].path.clone();
+        deleted.file_ops[0].file_op = entity::FileOp::Del as i32;
+        client.commit_data_commit_info(deleted).await.unwrap();
+        let stats = client.get_partition_stats_fast(table_id, "-5").await.unwrap().unwrap();
+        assert_eq!((stats.file_count, stats.total_bytes), (2, 30));
+        let table_stats = get_table_stats(client.client.lock().await.deref(), table_id)
+            .await
+            .unwrap();
+        assert_eq!((table_stats.file_count, table_stats.total_bytes), (2, 30));
+
+        client.delete_partition_stats_by_table_id(table_id).await.unwrap();
+        assert_eq!(client.rebuild_partition_stats(table_id).await.unwrap(), 1);
+        let rebuilt = client.get_partition_stats_fast(table_id, "-5").await.unwrap().unwrap();
+        assert_eq!((rebuilt.file_count, rebuilt.total_bytes), (2, 30));
+        // both stamped with the timestamp of the partition version
+        assert_eq!(rebuilt.last_commit_ts, stats.last_commit_ts);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        assert_eq!(client.get_partition_stats_fast(table_id, "-5").await.unwrap(), None);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_with_timestamp() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "commit_timestamp").await;
+        let meta_info = |partition_desc: &str| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            MetaInfo {
+                table_info: Some(table_info.clone()),
+                list_partition: vec![PartitionInfo {
+                    table_id: table_info.table_id.clone(),
+                    partition_desc: partition_desc.to_string(),
+                    snapshot: vec![entity::Uuid { high, low }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        };
+        let db_time = client.get_db_time().await.unwrap();
+        let past = db_time - 3_600_000;
+        client
+            .commit_data_with_timestamp(meta_info("-5"), CommitOp::AppendCommit, Some(past))
+            .await
+            .unwrap();
+        // the latest versions are listed without their timestamps
+        let partitions = client
+            .get_table_snapshot_at_timestamp(&table_info.table_id, past)
+            .await
+            .unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].timestamp, past);
+
+        // not before the latest version of the partition, and not committed if rejected
+        let committed = client
+            .commit_data_with_timestamp(meta_info("-5"), CommitOp::AppendCommit, Some(past - 1))
+            .await;
+        assert!(
+            matches!(&committed, Err(LakeSoulMetaDataError::InvalidInput(msg)) if msg.contains("latest version")),
+            "{:?}",
+            committed
+        );
+        let partition_info = client.get_all_partition_info(&table_info.table_id).await.unwrap();
+        assert_eq!(partition_info[0].version, 0);
+        // while other partitions may still be backfilled earlier
+        client
+            .commit_data_with_timestamp(meta_info("-3"), CommitOp::AppendCommit, Some(past - 1))
+            .await
+            .unwrap();
+
+        let future = db_time + COMMIT_TIMESTAMP_MAX_SKEW_MS + 3_600_000;
+        assert!(matches!(
+            client
+                .commit_data_with_timestamp(meta_info("-4"), CommitOp::AppendCommit, Some(future))
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_snapshot_at_timestamp_ties_by_version() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "snapshot_ties").await;
+        let table_id = table_info.table_id.as_str();
+        let timestamp = client.get_db_time().await.unwrap() - 1_000;
+        for _ in 0..2 {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            let meta_info = MetaInfo {
+                table_info: Some(table_info.clone()),
+                list_partition: vec![PartitionInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    snapshot: vec![entity::Uuid { high, low }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            client
+                .commit_data_with_timestamp(meta_info, CommitOp::AppendCommit, Some(timestamp))
+                .await
+                .unwrap();
+        }
+
+        // both versions carry the same timestamp, the higher version wins
+        let snapshot = client
+            .get_table_snapshot_at_timestamp(table_id, timestamp)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!((snapshot[0].version, snapshot[0].timestamp), (1, timestamp));
+        assert_eq!(snapshot[0].snapshot.len(), 2);
+        assert!(client
+            .get_table_snapshot_at_timestamp(table_id, timestamp - 1)
+            .await
+            .unwrap()
+            .is_empty());
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_streamed_listings_match() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "streamed_listings").await;
+        let table_id = table_info.table_id.as_str();
+        for (partition_desc, files) in [("date=1", 2), ("date=1", 1), ("date=2", 3)] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: (0..files)
+                        .map(|_| entity::DataFileOp {
+                            path: format!("{}/{}.parquet", table_info.table_path, uuid::Uuid::new_v4()),
+                            file_op: FileOp::Add as i32,
+                            size: 10,
+                            file_exist_cols: String::new(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let by_partition_desc = |mut partitions: Vec<PartitionInfo>| {
+            partitions.sort_by(|a, b| a.partition_desc.cmp(&b.partition_desc));
+            partitions
+        };
+        let partitions = by_partition_desc(client.get_all_partition_info(table_id).await.unwrap());
+        let streamed = client
+            .stream_all_partition_info(table_id)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(by_partition_desc(streamed), partitions);
+
+        for partition_info in &partitions {
+            let data_commit_info = client
+                .get_data_commit_info_of_single_partition(partition_info)
+                .await
+                .unwrap();
+            let streamed = client
+                .stream_data_commit_info_of_single_partition(partition_info)
+                .await
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            assert_eq!(data_commit_info.len(), partition_info.snapshot.len());
+            assert_eq!(streamed, data_commit_info);
+        }
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_file_stats() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "file_stats").await;
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        let commit_id = entity::Uuid { high, low };
+        let column_stats = HashMap::from([(
+            "id".to_string(),
+            entity::ColumnStats {
+                min: "1".to_string(),
+                max: "100".to_string(),
+                null_count: 2,
+            },
+        )]);
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(commit_id.clone()),
+                file_ops: ["with_stats", "without_stats"]
+                    .into_iter()
+                    .map(|name| entity::DataFileOp {
+                        path: format!("file:///tmp/lakesoul/{}.parquet", name),
+                        file_op: entity::FileOp::Add as i32,
+                        column_stats: if name == "with_stats" {
+                            column_stats.clone()
+                        } else {
+                            HashMap::new()
+                        },
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let file_stats = client
+            .get_file_stats_for_snapshot(&table_info.table_id, "-5", 0)
+            .await
+            .unwrap();
+        assert_eq!(file_stats.len(), 1);
+        assert_eq!(file_stats[0].path, "file:///tmp/lakesoul/with_stats.parquet");
+        assert_eq!(file_stats[0].column_stats, column_stats);
+
+        let partition_info = client
+            .get_all_partition_info(&table_info.table_id)
+            .await
+            .unwrap()
+            .remove(0);
+        let data_commit_info = client
+            .get_data_commit_info_with_stats_of_single_partition(&partition_info)
+            .await
+            .unwrap();
+        let file_ops = &data_commit_info[0].file_ops;
+        assert_eq!(file_ops[0].column_stats, column_stats);
+        assert!(file_ops[1].column_stats.is_empty());
+
+        client
+            .register_file_stats(
+                &commit_id,
+                vec![FileStats {
+                    path: file_ops[1].path.clone(),
+                    column_stats: column_stats.clone(),
+                    ..Default::default()
+                }],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .get_file_stats_for_snapshot(&table_info.table_id, "-5", 0)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // a failing insert rolls back the whole batch, the stats of the first file are not replaced
+        let err = client
+            .register_file_stats(
+                &commit_id,
+                vec![
+                    FileStats {
+                        path: file_ops[0].path.clone(),
+                        ..Default::default()
+                    },
+                    FileStats {
+                        path: "file:///tmp/lakesoul/\0.parquet".to_string(),
+                        column_stats: column_stats.clone(),
+                        ..Default::default()
+                    },
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LakeSoulMetaDataError::PostgresError(_)), "{:?}", err);
+        let file_stats = client
+            .get_file_stats_for_snapshot(&table_info.table_id, "-5", 0)
+            .await
+            .unwrap();
+        assert!(file_stats
+            .iter()
+            .all(|file_stats| file_stats.column_stats == column_stats));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_namespaces_with_table_counts() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let namespaces = [1, 2].map(|_| format!("table_counts_ns_{}", uuid::Uuid::new_v4().simple()));
+        for namespace in &namespaces {
+            client
+                .create_namespace(Namespace {
+                    namespace: namespace.clone(),
+                    properties: "{}".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let mut tables = vec![];
+        for _ in 0..2 {
+            let table_info = create_test_table(&client, "table_counts").await;
+            client
+                .move_table_to_namespace(&table_info.table_id, &namespaces[0])
+                .await
+                .unwrap();
+            tables.push(client.get_table_info_by_table_id(&table_info.table_id).await.unwrap());
+        }
+
+        let counts = client
+            .get_namespaces_with_table_counts()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(namespace, count)| (namespace.namespace, count))
+            .collect::<HashMap<String, i64>>();
+        assert_eq!(counts[&namespaces[0]], 2);
+        assert_eq!(counts[&namespaces[1]], 0);
+
+        for table_info in &tables {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+        for namespace in &namespaces {
+            client.delete_namespace_by_namespace(namespace).await.unwrap();
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_provenance() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_default_provenance(CommitProvenance {
+                engine: "rust".to_string(),
+                user: "tester".to_string(),
+                ..Default::default()
+            })
+            .build()
+            .await
+            .unwrap();
+        let table_info = create_test_table(&client, "commit_provenance").await;
+        for job_id in ["job-1", ""] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info_with_provenance(
+                    DataCommitInfo {
+                        table_id: table_info.table_id.clone(),
+                        partition_desc: "-5".to_string(),
+                        commit_id: Some(entity::Uuid { high, low }),
+                        file_ops: vec![entity::DataFileOp {
+                            path: format!("file:///tmp/lakesoul/{}.parquet", uuid::Uuid::new_v4()),
+                            file_op: entity::FileOp::Add as i32,
+                            ..Default::default()
+                        }],
+                        commit_op: CommitOp::AppendCommit as i32,
+                        domain: "public".to_string(),
+                        ..Default::default()
+                    },
+                    CommitProvenance {
+                        job_id: job_id.to_string(),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        // each version only carries the commit it added to the snapshot
+        let log = client.get_table_operation_log(&table_info.table_id, 10).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].provenance.len(), 1);
+        assert_eq!(
+            (
+                log[0].provenance[0].engine.as_str(),
+                log[0].provenance[0].user.as_str(),
+                log[0].provenance[0].job_id.as_str()
+            ),
+            ("rust", "tester", "")
+        );
+        assert_eq!(log[1].provenance.len(), 1);
+        assert_eq!(log[1].provenance[0].job_id, "job-1");
+        assert_eq!(log[1].provenance[0].version, 0);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_read_snapshot() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "read_snapshot").await;
+        async fn commit(client: &MetaDataClient, table_info: &TableInfo) {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data(
+                    MetaInfo {
+                        table_info: Some(table_info.clone()),
+                        list_partition: vec![PartitionInfo {
+                            table_id: table_info.table_id.clone(),
+                            partition_desc: "-5".to_string(),
+                            snapshot: vec![entity::Uuid { high, low }],
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                    CommitOp::AppendCommit,
+                )
+                .await
+                .unwrap();
+        }
+        commit(&client, &table_info).await;
+
+        let snapshot = client.begin_read_snapshot().await.unwrap();
+        let before = snapshot.get_all_partition_info(&table_info.table_id).await.unwrap();
+        commit(&client, &table_info).await;
+        let after = snapshot.get_all_partition_info(&table_info.table_id).await.unwrap();
+        assert_eq!(before, after);
+        assert_eq!(after[0].version, 0);
+        assert_eq!(
+            client.get_all_partition_info(&table_info.table_id).await.unwrap()[0].version,
+            1
+        );
+        assert_eq!(
+            snapshot
+                .get_table_info_by_table_path(&table_info.table_path)
+                .await
+                .unwrap(),
+            table_info
+        );
+        drop(snapshot);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_resolved_file_ops() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "resolved_file_ops").await;
+        let commit_id = uuid::Uuid::new_v4();
+        let (high, low) = commit_id.as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: vec![
+                    entity::DataFileOp {
+                        path: "part-0.parquet".to_string(),
+                        file_op: FileOp::Add as i32,
+                        size: 10,
+                        ..Default::default()
+                    },
+                    entity::DataFileOp {
+                        path: "file:///tmp/lakesoul/./old//part-1.parquet".to_string(),
+                        file_op: FileOp::Del as i32,
+                        size: 20,
+                        ..Default::default()
+                    },
+                ],
+                commit_op: CommitOp::MergeCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let file_ops = client
+            .get_resolved_file_ops(&table_info.table_id, "-5", &commit_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(file_ops.len(), 2);
+        assert_eq!(file_ops[0].file_op, FileOp::Add);
+        assert_eq!(file_ops[0].path, format!("{}/part-0.parquet", table_info.table_path));
+        assert_eq!(file_ops[1].file_op, FileOp::Del);
+        assert_eq!(file_ops[1].path, "file:///tmp/lakesoul/old/part-1.parquet");
+        assert!(matches!(
+            client
+                .get_resolved_file_ops(&table_info.table_id, "-5", &uuid::Uuid::new_v4().to_string())
+                .await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_table_tag() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "table_tag").await;
+        async fn commit(client: &MetaDataClient, table_info: &TableInfo) {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data(
+                    MetaInfo {
+                        table_info: Some(table_info.clone()),
+                        list_partition: vec![PartitionInfo {
+                            table_id: table_info.table_id.clone(),
+                            partition_desc: "-5".to_string(),
+                            snapshot: vec![entity::Uuid { high, low }],
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                    CommitOp::AppendCommit,
+                )
+                .await
+                .unwrap();
+        }
+        let table_id = table_info.table_id.as_str();
+        commit(&client, &table_info).await;
+        client.create_tag(table_id, "v1.0").await.unwrap();
+        assert!(matches!(
+            client.create_tag(table_id, "v1.0").await,
+            Err(LakeSoulMetaDataError::AlreadyExists(_))
+        ));
+        commit(&client, &table_info).await;
+
+        let tagged = client.read_at_tag(table_id, "v1.0").await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].partition_desc, "-5");
+        assert_eq!(tagged[0].version, 0);
+        assert_eq!(client.get_all_partition_info(table_id).await.unwrap()[0].version, 1);
+        assert!(matches!(
+            client.read_at_tag(table_id, "v2.0").await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        assert!(matches!(
+            client.read_at_tag(table_id, "v1.0").await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_table_info_cache() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_table_cache(Duration::from_secs(60))
+            .build()
+            .await
+            .unwrap();
+        let table_info = create_test_table(&client, "table_info_cache").await;
+        let table_id = table_info.table_id.as_str();
+        assert_eq!(client.get_table_info_by_table_id(table_id).await.unwrap(), table_info);
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 0, misses: 1 });
+
+        // the second lookup does not query the database
+        assert_eq!(
+            client.get_table_schema(table_id).await.unwrap(),
+            table_info.table_schema
+        );
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 1, misses: 1 });
+
+        // a commit drops the table from the cache
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(client.get_table_info_by_table_id(table_id).await.unwrap(), table_info);
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 1, misses: 2 });
+
+        // prefetched tables are served from the cache
+        let other_table_info = create_test_table(&client, "table_info_cache").await;
+        client
+            .prefetch_table_info(std::slice::from_ref(&other_table_info.table_id))
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .get_table_info_by_table_name(&other_table_info.table_name, "default")
+                .await
+                .unwrap(),
+            other_table_info
+        );
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 2, misses: 2 });
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        client
+            .delete_table_by_table_info_cascade(&other_table_info)
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_table_cache_warm_start() {
+        let dir = env::temp_dir().join(format!("lakesoul_table_cache_{}", uuid::Uuid::new_v4().simple()));
+        let cache_dir = &dir;
+        let build_client = || async move {
+            MetaDataClientBuilder::from_env()
+                .unwrap()
+                .with_table_cache_dir(cache_dir)
+                .build()
+                .await
+                .unwrap()
+        };
+        let client = build_client().await;
+        let table_info = create_test_table(&client, "table_cache").await;
+        let table_id = table_info.table_id.as_str();
+        assert_eq!(client.get_table_info_by_table_id(table_id).await.unwrap(), table_info);
+        let table_cache = client.table_cache.clone().unwrap();
+        table_cache.flush().await;
+
+        // a new client serves the table from the cache file
+        let warm_client = build_client().await;
+        assert_eq!(
+            warm_client
+                .get_table_info_by_table_name(&table_info.table_name, &table_info.table_namespace)
+                .await
+                .unwrap(),
+            table_info
+        );
+
+        // writes drop the table from the cache file
+        client.update_table_properties(table_id, r#"{"k":"v"}"#).await.unwrap();
+        table_cache.flush().await;
+        let properties = build_client()
+            .await
+            .get_table_info_by_table_id(table_id)
+            .await
+            .unwrap()
+            .properties;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&properties).unwrap(),
+            serde_json::json!({"k": "v"})
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        table_cache.flush().await;
+        assert!(build_client()
+            .await
+            .get_table_name_id_by_table_id(table_id)
+            .await
+            .unwrap()
+            .is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_list_and_delete_tags() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "list_tags").await;
+        let table_id = table_info.table_id.as_str();
+        assert!(client.list_tags(table_id).await.unwrap().is_empty());
+
+        client.create_tag(table_id, "v1.0").await.unwrap();
+        client.create_tag(table_id, "v2.0").await.unwrap();
+        let tags = client.list_tags(table_id).await.unwrap();
+        assert_eq!(
+            tags.iter().map(|tag_info| tag_info.tag.as_str()).collect::<Vec<_>>(),
+            vec!["v1.0", "v2.0"]
+        );
+        assert!(tags.iter().all(|tag_info| tag_info.timestamp > 0));
+
+        client.delete_tag(table_id, "v1.0").await.unwrap();
+        assert_eq!(client.list_tags(table_id).await.unwrap(), tags[1..]);
+        assert!(matches!(
+            client.delete_tag(table_id, "v1.0").await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_update_wrappers() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "update_wrappers").await;
+        let table_id = table_info.table_id.as_str();
+
+        let update = TableInfoUpdate {
+            table_schema: Some(r#"{"fields":[]}"#.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(client.update_table_info(table_id, &update).await.unwrap(), 1);
+        assert_eq!(
+            client.get_table_info_by_table_id(table_id).await.unwrap().table_schema,
+            r#"{"fields":[]}"#
+        );
+        assert_eq!(
+            client
+                .update_table_info(table_id, &TableInfoUpdate::default())
+                .await
+                .unwrap(),
+            0
+        );
+
+        async fn commit(client: &MetaDataClient, table_id: &str, commit_op: CommitOp) -> entity::Uuid {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: commit_op as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            // keep the versions apart in time
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            entity::Uuid { high, low }
+        }
+        let appended = commit(&client, table_id, CommitOp::AppendCommit).await;
+        let merged = commit(&client, table_id, CommitOp::MergeCommit).await;
+        let commit_id_string =
+            |commit_id: &entity::Uuid| uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low).to_string();
+        let data_commit_exists = |commit_id: entity::Uuid| {
+            let client = &client;
+            async move {
+                client
+                    .get_single_data_commit_info(table_id, "-5", &commit_id_string(&commit_id))
+                    .await
+                    .unwrap()
+                    .is_some()
+            }
+        };
+
+        // the latest version is kept along with the commits its snapshot references
+        assert_eq!(
+            client
+                .expire_partition_versions(table_id, "-5", i64::MAX)
+                .await
+                .unwrap(),
+            1
+        );
+        let versions = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, 1);
+        assert!(data_commit_exists(appended.clone()).await);
+        assert!(data_commit_exists(merged.clone()).await);
+        assert_eq!(
+            client
+                .expire_partition_versions(table_id, "-5", i64::MAX)
+                .await
+                .unwrap(),
+            0
+        );
+
+        client.delete_partition_cascade(table_id, "-5").await.unwrap();
+        assert!(client.get_all_partition_info(table_id).await.unwrap().is_empty());
+        assert!(!data_commit_exists(appended).await);
+        assert!(!data_commit_exists(merged).await);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_query_scalar_value() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "query_scalar_value").await;
+        let table_id = table_info.table_id.as_str();
+        client
+            .update_table_properties(table_id, r#"{"empty":""}"#)
+            .await
+            .unwrap();
+        let query = |table_id: &str, key: &str| {
+            client.execute_query_scalar_value(
+                DaoType::GetTablePropertyByTableIdAndKey as i32,
+                encode_query_params(&[table_id, key]),
+            )
+        };
+        assert_eq!(
+            query(table_id, "empty").await.unwrap(),
+            ScalarValue::Value(String::new())
+        );
+        assert_eq!(query(table_id, "missing").await.unwrap(), ScalarValue::Null);
+        assert_eq!(query("missing_table", "empty").await.unwrap(), ScalarValue::NoRows);
+
+        assert_eq!(
+            client.get_table_property(table_id, "empty").await.unwrap(),
+            Some(String::new())
+        );
+        assert_eq!(client.get_table_property(table_id, "missing").await.unwrap(), None);
+        assert!(matches!(
+            client.get_table_property("missing_table", "empty").await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_table_cache_invalidation() {
+        let build_client = |notifications: bool| async move {
+            MetaDataClientBuilder::from_env()
+                .unwrap()
+                .with_table_cache(Duration::from_secs(60))
+                .with_table_cache_notifications(notifications)
+                .build()
+                .await
+                .unwrap()
+        };
+        let client = build_client(false).await;
+        let table_info = create_test_table(&client, "table_cache_invalidation").await;
+        let table_id = table_info.table_id.as_str();
+        client.get_table_info_by_table_id(table_id).await.unwrap();
+        client.get_table_info_by_table_id(table_id).await.unwrap();
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 1, misses: 1 });
+
+        // the next lookup after an invalidation queries the database again
+        client.invalidate_table_cache(table_id);
+        client.get_table_info_by_table_id(table_id).await.unwrap();
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 1, misses: 2 });
+        client.invalidate_all_cache();
+        client.get_table_info_by_table_id(table_id).await.unwrap();
+        assert_eq!(client.table_cache_metrics(), TableCacheMetrics { hits: 1, misses: 3 });
+
+        // a write of another client reaches a subscribed client, but not the others before the ttl
+        let listening = build_client(true).await;
+        listening.get_table_info_by_table_id(table_id).await.unwrap();
+        let writer = MetaDataClient::from_env().await.unwrap();
+        writer
+            .update_table_properties(table_id, r#"{"notified":"true"}"#)
+            .await
+            .unwrap();
+        let mut notified = false;
+        for _ in 0..50 {
+            let properties = listening.get_table_info_by_table_id(table_id).await.unwrap().properties;
+            if properties.contains("notified") {
+                notified = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(notified);
+        let properties = client.get_table_info_by_table_id(table_id).await.unwrap().properties;
+        assert!(!properties.contains("notified"));
+
+        writer.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pg_config_endpoints() {
+        use std::str::FromStr;
+        use tokio_postgres::config::{Config, Host};
+
+        let hosts = |config: &str| Config::from_str(config).unwrap().get_hosts().to_vec();
+        let from_url = |url: &str| pg_config_from_jdbc_url(url, "lakesoul_test", "lakesoul test").unwrap();
+
+        let config = from_url("jdbc:postgresql://127.0.0.1:5432/lakesoul_test?stringtype=unspecified");
+        assert_eq!(
+            config,
+            "host=127.0.0.1 port=5432 dbname=lakesoul_test user=lakesoul_test password='lakesoul test'"
+        );
+        assert_eq!(
+            Config::from_str(&config).unwrap().get_password(),
+            Some(&b"lakesoul test"[..])
+        );
+
+        let config = from_url("jdbc:postgresql://[::1]:5433/lakesoul_test");
+        assert_eq!(hosts(&config), vec![Host::Tcp("::1".to_string())]);
+        assert_eq!(Config::from_str(&config).unwrap().get_ports(), &[5433]);
+        let config = from_url("jdbc:postgresql://[fe80::1]/lakesoul_test");
+        assert_eq!(hosts(&config), vec![Host::Tcp("fe80::1".to_string())]);
+        assert_eq!(Config::from_str(&config).unwrap().get_ports(), &[5432]);
+
+        let config = from_url("jdbc:postgresql:///lakesoul_test?host=/var/run/postgresql&stringtype=unspecified");
+        assert_eq!(hosts(&config), vec![Host::Unix(PathBuf::from("/var/run/postgresql"))]);
+
+        assert!(matches!(
+            pg_config_from_jdbc_url("jdbc:postgresql:///lakesoul_test", "", ""),
+            Err(LakeSoulMetaDataError::InvalidConfig(_))
+        ));
+
+        let builder = MetaDataClientBuilder::new().with_host("[::1]", 5433);
+        assert_eq!(hosts(&builder.config), vec![Host::Tcp("::1".to_string())]);
+        let builder = builder
+            .with_unix_socket("/tmp/pg sockets")
+            .with_unix_socket("/tmp/pg's sockets");
+        assert_eq!(
+            hosts(&builder.config),
+            vec![Host::Unix(PathBuf::from("/tmp/pg's sockets"))]
+        );
+        assert_eq!(Config::from_str(&builder.config).unwrap().get_ports(), &[5433]);
+        assert_eq!(
+            Config::from_str(&builder.config).unwrap().get_dbname(),
+            Some("lakesoul_test")
+        );
+    }
+
+    #[test]
+    fn test_prefer_writable_host() {
+        let config = prefer_writable_host("host=10.0.0.1,10.0.0.2 port=5432,5432 dbname=lakesoul_test");
+        assert!(crate::failover::seeks_writable_host(&config));
+        let (config, _) = crate::parse_config(&config).unwrap();
+        assert!(matches!(
+            config.get_target_session_attrs(),
+            tokio_postgres::config::TargetSessionAttrs::ReadWrite
+        ));
+
+        for config in [
+            "host=10.0.0.1 dbname=lakesoul_test",
+            "host=10.0.0.1,10.0.0.2 target_session_attrs=any dbname=lakesoul_test",
+        ] {
+            assert_eq!(prefer_writable_host(config), config);
+            assert!(!crate::failover::seeks_writable_host(config));
+        }
+    }
+
+    #[test]
+    fn test_pg_config_keepalives() {
+        let builder = MetaDataClientBuilder::new().with_keepalives_idle(Duration::from_secs(30));
+        let (config, _) = crate::parse_config(&builder.config).unwrap();
+        assert!(config.get_keepalives());
+        assert_eq!(config.get_keepalives_idle(), Duration::from_secs(30));
+
+        let builder = builder.with_schema("tenant_a").with_keepalives(false);
+        let (config, schema) = crate::parse_config(&builder.config).unwrap();
+        assert!(!config.get_keepalives());
+        assert_eq!(config.get_keepalives_idle(), Duration::from_secs(30));
+        assert_eq!(schema.as_deref(), Some("tenant_a"));
+
+        assert!(matches!(
+            crate::parse_config("keepalives_idle=soon"),
+            Err(LakeSoulMetaDataError::InvalidConfig(_))
+        ));
+
+        for (value, keepalives) in [("1", true), ("true", true), (" 0\r", false), ("false", false)] {
+            assert_eq!(parse_keepalives(value).unwrap(), keepalives);
+        }
+        for value in ["", "off", "False", "2"] {
+            assert!(matches!(
+                parse_keepalives(value),
+                Err(LakeSoulMetaDataError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_op_distribution() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "commit_op_distribution").await;
+        let table_id = table_info.table_id.as_str();
+        assert!(client.get_commit_op_distribution(table_id).await.unwrap().is_empty());
+
+        for commit_op in [CommitOp::AppendCommit, CommitOp::AppendCommit, CommitOp::MergeCommit] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: commit_op as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            client.get_commit_op_distribution(table_id).await.unwrap(),
+            HashMap::from([(CommitOp::AppendCommit as i32, 2), (CommitOp::MergeCommit as i32, 1)])
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_partition_commit_counts() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "partition_commit_counts").await;
+        let table_id = table_info.table_id.as_str();
+        assert!(client.get_partition_commit_counts(table_id).await.unwrap().is_empty());
+
+        for partition_desc in ["date=1", "date=1", "date=1", "date=2"] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            client.get_partition_commit_counts(table_id).await.unwrap(),
+            HashMap::from([("date=1".to_string(), 3), ("date=2".to_string(), 1)])
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_partition_exists_and_is_empty() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "partition_exists").await;
+        let table_id = table_info.table_id.as_str();
+        assert!(!client.partition_exists(table_id, "date=1").await.unwrap());
+        assert!(client.partition_is_empty(table_id, "date=1").await.unwrap());
+
+        let commit = |partition_desc: &str, commit_op: CommitOp, file_op: Option<FileOp>| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: partition_desc.to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: file_op
+                    .into_iter()
+                    .map(|file_op| entity::DataFileOp {
+                        path: format!("file:///tmp/{}/{}.parquet", table_id, uuid::Uuid::new_v4()),
+                        file_op: file_op as i32,
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: commit_op as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+        client
+            .commit_data_commit_info(commit("date=1", CommitOp::AppendCommit, Some(FileOp::Add)))
+            .await
+            .unwrap();
+        client
+            .commit_data_commit_info(commit("date=2", CommitOp::AppendCommit, None))
+            .await
+            .unwrap();
+        assert!(client.partition_exists(table_id, "date=1").await.unwrap());
+        assert!(!client.partition_is_empty(table_id, "date=1").await.unwrap());
+        assert!(client.partition_exists(table_id, "date=2").await.unwrap());
+        assert!(client.partition_is_empty(table_id, "date=2").await.unwrap());
+
+        // only added files count, a partition with deleted files alone stays empty
+        client
+            .commit_data_commit_info(commit("date=2", CommitOp::MergeCommit, Some(FileOp::Del)))
+            .await
+            .unwrap();
+        assert!(client.partition_is_empty(table_id, "date=2").await.unwrap());
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_partition_desc_normalization() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("partition_desc_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date,region;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let table_id = table_info.table_id.as_str();
+
+        for partition_desc in ["region=us,date=2023-01-01", "date=2023-01-01,region=us"] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: vec![entity::DataFileOp {
+                        path: format!("file:///tmp/{}/{}.parquet", table_name, uuid::Uuid::new_v4()),
+                        file_op: FileOp::Add as i32,
+                        ..Default::default()
+                    }],
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let partitions = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].partition_desc, "date=2023-01-01,region=us");
+        assert_eq!(partitions[0].version, 1);
+        assert_eq!(
+            client
+                .get_data_files_of_single_partition(&partitions[0])
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            client
+                .normalize_partition_desc(table_id, "region=us,date=2023-01-01")
+                .await
+                .unwrap(),
+            partitions[0].partition_desc
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_write_limits() {
+        let write_limits = WriteLimits {
+            max_properties_size: 64,
+            max_files_per_commit: 1,
+            ..Default::default()
+        };
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_write_limits(write_limits.clone())
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(client.health_report().await.write_limits, write_limits);
+
+        let table_info = create_test_table(&client, "write_limits").await;
+        let table_id = table_info.table_id.clone();
+        let properties = format!(r#"{{"k":"{}"}}"#, "v".repeat(64));
+        assert!(matches!(
+            client.update_table_properties(&table_id, &properties).await,
+            Err(LakeSoulMetaDataError::LimitExceeded(message)) if message.contains("max_properties_size=64")
+        ));
+        assert_eq!(
+            client.get_table_info_by_table_id(&table_id).await.unwrap().properties,
+            "{}"
+        );
+
+        let other_table_id = format!("table_{}", uuid::Uuid::new_v4());
+        let result = client
+            .create_table(TableInfo {
+                table_id: other_table_id.clone(),
+                table_name: format!("write_limits_{}", uuid::Uuid::new_v4().simple()),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", other_table_id),
+                properties: properties.clone(),
+                ..table_info.clone()
+            })
+            .await;
+        assert!(matches!(result, Err(LakeSoulMetaDataError::LimitExceeded(_))));
+        assert!(client
+            .get_table_name_id_by_table_id(&other_table_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        let result = client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: (0..2)
+                    .map(|i| entity::DataFileOp {
+                        path: format!("file:///tmp/lakesoul/write_limits/{}.parquet", i),
+                        file_op: entity::FileOp::Add as i32,
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(LakeSoulMetaDataError::LimitExceeded(message)) if message.contains("max_files_per_commit=1")
+        ));
+        assert!(client.get_all_partition_info(&table_id).await.unwrap().is_empty());
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_append_file_ops() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "append_file_ops").await;
+        let table_id = table_info.table_id.as_str();
+        let file_op = || entity::DataFileOp {
+            path: format!("file:///tmp/{}/{}.parquet", table_id, uuid::Uuid::new_v4()),
+            file_op: FileOp::Add as i32,
+            size: 1,
+            ..Default::default()
+        };
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        let commit_id = entity::Uuid { high, low };
+        let data_commit_info = DataCommitInfo {
+            table_id: table_id.to_string(),
+            partition_desc: "-5".to_string(),
+            commit_id: Some(commit_id.clone()),
+            file_ops: vec![file_op()],
+            commit_op: CommitOp::AppendCommit as i32,
+            domain: "public".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            client
+                .append_file_ops(table_id, "-5", &commit_id, vec![file_op()])
+                .await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client
+            .insert_data_commit_info_with_stats(&data_commit_info, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .append_file_ops(table_id, "-5", &commit_id, vec![file_op(), file_op()])
+                .await
+                .unwrap(),
+            2
+        );
+        client.commit_data_commit_info(data_commit_info).await.unwrap();
+        let partitions = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(
+            client
+                .get_data_files_of_single_partition(&partitions[0])
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        assert!(matches!(
+            client
+                .append_file_ops(table_id, "-5", &commit_id, vec![file_op()])
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test]
+    fn test_from_config_blocking() {
+        let config = MetaDataClientBuilder::from_env().unwrap().config;
+        let runtime = runtime::Runtime::new().unwrap();
+
+        // a client built on a runtime of its own is usable from others
+        let client = MetaDataClient::from_config_blocking(config.clone(), None).unwrap();
+        assert!(runtime.block_on(client.health_report()).connected);
+        assert!(
+            runtime::Runtime::new()
+                .unwrap()
+                .block_on(client.health_report())
+                .connected
+        );
+        drop(client);
+
+        let client = MetaDataClient::from_config_blocking(config, Some(runtime.handle().clone())).unwrap();
+        assert!(runtime.block_on(client.health_report()).connected);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_close() {
+        // the primary serves as its own secondary
+        let builder = MetaDataClientBuilder::from_env().unwrap();
+        let secondary_config = builder.config.clone();
+        let client = builder.with_secondary_config(secondary_config).build().await.unwrap();
+        client.warmup().await.unwrap();
+        let table_info = create_test_table(&client, "test_close").await;
+        let report = client.close(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(
+            report,
+            CloseReport {
+                in_flight: 0,
+                cancelled: 0,
+                closed: true
+            }
+        );
+        assert!(matches!(
+            client.get_table_info_by_table_id(&table_info.table_id).await,
+            Err(LakeSoulMetaDataError::ClientClosed(_))
+        ));
+        // the connections are ended rather than left to the drop of the client
+        assert!(client.client.lock().await.is_closed());
+        assert!(matches!(
+            client.failover.as_ref().unwrap().warmup().await,
+            Err(LakeSoulMetaDataError::ClientClosed(_))
+        ));
+        assert!(!client.close(Duration::from_secs(5)).await.unwrap().closed);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_multi_table() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let fact = create_test_table(&client, "multi_table_fact").await;
+        let dim = create_test_table(&client, "multi_table_dim").await;
+        let meta_info = |table_info: &TableInfo| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            MetaInfo {
+                table_info: Some(table_info.clone()),
+                list_partition: vec![PartitionInfo {
+                    table_id: table_info.table_id.clone(),
+                    partition_desc: "-5".to_string(),
+                    snapshot: vec![entity::Uuid { high, low }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        };
+
+        // the second commit to the dimension table conflicts with the first one, the fact table is rolled back too
+        let result = client
+            .commit_multi_table(vec![
+                (meta_info(&fact), CommitOp::AppendCommit),
+                (meta_info(&dim), CommitOp::AppendCommit),
+                (meta_info(&dim), CommitOp::AppendCommit),
+            ])
+            .await;
+        assert!(matches!(result, Err(LakeSoulMetaDataError::InvalidInput(_))));
+        assert!(client.get_all_partition_info(&fact.table_id).await.unwrap().is_empty());
+        assert!(client.get_all_partition_info(&dim.table_id).await.unwrap().is_empty());
+
+        client
+            .commit_multi_table(vec![
+                (meta_info(&fact), CommitOp::AppendCommit),
+                (meta_info(&dim), CommitOp::AppendCommit),
+            ])
+            .await
+            .unwrap();
+        for table_info in [&fact, &dim] {
+            let partitions = client.get_all_partition_info(&table_info.table_id).await.unwrap();
+            assert_eq!(partitions.len(), 1);
+            assert_eq!(partitions[0].version, 0);
+        }
+
+        client.delete_table_by_table_info_cascade(&fact).await.unwrap();
+        client.delete_table_by_table_info_cascade(&dim).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_table_read_snapshot() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "table_read_snapshot").await;
+        let table_id = table_info.table_id.as_str();
+        let commit = |path: &str| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: vec![entity::DataFileOp {
+                    path: path.to_string(),
+                    file_op: FileOp::Add as i32,
+                    ..Default::default()
+                }],
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+        client
+            .commit_data_commit_info(commit("file:///tmp/a.parquet"))
+            .await
+            .unwrap();
+        let snapshot = client.open_pinned_read_snapshot(table_id).await.unwrap();
+        assert!(snapshot.is_pinned());
+        client
+            .commit_data_commit_info(commit("file:///tmp/b.parquet"))
+            .await
+            .unwrap();
+        assert_eq!(
+            client.open_read_snapshot(table_id).await.unwrap().partitions()[0].version,
+            1
+        );
+        assert!(client.list_tags(table_id).await.unwrap().is_empty());
+
+        // the snapshot is decoded by another client, and its version survives expiry while pinned
+        let executor = MetaDataClient::from_env().await.unwrap();
+        let shipped = executor.decode_read_snapshot(&snapshot.encode_to_vec()).unwrap();
+        assert_eq!(
+            client
+                .expire_partition_versions(table_id, "-5", i64::MAX)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(shipped.data_files("-5").await.unwrap(), vec!["file:///tmp/a.parquet"]);
+        assert!(shipped.data_files("-4").await.unwrap().is_empty());
+
+        snapshot.release().await.unwrap();
+        assert_eq!(
+            client
+                .expire_partition_versions(table_id, "-5", i64::MAX)
+                .await
+                .unwrap(),
+            1
+        );
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_schema_history() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "schema_history").await;
+        let table_id = table_info.table_id.as_str();
+        let commit = || {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+        let old_schema = r#"{"fields":[{"name":"id"}]}"#;
+        let new_schema = r#"{"fields":[{"name":"id"},{"name":"name"}]}"#;
+        client.update_table_schema(table_id, old_schema).await.unwrap();
+        client.commit_data_commit_info(commit()).await.unwrap();
+        // versions are stamped in milliseconds, keep the schema change apart from the commits
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        client.update_table_schema(table_id, new_schema).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        client.commit_data_commit_info(commit()).await.unwrap();
+
+        assert_eq!(client.get_schema_at_version(table_id, 0).await.unwrap(), old_schema);
+        assert_eq!(client.get_schema_at_version(table_id, 1).await.unwrap(), new_schema);
+        assert!(matches!(
+            client.get_schema_at_version(table_id, 2).await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_schema_history_retention() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_schema_history_retention(2)
+            .build()
+            .await
+            .unwrap();
+        let table_info = create_test_table(&client, "schema_retention").await;
+        let table_id = table_info.table_id.as_str();
+        let commit = || {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: CommitOp::AppendCommit as i32,
+                ..Default::default()
+            }
+        };
+        let schema = |i: usize| format!(r#"{{"fields":[{{"name":"c{}"}}]}}"#, i);
+
+        let first = commit();
+        client.commit_data_commit_info(first.clone()).await.unwrap();
+        assert_eq!(
+            client
+                .get_schema_of_commit(table_id, first.commit_id.as_ref().unwrap())
+                .await
+                .unwrap(),
+            (0, "{}".to_string())
+        );
+        for i in 1..=3 {
+            client.update_table_schema(table_id, &schema(i)).await.unwrap();
+        }
+        let last = commit();
+        client.commit_data_commit_info(last.clone()).await.unwrap();
+        assert_eq!(
+            client
+                .get_schema_of_commit(table_id, last.commit_id.as_ref().unwrap())
+                .await
+                .unwrap(),
+            (3, schema(3))
+        );
+
+        // the schema the table was created with is past the retention
+        let history = client.get_schema_history(table_id).await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|(version, _, table_schema)| (*version, table_schema.clone()))
+                .collect::<Vec<_>>(),
+            vec![(1, schema(1)), (2, schema(2)), (3, schema(3))]
+        );
+        assert_eq!(history[0].1, 0);
+        assert!(history[1].1 > 0 && history[1].1 <= history[2].1);
+        assert!(matches!(
+            client
+                .get_schema_of_commit(table_id, first.commit_id.as_ref().unwrap())
+                .await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        assert!(matches!(
+            client.get_schema_history(table_id).await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+    }
+
+    fn backfill_meta_info(table_info: &TableInfo, partition_descs: &[String]) -> MetaInfo {
+        MetaInfo {
+            table_info: Some(table_info.clone()),
+            list_partition: partition_descs
+                .iter()
+                .map(|partition_desc| {
+                    let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+                    PartitionInfo {
+                        table_id: table_info.table_id.clone(),
+                        partition_desc: partition_desc.clone(),
+                        snapshot: vec![entity::Uuid { high, low }],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_new_partitions_only() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "new_partitions_only").await;
+        let table_id = table_info.table_id.as_str();
+        client
+            .commit_data(
+                backfill_meta_info(&table_info, &["date=1".to_string()]),
+                CommitOp::AppendCommit,
+            )
+            .await
+            .unwrap();
+
+        // date=1 exists already and falls back to the usual commit on top of its current version
+        let partition_descs = ["date=1", "date=2", "date=3"].map(String::from);
+        client
+            .commit_data_with_hint(
+                backfill_meta_info(&table_info, &partition_descs),
+                CommitOp::AppendCommit,
+                CommitHint::NewPartitionsOnly,
+            )
+            .await
+            .unwrap();
+        let partitions = client
+            .get_partition_info_by_table_id_and_partition_list(table_id, &partition_descs)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|partition_info| {
+                (
+                    partition_info.partition_desc,
+                    (partition_info.version, partition_info.snapshot.len()),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            partitions,
+            HashMap::from([
+                ("date=1".to_string(), (1, 2)),
+                ("date=2".to_string(), (0, 1)),
+                ("date=3".to_string(), (0, 1)),
+            ])
+        );
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_list_empty_tables() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let written = create_test_table(&client, "list_empty_tables").await;
+        let empty = create_test_table(&client, "list_empty_tables").await;
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: written.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let is_listed = |tables: &[TableInfo], table_info: &TableInfo| {
+            tables.iter().any(|table| table.table_id == table_info.table_id)
+        };
+        let in_default = client.list_empty_tables(Some("default")).await.unwrap();
+        assert!(is_listed(&in_default, &empty));
+        assert!(!is_listed(&in_default, &written));
+        assert!(is_listed(&client.list_empty_tables(None).await.unwrap(), &empty));
+        assert!(!is_listed(
+            &client.list_empty_tables(Some("no_such_namespace")).await.unwrap(),
+            &empty
+        ));
+
+        client.delete_table_by_table_info_cascade(&written).await.unwrap();
+        client.delete_table_by_table_info_cascade(&empty).await.unwrap();
+    }
+
+    /// Create a pg schema holding the tables of the catalog and the default namespace, returning its name.
+    async fn create_test_schema(client: &MetaDataClient) -> String {
+        let schema = format!("tenant_{}", uuid::Uuid::new_v4().simple());
+        let mut statements = vec![
+            format!("create schema {}", schema),
+            format!(
+                "create type {}.data_file_op as (path text, file_op text, size bigint, file_exist_cols text)",
+                schema
+            ),
+        ];
+        for table in [
+            "namespace",
+            "table_info",
+            "table_name_id",
+            "table_path_id",
+            "partition_info",
+            "data_commit_info",
+        ] {
+            statements.push(format!(
+                "create table {}.{} (like public.{} including all)",
+                schema, table, table
+            ));
+        }
+        statements.push(format!(
+            "alter table {0}.data_commit_info alter column file_ops type {0}.data_file_op[] using file_ops::text::{0}.data_file_op[]",
+            schema
+        ));
+        statements.push(format!(
+            "insert into {}.namespace select * from public.namespace where namespace = 'default'",
+            schema
+        ));
+        client
+            .client
+            .lock()
+            .await
+            .batch_execute(&statements.join(";"))
+            .await
+            .unwrap();
+        schema
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_schema_isolation() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let schema = create_test_schema(&client).await;
+
+        let tenant_client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_schema(&schema)
+            .build()
+            .await
+            .unwrap();
+        let tenant_table = create_test_table(&tenant_client, "schema_isolation").await;
+        assert!(tenant_client
+            .get_table_info_by_table_id(&tenant_table.table_id)
+            .await
+            .is_ok());
+        assert!(client.get_table_info_by_table_id(&tenant_table.table_id).await.is_err());
+        let table = create_test_table(&client, "schema_isolation").await;
+        assert!(tenant_client.get_table_info_by_table_id(&table.table_id).await.is_err());
+
+        // the schema has none of the optional tables, which the cleanup skips
+        tenant_client.meta_cleanup().await.unwrap();
+        assert!(tenant_client
+            .get_table_info_by_table_id(&tenant_table.table_id)
+            .await
+            .is_err());
+        assert!(client.get_table_info_by_table_id(&table.table_id).await.is_ok());
+
+        let missing = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_schema(&format!("{}_missing", schema))
+            .build()
+            .await;
+        assert!(matches!(missing, Err(LakeSoulMetaDataError::InvalidConfig(_))));
+
+        client.delete_table_by_table_info_cascade(&table).await.unwrap();
+        client
+            .client
+            .lock()
+            .await
+            .batch_execute(&format!("drop schema {} cascade", schema))
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_missing_optional_columns() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let schema = create_test_schema(&client).await;
+        client
+            .client
+            .lock()
+            .await
+            .batch_execute(&format!(
+                "alter table {0}.table_info drop column domain;
+                alter table {0}.table_name_id drop column domain;
+                alter table {0}.partition_info drop column domain",
+                schema
+            ))
+            .await
+            .unwrap();
+
+        let tenant_client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_schema(&schema)
+            .build()
+            .await
+            .unwrap();
+        let report = tenant_client.health_report().await;
+        assert!(report
+            .missing_optional_columns
+            .contains(&"table_name_id.domain".to_string()));
+        let table = create_test_table(&tenant_client, "missing_columns").await;
+        // read as the default, also where the queries filter by the missing column
+        let table_name_id = tenant_client
+            .get_table_name_id_by_table_id(&table.table_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(table_name_id.domain, "public");
+        assert_eq!(tenant_client.list_domains().await.unwrap(), ["public"]);
+        let page = tenant_client
+            .get_table_info_page_by_domain("public", None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].table_id, table.table_id);
+
+        client
+            .client
+            .lock()
+            .await
+            .batch_execute(&format!("drop schema {} cascade", schema))
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_sync_table_metadata() {
+        let source = MetaDataClient::from_env().await.unwrap();
+        let schema = create_test_schema(&source).await;
+        let target = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_schema(&schema)
+            .build()
+            .await
+            .unwrap();
+        let namespace = format!("sync_{}", uuid::Uuid::new_v4().simple());
+        source
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: String::new(),
+                domain: String::new(),
+            })
+            .await
+            .unwrap();
+        let table_name = format!("sync_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: namespace.clone(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date;".to_string(),
+            domain: "public".to_string(),
+        };
+        source.create_table(table_info.clone()).await.unwrap();
+        let table_id = table_info.table_id.as_str();
+        async fn commit(client: &MetaDataClient, table_info: &TableInfo, partition_desc: &str) {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_info.table_id.clone(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: vec![entity::DataFileOp {
+                        path: format!("{}/{}/part-{}.parquet", table_info.table_path, partition_desc, high),
+                        file_op: entity::FileOp::Add as i32,
+                        ..Default::default()
+                    }],
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        async fn files(client: &MetaDataClient, table_info: &TableInfo) -> Vec<String> {
+            let mut files = client
+                .get_data_files_by_table_name(&table_info.table_name, &table_info.table_namespace)
+                .await
+                .unwrap();
+            files.sort();
+            files
+        }
+        for partition_desc in ["date=1", "date=1", "date=2"] {
+            commit(&source, &table_info, partition_desc).await;
+        }
+
+        let summary = source.sync_namespace_metadata(&target, &namespace, 2).await.unwrap();
+        assert!(summary.failed.is_empty());
+        let report = &summary.synced[0];
+        assert!(report.table_created);
+        assert_eq!(report.partition_versions, 3);
+        assert_eq!(report.data_commits, 3);
+        assert!(report.diverged_partitions.is_empty());
+        assert_eq!(files(&target, &table_info).await, files(&source, &table_info).await);
+        let report = source.sync_table_metadata(&target, table_id).await.unwrap();
+        assert_eq!(report.partition_versions + report.data_commits, 0);
+
+        commit(&source, &table_info, "date=1").await;
+        let report = source.sync_table_metadata(&target, table_id).await.unwrap();
+        assert!(!report.table_created);
+        assert_eq!(report.partition_versions, 1);
+        assert_eq!(report.data_commits, 1);
+        assert_eq!(files(&target, &table_info).await, files(&source, &table_info).await);
+
+        // diverged on the target, which is left as it is
+        commit(&target, &table_info, "date=2").await;
+        commit(&source, &table_info, "date=2").await;
+        commit(&target, &table_info, "date=3").await;
+        let target_files = files(&target, &table_info).await;
+        let report = source.sync_table_metadata(&target, table_id).await.unwrap();
+        assert_eq!(report.partition_versions, 0);
+        assert_eq!(report.diverged_partitions, ["date=2", "date=3"]);
+        assert_eq!(files(&target, &table_info).await, target_files);
+        assert!(matches!(
+            source.sync_table_metadata(&target, "no_such_table").await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        source.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        source.delete_namespace_by_namespace(&namespace).await.unwrap();
+        source
+            .client
+            .lock()
+            .await
+            .batch_execute(&format!("drop schema {} cascade", schema))
+            .await
+            .unwrap();
+    }
+
+    #[test_log::test]
+    fn test_runtime_handle() {
+        let client_runtime = runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+        let build_runtime = runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let client = build_runtime
+            .block_on(
+                MetaDataClientBuilder::from_env()
+                    .unwrap()
+                    .with_runtime_handle(client_runtime.handle().clone())
+                    .build(),
+            )
+            .unwrap();
+        // the connection is driven by the client runtime, so it survives the runtime the client was built on
+        drop(build_runtime);
+
+        let other_runtime = runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        other_runtime.block_on(async {
+            let table_info = create_test_table(&client, "runtime_handle").await;
+            let snapshot = client.begin_read_snapshot().await.unwrap();
+            assert!(client.get_table_info_by_table_id(&table_info.table_id).await.is_ok());
+            drop(snapshot);
+            client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        });
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_truncate_table() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "truncate_table").await;
+        let table_id = table_info.table_id.as_str();
+        for _ in 0..2 {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        assert!(!client.get_all_partition_info(table_id).await.unwrap().is_empty());
+
+        client.truncate_table(table_id).await.unwrap();
+        assert_eq!(
+            client.get_table_info_by_table_id(table_id).await.unwrap().table_name,
+            table_info.table_name
+        );
+        assert!(client.get_all_partition_info(table_id).await.unwrap().is_empty());
+        let data_commits: i64 = client
+            .client
+            .lock()
+            .await
+            .query_one(
+                "select count(*) from data_commit_info where table_id = $1::TEXT",
+                &[&table_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(data_commits, 0);
+        assert!(matches!(
+            client.truncate_table("no_such_table").await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_partition_values() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("partition_values_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date,region;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let table_id = table_info.table_id.as_str();
+
+        for partition_desc in [
+            "date=2023-01-02,region=us",
+            "date=2023-01-01,region=us",
+            "date=2023-01-01,region=eu",
+            "date=a=b,region=eu",
+        ] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            client.get_partition_values(table_id, "date").await.unwrap(),
+            ["2023-01-01", "2023-01-02", "a=b"]
+        );
+        assert_eq!(
+            client.get_partition_values(table_id, "region").await.unwrap(),
+            ["eu", "us"]
+        );
+        assert_eq!(
+            client
+                .get_partition_values_with_filter(table_id, "date", &[("region", "us")])
+                .await
+                .unwrap(),
+            ["2023-01-01", "2023-01-02"]
+        );
+        assert!(client
+            .get_partition_values(table_id, "no_such_column")
+            .await
+            .unwrap()
+            .is_empty());
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_data_files_by_table_path() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("files_by_path_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date,region;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let table_id = table_info.table_id.as_str();
+        let table_path = table_info.table_path.as_str();
+
+        for partition_desc in [
+            "date=2023-01-02,region=us",
+            "date=2023-01-01,region=us",
+            "date=2023-01-01,region=eu",
+        ] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: vec![entity::DataFileOp {
+                        path: format!("{}/{}/part.parquet", table_path, partition_desc),
+                        file_op: entity::FileOp::Add as i32,
+                        ..Default::default()
+                    }],
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        // the same files as resolving the table id first
+        let resolved = client.get_table_info_by_table_path(table_path).await.unwrap();
+        let us_partitions = client
+            .get_all_partition_info(&resolved.table_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|partition_info| partition_info.partition_desc.ends_with("region=us"))
+            .collect::<Vec<_>>();
+        let mut expected = client.get_data_files_of_partitions(us_partitions).await.unwrap();
+        let mut files = client
+            .get_data_files_by_table_path(table_path, vec![("region", "us")])
+            .await
+            .unwrap();
+        expected.sort();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files, expected);
+
+        let files = client
+            .get_data_files_by_table_path(table_path, vec![("region", "eu"), ("date", "2023-01-01")])
+            .await
+            .unwrap();
+        let eu_file = format!("{}/date=2023-01-01,region=eu/part.parquet", table_path);
+        assert_eq!(files, [eu_file]);
+        let mut all = client.get_data_files_by_table_path(table_path, vec![]).await.unwrap();
+        let mut by_name = client
+            .get_data_files_by_table_name(&table_name, "default")
+            .await
+            .unwrap();
+        all.sort();
+        by_name.sort();
+        assert_eq!(all, by_name);
+        assert!(matches!(
+            client
+                .get_data_files_by_table_path("file:///tmp/lakesoul/no_such_table", vec![])
+                .await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_export_import_catalog() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let namespace = format!("export_{}", uuid::Uuid::new_v4().simple());
+        client
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: String::new(),
+                domain: String::new(),
+            })
+            .await
+            .unwrap();
+        let mut table_infos = vec![];
+        for _ in 0..2 {
+            let table_name = format!("export_{}", uuid::Uuid::new_v4().simple());
+            let table_info = TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: namespace.clone(),
+                table_name: table_name.clone(),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                table_schema: "{}".to_string(),
+                properties: "{}".to_string(),
+                partitions: "date;".to_string(),
+                domain: "public".to_string(),
+            };
+            client.create_table(table_info.clone()).await.unwrap();
+            let partition_descs = ["date=2023-01-01", "date=2023-01-01", "date=2023-01-02"];
+            for (i, partition_desc) in partition_descs.iter().enumerate() {
+                let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+                client
+                    .commit_data_commit_info(DataCommitInfo {
+                        table_id: table_info.table_id.clone(),
+                        partition_desc: partition_desc.to_string(),
+                        commit_id: Some(entity::Uuid { high, low }),
+                        file_ops: vec![entity::DataFileOp {
+                            path: format!("{}/{}/part-{}.parquet", table_info.table_path, partition_desc, i),
+                            file_op: entity::FileOp::Add as i32,
+                            ..Default::default()
+                        }],
+                        commit_op: CommitOp::AppendCommit as i32,
+                        domain: "public".to_string(),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+            }
+            table_infos.push(table_info);
+        }
+        let mut files = vec![];
+        let mut versions = vec![];
+        for table_info in &table_infos {
+            let mut table_files = client
+                .get_data_files_by_table_name(&table_info.table_name, &namespace)
+                .await
+                .unwrap();
+            table_files.sort();
+            assert_eq!(table_files.len(), 3);
+            files.push(table_files);
+            let mut table_versions = client
+                .get_recent_partition_versions(&table_info.table_id, 10)
+                .await
+                .unwrap();
+            table_versions
+                .sort_by_key(|partition_info| (partition_info.partition_desc.clone(), partition_info.version));
+            versions.push(table_versions);
+        }
+
+        let mut export = vec![];
+        let manifest = client
+            .export_catalog(Some(vec![namespace.clone()]), &mut export)
+            .await
+            .unwrap();
+        assert!(manifest.complete);
+        assert_eq!(manifest.namespaces, std::slice::from_ref(&namespace));
+        assert_eq!(manifest.exported_tables.len(), 2);
+        assert_eq!(ExportManifest::recover(export.as_slice()).unwrap(), manifest);
+
+        // resumed after the first table, cutting the checkpoint of the second one short
+        let mut resumed = export[..export.len() - 6].to_vec();
+        let recovered = ExportManifest::recover(resumed.as_slice()).unwrap();
+        assert!(!recovered.complete);
+        assert_eq!(recovered.exported_tables, manifest.exported_tables[..1]);
+        resumed.truncate(recovered.resume_offset as usize);
+        let resumed_manifest = client.resume_export_catalog(recovered, &mut resumed).await.unwrap();
+        assert_eq!(resumed_manifest, manifest);
+        assert_eq!(resumed, export);
+
+        assert!(matches!(
+            client.import_catalog(export.as_slice(), ConflictPolicy::Fail).await,
+            Err(LakeSoulMetaDataError::AlreadyExists(_))
+        ));
+        let summary = client
+            .import_catalog(export.as_slice(), ConflictPolicy::Skip)
+            .await
+            .unwrap();
+        assert_eq!(summary.tables, 0);
+        assert_eq!(summary.skipped_tables.len(), 2);
+
+        for table_info in &table_infos {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+        let summary = client
+            .import_catalog(export.as_slice(), ConflictPolicy::Fail)
+            .await
+            .unwrap();
+        assert_eq!(summary.namespaces, 0);
+        assert_eq!(summary.tables, 2);
+        assert_eq!(summary.data_commits, 6);
+        assert_eq!(summary.partition_versions, 6);
+        let summary = client
+            .import_catalog(export.as_slice(), ConflictPolicy::Replace)
+            .await
+            .unwrap();
+        assert_eq!(summary.tables, 2);
+        for (i, table_info) in table_infos.iter().enumerate() {
+            let mut table_files = client
+                .get_data_files_by_table_name(&table_info.table_name, &namespace)
+                .await
+                .unwrap();
+            table_files.sort();
+            assert_eq!(table_files, files[i]);
+            let mut imported_versions = client
+                .get_recent_partition_versions(&table_info.table_id, 10)
+                .await
+                .unwrap();
+            imported_versions
+                .sort_by_key(|partition_info| (partition_info.partition_desc.clone(), partition_info.version));
+            assert_eq!(imported_versions, versions[i]);
+        }
+
+        assert!(matches!(
+            client
+                .import_catalog(&export[..export.len() - 5], ConflictPolicy::Replace)
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        // cut short in the checkpoint of the second table, which is deleted again to be imported by a rerun
+        assert!(client
+            .import_catalog(&export[..export.len() - 6], ConflictPolicy::Replace)
+            .await
+            .is_err());
+        assert!(client
+            .get_table_info_by_table_id(&table_infos[0].table_id)
+            .await
+            .is_ok());
+        assert!(matches!(
+            client.get_table_info_by_table_id(&table_infos[1].table_id).await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+        let summary = client
+            .import_catalog(export.as_slice(), ConflictPolicy::Skip)
+            .await
+            .unwrap();
+        assert_eq!(summary.skipped_tables, vec![table_infos[0].table_name.clone()]);
+        assert_eq!(summary.tables, 1);
+        assert_eq!(summary.data_commits, 3);
+        assert_eq!(summary.partition_versions, 3);
+        for table_info in &table_infos {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+        client.delete_namespace_by_namespace(&namespace).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_assert_commit_belongs() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "commit_belongs").await;
+        let table_id = table_info.table_id.as_str();
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        let commit_id = entity::Uuid { high, low };
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(commit_id.clone()),
+                file_ops: vec![],
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        client.assert_commit_belongs(table_id, "-5", &commit_id).await.unwrap();
+
+        let other = create_test_table(&client, "commit_belongs_other").await;
+        let err = client
+            .assert_commit_belongs(&other.table_id, "-5", &commit_id)
+            .await
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(matches!(err, LakeSoulMetaDataError::NotFound(_)));
+        assert!(message.contains(&uuid::Uuid::from_u64_pair(high, low).to_string()));
+        assert!(message.contains(&other.table_id));
+        assert!(matches!(
+            client
+                .assert_commit_belongs(table_id, "date=2023-01-01", &commit_id)
+                .await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        client.delete_table_by_table_info_cascade(&other).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_max_result_bytes() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "max_result_bytes").await;
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: (0..100)
+                    .map(|i| entity::DataFileOp {
+                        path: format!("{}/part-{:05}.parquet", table_info.table_path, i),
+                        file_op: entity::FileOp::Add as i32,
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let files = client
+            .get_data_files_by_table_name(&table_info.table_name, "default")
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 100);
+
+        let limited = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_max_result_bytes(1024)
+            .build()
+            .await
+            .unwrap();
+        // small results are served
+        assert!(limited.get_table_info_by_table_id(&table_info.table_id).await.is_ok());
+        assert!(matches!(
+            limited
+                .get_data_files_by_table_name(&table_info.table_name, "default")
+                .await,
+            Err(LakeSoulMetaDataError::ResultTooLarge(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_data_retry_deduplicated() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "commit_retry").await;
+        let commit_ids = (0..3)
+            .map(|_| {
+                let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+                entity::Uuid { high, low }
+            })
+            .collect::<Vec<_>>();
+        let commit = |snapshot: Vec<entity::Uuid>| MetaInfo {
+            table_info: Some(table_info.clone()),
+            list_partition: vec![PartitionInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                snapshot,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let first = commit(commit_ids[..2].to_vec());
+        client.commit_data(first.clone(), CommitOp::AppendCommit).await.unwrap();
+        let committed = client.get_all_partition_info(&table_info.table_id).await.unwrap();
+
+        // retrying a commit which went through leaves the partition as it is
+        client.commit_data(first, CommitOp::AppendCommit).await.unwrap();
+        assert_eq!(
+            client.get_all_partition_info(&table_info.table_id).await.unwrap(),
+            committed
+        );
+        assert_eq!(
+            client.commit_metrics(),
+            CommitMetrics {
+                retried_commits: 1,
+                duplicate_commit_ids: 2
+            }
+        );
+
+        client
+            .commit_data(commit(commit_ids[1..].to_vec()), CommitOp::MergeCommit)
+            .await
+            .unwrap();
+        let partitions = client.get_all_partition_info(&table_info.table_id).await.unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].version, committed[0].version + 1);
+        assert_eq!(partitions[0].snapshot, commit_ids);
+        assert_eq!(
+            client.commit_metrics(),
+            CommitMetrics {
+                retried_commits: 1,
+                duplicate_commit_ids: 3
+            }
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_table_last_updated() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "last_updated").await;
+        assert_eq!(client.get_table_last_updated(&table_info.table_id).await.unwrap(), None);
+
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: vec![],
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let last_updated = client
+            .get_table_last_updated(&table_info.table_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((client.get_db_time().await.unwrap() - last_updated).abs() < 60_000);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_op_options() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        // the timeout covers the whole operation, queries done already included
+        let missing_table_id = format!("table_{}", uuid::Uuid::new_v4());
+        assert!(matches!(
+            client
+                .run_with_options(OpOptions::new().with_timeout(Duration::from_millis(50)), async {
+                    let partitions = client.get_partitions_as_json(&missing_table_id).await?;
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Ok(partitions)
+                })
+                .await,
+            Err(LakeSoulMetaDataError::Timeout(_))
+        ));
+        assert_eq!(
+            client
+                .get_partitions_as_json_with_options(
+                    &missing_table_id,
+                    OpOptions::new().with_timeout(Duration::from_secs(10))
+                )
+                .await
+                .unwrap(),
+            "[]"
+        );
+
+        let table_info = create_test_table(&client, "op_options").await;
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_with_options(
+                MetaInfo {
+                    table_info: Some(table_info.clone()),
+                    list_partition: vec![PartitionInfo {
+                        table_id: table_info.table_id.clone(),
+                        partition_desc: "-5".to_string(),
+                        snapshot: vec![entity::Uuid { high, low }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                CommitOp::AppendCommit,
+                OpOptions::new().with_no_retry(),
+            )
+            .await
+            .unwrap();
+        let partitions = client
+            .get_all_partition_info_with_options(
+                &table_info.table_id,
+                OpOptions::new().with_max_retry(5).with_timeout(Duration::from_secs(10)),
+            )
+            .await
+            .unwrap();
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        assert_eq!(partitions.len(), 1);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_warmup() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_warmup(true)
+            .build()
+            .await
+            .unwrap();
+        let prepared = client.prepared.lock().await.prepared.len();
+        assert!(prepared > 0);
+
+        let table_info = create_test_table(&client, "warmup").await;
+        client.get_all_partition_info(&table_info.table_id).await.unwrap();
+        client.get_table_info_by_table_id(&table_info.table_id).await.unwrap();
+        // the statements run after the warmup were all prepared by it
+        assert_eq!(client.prepared.lock().await.prepared.len(), prepared);
+        client.warmup().await.unwrap();
+        assert_eq!(client.prepared.lock().await.prepared.len(), prepared);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_catalog_overview() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let namespace = format!("overview_{}", uuid::Uuid::new_v4().simple());
+        client
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: "".to_string(),
+                domain: "public".to_string(),
+            })
+            .await
+            .unwrap();
+        let find = |overviews: Vec<NamespaceOverview>| {
+            overviews
+                .into_iter()
+                .find(|overview| overview.namespace == namespace)
+                .unwrap()
+        };
+        assert_eq!(
+            find(client.get_catalog_overview().await.unwrap()),
+            NamespaceOverview {
+                namespace: namespace.clone(),
+                table_count: 0,
+                partition_count: 0,
+                data_commit_count: 0,
+                last_updated: None,
+            }
+        );
+
+        let mut table_infos = vec![];
+        for _ in 0..2 {
+            let table_name = format!("overview_{}", uuid::Uuid::new_v4().simple());
+            let table_info = TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: namespace.clone(),
+                table_name: table_name.clone(),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                table_schema: "{}".to_string(),
+                properties: "{}".to_string(),
+                partitions: ";".to_string(),
+                domain: "public".to_string(),
+            };
+            client.create_table(table_info.clone()).await.unwrap();
+            table_infos.push(table_info);
+        }
+        for _ in 0..2 {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_infos[0].table_id.clone(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: vec![],
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let overview = find(client.get_catalog_overview().await.unwrap());
+        assert_eq!(overview.table_count, 2);
+        assert_eq!(overview.partition_count, 1);
+        assert_eq!(overview.data_commit_count, 2);
+        assert_eq!(
+            overview.last_updated,
+            client.get_table_last_updated(&table_infos[0].table_id).await.unwrap()
+        );
+
+        for table_info in &table_infos {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+        client.delete_namespace_by_namespace(&namespace).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_list_empty_partitions() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "empty_partitions").await;
+        let path = format!("{}/part-00000.parquet", table_info.table_path);
+        for file_op in [FileOp::Add, FileOp::Del] {
+            assert!(client
+                .list_empty_partitions(&table_info.table_id)
+                .await
+                .unwrap()
+                .is_empty());
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_info.table_id.clone(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: vec![entity::DataFileOp {
+                        path: path.clone(),
+                        file_op: file_op as i32,
+                        ..Default::default()
+                    }],
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            client.list_empty_partitions(&table_info.table_id).await.unwrap(),
+            vec!["-5".to_string()]
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_overwrite_table() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "overwrite_table").await;
+        let table_id = table_info.table_id.as_str();
+        let data_commit = |partition_desc: &str, file: &str| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: partition_desc.to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: vec![entity::DataFileOp {
+                    path: format!("{}/{}", table_info.table_path, file),
+                    file_op: FileOp::Add as i32,
+                    ..Default::default()
+                }],
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+        for partition_desc in ["a=1", "a=2"] {
+            client
+                .commit_data_commit_info(data_commit(partition_desc, "old.parquet"))
+                .await
+                .unwrap();
+        }
+
+        let new_commit = data_commit("a=2", "new.parquet");
+        client
+            .overwrite_table(
+                table_id,
+                vec![
+                    ("a=2".to_string(), vec![new_commit.clone()]),
+                    ("a=3".to_string(), vec![data_commit("a=3", "new.parquet")]),
+                ],
+            )
+            .await
+            .unwrap();
+        let partitions = client.get_all_partition_info(table_id).await.unwrap();
+        let partition = |partition_desc: &str| {
+            partitions
+                .iter()
+                .find(|partition_info| partition_info.partition_desc == partition_desc)
+                .unwrap()
+        };
+        assert_eq!(partitions.len(), 3);
+        assert_eq!(partition("a=1").version, 1);
+        assert!(partition("a=1").snapshot.is_empty());
+        assert_eq!(partition("a=2").version, 1);
+        assert_eq!(partition("a=2").snapshot, vec![new_commit.commit_id.clone().unwrap()]);
+        assert_eq!(partition("a=3").version, 0);
+        assert!(client.partition_is_empty(table_id, "a=1").await.unwrap());
+        assert!(!client.partition_is_empty(table_id, "a=2").await.unwrap());
+
+        // a commit between reading the current versions and writing the new ones aborts the whole overwrite
+        let cur_versions = partitions
+            .iter()
+            .map(|partition_info| (partition_info.partition_desc.clone(), partition_info.version))
+            .collect();
+        client
+            .commit_data_commit_info(data_commit("a=1", "concurrent.parquet"))
+            .await
+            .unwrap();
+        let committed = client.get_all_partition_info(table_id).await.unwrap();
+        let snapshot = Snapshot::dedup([uuid::Uuid::new_v4()]).0;
+        assert!(matches!(
+            client
+                .insert_overwrite_versions(&table_info, vec![("a=2".to_string(), snapshot)], cur_versions)
+                .await,
+            Err(LakeSoulMetaDataError::ConcurrentCommit(_))
+        ));
+        assert_eq!(client.get_all_partition_info(table_id).await.unwrap(), committed);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_error_context() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_id = format!("table_{}", uuid::Uuid::new_v4());
+        let err = client.get_table_info_by_table_id(&table_id).await.unwrap_err();
+        assert!(matches!(err, LakeSoulMetaDataError::NotFound(_)));
+        let message = err.to_string();
+        assert!(message.contains(&format!("in get_table_info_by_table_id of table '{}'", table_id)));
+        assert!(message.contains("at attempt 1"));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_conflict_policy() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_conflict_policy(Arc::new(RebaseAppends))
+            .build()
+            .await
+            .unwrap();
+        let table_info = create_test_table(&client, "conflict_policy").await;
+        let table_id = table_info.table_id.as_str();
+        let commit_id = || {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            entity::Uuid { high, low }
+        };
+        let meta_info = |commit_id: &entity::Uuid| MetaInfo {
+            table_info: Some(table_info.clone()),
+            list_partition: vec![PartitionInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "a=1".to_string(),
+                snapshot: vec![commit_id.clone()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let (first, racing, concurrent) = (commit_id(), commit_id(), commit_id());
+        client
+            .commit_data(meta_info(&first), CommitOp::AppendCommit)
+            .await
+            .unwrap();
+        // the racing append computed its version before the concurrent one was committed
+        let (_, stale, _) = client
+            .new_partition_versions(meta_info(&racing), CommitOp::AppendCommit, CommitHint::Default)
+            .await
+            .unwrap();
+        client
+            .commit_data(meta_info(&concurrent), CommitOp::AppendCommit)
+            .await
+            .unwrap();
+        let inserted = client
+            .insert_resolving_conflicts(
+                meta_info(&racing),
+                CommitOp::AppendCommit,
+                stale.clone(),
+                false,
+                None,
+                &RebaseAppends,
+            )
+            .await
+            .unwrap();
+        assert_eq!(inserted, 1);
+        let partition = client.get_all_partition_info(table_id).await.unwrap().remove(0);
+        assert_eq!(partition.version, 2);
+        assert_eq!(partition.snapshot, vec![first, concurrent, racing.clone()]);
+
+        // failing leaves the partition as committed meanwhile
+        assert!(matches!(
+            client
+                .insert_resolving_conflicts(
+                    meta_info(&racing),
+                    CommitOp::AppendCommit,
+                    stale,
+                    false,
+                    None,
+                    &FailOnConflict,
+                )
+                .await,
+            Err(LakeSoulMetaDataError::ConcurrentCommit(_))
+        ));
+        assert_eq!(client.get_all_partition_info(table_id).await.unwrap(), vec![partition]);
+
+        // the table property overrides the policy of the client
+        assert_eq!(
+            client.conflict_policy_of(&table_info).unwrap().unwrap().name(),
+            "rebase_appends"
+        );
+        let named = |name: &str| TableInfo {
+            properties: serde_json::json!({ COMMIT_CONFLICT_POLICY: name }).to_string(),
+            ..table_info.clone()
+        };
+        assert_eq!(
+            client.conflict_policy_of(&named("fail")).unwrap().unwrap().name(),
+            "fail"
+        );
+        assert!(matches!(
+            client.conflict_policy_of(&named("unknown")),
+            Err(LakeSoulMetaDataError::InvalidConfig(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_schemas_by_table_ids() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let first = create_test_table(&client, "schemas_first").await;
+        let second = create_test_table(&client, "schemas_second").await;
+        client
+            .update_table_schema(&second.table_id, r#"{"type":"struct","fields":[]}"#)
+            .await
+            .unwrap();
+        let table_ids = [
+            first.table_id.clone(),
+            second.table_id.clone(),
+            format!("table_{}", uuid::Uuid::new_v4()),
+        ];
+        let schemas = client.get_schemas_by_table_ids(&table_ids).await.unwrap();
+        assert_eq!(schemas.len(), 2);
+        assert_eq!(schemas[&first.table_id], "{}");
+        assert_eq!(schemas[&second.table_id], r#"{"type":"struct","fields":[]}"#);
+
+        client.delete_table_by_table_info_cascade(&first).await.unwrap();
+        client.delete_table_by_table_info_cascade(&second).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_read_only_database() {
+        let builder = MetaDataClientBuilder::from_env().unwrap();
+        // a standby refuses writes alike
+        let config = set_config_property(&builder.config, "options", "-c default_transaction_read_only=on");
+        let client = builder.with_config(config).build().await.unwrap();
+        let report = client.health_report().await;
+        assert!(report.connected);
+        assert!(report.read_only);
+
+        let err = client
+            .create_namespace(Namespace {
+                namespace: format!("read_only_ns_{}", uuid::Uuid::new_v4().simple()),
+                properties: "{}".to_string(),
+                comment: "".to_string(),
+                domain: "public".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), LakeSoulMetaDataError::ReadOnlyDatabase(_)));
+        assert!(!err.is_retryable());
+        assert!(err.to_string().contains("target_session_attrs=read-write"));
+
+        let writable = MetaDataClient::from_env().await.unwrap();
+        assert!(!writable.health_report().await.read_only);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_file_op_stream() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "file_op_stream").await;
+        for (partition_desc, file_count) in [("a=1", 3), ("a=2", 2)] {
+            for file in 0..file_count {
+                let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+                client
+                    .commit_data_commit_info(DataCommitInfo {
+                        table_id: table_info.table_id.clone(),
+                        partition_desc: partition_desc.to_string(),
+                        commit_id: Some(entity::Uuid { high, low }),
+                        file_ops: vec![entity::DataFileOp {
+                            path: format!("{}/{}/{}.parquet", table_info.table_path, partition_desc, file),
+                            file_op: FileOp::Add as i32,
+                            ..Default::default()
+                        }],
+                        commit_op: CommitOp::AppendCommit as i32,
+                        domain: "public".to_string(),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let mut streamed = client
+            .file_op_stream(&table_info.table_id)
+            .map_ok(|file_op| file_op.path)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut listed = client
+            .get_data_files_by_table_name(&table_info.table_name, "default")
+            .await
+            .unwrap();
+        streamed.sort();
+        listed.sort();
+        assert_eq!(streamed.len(), 5);
+        assert_eq!(streamed, listed);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_all_table_info_by_domain() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let domain = format!("tenant_{}", suffix);
+        let namespace = format!("domain_ns_{}", suffix);
+        client
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: String::new(),
+                domain: domain.clone(),
+            })
+            .await
+            .unwrap();
+        let table_info = |namespace: &str, domain: &str| {
+            let table_name = format!("by_domain_{}", uuid::Uuid::new_v4().simple());
+            TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: namespace.to_string(),
+                table_name: table_name.clone(),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                table_schema: "{}".to_string(),
+                properties: "{}".to_string(),
+                partitions: ";".to_string(),
+                domain: domain.to_string(),
+            }
+        };
+        // the first one inherits the domain of its namespace
+        let mut tenant_tables = [table_info(&namespace, ""), table_info("default", &domain)];
+        let other = table_info("default", &format!("other_{}", suffix));
+        for table_info in tenant_tables.iter().chain([&other]) {
+            client.create_table(table_info.clone()).await.unwrap();
+        }
+        tenant_tables[0].domain = domain.clone();
+        tenant_tables.sort_by(|a, b| a.table_id.cmp(&b.table_id));
+
+        let listed = client.get_all_table_info_by_domain(&domain).await.unwrap();
+        assert_eq!(
+            listed
+                .iter()
+                .map(|table_info| table_info.table_id.as_str())
+                .collect::<Vec<_>>(),
+            tenant_tables
+                .iter()
+                .map(|table_info| table_info.table_id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(listed.iter().all(|table_info| table_info.domain == domain));
+        let page = client
+            .get_table_info_page_by_domain(&domain, Some(&tenant_tables[0].table_id), 1)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].table_id, tenant_tables[1].table_id);
+
+        // commits are recorded in the domain of the table, not in the default domain of the client
+        let inherited = tenant_tables
+            .iter()
+            .find(|table_info| table_info.table_namespace == namespace)
+            .unwrap();
+        assert_eq!(client.get_table_domain(&inherited.table_id).await.unwrap(), domain);
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: inherited.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: vec![entity::DataFileOp {
+                    path: "file:///tmp/lakesoul/by_domain.parquet".to_string(),
+                    file_op: FileOp::Add as i32,
+                    ..Default::default()
+                }],
+                commit_op: CommitOp::AppendCommit as i32,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let partitions = client.get_all_partition_info(&inherited.table_id).await.unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].domain, domain);
+
+        for table_info in tenant_tables.iter().chain([&other]) {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+        client.delete_namespace_by_namespace(&namespace).await.unwrap();
+        assert!(client.get_all_table_info_by_domain(&domain).await.unwrap().is_empty());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_analyze_after_bulk_commit() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_analyze_after_commit_rows(10)
+            .build()
+            .await
+            .unwrap();
+        let table_name = format!("bulk_commit_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "p;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let commit = |partitions: std::ops::Range<usize>| {
+            client.register_external_files_by_partition(
+                &table_info.table_id,
+                partitions
+                    .map(|i| {
+                        (
+                            format!("p={}", i),
+                            vec![ExternalFile {
+                                path: format!("{}/p={}/part-0.parquet", table_info.table_path, i),
+                                size: 1024,
+                            }],
+                        )
+                    })
+                    .collect(),
+                false,
+            )
+        };
+
+        // the threshold is not exceeded
+        commit(0..10).await.unwrap();
+        assert_eq!(client.analyzes_after_commit(), 0);
+
+        commit(10..30).await.unwrap();
+        for _ in 0..100 {
+            if client.analyzes_after_commit() > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(client.analyzes_after_commit(), 1);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_list_tables_by_size() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let small = create_test_table(&client, "by_size_small").await;
+        let large = create_test_table(&client, "by_size_large").await;
+        for (table_info, sizes) in [(&small, vec![10, 20]), (&large, vec![1000])] {
+            let files = sizes
+                .into_iter()
+                .enumerate()
+                .map(|(i, size)| ExternalFile {
+                    path: format!("{}/part-{}.parquet", table_info.table_path, i),
+                    size,
+                })
+                .collect();
+            client
+                .register_external_files(&table_info.table_id, files, "-5")
+                .await
+                .unwrap();
+        }
+
+        let tables = client.list_tables_by_size(i64::MAX).await.unwrap();
+        assert!(tables.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let position = |table_info: &TableInfo| {
+            tables
+                .iter()
+                .position(|(listed, _)| listed.table_id == table_info.table_id)
+                .unwrap()
+        };
+        assert!(position(&large) < position(&small));
+        assert_eq!(tables[position(&large)].1, 1000);
+        assert_eq!(tables[position(&small)].1, 30);
+        assert_eq!(tables[position(&small)].0, small);
+        assert_eq!(client.list_tables_by_size(1).await.unwrap().len(), 1);
+        assert!(matches!(
+            client.list_tables_by_size(-1).await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        for table_info in [&small, &large] {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_normalize_file_paths() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_normalize_file_paths(PathMode::MakeRelative)
+            .build()
+            .await
+            .unwrap();
+        let raw_client = MetaDataClient::from_env().await.unwrap();
+        let commit = |table_id: &str, path: &str| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: vec![entity::DataFileOp {
+                    path: path.to_string(),
+                    file_op: FileOp::Add as i32,
+                    size: 1,
+                    ..Default::default()
+                }],
+                commit_op: CommitOp::AppendCommit as i32,
+                ..Default::default()
+            }
+        };
+        for root in [
+            "s3://bucket/warehouse",
+            "hdfs://namenode:8020/warehouse",
+            "file:/tmp/lakesoul",
+        ] {
+            let table_name = format!("path mode ü {}", uuid::Uuid::new_v4().simple());
+            let table_info = TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: "default".to_string(),
+                table_name: table_name.clone(),
+                table_path: format!("{}/{}", root, table_name),
+                table_schema: "{}".to_string(),
+                properties: "{}".to_string(),
+                partitions: ";".to_string(),
+                domain: "public".to_string(),
+            };
+            client.create_table(table_info.clone()).await.unwrap();
+            let table_id = table_info.table_id.as_str();
+            let path = format!("{}/with space/数据 ø.parquet", table_info.table_path);
+            client.commit_data_commit_info(commit(table_id, &path)).await.unwrap();
+
+            let qualified = normalize_file_path("", &path);
+            assert_eq!(
+                client
+                    .get_data_files_by_table_name(&table_name, "default")
+                    .await
+                    .unwrap(),
+                vec![qualified.clone()]
+            );
+            let file_ops = client.file_op_stream(table_id).try_collect::<Vec<_>>().await.unwrap();
+            assert_eq!(file_ops[0].path, qualified);
+            assert_eq!(
+                client
+                    .get_data_files_by_table_path(&table_info.table_path, vec![])
+                    .await
+                    .unwrap(),
+                vec![qualified.clone()]
+            );
+            assert_eq!(
+                client.sample_file_paths(table_id, 10).await.unwrap(),
+                vec![qualified.clone()]
+            );
+            let partition_info = client.get_all_partition_info(table_id).await.unwrap().remove(0);
+            let paths = |data_commit_info_list: Vec<DataCommitInfo>| {
+                data_commit_info_list
+                    .into_iter()
+                    .flat_map(|data_commit_info| data_commit_info.file_ops)
+                    .map(|file_op| file_op.path)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                paths(
+                    client
+                        .get_data_commit_info_with_stats_of_single_partition(&partition_info)
+                        .await
+                        .unwrap()
+                ),
+                vec![qualified.clone()]
+            );
+            let streamed = client
+                .stream_data_commit_info_of_single_partition(&partition_info)
+                .await
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            assert_eq!(paths(streamed), vec![qualified.clone()]);
+            let read_snapshot = client.begin_read_snapshot().await.unwrap();
+            assert_eq!(
+                read_snapshot
+                    .get_data_files_of_single_partition(&partition_info)
+                    .await
+                    .unwrap(),
+                vec![qualified.clone()]
+            );
+            let split_desc_array = crate::transfusion::split_desc_array(
+                raw_client.client.lock().await.deref(),
+                &mut crate::PreparedStatementMap::new(),
+                &table_name,
+                "default",
+            )
+            .await
+            .unwrap();
+            assert_eq!(split_desc_array.0[0].file_paths, vec![qualified.clone()]);
+            // stored relative to the table path
+            assert_eq!(
+                raw_client
+                    .get_data_files_by_table_name(&table_name, "default")
+                    .await
+                    .unwrap(),
+                vec!["with space/数据 ø.parquet".to_string()]
+            );
+
+            let other_file_system = if root.starts_with("file:") {
+                "s3://bucket/part-0.parquet"
+            } else {
+                "file:///tmp/part-0.parquet"
+            };
+            for path in ["", other_file_system] {
+                assert!(matches!(
+                    client.commit_data_commit_info(commit(table_id, path)).await,
+                    Err(LakeSoulMetaDataError::InvalidInput(_))
+                ));
+            }
+            client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_hooks() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "commit_hooks").await;
+        let committed = Arc::new(std::sync::Mutex::new(vec![]));
+        client.register_commit_hook(Arc::new(|_: &MetaInfo, _: CommitOp| panic!("broken hook")));
+        {
+            let committed = committed.clone();
+            client.register_commit_hook(Arc::new(move |meta_info: &MetaInfo, commit_op: CommitOp| {
+                committed
+                    .lock()
+                    .unwrap()
+                    .push((meta_info.table_info.as_ref().unwrap().table_id.clone(), commit_op))
+            }));
+        }
+
+        let commit = |commit_op: CommitOp| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_info.table_id.clone(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: commit_op as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            }
+        };
+        client
+            .commit_data_commit_info(commit(CommitOp::AppendCommit))
+            .await
+            .unwrap();
+        client
+            .commit_data_commit_info(commit(CommitOp::MergeCommit))
+            .await
+            .unwrap();
+        // the panicking hook neither failed the commits nor kept the other hook from running
+        assert_eq!(
+            *committed.lock().unwrap(),
+            [
+                (table_info.table_id.clone(), CommitOp::AppendCommit),
+                (table_info.table_id.clone(), CommitOp::MergeCommit),
+            ]
+        );
+        let partitions = client.get_all_partition_info(&table_info.table_id).await.unwrap();
+        assert_eq!(partitions.iter().map(|partition| partition.version).max(), Some(1));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_strict_table_properties() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_strict_table_properties(true)
+            .build()
+            .await
+            .unwrap();
+        let table_info = |properties: &str| {
+            let table_name = format!("strict_properties_{}", uuid::Uuid::new_v4().simple());
+            TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: "default".to_string(),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                table_name,
+                table_schema: "{}".to_string(),
+                properties: properties.to_string(),
+                partitions: ";".to_string(),
+                domain: "public".to_string(),
+            }
+        };
+
+        let typo = table_info(r#"{"hashBucketNumb":"4"}"#);
+        assert!(matches!(
+            client.create_table(typo.clone()).await,
+            Err(LakeSoulMetaDataError::InvalidInput(message)) if message.contains("did you mean 'hashBucketNum'")
+        ));
+        assert!(matches!(
+            client.get_table_info_by_table_id(&typo.table_id).await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        let table_info = table_info(r#"{"hashBucketNum":"4","spark.custom.option":"x"}"#);
+        client.create_table(table_info.clone()).await.unwrap();
+        assert!(matches!(
+            client
+                .update_table_properties(&table_info.table_id, r#"{"hashBucketNum":"0"}"#)
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        client
+            .update_table_properties(&table_info.table_id, r#"{"hashBucketNum":"8","compaction.ttl":"3"}"#)
+            .await
+            .unwrap();
+
+        // without the strict flag, the issues are only logged
+        let lenient = MetaDataClient::from_env().await.unwrap();
+        lenient
+            .update_table_properties(&table_info.table_id, r#"{"hashBucketNum":"8","compaction_ttl":"3"}"#)
+            .await
+            .unwrap();
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_drop_all_tables_in_namespace() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let namespace = format!("drop_all_ns_{}", uuid::Uuid::new_v4().simple());
+        client
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: "{}".to_string(),
+                comment: "".to_string(),
+                domain: "public".to_string(),
+            })
+            .await
+            .unwrap();
+        for _ in 0..5 {
+            let table_name = format!("drop_all_{}", uuid::Uuid::new_v4().simple());
+            let table_id = format!("table_{}", uuid::Uuid::new_v4());
+            client
+                .create_table(TableInfo {
+                    table_id: table_id.clone(),
+                    table_namespace: namespace.clone(),
+                    table_name: table_name.clone(),
+                    table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                    table_schema: "{}".to_string(),
+                    properties: "{}".to_string(),
+                    partitions: ";".to_string(),
+                    domain: "public".to_string(),
+                })
+                .await
+                .unwrap();
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id,
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let reported = Arc::new(std::sync::Mutex::new(vec![]));
+        let progress = {
+            let reported = reported.clone();
+            Box::new(move |progress: DropProgress| reported.lock().unwrap().push(progress))
+        };
+        let summary = client
+            .drop_all_tables_in_namespace(&namespace, 3, Some(progress))
+            .await
+            .unwrap();
+        assert_eq!(summary.dropped.len(), 5);
+        assert!(summary.failed.is_empty());
+        let reported = reported.lock().unwrap().clone();
+        assert_eq!(
+            reported.iter().map(|progress| progress.done).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert!(reported.iter().all(|progress| progress.dropped && progress.total == 5));
+        assert!(client
+            .get_all_table_name_id_by_namespace(&namespace)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(client.get_table_info_by_table_id(&reported[0].table_id).await.is_err());
+
+        let summary = client.drop_all_tables_in_namespace(&namespace, 3, None).await.unwrap();
+        assert!(summary.dropped.is_empty() && summary.failed.is_empty());
+        client.delete_namespace_by_namespace(&namespace).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_recent_partition_versions() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("recent_versions_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let table_id = table_info.table_id.as_str();
+
+        for partition_desc in ["date=1", "date=2", "date=1", "date=3"] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: partition_desc.to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            // commit timestamps are in milliseconds
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let versions = |partitions: Vec<PartitionInfo>| {
+            partitions
+                .into_iter()
+                .map(|partition_info| (partition_info.partition_desc, partition_info.version))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            versions(client.get_recent_partition_versions(table_id, 3).await.unwrap()),
+            [
+                ("date=3".to_string(), 0),
+                ("date=1".to_string(), 1),
+                ("date=2".to_string(), 0)
+            ]
+        );
+        assert_eq!(
+            client.get_recent_partition_versions(table_id, 10).await.unwrap().len(),
+            4
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_check_table_consistency() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "consistency").await;
+        let table_id = table_info.table_id.as_str();
+        for _ in 0..3 {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: vec![entity::DataFileOp {
+                        path: format!("{}/{}.parquet", table_info.table_path, uuid::Uuid::new_v4()),
+                        file_op: FileOp::Add as i32,
+                        ..Default::default()
+                    }],
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let report = client.check_table_consistency(table_id).await.unwrap();
+        assert!(report.is_consistent(), "{:?}", report);
+
+        let missing = uuid::Uuid::new_v4();
+        client
+            .client
+            .lock()
+            .await
+            .execute(
+                "update partition_info set snapshot = array_append(snapshot, $2::UUID)
+                where table_id = $1::TEXT and version = 2",
+                &[&table_id, &missing],
+            )
+            .await
+            .unwrap();
+        client
+            .client
+            .lock()
+            .await
+            .execute(
+                "delete from partition_info where table_id = $1::TEXT and version = 1",
+                &[&table_id],
+            )
+            .await
+            .unwrap();
+        let report = client.check_table_consistency_with_fix(table_id, true).await.unwrap();
+        assert_eq!(
+            report.violations,
+            [
+                ConsistencyViolation::VersionGap {
+                    partition_desc: "-5".to_string(),
+                    from: 0,
+                    to: 2,
+                },
+                ConsistencyViolation::MissingCommit {
+                    partition_desc: "-5".to_string(),
+                    version: 2,
+                    commit_id: missing.to_string(),
+                },
+            ]
+        );
+        assert_eq!(report.fixed_versions, 1);
+        let report = client.check_table_consistency(table_id).await.unwrap();
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.fixed_versions, 0);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_validate_schema_against() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "validate_schema").await;
+        let table_id = table_info.table_id.as_str();
+        let table_schema = r#"{"type":"struct","fields":[
+            {"name":"id","type":"long","nullable":false,"metadata":{}},
+            {"name":"name","type":"string","nullable":true,"metadata":{}}]}"#;
+        client.update_table_schema(table_id, table_schema).await.unwrap();
+
+        assert!(
+            client
+                .validate_schema_against(table_id, table_schema)
+                .await
+                .unwrap()
+                .matches
+        );
+        let candidate_schema = r#"{"type":"struct","fields":[
+            {"name":"id","type":"long","nullable":false,"metadata":{}},
+            {"name":"name","type":"integer","nullable":true,"metadata":{}}]}"#;
+        let validation = client
+            .validate_schema_against(table_id, candidate_schema)
+            .await
+            .unwrap();
+        assert!(!validation.matches && !validation.compatible_subset);
+        assert_eq!(
+            validation.mismatches,
+            [crate::FieldMismatch::TypeMismatch {
+                field: "name".to_string(),
+                table_type: "string".to_string(),
+                candidate_type: "integer".to_string(),
+            }]
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_register_external_files() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "external_files").await;
+        let table_id = table_info.table_id.as_str();
+        let external_file = |path: String| ExternalFile { path, size: 1024 };
+        let outside = vec![external_file("s3://bucket/elsewhere/part-0.parquet".to_string())];
+        assert!(matches!(
+            client.register_external_files(table_id, outside.clone(), "-5").await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        let escaping = vec![external_file(format!("{}/../part-0.parquet", table_info.table_path))];
+        assert!(matches!(
+            client.register_external_files(table_id, escaping, "-5").await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        let files = (0..3)
+            .map(|i| external_file(format!("{}/part-{}.parquet", table_info.table_path, i)))
+            .collect::<Vec<_>>();
+        client
+            .register_external_files(table_id, files.clone(), "-5")
+            .await
+            .unwrap();
+        client
+            .register_external_files_by_partition(table_id, vec![("-5".to_string(), outside.clone())], true)
+            .await
+            .unwrap();
+
+        let partitions = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].version, 1);
+        let data_files = client.get_data_files_of_single_partition(&partitions[0]).await.unwrap();
+        let expected = files
+            .iter()
+            .chain(&outside)
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(data_files, expected);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_lock_partition_for_compaction() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "compaction_lock").await;
+        let table_id = table_info.table_id.as_str();
+
+        let attempts =
+            futures::future::join_all((0..4).map(|_| client.lock_partition_for_compaction(table_id, "-5"))).await;
+        assert_eq!(attempts.iter().filter(|attempt| attempt.is_ok()).count(), 1);
+        assert!(attempts
+            .iter()
+            .filter_map(|attempt| attempt.as_ref().err())
+            .all(|err| matches!(err, LakeSoulMetaDataError::Locked(_))));
+        let lock = attempts.into_iter().find_map(|attempt| attempt.ok()).unwrap();
+
+        let waiting = Arc::new(
+            MetaDataClientBuilder::from_env()
+                .unwrap()
+                .with_compaction_lock_wait(true)
+                .build()
+                .await
+                .unwrap(),
+        );
+        let waiter = {
+            let waiting = waiting.clone();
+            let table_id = table_id.to_string();
+            tokio::spawn(async move { waiting.lock_partition_for_compaction(&table_id, "-5").await })
+        };
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiter.is_finished());
+        lock.release().await.unwrap();
+        let lock = waiter.await.unwrap().unwrap();
+        assert!(matches!(
+            client.lock_partition_for_compaction(table_id, "-5").await,
+            Err(LakeSoulMetaDataError::Locked(_))
+        ));
+        lock.release().await.unwrap();
+        client
+            .lock_partition_for_compaction(table_id, "-5")
+            .await
+            .unwrap()
+            .release()
+            .await
+            .unwrap();
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_create_table_with_namespace_defaults() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let namespace = format!("table_defaults_{}", uuid::Uuid::new_v4().simple());
+        client
+            .create_namespace(Namespace {
+                namespace: namespace.clone(),
+                properties: serde_json::json!({
+                    "table.default.hashBucketNum": "4",
+                    "table.default.lakesoul_cdc_change_column": "op",
+                    "owner": "platform",
+                })
+                .to_string(),
+                comment: String::new(),
+                domain: "public".to_string(),
+            })
+            .await
+            .unwrap();
+        let table_info = |namespace: &str, properties: &str| {
+            let table_name = format!("table_defaults_{}", uuid::Uuid::new_v4().simple());
+            TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: namespace.to_string(),
+                table_name: table_name.clone(),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                table_schema: "{}".to_string(),
+                properties: properties.to_string(),
+                partitions: ";".to_string(),
+                domain: "public".to_string(),
+            }
+        };
+
+        let inheriting = table_info(&namespace, r#"{"hashBucketNum":"8"}"#);
+        let outcome = client.create_table_with_outcome(inheriting.clone()).await.unwrap();
+        assert!(outcome.created);
+        let properties = serde_json::from_str::<serde_json::Value>(&outcome.properties).unwrap();
+        assert_eq!(
+            properties,
+            serde_json::json!({"hashBucketNum": "8", "lakesoul_cdc_change_column": "op"})
+        );
+        assert_eq!(
+            client
+                .get_table_info_by_table_id(&inheriting.table_id)
+                .await
+                .unwrap()
+                .properties,
+            outcome.properties
+        );
+        let outcome = client
+            .create_table_if_not_exists(table_info(&namespace, "{}"))
+            .await
+            .unwrap();
+        assert!(outcome.created);
+        let existing = client
+            .create_table_if_not_exists(TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                properties: "{}".to_string(),
+                ..inheriting.clone()
+            })
+            .await
+            .unwrap();
+        assert!(!existing.created);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&existing.properties).unwrap(),
+            properties
+        );
+
+        // without defaults the properties are kept verbatim
+        let plain = table_info("default", r#"{"hashBucketNum":"2"}"#);
+        let outcome = client.create_table_with_outcome(plain.clone()).await.unwrap();
+        assert_eq!(outcome.properties, r#"{"hashBucketNum":"2"}"#);
+
+        client.delete_table_by_table_info_cascade(&plain).await.unwrap();
+        let summary = client.drop_all_tables_in_namespace(&namespace, 1, None).await.unwrap();
+        assert_eq!(summary.dropped.len(), 2);
+        client.delete_namespace_by_namespace(&namespace).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_partitions_as_json() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "partitions_json").await;
+        let table_id = table_info.table_id.as_str();
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: CommitOp::AppendCommit as i32,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let partitions: serde_json::Value =
+            serde_json::from_str(&client.get_partitions_as_json(table_id).await.unwrap()).unwrap();
+        let partitions = partitions.as_array().unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0]["partition_desc"], "-5");
+        assert_eq!(partitions[0]["commit_op"], "AppendCommit");
+        assert_eq!(
+            partitions[0]["snapshot"],
+            serde_json::json!([uuid::Uuid::from_u64_pair(high, low).to_string()])
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_table_infos() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_table_cache(Duration::from_secs(60))
+            .build()
+            .await
+            .unwrap();
+        let mut tables = vec![];
+        for _ in 0..50 {
+            tables.push(create_test_table(&client, "table_infos").await);
+        }
+        let mut names = tables
+            .iter()
+            .map(|table_info| (table_info.table_name.clone(), table_info.table_namespace.clone()))
+            .collect::<Vec<_>>();
+        names.push(("missing_table".to_string(), "default".to_string()));
+        names.push((tables[0].table_name.clone(), String::new()));
+
+        let table_infos = client.get_table_infos(&names).await.unwrap();
+        let cached = client.get_table_infos(&names).await.unwrap();
+        assert_eq!(table_infos.len(), 51);
+        assert_eq!(cached, table_infos);
+        assert!(!table_infos.contains_key(&("missing_table".to_string(), "default".to_string())));
+        assert_eq!(table_infos[&(tables[0].table_name.clone(), String::new())], tables[0]);
+        for table_info in &tables {
+            assert_eq!(
+                table_infos[&(table_info.table_name.clone(), table_info.table_namespace.clone())],
+                *table_info
+            );
+        }
+        assert!(client.get_table_infos(&[]).await.unwrap().is_empty());
+
+        for table_info in &tables {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rollback_partition() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "rollback").await;
+        let table_id = table_info.table_id.as_str();
+        for _ in 0..3 {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            client.rollback_partition(table_id, "-5", 7).await,
+            Err(LakeSoulMetaDataError::NotFound(_))
+        ));
+
+        client.rollback_partition(table_id, "-5", 1).await.unwrap();
+        let versions = client.get_recent_partition_versions(table_id, 10).await.unwrap();
+        let version = |version: i32| {
+            versions
+                .iter()
+                .find(|partition_info| partition_info.version == version)
+                .unwrap()
+        };
+        assert_eq!(version(3).commit_op(), CommitOp::RollbackCommit);
+        assert_eq!(version(3).snapshot, version(1).snapshot);
+        assert_eq!(version(1).snapshot.len(), 2);
+        assert_eq!(version(2).snapshot.len(), 3);
+        let head = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(head[0].version, 3);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_duplicated_snapshot_commits() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "duplicated_snapshot").await;
+        let table_id = table_info.table_id.as_str();
+        let commit_id = uuid::Uuid::new_v4();
+        // a version written with a duplicated commit id before snapshots were checked
+        client
+            .client
+            .lock()
+            .await
+            .execute(
+                "insert into partition_info(table_id, partition_desc, version, commit_op, snapshot, expression, domain)
+                values ($1::TEXT, '-5', 0, 'AppendCommit', $2::_UUID, '', 'public')",
+                &[&table_id, &vec![commit_id, commit_id]],
+            )
+            .await
+            .unwrap();
+        let partitions = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(partitions[0].snapshot.len(), 1);
+
+        // committing the same commit again is a retry, which writes no version listing it twice
+        let (high, low) = commit_id.as_u64_pair();
+        let meta_info = |snapshot: Vec<entity::Uuid>| MetaInfo {
+            table_info: Some(table_info.clone()),
+            list_partition: vec![PartitionInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                snapshot,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        client
+            .commit_data(meta_info(vec![entity::Uuid { high, low }]), CommitOp::AppendCommit)
+            .await
+            .unwrap();
+        let partitions = client.get_all_partition_info(table_id).await.unwrap();
+        assert_eq!(partitions[0].version, 0);
+        assert_eq!(partitions[0].snapshot, vec![entity::Uuid { high, low }]);
+        assert!(matches!(
+            client
+                .commit_data(
+                    meta_info(vec![entity::Uuid { high, low }, entity::Uuid { high, low }]),
+                    CommitOp::RollbackCommit
+                )
+                .await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_commit_data_partial() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("partial_commit_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: "date;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        let table_id = table_info.table_id.as_str();
+        let partition = |partition_desc: &str| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            PartitionInfo {
+                table_id: table_id.to_string(),
+                partition_desc: partition_desc.to_string(),
+                snapshot: vec![entity::Uuid { high, low }],
+                ..Default::default()
+            }
+        };
+        let meta_info = |list_partition: Vec<PartitionInfo>, read_partition_info: Vec<PartitionInfo>| MetaInfo {
+            table_info: Some(table_info.clone()),
+            list_partition,
+            read_partition_info,
+        };
+        client
+            .commit_data(
+                meta_info(vec![partition("date=1"), partition("date=2")], vec![]),
+                CommitOp::AppendCommit,
+            )
+            .await
+            .unwrap();
+        let read = client.get_all_partition_info(table_id).await.unwrap();
+
+        // another writer commits to date=2 after it has been read
+        client
+            .commit_data(meta_info(vec![partition("date=2")], vec![]), CommitOp::AppendCommit)
+            .await
+            .unwrap();
+        let report = client
+            .commit_data_partial(
+                meta_info(vec![partition("date=1"), partition("date=2")], read),
+                CommitOp::AppendCommit,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            PartialCommitReport {
+                committed: vec!["date=1".to_string()],
+                conflicted: vec!["date=2".to_string()],
+            }
+        );
+        let versions = client
+            .get_all_partition_info(table_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|partition_info| (partition_info.partition_desc, partition_info.version))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(versions["date=1"], 1);
+        assert_eq!(versions["date=2"], 1);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_secondary_failover() {
+        // the primary serves as its own secondary, whose reads are stale as long as the primary is down
+        let builder = MetaDataClientBuilder::from_env().unwrap();
+        let secondary_config = builder.config.clone();
+        let client = builder
+            .with_secondary_config(secondary_config)
+            .with_failback_interval(Duration::from_secs(2))
+            .build()
+            .await
+            .unwrap();
+        let table_info = create_test_table(&client, "failover").await;
+        assert!(!client.is_serving_stale_reads());
+
+        // simulate an outage of the primary by terminating its connection
+        let pid = client
+            .client
+            .lock()
+            .await
+            .query_one("select pg_backend_pid()", &[])
+            .await
+            .unwrap()
+            .get::<_, i32>(0);
+        let admin = MetaDataClient::from_env().await.unwrap();
+        admin
+            .client
+            .lock()
+            .await
+            .execute("select pg_terminate_backend($1)", &[&pid])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stale = client.get_table_info_by_table_id(&table_info.table_id).await.unwrap();
+        assert_eq!(stale, table_info);
+        assert!(client.is_serving_stale_reads());
+        // writes require the primary
+        assert!(client
+            .update_table_properties(&table_info.table_id, r#"{"k":"v"}"#)
+            .await
+            .is_err());
+
+        // the health check reconnects to the primary
+        for _ in 0..100 {
+            if !client.is_serving_stale_reads() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(!client.is_serving_stale_reads());
+        client
+            .update_table_properties(&table_info.table_id, r#"{"k":"v"}"#)
+            .await
+            .unwrap();
+
+        admin.delete_table_by_table_info_cascade(&table_info).await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_trace_propagation() {
+        let application_name = |client: &MetaDataClient| {
+            let client = client.client.clone();
+            async move {
+                client
+                    .lock()
+                    .await
+                    .query_one("select current_setting('application_name')", &[])
+                    .await
+                    .unwrap()
+                    .get::<_, String>(0)
+            }
+        };
+        let context =
+            TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_trace_propagation(TracePropagation::ApplicationName)
+            .build()
+            .await
+            .unwrap();
+        let untraced = application_name(&client).await;
+        context.clone().scope(client.get_all_namespace()).await.unwrap();
+        let traced = application_name(&client).await;
+        assert!(traced.ends_with(&context.traceparent()), "{}", traced);
+        assert!(traced.len() <= 63);
+        // operations outside of the trace untag the connection
+        client.get_all_namespace().await.unwrap();
+        assert_eq!(application_name(&client).await, untraced);
+
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_trace_propagation(TracePropagation::SqlComment)
+            .build()
+            .await
+            .unwrap();
+        context.clone().scope(client.get_all_namespace()).await.unwrap();
+        assert_eq!(
+            client.prepared.lock().await.context.statement_comment,
+            Some(format!("traceparent='{}'", context.traceparent()))
+        );
+        client.get_all_namespace().await.unwrap();
+        assert_eq!(client.prepared.lock().await.context.statement_comment, None);
+
+        // disabled by default, when the connection is left as is
+        let client = MetaDataClient::from_env().await.unwrap();
+        let untraced = application_name(&client).await;
+        context.scope(client.get_all_namespace()).await.unwrap();
+        assert_eq!(application_name(&client).await, untraced);
+        assert_eq!(client.prepared.lock().await.context.statement_comment, None);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_list_domains() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let mut table_infos = vec![];
+        for tenant in ["tenant_a", "tenant_b"] {
+            let table_name = format!("list_domains_{}_{}", tenant, suffix);
+            let table_info = TableInfo {
+                table_id: format!("table_{}", uuid::Uuid::new_v4()),
+                table_namespace: "default".to_string(),
+                table_name: table_name.clone(),
+                table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+                table_schema: "{}".to_string(),
+                properties: "{}".to_string(),
+                partitions: ";".to_string(),
+                domain: format!("{}_{}", tenant, suffix),
+            };
+            client.create_table(table_info.clone()).await.unwrap();
+            table_infos.push(table_info);
+        }
+
+        let domains = client.list_domains().await.unwrap();
+        for table_info in &table_infos {
+            assert_eq!(domains.iter().filter(|domain| **domain == table_info.domain).count(), 1);
+        }
+        assert!(domains.windows(2).all(|pair| pair[0] < pair[1]));
+
+        for table_info in &table_infos {
+            client.delete_table_by_table_info_cascade(table_info).await.unwrap();
+        }
+        assert!(!client.list_domains().await.unwrap().contains(&table_infos[0].domain));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_partition_history_and_table_stats() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "describe").await;
+        let table_id = table_info.table_id.as_str();
+        let history = |limit: Option<i64>| {
+            let client = &client;
+            async move {
+                get_partition_history(client.client.lock().await.deref(), table_id, "-5", limit)
+                    .await
+                    .unwrap()
+            }
+        };
+        let stats = || async {
+            get_table_stats(client.client.lock().await.deref(), table_id)
+                .await
+                .unwrap()
+        };
+        let commit = |sizes: Vec<i64>| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: sizes
+                    .into_iter()
+                    .map(|size| entity::DataFileOp {
+                        path: format!("file:///tmp/lakesoul/{}.parquet", uuid::Uuid::new_v4()),
+                        file_op: entity::FileOp::Add as i32,
+                        size,
+                        file_exist_cols: String::new(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
             }
-            _ => {}
         };
-        let table_info = Some(self.get_table_info_by_table_id(table_id).await?);
-        let domain = self.get_table_domain(table_id)?;
-        self.commit_data(
-            MetaInfo {
-                table_info,
-                list_partition: vec![PartitionInfo {
-                    table_id: table_id.clone(),
-                    partition_desc: partition_desc.clone(),
-                    commit_op,
-                    domain,
-                    snapshot: vec![commit_id.clone()],
-                    ..Default::default()
-                }],
+
+        // a table without versions has an empty history rather than none
+        assert!(history(None).await.is_empty());
+        assert_eq!(
+            stats().await,
+            entity::TableStats {
+                table_id: table_id.to_string(),
                 ..Default::default()
-            },
-            CommitOp::try_from(commit_op)
-                .map_err(|_| LakeSoulMetaDataError::Internal("unknown commit_op".to_string()))?,
-        )
-        .await
-    }
+            }
+        );
 
-    pub fn get_table_domain(&self, _table_id: &str) -> Result<String> {
-        // todo: get property table_domain
-        Ok("public".to_string())
+        client.commit_data_commit_info(commit(vec![10, 20])).await.unwrap();
+        client.commit_data_commit_info(commit(vec![30])).await.unwrap();
+        let entries = history(None).await;
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].version > entries[1].version);
+        assert_eq!(entries[0].commit_op, CommitOp::AppendCommit as i32);
+        assert_eq!((entries[0].commit_count, entries[0].added_commit_count), (2, 1));
+        assert_eq!((entries[1].commit_count, entries[1].added_commit_count), (1, 1));
+        assert_eq!(history(Some(1)).await, entries[..1]);
+
+        let table_stats = stats().await;
+        assert_eq!(table_stats.partition_count, 1);
+        assert_eq!((table_stats.file_count, table_stats.total_bytes), (3, 60));
+        assert_eq!(table_stats.last_commit_ts, entries[0].timestamp);
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_all_table_name_id_by_namespace(&self, namespace: &str) -> Result<Vec<TableNameId>> {
-        match self
-            .execute_query(DaoType::ListTableNameByNamespace as i32, namespace.to_string())
+    #[test_log::test(tokio::test)]
+    async fn test_forward_compatibility() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "forward_compat").await;
+        let table_id = table_info.table_id.as_str();
+
+        // a partition version committed by a newer client with a commit op unknown to this one
+        client
+            .client
+            .lock()
             .await
-        {
-            Ok(wrapper) => Ok(wrapper.table_name_id),
-            Err(e) => Err(e),
-        }
-    }
+            .execute(
+                "insert into partition_info(table_id, partition_desc, version, commit_op, snapshot, timestamp, domain)
+                values($1::TEXT, '-5', 0, 'FutureCommit', '{}', 1, 'public')",
+                &[&table_id],
+            )
+            .await
+            .unwrap();
+        let partition_info = client.get_all_partition_info(table_id).await.unwrap().pop().unwrap();
+        assert!(partition_info.commit_op <= UNKNOWN_COMMIT_OP_CODE);
+        assert!(client
+            .compatibility_warnings()
+            .contains(&CompatibilityWarning::UnknownCommitOp("FutureCommit".to_string())));
 
-    pub async fn get_all_namespace(&self) -> Result<Vec<Namespace>> {
-        self.execute_query(DaoType::ListNamespaces as i32, String::new())
+        // copied under its own name rather than dropped or replaced by a known one
+        client
+            .execute_insert(
+                DaoType::InsertPartitionInfo as i32,
+                JniWrapper {
+                    partition_info: vec![PartitionInfo {
+                        version: 1,
+                        ..partition_info.clone()
+                    }],
+                    ..Default::default()
+                },
+            )
             .await
-            .map(|wrapper| wrapper.namespace)
-    }
+            .unwrap();
+        let commit_op = client
+            .client
+            .lock()
+            .await
+            .query_one(
+                "select commit_op from partition_info where table_id = $1::TEXT and version = 1",
+                &[&table_id],
+            )
+            .await
+            .unwrap()
+            .get::<_, String>(0);
+        assert_eq!(commit_op, "FutureCommit");
+        let json = client.get_partitions_as_json(table_id).await.unwrap();
+        assert!(json.contains(r#""commit_op":"FutureCommit""#), "{}", json);
 
-    pub async fn get_namespace_by_namespace(&self, namespace: &str) -> Result<Namespace> {
-        self.execute_query(
-            DaoType::SelectNamespaceByNamespace as i32,
-            [namespace].join(PARAM_DELIM),
+        // listed under its code, and recorded by the client which read it only
+        let other = MetaDataClient::from_env().await.unwrap();
+        let log = other.get_table_operation_log(table_id, 10).await.unwrap();
+        assert_eq!(
+            log.iter().map(|entry| entry.commit_op).collect::<Vec<_>>(),
+            vec![partition_info.commit_op; 2]
+        );
+        assert_eq!(
+            other.get_commit_op_distribution(table_id).await.unwrap(),
+            HashMap::from([(partition_info.commit_op, 2)])
+        );
+        assert_eq!(
+            other.compatibility_warnings(),
+            vec![CompatibilityWarning::UnknownCommitOp("FutureCommit".to_string())]
+        );
+        assert!(MetaDataClient::from_env()
+            .await
+            .unwrap()
+            .compatibility_warnings()
+            .is_empty());
+        // but not committed, its conflicts being resolved by rules this client lacks
+        let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+        let committed = client
+            .commit_data_commit_info(DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                commit_op: partition_info.commit_op,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(
+            matches!(&committed, Err(LakeSoulMetaDataError::InvalidInput(msg)) if msg.contains("FutureCommit")),
+            "{:?}",
+            committed
+        );
+        assert!(client
+            .get_single_data_commit_info(table_id, "-5", &uuid::Uuid::from_u64_pair(high, low).to_string())
+            .await
+            .unwrap()
+            .is_none());
+
+        // property keys of a newer client survive the merge of a property this client sets
+        let properties = r#"{"future.option":{"nested":[1,2.5,"x"]},"future.flag":true}"#;
+        client.update_table_properties(table_id, properties).await.unwrap();
+        client.set_change_tracking_enabled(table_id, true).await.unwrap();
+        let mut merged = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(
+            &client.get_table_info_by_table_id(table_id).await.unwrap().properties,
         )
-        .await
-        .map(|wrapper| wrapper.namespace[0].clone())
+        .unwrap();
+        assert_eq!(merged.remove(CHANGE_TRACKING_ENABLED), Some(serde_json::json!("true")));
+        let expected = serde_json::from_str::<serde_json::Map<_, _>>(properties).unwrap();
+        assert_eq!(merged, expected);
+        assert!(client
+            .compatibility_warnings()
+            .contains(&CompatibilityWarning::UnknownPropertyKey("future.flag".to_string())));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_table_name_id_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableNameId> {
-        match self
-            .execute_query(
-                DaoType::SelectTableNameIdByTableName as i32,
-                [table_name, namespace].join(PARAM_DELIM),
+    #[test_log::test(tokio::test)]
+    async fn test_create_table_if_not_exists() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let other = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("if_not_exists_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: "{}".to_string(),
+            properties: "{}".to_string(),
+            partitions: ";".to_string(),
+            domain: "public".to_string(),
+        };
+
+        // concurrent calls create the table once
+        let (first, second) = tokio::join!(
+            client.create_table_if_not_exists(table_info.clone()),
+            other.create_table_if_not_exists(table_info.clone())
+        );
+        assert!(first.unwrap().created ^ second.unwrap().created);
+        let again = client.create_table_if_not_exists(table_info.clone()).await.unwrap();
+        assert!(!again.created);
+        let created = client.get_table_info_by_table_id(&table_info.table_id).await.unwrap();
+        assert_eq!(created, table_info);
+
+        // a table of another name at the same path is rejected without leaving anything behind
+        let conflicting = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_name: format!("{}_other", table_name),
+            ..table_info.clone()
+        };
+        assert!(matches!(
+            client.create_table_if_not_exists(conflicting.clone()).await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        let leftover = client
+            .client
+            .lock()
+            .await
+            .query_one(
+                "select count(*) from table_name_id where table_name = $1::TEXT",
+                &[&conflicting.table_name],
             )
             .await
-        {
-            Ok(wrapper) => Ok(wrapper.table_name_id[0].clone()),
-            Err(err) => Err(err),
+            .unwrap()
+            .get::<_, i64>(0);
+        assert_eq!(leftover, 0);
+
+        for incomplete in [
+            TableInfo {
+                table_id: String::new(),
+                ..conflicting.clone()
+            },
+            TableInfo {
+                table_name: String::new(),
+                ..conflicting.clone()
+            },
+        ] {
+            assert!(matches!(
+                client.create_table(incomplete.clone()).await,
+                Err(LakeSoulMetaDataError::InvalidInput(_))
+            ));
+            assert!(matches!(
+                client.create_table_if_not_exists(incomplete).await,
+                Err(LakeSoulMetaDataError::InvalidInput(_))
+            ));
         }
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_table_info_by_table_name(&self, table_name: &str, namespace: &str) -> Result<TableInfo> {
-        match self
-            .execute_query(
-                DaoType::SelectTableInfoByTableNameAndNameSpace as i32,
-                [table_name, namespace].join(PARAM_DELIM),
-            )
-            .await
-        {
-            Ok(wrapper) if wrapper.table_info.is_empty() => Err(crate::error::LakeSoulMetaDataError::NotFound(
-                format!("Table '{}' not found", table_name),
-            )),
-            Ok(wrapper) => Ok(wrapper.table_info[0].clone()),
-            Err(err) => Err(err),
+    #[test_log::test(tokio::test)]
+    async fn test_sample_file_paths() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "sample_file_paths").await;
+        let table_id = table_info.table_id.as_str();
+        for _ in 0..2 {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data_commit_info(DataCommitInfo {
+                    table_id: table_id.to_string(),
+                    partition_desc: "-5".to_string(),
+                    commit_id: Some(entity::Uuid { high, low }),
+                    file_ops: (0..3)
+                        .map(|_| entity::DataFileOp {
+                            path: format!("file:///tmp/lakesoul/{}.parquet", uuid::Uuid::new_v4()),
+                            file_op: entity::FileOp::Add as i32,
+                            size: 1,
+                            file_exist_cols: String::new(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    commit_op: CommitOp::AppendCommit as i32,
+                    domain: "public".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
         }
+
+        let all = client.sample_file_paths(table_id, 100).await.unwrap();
+        assert_eq!(all.len(), 6);
+        let sample = client.sample_file_paths(table_id, 4).await.unwrap();
+        assert_eq!(sample.len(), 4);
+        assert!(sample.iter().all(|path| all.contains(path)));
+        assert!(client.sample_file_paths(table_id, 0).await.unwrap().is_empty());
+        assert!(matches!(
+            client.sample_file_paths(table_id, -1).await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_table_info_by_table_path(&self, table_path: &str) -> Result<TableInfo> {
-        match self
-            .execute_query(DaoType::SelectTablePathIdByTablePath as i32, table_path.to_string())
-            .await
-        {
-            Ok(wrapper) if wrapper.table_info.is_empty() => Err(crate::error::LakeSoulMetaDataError::NotFound(
-                format!("Table '{}' not found", table_path),
-            )),
-            Ok(wrapper) => Ok(wrapper.table_info[0].clone()),
-            Err(err) => Err(err),
+    #[test_log::test(tokio::test)]
+    async fn test_compact_partition_snapshot() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "compact_snapshot").await;
+        let table_id = table_info.table_id.as_str();
+        let append = |files: usize| {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client.commit_data_commit_info(DataCommitInfo {
+                table_id: table_id.to_string(),
+                partition_desc: "-5".to_string(),
+                commit_id: Some(entity::Uuid { high, low }),
+                file_ops: (0..files)
+                    .map(|_| entity::DataFileOp {
+                        path: format!("file:///tmp/lakesoul/{}.parquet", uuid::Uuid::new_v4()),
+                        file_op: entity::FileOp::Add as i32,
+                        size: 1,
+                        file_exist_cols: String::new(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                commit_op: CommitOp::AppendCommit as i32,
+                domain: "public".to_string(),
+                ..Default::default()
+            })
+        };
+        let latest = || async { client.get_all_partition_info(table_id).await.unwrap().remove(0) };
+
+        assert_eq!(client.compact_partition_snapshot(table_id, "-5").await.unwrap(), 0);
+        append(1).await.unwrap();
+        assert_eq!(client.compact_partition_snapshot(table_id, "-5").await.unwrap(), 0);
+        for files in [2, 1, 3, 1] {
+            append(files).await.unwrap();
         }
+        let before = latest().await;
+        assert_eq!(before.snapshot.len(), 5);
+        let files = client.get_data_files_of_single_partition(&before).await.unwrap();
+
+        assert_eq!(client.compact_partition_snapshot(table_id, "-5").await.unwrap(), 5);
+        let compacted = latest().await;
+        assert_eq!(compacted.version, before.version + 1);
+        assert_eq!(compacted.commit_op(), CommitOp::CompactionCommit);
+        assert_eq!(compacted.snapshot.len(), 1);
+        let compacted_files = client.get_data_files_of_single_partition(&compacted).await.unwrap();
+        assert_eq!(compacted_files, files);
+        // the earlier versions are still readable
+        assert_eq!(client.get_data_files_of_single_partition(&before).await.unwrap(), files);
+
+        append(2).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            client
+                .compact_partition_snapshot_with_gc(table_id, "-5", true)
+                .await
+                .unwrap(),
+            2
+        );
+        let gc_compacted = latest().await;
+        let gc_compacted_files = client.get_data_files_of_single_partition(&gc_compacted).await.unwrap();
+        assert_eq!(gc_compacted_files.len(), files.len() + 2);
+        let versions = client.get_recent_partition_versions(table_id, 100).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(
+            client.get_partition_commit_counts(table_id).await.unwrap(),
+            HashMap::from([("-5".to_string(), 1)])
+        );
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_table_info_by_table_id(&self, table_id: &str) -> Result<TableInfo> {
-        match self
-            .execute_query(DaoType::SelectTableInfoByTableId as i32, table_id.to_string())
+    #[test_log::test(tokio::test)]
+    async fn test_meta_cleanup_interlock() {
+        let client = MetaDataClientBuilder::from_env()
+            .unwrap()
+            .with_clean_meta_database_pattern("*_staging".to_string())
+            .build()
             .await
-        {
-            Ok(wrapper) => Ok(wrapper.table_info[0].clone()),
-            Err(err) => Err(err),
-        }
+            .unwrap();
+        let table_info = create_test_table(&client, "cleanup_interlock").await;
+        assert!(matches!(
+            client.meta_cleanup().await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+        assert!(client.get_table_info_by_table_id(&table_info.table_id).await.is_ok());
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_table_operation_log() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "operation_log").await;
+        for commit_op in [CommitOp::AppendCommit, CommitOp::MergeCommit] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data(
+                    MetaInfo {
+                        table_info: Some(table_info.clone()),
+                        list_partition: vec![PartitionInfo {
+                            table_id: table_info.table_id.clone(),
+                            partition_desc: "-5".to_string(),
+                            snapshot: vec![entity::Uuid { high, low }],
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                    commit_op,
+                )
+                .await
+                .unwrap();
+        }
 
-    pub async fn get_data_files_by_table_name(
-        &self,
-        table_name: &str,
-        namespace: &str,
-    ) -> Result<Vec<String>> {
-        let table_info = self.get_table_info_by_table_name(table_name, namespace).await?;
-        debug!("table_info: {:?}", table_info);
-        let partition_list = self.get_all_partition_info(table_info.table_id.as_str()).await?;
-        debug!(
-            "{} 's partition_list: {:?}",
-            table_info.table_id.as_str(),
-            partition_list
+        let log = client.get_table_operation_log(&table_info.table_id, 10).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].version, log[0].commit_op()), (1, CommitOp::MergeCommit));
+        assert_eq!((log[1].version, log[1].commit_op()), (0, CommitOp::AppendCommit));
+        assert!(log[0].timestamp >= log[1].timestamp);
+        assert_eq!(
+            client
+                .get_table_operation_log(&table_info.table_id, 1)
+                .await
+                .unwrap()
+                .len(),
+            1
         );
-        self.get_data_files_of_partitions(partition_list).await
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_data_files_of_partitions(
-        &self, 
-        partition_list: Vec<PartitionInfo>, 
-    ) -> Result<Vec<String>> {
-        let mut data_files = Vec::<String>::new();
-        for partition_info in &partition_list {
-            let _data_file_list = self.get_data_files_of_single_partition(partition_info).await?;
-            data_files.extend_from_slice(&_data_file_list);
-            
+    #[test_log::test(tokio::test)]
+    async fn test_estimate_partition_count() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "estimate_count").await;
+        for (partition_desc, commit_op) in [
+            ("-5", CommitOp::AppendCommit),
+            ("-4", CommitOp::AppendCommit),
+            ("-3", CommitOp::AppendCommit),
+            ("-4", CommitOp::MergeCommit),
+        ] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data(
+                    MetaInfo {
+                        table_info: Some(table_info.clone()),
+                        list_partition: vec![PartitionInfo {
+                            table_id: table_info.table_id.clone(),
+                            partition_desc: partition_desc.to_string(),
+                            snapshot: vec![entity::Uuid { high, low }],
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                    commit_op,
+                )
+                .await
+                .unwrap();
         }
-        Ok(data_files)
-
-    }
 
-    pub async fn get_data_files_of_single_partition(
-        &self, 
-        partition_info: &PartitionInfo, 
-    ) -> Result<Vec<String>> {
-        let data_commit_info_list = self.get_data_commit_info_of_single_partition(partition_info).await?;
-        // let data_commit_info_list = Vec::<DataCommitInfo>::new();
-        let data_file_list = data_commit_info_list
-            .iter()
-            .flat_map(|data_commit_info| {
-                data_commit_info
-                    .file_ops
-                    .iter()
-                    .map(|file_op| file_op.path.clone())
-                    .collect::<Vec<String>>()
-            })
-            .collect::<Vec<String>>();
-        Ok(data_file_list)
+        for (filter, expected) in [
+            (PartitionFilter::default(), 3),
+            (
+                PartitionFilter {
+                    partition_descs: vec!["-5".to_string(), "-4".to_string(), "-1".to_string()],
+                    ..Default::default()
+                },
+                2,
+            ),
+            (
+                PartitionFilter {
+                    commit_op: Some(CommitOp::AppendCommit),
+                    ..Default::default()
+                },
+                2,
+            ),
+            (
+                PartitionFilter {
+                    committed_after: Some(i64::MAX),
+                    ..Default::default()
+                },
+                0,
+            ),
+        ] {
+            let partitions = client
+                .get_partitions_by_filter(&table_info.table_id, &filter)
+                .await
+                .unwrap();
+            assert_eq!(partitions.len(), expected, "{:?}", filter);
+            assert_eq!(
+                client
+                    .estimate_partition_count(&table_info.table_id, &filter)
+                    .await
+                    .unwrap(),
+                partitions.len() as i64
+            );
+        }
 
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_partition_filter_coercion() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_name = format!("filter_coercion_{}", uuid::Uuid::new_v4().simple());
+        let table_info = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_namespace: "default".to_string(),
+            table_name: table_name.clone(),
+            table_path: format!("file:///tmp/lakesoul/metadata_client_test/{}", table_name),
+            table_schema: r#"{"type":"struct","fields":[{"name":"date","type":"date","nullable":true,"metadata":{}}]}"#
+                .to_string(),
+            properties: "{}".to_string(),
+            partitions: "date;".to_string(),
+            domain: "public".to_string(),
+        };
+        client.create_table(table_info.clone()).await.unwrap();
+        for partition_desc in ["date=2023-1-1", "date=2023-1-2"] {
+            let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+            client
+                .commit_data(
+                    MetaInfo {
+                        table_info: Some(table_info.clone()),
+                        list_partition: vec![PartitionInfo {
+                            table_id: table_info.table_id.clone(),
+                            partition_desc: partition_desc.to_string(),
+                            snapshot: vec![entity::Uuid { high, low }],
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                    CommitOp::AppendCommit,
+                )
+                .await
+                .unwrap();
+        }
 
-    async fn get_data_commit_info_of_single_partition(
-        &self,
-        partition_info: &PartitionInfo,
-    ) -> Result<Vec<DataCommitInfo>> {
-        let table_id = &partition_info.table_id;
-        let partition_desc = &partition_info.partition_desc;
-        let joined_commit_id = &partition_info
-            .snapshot
-            .iter()
-            .map(|commit_id| format!("{:0>16x}{:0>16x}", commit_id.high, commit_id.low))
-            .collect::<Vec<String>>()
-            .join("");
-        let joined_string = [table_id.as_str(), partition_desc.as_str(), joined_commit_id.as_str()].join(PARAM_DELIM);
-        match self
-            .execute_query(
-                DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList as i32,
-                joined_string,
-            )
+        let filter = PartitionFilter {
+            partition_descs: vec!["date=2023-01-01".to_string()],
+            ..Default::default()
+        };
+        let partitions = client
+            .get_partitions_by_filter(&table_info.table_id, &filter)
             .await
-        {
-            Ok(wrapper) => Ok(wrapper.data_commit_info),
-            Err(e) => Err(e),
-        }
+            .unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].partition_desc, "date=2023-1-1");
+        assert_eq!(
+            client
+                .estimate_partition_count(&table_info.table_id, &filter)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let filter = PartitionFilter {
+            partition_descs: vec!["date=2023-13-01".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            client.get_partitions_by_filter(&table_info.table_id, &filter).await,
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&table_info).await.unwrap();
     }
 
-    pub async fn get_schema_by_table_name(&self, table_name: &str, namespace: &str) -> Result<String> {
-        let table_info = self.get_table_info_by_table_name(table_name, namespace).await?;
-        Ok(table_info.table_schema)
+    #[test_log::test(tokio::test)]
+    async fn test_health_report() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let report = client.health_report().await;
+        assert!(report.connected);
+        assert!(!report.is_degraded());
     }
 
-    pub async fn get_all_partition_info(&self, table_id: &str) -> Result<Vec<PartitionInfo>> {
-        match self
-            .execute_query(DaoType::ListPartitionByTableId as i32, table_id.to_string())
+    #[test_log::test(tokio::test)]
+    async fn test_move_table_to_namespace() {
+        let client = MetaDataClient::from_env().await.unwrap();
+        let table_info = create_test_table(&client, "move_table").await;
+        let new_namespace = format!("move_table_ns_{}", uuid::Uuid::new_v4().simple());
+        client
+            .create_namespace(Namespace {
+                namespace: new_namespace.clone(),
+                properties: "{}".to_string(),
+                comment: "".to_string(),
+                domain: "public".to_string(),
+            })
             .await
-        {
-            Ok(wrapper) => Ok(wrapper.partition_info),
-            Err(e) => Err(e),
-        }
-    }
+            .unwrap();
 
-    pub async fn get_single_data_commit_info(
-        &self,
-        table_id: &str,
-        partition_desc: &str,
-        commit_id: &str,
-    ) -> Result<Option<DataCommitInfo>> {
-        match self
-            .execute_query(
-                DaoType::SelectOneDataCommitInfoByTableIdAndPartitionDescAndCommitId as i32,
-                [table_id, partition_desc, commit_id].join(PARAM_DELIM),
-            )
+        client
+            .move_table_to_namespace(&table_info.table_id, &new_namespace)
             .await
-        {
-            Ok(wrapper) => Ok(if wrapper.data_commit_info.is_empty() {
-                None
-            } else {
-                Some(wrapper.data_commit_info[0].clone())
-            }),
-            Err(e) => Err(e),
-        }
+            .unwrap();
+        let in_namespace = |table_name_ids: Vec<TableNameId>| {
+            table_name_ids
+                .iter()
+                .any(|table_name_id| table_name_id.table_id == table_info.table_id)
+        };
+        assert!(!in_namespace(
+            client.get_all_table_name_id_by_namespace("default").await.unwrap()
+        ));
+        assert!(in_namespace(
+            client.get_all_table_name_id_by_namespace(&new_namespace).await.unwrap()
+        ));
+        let moved = client.get_table_info_by_table_id(&table_info.table_id).await.unwrap();
+        assert_eq!(moved.table_namespace, new_namespace);
+
+        // a second table with the same name cannot be moved into the namespace
+        let duplicated = TableInfo {
+            table_id: format!("table_{}", uuid::Uuid::new_v4()),
+            table_path: format!("{}_duplicated", table_info.table_path),
+            ..table_info.clone()
+        };
+        client.create_table(duplicated.clone()).await.unwrap();
+        assert!(matches!(
+            client
+                .move_table_to_namespace(&duplicated.table_id, &new_namespace)
+                .await,
+            Err(LakeSoulMetaDataError::AlreadyExists(_))
+        ));
+
+        client.delete_table_by_table_info_cascade(&moved).await.unwrap();
+        client.delete_table_by_table_info_cascade(&duplicated).await.unwrap();
+        client.delete_namespace_by_namespace(&new_namespace).await.unwrap();
     }
 
-    pub async fn get_partition_info_by_table_id_and_partition_list(
-        &self,
-        table_id: &str,
-        partition_desc_list: &[String],
-    ) -> Result<Vec<PartitionInfo>> {
-        match self
-            .execute_query(
-                DaoType::ListPartitionDescByTableIdAndParList as i32,
-                [table_id, partition_desc_list.join(PARTITION_DESC_DELIM).as_str()].join(PARAM_DELIM),
-            )
-            .await
-        {
-            Ok(wrapper) => Ok(wrapper.partition_info),
-            Err(e) => Err(e),
+    #[test]
+    fn test_normalize_file_path() {
+        let table_path = "s3://bucket/warehouse/table/";
+        for (path, normalized) in [
+            (
+                "s3://bucket/warehouse/table/part-0.parquet",
+                "s3://bucket/warehouse/table/part-0.parquet",
+            ),
+            ("file:///tmp/a/./b//c.parquet", "file:///tmp/a/b/c.parquet"),
+            ("file:/tmp/a/../c.parquet", "file:///tmp/c.parquet"),
+            ("/tmp/c.parquet", "file:///tmp/c.parquet"),
+            (
+                "range=1/part-0.parquet",
+                "s3://bucket/warehouse/table/range=1/part-0.parquet",
+            ),
+        ] {
+            assert_eq!(normalize_file_path(table_path, path), normalized);
         }
     }
-}
 
-pub fn table_path_id_from_table_info(table_info: &TableInfo) -> TablePathId {
-    TablePathId {
-        table_path: table_info.table_path.clone(),
-        table_id: table_info.table_id.clone(),
-        table_namespace: table_info.table_namespace.clone(),
-        domain: table_info.domain.clone(),
+    #[test]
+    fn test_store_file_path() {
+        for (table_path, path) in [
+            (
+                "s3://bucket/warehouse/table",
+                "s3://bucket/warehouse/table/date=2024-01-01/part 0 ü.parquet",
+            ),
+            (
+                "hdfs://namenode:8020/warehouse/table/",
+                "hdfs://namenode:8020/warehouse/table/数据/part-0.parquet",
+            ),
+            (
+                "file:/tmp/warehouse/table",
+                "file:/tmp/warehouse/table/with space/part-ø.parquet",
+            ),
+        ] {
+            check_file_path(table_path, path).unwrap();
+            let qualified = normalize_file_path("", path);
+            let relative = store_file_path(PathMode::MakeRelative, table_path, path);
+            assert!(!is_absolute_path(&relative));
+            assert_eq!(normalize_file_path(table_path, &relative), qualified);
+            assert_eq!(
+                store_file_path(PathMode::MakeAbsolute, table_path, &relative),
+                qualified
+            );
+            assert_eq!(store_file_path(PathMode::KeepAbsolute, table_path, path), path);
+        }
+        // files outside of the table path stay qualified
+        assert_eq!(
+            store_file_path(
+                PathMode::MakeRelative,
+                "s3://bucket/table",
+                "s3a://other/part-0.parquet"
+            ),
+            "s3a://other/part-0.parquet"
+        );
+        check_file_path("s3://bucket/table", "s3a://other/part-0.parquet").unwrap();
+        check_file_path("s3://bucket/table", "part-0.parquet").unwrap();
+        for path in ["", " ", "file:///tmp/part-0.parquet", "/tmp/part-0.parquet"] {
+            assert!(matches!(
+                check_file_path("s3://bucket/table", path),
+                Err(LakeSoulMetaDataError::InvalidInput(_))
+            ));
+        }
+        assert_eq!(PathMode::try_from("make_relative").unwrap(), PathMode::MakeRelative);
+        assert!(PathMode::try_from("relative").is_err());
     }
-}
 
-pub fn table_name_id_from_table_info(table_info: &TableInfo) -> TableNameId {
-    TableNameId {
-        table_name: table_info.table_name.clone(),
-        table_id: table_info.table_id.clone(),
-        table_namespace: table_info.table_namespace.clone(),
-        domain: table_info.domain.clone(),
+    #[test]
+    fn test_sort_partitions_by_integer_key() {
+        let schema = r#"{"type":"struct","fields":[{"name":"id","type":"integer","nullable":true,"metadata":{}}]}"#;
+        let ordering = PartitionValueOrdering::from_table_schema(schema, "id");
+        assert_eq!(ordering, PartitionValueOrdering::Numeric);
+
+        let mut partition_list = ["id=10", "id=9", "id=100", "id=-1"]
+            .iter()
+            .map(|desc| PartitionInfo {
+                partition_desc: desc.to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        sort_partitions_by_key(&mut partition_list, "id", ordering, true);
+        let sorted = partition_list
+            .iter()
+            .map(|partition_info| partition_info.partition_desc.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, vec!["id=-1", "id=9", "id=10", "id=100"]);
+
+        sort_partitions_by_key(&mut partition_list, "id", ordering, false);
+        assert_eq!(partition_list[0].partition_desc, "id=100");
     }
 }
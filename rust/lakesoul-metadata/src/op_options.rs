@@ -0,0 +1,100 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-call overrides of the retries and the timeout of client operations, e.g. for interactive callers which
+//! would rather fail fast than retry, or bulk jobs wanting more attempts. The options hold for every query an
+//! operation issues while it runs in [`OpOptions::scope`], falling back to the defaults of the client.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+tokio::task_local! {
+    static CURRENT: OpOptions;
+}
+
+/// Options of a single call, see [`crate::MetaDataClient::run_with_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpOptions {
+    /// Attempts of each query, the `max_retry` of the client if None.
+    pub max_retry: Option<usize>,
+    /// Time the whole call may take before failing with Timeout. Statements sent already may still complete on
+    /// the database, so a timed out commit is to be retried rather than assumed not to have happened.
+    pub timeout: Option<Duration>,
+    /// Run each query once whatever `max_retry` is.
+    pub no_retry: bool,
+}
+
+impl OpOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_retry(mut self, max_retry: usize) -> Self {
+        self.max_retry = Some(max_retry);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_no_retry(mut self) -> Self {
+        self.no_retry = true;
+        self
+    }
+
+    /// Attempts of each query of a client configured with `max_retry`.
+    fn attempts(&self, max_retry: usize) -> usize {
+        if self.no_retry {
+            1
+        } else {
+            self.max_retry.unwrap_or(max_retry)
+        }
+    }
+
+    /// Run `f` with these options, failing with Timeout if it takes longer than the timeout.
+    pub(crate) async fn scope<F: Future>(self, f: F) -> Result<F::Output> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, CURRENT.scope(self, f))
+                .await
+                .map_err(|_| LakeSoulMetaDataError::Timeout(format!("operation took longer than {:?}", timeout))),
+            None => Ok(CURRENT.scope(self, f).await),
+        }
+    }
+}
+
+/// Attempts of each query of the running operation, `max_retry` of the client unless overridden.
+pub(crate) fn current_max_retry(max_retry: usize) -> usize {
+    CURRENT
+        .try_with(|options| options.attempts(max_retry))
+        .unwrap_or(max_retry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current_max_retry() {
+        assert_eq!(current_max_retry(3), 3);
+        let options = OpOptions::new().with_max_retry(5);
+        assert_eq!(options.clone().scope(async { current_max_retry(3) }).await.unwrap(), 5);
+        let options = options.with_no_retry();
+        assert_eq!(options.scope(async { current_max_retry(3) }).await.unwrap(), 1);
+        assert_eq!(OpOptions::new().scope(async { current_max_retry(3) }).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let options = OpOptions::new().with_timeout(Duration::from_millis(10));
+        assert!(matches!(
+            options.clone().scope(tokio::time::sleep(Duration::from_secs(10))).await,
+            Err(LakeSoulMetaDataError::Timeout(_))
+        ));
+        assert_eq!(options.scope(async { 1 }).await.unwrap(), 1);
+    }
+}
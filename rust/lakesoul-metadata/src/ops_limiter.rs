@@ -0,0 +1,304 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Caps on the metadata operations of one client, protecting the shared database from runaway callers.
+//! Operations wait for a concurrency slot and a rate limit token, or fail fast with QueueFull if configured so.
+//! Once the limiter is closed new operations fail with ClientClosed, while those in flight can be waited for.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tokio::time::Instant;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+/// Counters of the operations held back by the limits of a client, see [`crate::MetaDataClient::limiter_metrics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LimiterMetrics {
+    /// Operations which had to wait for a concurrency slot or a rate limit token.
+    pub waited_ops: u64,
+    /// Total time spent waiting, in milliseconds.
+    pub wait_time_ms: u64,
+    /// Operations rejected with QueueFull in fail-fast mode.
+    pub rejected_ops: u64,
+}
+
+/// Token bucket refilled at `rate` tokens per second, holding at most one second worth of tokens.
+/// Tokens go negative to hand out reservations, so that waiters are served in order.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(ops_per_sec: u32) -> Self {
+        let rate = ops_per_sec.max(1) as f64;
+        Self {
+            rate,
+            tokens: rate,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.refilled_at).as_secs_f64() * self.rate).min(self.rate);
+        self.refilled_at = now;
+    }
+
+    /// Time to wait until the next token is available, zero if there is one now.
+    fn wait_time(&mut self) -> Duration {
+        self.refill();
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+
+    /// Take a token, returning how long to wait before it may be used.
+    fn reserve(&mut self) -> Duration {
+        let wait_time = self.wait_time();
+        self.tokens -= 1.0;
+        wait_time
+    }
+}
+
+tokio::task_local! {
+    /// Set while an operation admitted by [`OpsLimiter::admit`] runs, its own operations are let through even if
+    /// the limiter is closed meanwhile.
+    static ADMITTED: ();
+}
+
+/// Count of the operations in flight, notifying waiters when it drops to zero.
+#[derive(Debug, Default)]
+struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+/// Operation counted in flight until dropped.
+#[derive(Debug)]
+pub(crate) struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.in_flight.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.in_flight.idle.notify_waiters();
+        }
+    }
+}
+
+/// Slot of a limited operation, released when dropped.
+#[derive(Debug)]
+pub(crate) struct OpsPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+    _in_flight: InFlightGuard,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct OpsLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+    token_bucket: Option<Mutex<TokenBucket>>,
+    fail_fast: bool,
+    closed: AtomicBool,
+    in_flight: Arc<InFlight>,
+    waited_ops: AtomicU64,
+    wait_time_ms: AtomicU64,
+    rejected_ops: AtomicU64,
+}
+
+impl OpsLimiter {
+    /// Limits left as None are unlimited.
+    pub(crate) fn new(max_concurrent_ops: Option<usize>, ops_per_sec: Option<u32>, fail_fast: bool) -> Self {
+        Self {
+            semaphore: max_concurrent_ops.map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            token_bucket: ops_per_sec.map(|rate| Mutex::new(TokenBucket::new(rate))),
+            fail_fast,
+            ..Default::default()
+        }
+    }
+
+    /// Count an operation in flight, failing with ClientClosed once closed unless it is part of an admitted one.
+    pub(crate) fn enter(&self) -> Result<InFlightGuard> {
+        if self.is_closed() && ADMITTED.try_with(|_| ()).is_err() {
+            return Err(LakeSoulMetaDataError::ClientClosed(
+                "client is closed to new operations".to_string(),
+            ));
+        }
+        self.in_flight.count.fetch_add(1, Ordering::SeqCst);
+        Ok(InFlightGuard {
+            in_flight: self.in_flight.clone(),
+        })
+    }
+
+    /// Run `operation`, such as a commit made of several operations, as a whole: it is waited for by
+    /// [`OpsLimiter::drain`] and the operations it issues are let through even if the limiter is closed meanwhile.
+    pub(crate) async fn admit<F: Future>(&self, operation: F) -> Result<F::Output> {
+        let _in_flight = self.enter()?;
+        Ok(ADMITTED.scope((), operation).await)
+    }
+
+    pub(crate) async fn acquire(&self) -> Result<OpsPermit> {
+        let in_flight = self.enter()?;
+        let start = Instant::now();
+        let mut waited = false;
+        let permit = match &self.semaphore {
+            None => None,
+            Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(TryAcquireError::NoPermits) if self.fail_fast => {
+                    return Err(self.reject("max_concurrent_ops reached"));
+                }
+                Err(_) => {
+                    waited = true;
+                    Some(
+                        semaphore
+                            .clone()
+                            .acquire_owned()
+                            .await
+                            .map_err(|e| LakeSoulMetaDataError::Internal(e.to_string()))?,
+                    )
+                }
+            },
+        };
+        if let Some(token_bucket) = &self.token_bucket {
+            let wait_time = {
+                let mut token_bucket = token_bucket.lock().unwrap();
+                if self.fail_fast && !token_bucket.wait_time().is_zero() {
+                    return Err(self.reject("rate limit reached"));
+                }
+                token_bucket.reserve()
+            };
+            if !wait_time.is_zero() {
+                waited = true;
+                tokio::time::sleep(wait_time).await;
+            }
+        }
+        if waited {
+            self.waited_ops.fetch_add(1, Ordering::Relaxed);
+            self.wait_time_ms
+                .fetch_add(start.elapsed().as_millis() as u64, Ordering::Relaxed);
+        }
+        Ok(OpsPermit {
+            _permit: permit,
+            _in_flight: in_flight,
+        })
+    }
+
+    /// Reject new operations from now on, returning whether the limiter was open.
+    pub(crate) fn close(&self) -> bool {
+        !self.closed.swap(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.count.load(Ordering::SeqCst)
+    }
+
+    /// Wait until no operation is in flight, returning false if some still are after `timeout`.
+    pub(crate) async fn drain(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                // created before checking the count, so that a notification in between is not missed
+                let idle = self.in_flight.idle.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    fn reject(&self, reason: &str) -> LakeSoulMetaDataError {
+        self.rejected_ops.fetch_add(1, Ordering::Relaxed);
+        LakeSoulMetaDataError::QueueFull(reason.to_string())
+    }
+
+    pub(crate) fn metrics(&self) -> LimiterMetrics {
+        LimiterMetrics {
+            waited_ops: self.waited_ops.load(Ordering::Relaxed),
+            wait_time_ms: self.wait_time_ms.load(Ordering::Relaxed),
+            rejected_ops: self.rejected_ops.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrency_limit_fail_fast() {
+        let limiter = OpsLimiter::new(Some(1), None, true);
+        let permit = limiter.acquire().await.unwrap();
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(LakeSoulMetaDataError::QueueFull(_))
+        ));
+        drop(permit);
+        assert!(limiter.acquire().await.is_ok());
+        assert_eq!(limiter.metrics().rejected_ops, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_waits() {
+        let limiter = OpsLimiter::new(None, Some(10), false);
+        let start = Instant::now();
+        for _ in 0..12 {
+            limiter.acquire().await.unwrap();
+        }
+        // the first second worth of tokens is free, the next two are 100ms apart
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        let metrics = limiter.metrics();
+        assert!(metrics.waited_ops >= 2);
+        assert_eq!(metrics.rejected_ops, 0);
+    }
+
+    #[tokio::test]
+    async fn test_close_and_drain() {
+        let limiter = Arc::new(OpsLimiter::new(None, None, false));
+        let permit = limiter.acquire().await.unwrap();
+        let (resume, resumed) = tokio::sync::oneshot::channel::<()>();
+        let commit = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                limiter
+                    .admit(async {
+                        resumed.await.unwrap();
+                        limiter.acquire().await.map(|_| ())
+                    })
+                    .await
+            }
+        });
+        while limiter.in_flight() < 2 {
+            tokio::task::yield_now().await;
+        }
+        assert!(limiter.close());
+        assert!(!limiter.close());
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(LakeSoulMetaDataError::ClientClosed(_))
+        ));
+        assert!(!limiter.drain(Duration::from_millis(10)).await);
+        drop(permit);
+        resume.send(()).unwrap();
+        assert!(limiter.drain(Duration::from_secs(5)).await);
+        // the operation issued by the admitted commit went through
+        assert!(matches!(commit.await.unwrap(), Ok(Ok(()))));
+        assert_eq!(limiter.in_flight(), 0);
+    }
+}
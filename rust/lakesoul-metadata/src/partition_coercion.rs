@@ -0,0 +1,163 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Comparison of partition values by the type of their column rather than as strings, so that filters match the
+//! partitions whose values are written differently but equal, e.g. the dates `2023-1-1` and `2023-01-01`.
+
+use std::collections::HashMap;
+
+use proto::proto::entity::TableInfo;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+use crate::schema_validation::field_types;
+use crate::transfusion::config::{
+    LAKESOUL_EMPTY_STRING, LAKESOUL_NULL_STRING, LAKESOUL_PARTITION_DESC_KV_DELIM, LAKESOUL_RANGE_PARTITION_SPLITTER,
+};
+use crate::transfusion::{canonical_partition_desc, range_partition_keys};
+
+/// Types whose values have several spellings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    Integral,
+    Date,
+    Boolean,
+}
+
+impl ValueType {
+    fn of(spark_type: &str) -> Option<Self> {
+        match spark_type {
+            "byte" | "short" | "integer" | "long" => Some(Self::Integral),
+            "date" => Some(Self::Date),
+            "boolean" => Some(Self::Boolean),
+            _ => None,
+        }
+    }
+
+    /// The canonical spelling of `value`, None if it is not a value of the type.
+    fn normalize(self, value: &str) -> Option<String> {
+        match self {
+            Self::Integral => value.parse::<i64>().ok().map(|value| value.to_string()),
+            Self::Date => normalize_date(value),
+            Self::Boolean => match value.to_lowercase().as_str() {
+                "true" => Some("true".to_string()),
+                "false" => Some("false".to_string()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// `value` as `yyyy-MM-dd`, None if it is not a valid date written as year, month and day separated by `-`.
+fn normalize_date(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    (year <= 9999 && (1..=days).contains(&day)).then(|| format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// Canonical form of the partition descs of a table, with the values of its typed range partition columns spelled
+/// alike.
+#[derive(Debug, Clone)]
+pub(crate) struct PartitionValueCoercion {
+    range_keys: Vec<String>,
+    types: HashMap<String, ValueType>,
+}
+
+impl PartitionValueCoercion {
+    /// Coercion of the partition values of `table_info`, None if all of its range partition columns compare as
+    /// strings or its schema cannot be read.
+    pub(crate) fn for_table(table_info: &TableInfo) -> Option<Self> {
+        let range_keys = range_partition_keys(&table_info.partitions);
+        let fields = field_types(&table_info.table_schema).ok()?;
+        let types = range_keys
+            .iter()
+            .filter_map(|key| Some((key.clone(), ValueType::of(fields.get(key)?)?)))
+            .collect::<HashMap<_, _>>();
+        (!types.is_empty()).then_some(Self { range_keys, types })
+    }
+
+    /// `partition_desc` in canonical form with its values spelled canonically for their type, failing with
+    /// InvalidInput on a value not of the type of its column.
+    pub(crate) fn normalize(&self, partition_desc: &str) -> Result<String> {
+        let partition_desc = canonical_partition_desc(partition_desc, &self.range_keys);
+        partition_desc
+            .split(LAKESOUL_RANGE_PARTITION_SPLITTER)
+            .map(|kv| match kv.split_once(LAKESOUL_PARTITION_DESC_KV_DELIM) {
+                Some((key, value)) if value != LAKESOUL_NULL_STRING && value != LAKESOUL_EMPTY_STRING => {
+                    match self.types.get(key) {
+                        Some(value_type) => value_type
+                            .normalize(value)
+                            .map(|value| [key, &value].join(LAKESOUL_PARTITION_DESC_KV_DELIM))
+                            .ok_or_else(|| {
+                                LakeSoulMetaDataError::InvalidInput(format!(
+                                    "partition value '{}' of column {} is not a {:?} value",
+                                    value, key, value_type
+                                ))
+                            }),
+                        None => Ok(kv.to_string()),
+                    }
+                }
+                _ => Ok(kv.to_string()),
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|kvs| kvs.join(LAKESOUL_RANGE_PARTITION_SPLITTER))
+    }
+
+    /// [`Self::normalize`] of a recorded partition_desc, kept as is if its values are not of their types.
+    pub(crate) fn normalize_recorded(&self, partition_desc: &str) -> String {
+        self.normalize(partition_desc)
+            .unwrap_or_else(|_| partition_desc.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_value_coercion() {
+        let table_info = TableInfo {
+            table_schema: r#"{"type":"struct","fields":[
+                {"name":"date","type":"date","nullable":true,"metadata":{}},
+                {"name":"id","type":"integer","nullable":true,"metadata":{}},
+                {"name":"region","type":"string","nullable":true,"metadata":{}}]}"#
+                .to_string(),
+            partitions: "date,id,region;".to_string(),
+            ..Default::default()
+        };
+        let coercion = PartitionValueCoercion::for_table(&table_info).unwrap();
+        assert_eq!(
+            coercion.normalize("region=us,id=007,date=2023-1-1").unwrap(),
+            "date=2023-01-01,id=7,region=us"
+        );
+        assert_eq!(
+            coercion
+                .normalize(&format!("date={},id=1,region=us", LAKESOUL_NULL_STRING))
+                .unwrap(),
+            format!("date={},id=1,region=us", LAKESOUL_NULL_STRING)
+        );
+        for partition_desc in ["date=2023-02-29,id=1,region=us", "date=2024-1-1,id=one,region=us"] {
+            assert!(matches!(
+                coercion.normalize(partition_desc),
+                Err(LakeSoulMetaDataError::InvalidInput(_))
+            ));
+            assert_eq!(coercion.normalize_recorded(partition_desc), partition_desc);
+        }
+        assert_eq!(normalize_date("2024-2-29").as_deref(), Some("2024-02-29"));
+        assert_eq!(normalize_date("2023-01"), None);
+
+        let untyped = TableInfo {
+            partitions: "region;".to_string(),
+            ..table_info
+        };
+        assert!(PartitionValueCoercion::for_table(&untyped).is_none());
+    }
+}
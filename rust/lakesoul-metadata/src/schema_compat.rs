@@ -0,0 +1,301 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compatibility with metadata databases that are migrated after the compute fleet is upgraded.
+//! Missing optional columns are read as constant defaults and skipped on insert,
+//! while missing required columns fail at connection time.
+
+use std::collections::{HashMap, HashSet};
+
+use tokio_postgres::Client;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+/// Columns without which the DAO layer cannot work.
+const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    ("namespace", &["namespace"]),
+    (
+        "table_info",
+        &[
+            "table_id",
+            "table_name",
+            "table_path",
+            "table_schema",
+            "partitions",
+            "table_namespace",
+        ],
+    ),
+    ("table_name_id", &["table_name", "table_id", "table_namespace"]),
+    ("table_path_id", &["table_path", "table_id", "table_namespace"]),
+    (
+        "partition_info",
+        &[
+            "table_id",
+            "partition_desc",
+            "version",
+            "commit_op",
+            "timestamp",
+            "snapshot",
+            "expression",
+        ],
+    ),
+    (
+        "data_commit_info",
+        &[
+            "table_id",
+            "partition_desc",
+            "commit_id",
+            "file_ops",
+            "commit_op",
+            "committed",
+            "timestamp",
+        ],
+    ),
+];
+
+/// Tables added by later schema versions, whose features are unavailable while they are missing.
+const OPTIONAL_TABLES: &[&str] = &[
+    "partition_stats",
+    "file_stats",
+    "commit_provenance",
+    "table_tag",
+    "table_schema_history",
+    "commit_schema_version",
+];
+
+/// Columns added by later schema versions, with the value read in place of a missing column.
+const OPTIONAL_COLUMNS: &[(&str, &str, &str)] = &[
+    ("namespace", "properties", "'{}'::JSON"),
+    ("namespace", "comment", "''::TEXT"),
+    ("namespace", "domain", "'public'::TEXT"),
+    ("table_info", "properties", "'{}'::JSON"),
+    ("table_info", "domain", "'public'::TEXT"),
+    ("table_name_id", "domain", "'public'::TEXT"),
+    ("table_path_id", "domain", "'public'::TEXT"),
+    ("partition_info", "domain", "'public'::TEXT"),
+    ("partition_info", "change_tracking", "false"),
+    ("data_commit_info", "domain", "'public'::TEXT"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingColumn {
+    pub table: String,
+    pub column: String,
+    default_value: String,
+}
+
+/// Optional columns and tables missing from the connected database, empty when the schema is up to date.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnFallbacks {
+    missing: Vec<MissingColumn>,
+    missing_tables: Vec<String>,
+}
+
+impl ColumnFallbacks {
+    pub fn is_degraded(&self) -> bool {
+        !self.missing.is_empty() || !self.missing_tables.is_empty()
+    }
+
+    pub fn missing_columns(&self) -> &[MissingColumn] {
+        &self.missing
+    }
+
+    pub fn missing_tables(&self) -> &[String] {
+        &self.missing_tables
+    }
+
+    pub fn is_table_missing(&self, table: &str) -> bool {
+        self.missing_tables.iter().any(|missing| missing == table)
+    }
+
+    fn from_existing_columns(existing: &HashMap<String, HashSet<String>>) -> Result<Self> {
+        let missing_required = REQUIRED_COLUMNS
+            .iter()
+            .flat_map(|(table, columns)| columns.iter().map(move |column| (*table, *column)))
+            .filter(|(table, column)| !existing.get(*table).is_some_and(|columns| columns.contains(*column)))
+            .map(|(table, column)| format!("{}.{}", table, column))
+            .collect::<Vec<String>>();
+        if !missing_required.is_empty() {
+            return Err(LakeSoulMetaDataError::IncompatibleSchema(format!(
+                "required columns missing: {}",
+                missing_required.join(", ")
+            )));
+        }
+        Ok(Self {
+            missing: OPTIONAL_COLUMNS
+                .iter()
+                .filter(|(table, column, _)| !existing[*table].contains(*column))
+                .map(|(table, column, default_value)| MissingColumn {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                    default_value: default_value.to_string(),
+                })
+                .collect(),
+            missing_tables: OPTIONAL_TABLES
+                .iter()
+                .filter(|table| existing.get(**table).is_none_or(|columns| columns.is_empty()))
+                .map(|table| table.to_string())
+                .collect(),
+        })
+    }
+
+    /// Whether `table.column` is an optional column missing from the database.
+    pub fn is_column_missing(&self, table: &str, column: &str) -> bool {
+        self.missing_column(table, column).is_some()
+    }
+
+    fn missing_column(&self, table: &str, column: &str) -> Option<&MissingColumn> {
+        self.missing
+            .iter()
+            .find(|missing| missing.table == table && missing.column == column)
+    }
+
+    /// `column` of `table` qualified by `alias` unless empty, or its default value if the column is missing.
+    pub(crate) fn column(&self, alias: &str, table: &str, column: &str) -> String {
+        match self.missing_column(table, column) {
+            Some(missing) => missing.default_value.clone(),
+            None if alias.is_empty() => column.to_string(),
+            None => format!("{}.{}", alias, column),
+        }
+    }
+
+    /// Select list of `columns` of `table` qualified by `alias` unless empty, the missing ones read as their
+    /// default values.
+    pub(crate) fn select_list(&self, alias: &str, table: &str, columns: &[&str]) -> String {
+        columns
+            .iter()
+            .map(|column| match self.missing_column(table, column) {
+                Some(missing) => format!("{} as {}", missing.default_value, column),
+                None => self.column(alias, table, column),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Insert of `values` into `columns` of `table`. The missing columns are skipped by inserting from a VALUES
+    /// list still holding their values, so that every param keeps its type.
+    pub(crate) fn insert(&self, table: &str, columns: &[&str], values: &[&str]) -> String {
+        let kept = columns
+            .iter()
+            .filter(|column| !self.is_column_missing(table, column))
+            .copied()
+            .collect::<Vec<&str>>();
+        if kept.len() == columns.len() {
+            return format!(
+                "insert into {}({}) values({})",
+                table,
+                columns.join(", "),
+                values.join(", ")
+            );
+        }
+        format!(
+            "insert into {}({}) select {} from (values({})) as v({})",
+            table,
+            kept.join(", "),
+            kept.join(", "),
+            values.join(", "),
+            columns.join(", ")
+        )
+    }
+}
+
+/// Probe the columns of the metadata tables in the current schema.
+pub async fn probe_column_fallbacks(client: &Client) -> Result<ColumnFallbacks> {
+    let tables = REQUIRED_COLUMNS
+        .iter()
+        .map(|(table, _)| table.to_string())
+        .chain(OPTIONAL_TABLES.iter().map(|table| table.to_string()))
+        .collect::<Vec<String>>();
+    let rows = client
+        .query(
+            "select table_name::TEXT, column_name::TEXT
+            from information_schema.columns
+            where table_schema = current_schema() and table_name = ANY($1::TEXT[])",
+            &[&tables],
+        )
+        .await?;
+    let mut existing = tables
+        .iter()
+        .map(|table| (table.clone(), HashSet::new()))
+        .collect::<HashMap<String, HashSet<String>>>();
+    for row in rows {
+        let table: String = row.get(0);
+        if let Some(columns) = existing.get_mut(&table) {
+            columns.insert(row.get(1));
+        }
+    }
+    ColumnFallbacks::from_existing_columns(&existing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn without(table: &str, column: &str) -> ColumnFallbacks {
+        let mut existing = REQUIRED_COLUMNS
+            .iter()
+            .map(|(table, columns)| {
+                (
+                    table.to_string(),
+                    columns.iter().map(|column| column.to_string()).collect::<HashSet<_>>(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        for (optional_table, optional_column, _) in OPTIONAL_COLUMNS {
+            if (*optional_table, *optional_column) != (table, column) {
+                existing
+                    .get_mut(*optional_table)
+                    .unwrap()
+                    .insert(optional_column.to_string());
+            }
+        }
+        for table in OPTIONAL_TABLES {
+            existing.insert(table.to_string(), HashSet::from(["table_id".to_string()]));
+        }
+        ColumnFallbacks::from_existing_columns(&existing).unwrap()
+    }
+
+    #[test]
+    fn test_fallback_for_missing_domain() {
+        let fallbacks = without("table_name_id", "domain");
+        assert!(fallbacks.is_degraded());
+        assert!(fallbacks.is_column_missing("table_name_id", "domain"));
+
+        let columns = ["table_name", "table_id", "table_namespace", "domain"];
+        assert_eq!(
+            fallbacks.select_list("", "table_name_id", &columns),
+            "table_name, table_id, table_namespace, 'public'::TEXT as domain"
+        );
+        assert_eq!(fallbacks.column("n", "table_name_id", "domain"), "'public'::TEXT");
+        assert_eq!(
+            fallbacks.insert(
+                "table_name_id",
+                &["table_id", "table_name", "table_namespace", "domain"],
+                &["$1::TEXT", "$2::TEXT", "$3::TEXT", "$4::TEXT"]
+            ),
+            "insert into table_name_id(table_id, table_name, table_namespace) \
+            select table_id, table_name, table_namespace \
+            from (values($1::TEXT, $2::TEXT, $3::TEXT, $4::TEXT)) as v(table_id, table_name, table_namespace, domain)"
+        );
+        // other tables are untouched
+        assert_eq!(
+            fallbacks.select_list("p", "table_path_id", &["table_path", "domain"]),
+            "p.table_path, p.domain"
+        );
+        assert_eq!(
+            fallbacks.insert("table_path_id", &["table_path", "domain"], &["$1::TEXT", "$2::TEXT"]),
+            "insert into table_path_id(table_path, domain) values($1::TEXT, $2::TEXT)"
+        );
+        assert!(!fallbacks.is_table_missing("partition_stats"));
+    }
+
+    #[test]
+    fn test_missing_required_column() {
+        let existing = HashMap::from([("namespace".to_string(), HashSet::new())]);
+        assert!(matches!(
+            ColumnFallbacks::from_existing_columns(&existing),
+            Err(LakeSoulMetaDataError::IncompatibleSchema(_))
+        ));
+    }
+}
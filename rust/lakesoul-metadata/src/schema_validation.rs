@@ -0,0 +1,220 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Field-level comparison of a candidate schema, e.g. the schema of the files about to be committed, with the schema
+//! of a table. Both schemas may be spark-formatted or arrow-java-formatted json, types are compared by their spark
+//! names so that the two formats can be mixed.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+/// A difference between a candidate schema and the table schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldMismatch {
+    /// The field has another type in the table.
+    TypeMismatch {
+        field: String,
+        table_type: String,
+        candidate_type: String,
+    },
+    /// The field is nullable in the candidate but not in the table.
+    NullabilityMismatch { field: String },
+    /// The field is not in the table.
+    UnknownField { field: String },
+    /// The table field is not in the candidate, which is only compatible if it is nullable.
+    MissingField { field: String, nullable: bool },
+}
+
+/// Outcome of [`crate::MetaDataClient::validate_schema_against`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaValidation {
+    /// The candidate has the fields of the table with the same types and nullability, in any order.
+    pub matches: bool,
+    /// The candidate can be written to the table: its fields are table fields of the same types, without nulls
+    /// for non nullable fields, and the table fields it lacks are nullable.
+    pub compatible_subset: bool,
+    pub mismatches: Vec<FieldMismatch>,
+}
+
+struct Field {
+    name: String,
+    data_type: String,
+    nullable: bool,
+}
+
+/// Compare `candidate_schema` with `table_schema`, in the order of the candidate fields then of the table fields.
+pub fn validate_schema(table_schema: &str, candidate_schema: &str) -> Result<SchemaValidation> {
+    let table_fields = parse_fields(table_schema, "table")?;
+    let candidate_fields = parse_fields(candidate_schema, "candidate")?;
+    let table_by_name = table_fields
+        .iter()
+        .map(|field| (field.name.as_str(), field))
+        .collect::<HashMap<_, _>>();
+
+    let mut mismatches = vec![];
+    for candidate in &candidate_fields {
+        match table_by_name.get(candidate.name.as_str()) {
+            None => mismatches.push(FieldMismatch::UnknownField {
+                field: candidate.name.clone(),
+            }),
+            Some(table) if table.data_type != candidate.data_type => mismatches.push(FieldMismatch::TypeMismatch {
+                field: candidate.name.clone(),
+                table_type: table.data_type.clone(),
+                candidate_type: candidate.data_type.clone(),
+            }),
+            Some(table) if candidate.nullable && !table.nullable => {
+                mismatches.push(FieldMismatch::NullabilityMismatch {
+                    field: candidate.name.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for table in &table_fields {
+        if !candidate_fields.iter().any(|candidate| candidate.name == table.name) {
+            mismatches.push(FieldMismatch::MissingField {
+                field: table.name.clone(),
+                nullable: table.nullable,
+            });
+        }
+    }
+
+    let compatible_subset = mismatches
+        .iter()
+        .all(|mismatch| matches!(mismatch, FieldMismatch::MissingField { nullable: true, .. }));
+    let matches = mismatches.is_empty()
+        && candidate_fields.iter().all(|candidate| {
+            table_by_name
+                .get(candidate.name.as_str())
+                .is_some_and(|table| table.nullable == candidate.nullable)
+        });
+    Ok(SchemaValidation {
+        matches,
+        compatible_subset,
+        mismatches,
+    })
+}
+
+/// Spark name of the type of each field of a spark-formatted or arrow-java-formatted schema, by field name.
+pub(crate) fn field_types(schema: &str) -> Result<HashMap<String, String>> {
+    Ok(parse_fields(schema, "table")?
+        .into_iter()
+        .map(|field| (field.name, field.data_type))
+        .collect())
+}
+
+fn parse_fields(schema: &str, which: &str) -> Result<Vec<Field>> {
+    let schema: Value = serde_json::from_str(schema)
+        .map_err(|e| LakeSoulMetaDataError::InvalidInput(format!("{} schema is not valid json: {}", which, e)))?;
+    let fields = schema["fields"]
+        .as_array()
+        .ok_or_else(|| LakeSoulMetaDataError::InvalidInput(format!("{} schema has no fields", which)))?;
+    fields
+        .iter()
+        .map(|field| {
+            let name = field["name"].as_str().ok_or_else(|| {
+                LakeSoulMetaDataError::InvalidInput(format!("{} schema has a field without name", which))
+            })?;
+            Ok(Field {
+                name: name.to_string(),
+                data_type: type_name(field),
+                nullable: field["nullable"].as_bool().unwrap_or(true),
+            })
+        })
+        .collect()
+}
+
+/// Spark name of the type of a spark-formatted or arrow-java-formatted field, nested types are kept as json.
+fn type_name(field: &Value) -> String {
+    let arrow_type = match &field["type"] {
+        Value::String(spark_type) => return spark_type.to_lowercase(),
+        Value::Object(arrow_type) if arrow_type.contains_key("name") => arrow_type,
+        spark_type => return spark_type.to_string(),
+    };
+    let name = arrow_type["name"].as_str().unwrap_or_default().to_lowercase();
+    match name.as_str() {
+        "int" => match arrow_type["bitWidth"].as_i64() {
+            Some(8) => "byte".to_string(),
+            Some(16) => "short".to_string(),
+            Some(64) => "long".to_string(),
+            _ => "integer".to_string(),
+        },
+        "floatingpoint" => match arrow_type["precision"].as_str() {
+            Some("DOUBLE") => "double".to_string(),
+            _ => "float".to_string(),
+        },
+        "utf8" | "largeutf8" => "string".to_string(),
+        "binary" | "largebinary" => "binary".to_string(),
+        "bool" => "boolean".to_string(),
+        "date" => "date".to_string(),
+        "timestamp" => "timestamp".to_string(),
+        "decimal" => format!("decimal({},{})", arrow_type["precision"], arrow_type["scale"]),
+        _ => serde_json::json!({"type": arrow_type, "children": field["children"]}).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE_SCHEMA: &str = r#"{"type":"struct","fields":[
+        {"name":"id","type":"long","nullable":false,"metadata":{}},
+        {"name":"name","type":"string","nullable":true,"metadata":{}},
+        {"name":"price","type":"decimal(10,2)","nullable":true,"metadata":{}}]}"#;
+
+    #[test]
+    fn test_validate_schema() {
+        let validation = validate_schema(TABLE_SCHEMA, TABLE_SCHEMA).unwrap();
+        assert!(validation.matches && validation.compatible_subset);
+
+        // arrow-java format, without the nullable price
+        let subset = r#"{"fields":[
+            {"name":"name","nullable":true,"type":{"name":"utf8"},"children":[]},
+            {"name":"id","nullable":false,"type":{"name":"int","bitWidth":64,"isSigned":true},"children":[]}]}"#;
+        let validation = validate_schema(TABLE_SCHEMA, subset).unwrap();
+        assert!(!validation.matches && validation.compatible_subset);
+        assert_eq!(
+            validation.mismatches,
+            [FieldMismatch::MissingField {
+                field: "price".to_string(),
+                nullable: true
+            }]
+        );
+
+        let mismatched = r#"{"type":"struct","fields":[
+            {"name":"id","type":"integer","nullable":true,"metadata":{}},
+            {"name":"extra","type":"string","nullable":true,"metadata":{}}]}"#;
+        let validation = validate_schema(TABLE_SCHEMA, mismatched).unwrap();
+        assert!(!validation.matches && !validation.compatible_subset);
+        assert_eq!(
+            validation.mismatches,
+            [
+                FieldMismatch::TypeMismatch {
+                    field: "id".to_string(),
+                    table_type: "long".to_string(),
+                    candidate_type: "integer".to_string()
+                },
+                FieldMismatch::UnknownField {
+                    field: "extra".to_string()
+                },
+                FieldMismatch::MissingField {
+                    field: "name".to_string(),
+                    nullable: true
+                },
+                FieldMismatch::MissingField {
+                    field: "price".to_string(),
+                    nullable: true
+                },
+            ]
+        );
+
+        assert!(matches!(
+            validate_schema(TABLE_SCHEMA, "not json"),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! The commit ids making up a partition version, kept as an ordered set. A commit listed twice in a snapshot has
+//! its files read twice, so snapshots are only ever extended with commits they do not hold yet.
+
+use std::collections::HashSet;
+
+use proto::proto::entity;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+/// Commit ids of a partition version in commit order, each at most once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    commit_ids: Vec<uuid::Uuid>,
+    seen: HashSet<uuid::Uuid>,
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the snapshot from its proto representation, failing with InvalidInput on a duplicate commit id.
+    pub fn try_from_proto(commit_ids: &[entity::Uuid]) -> Result<Self> {
+        let mut snapshot = Self::new();
+        for commit_id in commit_ids {
+            let commit_id = uuid::Uuid::from_u64_pair(commit_id.high, commit_id.low);
+            if !snapshot.insert(commit_id) {
+                return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                    "commit {} is listed twice in the snapshot",
+                    commit_id
+                )));
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Build the snapshot keeping the first occurrence of each commit id, for snapshots written before duplicates
+    /// were rejected. Returns the number of duplicates dropped.
+    pub fn dedup(commit_ids: impl IntoIterator<Item = uuid::Uuid>) -> (Self, usize) {
+        let mut snapshot = Self::new();
+        let mut dropped = 0;
+        for commit_id in commit_ids {
+            if !snapshot.insert(commit_id) {
+                dropped += 1;
+            }
+        }
+        (snapshot, dropped)
+    }
+
+    pub fn to_proto(&self) -> Vec<entity::Uuid> {
+        self.commit_ids
+            .iter()
+            .map(|commit_id| {
+                let (high, low) = commit_id.as_u64_pair();
+                entity::Uuid { high, low }
+            })
+            .collect()
+    }
+
+    pub fn commit_ids(&self) -> &[uuid::Uuid] {
+        &self.commit_ids
+    }
+
+    pub fn len(&self) -> usize {
+        self.commit_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commit_ids.is_empty()
+    }
+
+    pub fn contains(&self, commit_id: &uuid::Uuid) -> bool {
+        self.seen.contains(commit_id)
+    }
+
+    /// Append the commit id unless the snapshot holds it already, returns whether it was appended.
+    pub fn insert(&mut self, commit_id: uuid::Uuid) -> bool {
+        if !self.seen.insert(commit_id) {
+            return false;
+        }
+        self.commit_ids.push(commit_id);
+        true
+    }
+
+    /// Append the commits of `other` the snapshot does not hold yet, in their order, returns how many were
+    /// appended. Appending the same commit again, e.g. on a retried commit, leaves the snapshot as is.
+    pub fn merge_append(&mut self, other: &Snapshot) -> usize {
+        other
+            .commit_ids
+            .iter()
+            .filter(|commit_id| self.insert(**commit_id))
+            .count()
+    }
+
+    /// Overwrite the snapshot with `other`, returning the previous commits.
+    pub fn replace(&mut self, other: Snapshot) -> Snapshot {
+        std::mem::replace(self, other)
+    }
+
+    /// The commits of the snapshot which `other` does not hold, in order.
+    pub fn diff(&self, other: &Snapshot) -> Snapshot {
+        Self::dedup(
+            self.commit_ids
+                .iter()
+                .filter(|commit_id| !other.contains(commit_id))
+                .copied(),
+        )
+        .0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let ids = (0..4).map(|_| uuid::Uuid::new_v4()).collect::<Vec<_>>();
+        let proto = |ids: &[uuid::Uuid]| Snapshot::dedup(ids.iter().copied()).0.to_proto();
+
+        let mut snapshot = Snapshot::try_from_proto(&proto(&ids[..2])).unwrap();
+        assert_eq!(snapshot.commit_ids(), &ids[..2]);
+        let duplicated = [proto(&ids[..2]), proto(&ids[..1])].concat();
+        assert!(matches!(
+            Snapshot::try_from_proto(&duplicated),
+            Err(LakeSoulMetaDataError::InvalidInput(_))
+        ));
+
+        // appending commits again does not duplicate them
+        let other = Snapshot::try_from_proto(&proto(&ids[1..3])).unwrap();
+        assert_eq!(snapshot.merge_append(&other), 1);
+        assert_eq!(snapshot.merge_append(&other), 0);
+        assert_eq!(snapshot.commit_ids(), &ids[..3]);
+
+        assert_eq!(snapshot.diff(&other).commit_ids(), &ids[..1]);
+        let previous = snapshot.replace(Snapshot::try_from_proto(&proto(&ids[3..])).unwrap());
+        assert_eq!(previous.len(), 3);
+        assert_eq!(snapshot.commit_ids(), &ids[3..]);
+
+        let (snapshot, dropped) = Snapshot::dedup([ids[0], ids[1], ids[0], ids[1], ids[2]]);
+        assert_eq!(dropped, 2);
+        assert_eq!(snapshot.commit_ids(), &ids[..3]);
+        assert_eq!(Snapshot::try_from_proto(&snapshot.to_proto()).unwrap(), snapshot);
+    }
+}
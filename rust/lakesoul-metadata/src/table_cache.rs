@@ -0,0 +1,720 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Optional read-through cache of TableInfo and TableNameId, sparing query engines repeated lookups during planning.
+//! Entries are served until they are older than the ttl, and every write or commit to a table drops its entries.
+//! The cache can be persisted to a file, letting short-lived processes start warm: entries loaded from it are
+//! served for a grace period while they are refreshed in the background. Changes are written shortly after, merged
+//! with those saved by other processes meanwhile, and the file records when tables were invalidated so that a
+//! process saving later does not restore entries dropped by another.
+//! Writes of other processes are only seen once the ttl expires, unless the client subscribes to the notifications
+//! sent by the triggers of `table_info` and `table_name_id`, see [`listen_notifications`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::runtime;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_postgres::{AsyncMessage, NoTls};
+use tracing::{debug, warn};
+
+use proto::proto::entity::{TableInfo, TableNameId};
+
+use crate::error::Result;
+use crate::parse_config;
+
+pub(crate) const TABLE_CACHE_FILE_NAME: &str = "lakesoul_table_cache.json";
+pub(crate) const DEFAULT_TABLE_CACHE_TTL: Duration = Duration::from_secs(60);
+pub(crate) const DEFAULT_TABLE_CACHE_GRACE_PERIOD: Duration = Duration::from_secs(60);
+/// Channel notified with the table_id of every updated or deleted `table_info` and `table_name_id` row.
+pub(crate) const TABLE_CACHE_NOTIFY_CHANNEL: &str = "lakesoul_table_cache_notify";
+/// Wait between attempts to subscribe again to the notifications after the connection was lost.
+const NOTIFY_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// Delay of writing the cache file after a change, the changes made meanwhile are written along.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Lookups served by the table cache of a client, see [`crate::MetaDataClient::table_cache_metrics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableCacheMetrics {
+    /// Lookups answered from the cache.
+    pub hits: u64,
+    /// Lookups which went to the database.
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedEntry<T> {
+    fetched_at_ms: u64,
+    value: T,
+    /// Fetched by this process rather than loaded from the cache file.
+    #[serde(skip)]
+    fetched_here: bool,
+}
+
+/// Content of the cache file, entries are keyed by table_id.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct CacheFile {
+    /// Database the entries were fetched from, entries of another database are discarded on load.
+    source: String,
+    table_info: HashMap<String, CachedEntry<TableInfo>>,
+    table_name_id: HashMap<String, CachedEntry<TableNameId>>,
+    /// When each table was last invalidated, entries fetched before are stale. Kept for as long as entries are
+    /// served, see [`TableCache::retention`].
+    #[serde(default)]
+    invalidated_at_ms: HashMap<String, u64>,
+    /// When all the tables were last invalidated.
+    #[serde(default)]
+    all_invalidated_at_ms: u64,
+}
+
+/// How an entry is looked up.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum CacheKey {
+    Id(String),
+    /// Namespace and name of the table.
+    Name(String, String),
+    Path(String),
+}
+
+/// The table_id of the cached entries by their other keys.
+#[derive(Debug, Default)]
+pub(crate) struct CacheIndex {
+    table_info: HashMap<CacheKey, String>,
+    table_name_id: HashMap<CacheKey, String>,
+}
+
+pub(crate) trait CachedEntity: Clone + Serialize + DeserializeOwned {
+    fn table_id(&self) -> &str;
+
+    /// Keys other than the table_id the entity is looked up by.
+    fn keys(&self) -> Vec<CacheKey>;
+
+    fn entries(file: &mut CacheFile) -> &mut HashMap<String, CachedEntry<Self>>;
+
+    fn index(index: &mut CacheIndex) -> &mut HashMap<CacheKey, String>;
+}
+
+impl CachedEntity for TableInfo {
+    fn table_id(&self) -> &str {
+        &self.table_id
+    }
+
+    fn keys(&self) -> Vec<CacheKey> {
+        vec![
+            CacheKey::Name(self.table_namespace.clone(), self.table_name.clone()),
+            CacheKey::Path(self.table_path.clone()),
+        ]
+    }
+
+    fn entries(file: &mut CacheFile) -> &mut HashMap<String, CachedEntry<Self>> {
+        &mut file.table_info
+    }
+
+    fn index(index: &mut CacheIndex) -> &mut HashMap<CacheKey, String> {
+        &mut index.table_info
+    }
+}
+
+impl CachedEntity for TableNameId {
+    fn table_id(&self) -> &str {
+        &self.table_id
+    }
+
+    fn keys(&self) -> Vec<CacheKey> {
+        vec![CacheKey::Name(self.table_namespace.clone(), self.table_name.clone())]
+    }
+
+    fn entries(file: &mut CacheFile) -> &mut HashMap<String, CachedEntry<Self>> {
+        &mut file.table_name_id
+    }
+
+    fn index(index: &mut CacheIndex) -> &mut HashMap<CacheKey, String> {
+        &mut index.table_name_id
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    file: CacheFile,
+    index: CacheIndex,
+    /// Bumped by every invalidation, fetches started before one are not cached.
+    generation: u64,
+    /// Changed since the file was last written.
+    dirty: bool,
+}
+
+impl CacheState {
+    fn lookup<T: CachedEntity>(&mut self, key: &CacheKey) -> Option<&CachedEntry<T>> {
+        let table_id = match key {
+            CacheKey::Id(table_id) => table_id,
+            key => T::index(&mut self.index).get(key)?,
+        };
+        T::entries(&mut self.file).get(table_id)
+    }
+
+    fn insert<T: CachedEntity>(&mut self, entry: CachedEntry<T>) {
+        let table_id = entry.value.table_id().to_string();
+        self.remove::<T>(&table_id);
+        let index = T::index(&mut self.index);
+        for key in entry.value.keys() {
+            index.insert(key, table_id.clone());
+        }
+        T::entries(&mut self.file).insert(table_id, entry);
+    }
+
+    fn remove<T: CachedEntity>(&mut self, table_id: &str) {
+        if let Some(entry) = T::entries(&mut self.file).remove(table_id) {
+            let index = T::index(&mut self.index);
+            for key in entry.value.keys() {
+                // the key may have been taken over by another table of the same name or path
+                if index.get(&key).is_some_and(|indexed| indexed == table_id) {
+                    index.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// The table ids of the entries of `key`.
+    fn table_ids_of(&self, key: &CacheKey) -> Vec<String> {
+        [self.index.table_info.get(key), self.index.table_name_id.get(key)]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TableCache {
+    /// Cache file, None for an in-memory cache.
+    path: Option<PathBuf>,
+    ttl: Duration,
+    grace_period: Duration,
+    state: Mutex<CacheState>,
+    /// A write of the file is scheduled, see [`TableCache::save`].
+    save_scheduled: AtomicBool,
+    /// Held while writing the file, so that the writes of this process are not reordered.
+    write_lock: Mutex<()>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The pg config without the password, identifying the database of the cached entries.
+pub(crate) fn cache_source(config: &str) -> String {
+    config
+        .split_whitespace()
+        .filter(|property| !property.starts_with("password="))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl TableCache {
+    pub(crate) fn in_memory(ttl: Duration) -> Self {
+        Self {
+            path: None,
+            ttl,
+            grace_period: Duration::ZERO,
+            state: Default::default(),
+            save_scheduled: Default::default(),
+            write_lock: Default::default(),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    /// Load the cache file of `dir`, starting empty if it is missing, unreadable or of another database.
+    pub(crate) fn open(dir: &Path, source: String, ttl: Duration, grace_period: Duration) -> Self {
+        let path = dir.join(TABLE_CACHE_FILE_NAME);
+        let file = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<CacheFile>(&content) {
+                Ok(file) if file.source == source => file,
+                Ok(_) => CacheFile::default(),
+                Err(e) => {
+                    warn!("discarding unreadable table cache {}: {}", path.display(), e);
+                    CacheFile::default()
+                }
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => CacheFile::default(),
+            Err(e) => {
+                warn!("discarding unreadable table cache {}: {}", path.display(), e);
+                CacheFile::default()
+            }
+        };
+        let mut state = CacheState {
+            file: CacheFile {
+                source,
+                table_info: Default::default(),
+                table_name_id: Default::default(),
+                ..file
+            },
+            ..Default::default()
+        };
+        for entry in file.table_info.into_values() {
+            state.insert(entry);
+        }
+        for entry in file.table_name_id.into_values() {
+            state.insert(entry);
+        }
+        Self {
+            path: Some(path),
+            ttl,
+            grace_period,
+            state: Mutex::new(state),
+            save_scheduled: Default::default(),
+            write_lock: Default::default(),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    pub(crate) fn get<T: CachedEntity>(&self, key: &CacheKey) -> Option<T> {
+        let now_ms = now_ms();
+        let is_live = |entry: &CachedEntry<T>| {
+            let max_age = if entry.fetched_here {
+                self.ttl
+            } else {
+                self.grace_period
+            };
+            now_ms.saturating_sub(entry.fetched_at_ms) <= max_age.as_millis() as u64
+        };
+        let mut state = self.state.lock().unwrap();
+        let value = state
+            .lookup::<T>(key)
+            .filter(|entry| is_live(entry))
+            .map(|entry| entry.value.clone());
+        match value {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        value
+    }
+
+    pub(crate) fn metrics(&self) -> TableCacheMetrics {
+        TableCacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    pub(crate) fn table_ids(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let mut table_ids = state
+            .file
+            .table_info
+            .keys()
+            .chain(state.file.table_name_id.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        table_ids.sort();
+        table_ids.dedup();
+        table_ids
+    }
+
+    /// Cache `value` fetched after `generation` was observed, unless the table was written since.
+    pub(crate) fn put<T: CachedEntity>(self: &Arc<Self>, generation: u64, value: T) {
+        if self.insert(generation, value) {
+            self.save();
+        }
+    }
+
+    /// Like [`Self::put`] for values fetched together, saving the file once.
+    pub(crate) fn put_all<T: CachedEntity>(self: &Arc<Self>, generation: u64, values: impl IntoIterator<Item = T>) {
+        let mut inserted = false;
+        for value in values {
+            inserted |= self.insert(generation, value);
+        }
+        if inserted {
+            self.save();
+        }
+    }
+
+    /// Replace the entries of `table_id` with a fetched value, or drop them if the table is gone.
+    /// The file is not saved, see [`Self::save`].
+    pub(crate) fn refresh<T: CachedEntity>(&self, generation: u64, table_id: &str, value: Option<T>) {
+        match value {
+            Some(value) => {
+                self.insert(generation, value);
+            }
+            None => {
+                let mut state = self.state.lock().unwrap();
+                if state.generation == generation {
+                    state.remove::<T>(table_id);
+                    state.dirty = true;
+                }
+            }
+        }
+    }
+
+    fn insert<T: CachedEntity>(&self, generation: u64, value: T) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return false;
+        }
+        state.insert(CachedEntry {
+            fetched_at_ms: now_ms(),
+            value,
+            fetched_here: true,
+        });
+        state.dirty = true;
+        true
+    }
+
+    /// Drop the entries of `table_id`, and those of any table with the given name or path.
+    pub(crate) fn invalidate(
+        self: &Arc<Self>,
+        table_id: Option<&str>,
+        table_name: Option<(&str, &str)>,
+        table_path: Option<&str>,
+    ) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.generation += 1;
+            let mut table_ids = table_id.map(str::to_string).into_iter().collect::<Vec<_>>();
+            if let Some((namespace, table_name)) = table_name {
+                table_ids.extend(state.table_ids_of(&CacheKey::Name(namespace.to_string(), table_name.to_string())));
+            }
+            if let Some(table_path) = table_path {
+                table_ids.extend(state.table_ids_of(&CacheKey::Path(table_path.to_string())));
+            }
+            let now_ms = now_ms();
+            for table_id in table_ids {
+                state.remove::<TableInfo>(&table_id);
+                state.remove::<TableNameId>(&table_id);
+                state.file.invalidated_at_ms.insert(table_id, now_ms);
+            }
+            state.dirty = true;
+        }
+        self.save();
+    }
+
+    /// Drop all the entries.
+    pub(crate) fn invalidate_all(self: &Arc<Self>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.generation += 1;
+            state.file.table_info.clear();
+            state.file.table_name_id.clear();
+            state.index = Default::default();
+            state.file.all_invalidated_at_ms = now_ms();
+            state.dirty = true;
+        }
+        self.save();
+    }
+
+    /// Write the changes to the cache file after [`SAVE_DELAY`], on a blocking thread. Without a runtime to wait
+    /// on, the file is written right away.
+    pub(crate) fn save(self: &Arc<Self>) {
+        if self.path.is_none() || self.save_scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let Ok(runtime) = runtime::Handle::try_current() else {
+            self.save_scheduled.store(false, Ordering::Release);
+            self.write_pending();
+            return;
+        };
+        // changes still pending when the cache is dropped are written by then
+        let table_cache = Arc::downgrade(self);
+        runtime.spawn(async move {
+            tokio::time::sleep(SAVE_DELAY).await;
+            let Some(table_cache) = table_cache.upgrade() else {
+                return;
+            };
+            table_cache.save_scheduled.store(false, Ordering::Release);
+            let _ = tokio::task::spawn_blocking(move || table_cache.write_pending()).await;
+        });
+    }
+
+    /// Write the changes to the cache file now rather than after [`SAVE_DELAY`].
+    pub(crate) async fn flush(self: &Arc<Self>) {
+        let table_cache = self.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || table_cache.write_pending()).await {
+            warn!("failed to save table cache: {}", e);
+        }
+    }
+
+    /// How long invalidations are recorded in the cache file, past which any entry fetched before is expired.
+    fn retention(&self) -> Duration {
+        self.ttl.max(self.grace_period)
+    }
+
+    /// Merge the changes with the cache file and write it through a temporary file, so concurrent processes never
+    /// read a partial one. Only the entries fetched by this process are written, those loaded from the file may
+    /// have been dropped by others since. Failures are logged only, the cache is an optimization.
+    fn write_pending(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _write_lock = self.write_lock.lock().unwrap();
+        let retained_since_ms = now_ms().saturating_sub(self.retention().as_millis() as u64);
+        let pending = {
+            let mut state = self.state.lock().unwrap();
+            if !state.dirty {
+                return;
+            }
+            state.dirty = false;
+            state
+                .file
+                .invalidated_at_ms
+                .retain(|_, invalidated_at_ms| *invalidated_at_ms >= retained_since_ms);
+            fn fetched_here<T: Clone>(entries: &HashMap<String, CachedEntry<T>>) -> HashMap<String, CachedEntry<T>> {
+                entries
+                    .iter()
+                    .filter(|(_, entry)| entry.fetched_here)
+                    .map(|(table_id, entry)| (table_id.clone(), entry.clone()))
+                    .collect()
+            }
+            CacheFile {
+                source: state.file.source.clone(),
+                table_info: fetched_here(&state.file.table_info),
+                table_name_id: fetched_here(&state.file.table_name_id),
+                invalidated_at_ms: state.file.invalidated_at_ms.clone(),
+                all_invalidated_at_ms: state.file.all_invalidated_at_ms,
+            }
+        };
+        let saved = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<CacheFile>(&content)
+                .ok()
+                .filter(|saved| saved.source == pending.source),
+            Err(_) => None,
+        };
+        let merged = merge_cache_files(saved.unwrap_or_default(), pending, retained_since_ms);
+        if let Err(e) = Self::write_file(path, &merged) {
+            warn!("failed to save table cache {}: {}", path.display(), e);
+        }
+    }
+
+    fn write_file(path: &Path, file: &CacheFile) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&tmp_path, serde_json::to_vec(file)?)?;
+        if let Err(e) = fs::rename(&tmp_path, path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TableCache {
+    fn drop(&mut self) {
+        self.write_pending();
+    }
+}
+
+/// Merge `pending` changes into the `saved` cache file: the newer entry of a table is kept, and entries fetched
+/// before the table was invalidated by either are dropped. Invalidations before `retained_since_ms` are forgotten.
+fn merge_cache_files(saved: CacheFile, pending: CacheFile, retained_since_ms: u64) -> CacheFile {
+    fn merge_entries<T>(
+        saved: HashMap<String, CachedEntry<T>>,
+        pending: HashMap<String, CachedEntry<T>>,
+    ) -> HashMap<String, CachedEntry<T>> {
+        let mut merged = saved;
+        for (table_id, entry) in pending {
+            if merged
+                .get(&table_id)
+                .is_none_or(|saved| saved.fetched_at_ms < entry.fetched_at_ms)
+            {
+                merged.insert(table_id, entry);
+            }
+        }
+        merged
+    }
+    let mut invalidated_at_ms = saved.invalidated_at_ms;
+    for (table_id, pending_at_ms) in pending.invalidated_at_ms {
+        let invalidated_at_ms = invalidated_at_ms.entry(table_id).or_default();
+        *invalidated_at_ms = (*invalidated_at_ms).max(pending_at_ms);
+    }
+    let all_invalidated_at_ms = saved.all_invalidated_at_ms.max(pending.all_invalidated_at_ms);
+    let is_live = |table_id: &String, fetched_at_ms: u64| {
+        fetched_at_ms > all_invalidated_at_ms
+            && invalidated_at_ms
+                .get(table_id)
+                .is_none_or(|invalidated_at_ms| fetched_at_ms > *invalidated_at_ms)
+    };
+    let mut table_info = merge_entries(saved.table_info, pending.table_info);
+    table_info.retain(|table_id, entry| is_live(table_id, entry.fetched_at_ms));
+    let mut table_name_id = merge_entries(saved.table_name_id, pending.table_name_id);
+    table_name_id.retain(|table_id, entry| is_live(table_id, entry.fetched_at_ms));
+    invalidated_at_ms.retain(|_, invalidated_at_ms| *invalidated_at_ms >= retained_since_ms);
+    CacheFile {
+        source: pending.source,
+        table_info,
+        table_name_id,
+        invalidated_at_ms,
+        all_invalidated_at_ms,
+    }
+}
+
+/// Subscribe to [`TABLE_CACHE_NOTIFY_CHANNEL`] on a connection of its own, dropping the notified tables from the
+/// cache. Notifications sent while the subscription is down are lost, so the whole cache is dropped each time it is
+/// established again. The owner aborts the task when done with the cache.
+pub(crate) fn listen_notifications(
+    table_cache: &Arc<TableCache>,
+    config: String,
+    runtime: &runtime::Handle,
+) -> JoinHandle<()> {
+    let table_cache = Arc::downgrade(table_cache);
+    runtime.spawn(async move {
+        loop {
+            if let Err(e) = listen(&table_cache, &config).await {
+                warn!("table cache notifications interrupted: {}", e);
+            }
+            match table_cache.upgrade() {
+                Some(table_cache) => table_cache.invalidate_all(),
+                None => return,
+            }
+            tokio::time::sleep(NOTIFY_RECONNECT_INTERVAL).await;
+        }
+    })
+}
+
+/// Listen until the connection is lost or the cache is dropped.
+async fn listen(table_cache: &Weak<TableCache>, config: &str) -> Result<()> {
+    // notifications are sent database wide, the schema does not matter
+    let (config, _) = parse_config(config)?;
+    let (client, mut connection) = config.connect(NoTls).await?;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(Ok(message)) = messages.next().await {
+            if let AsyncMessage::Notification(notification) = message {
+                if sender.send(notification.payload().to_string()).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    client
+        .batch_execute(&format!("LISTEN {}", TABLE_CACHE_NOTIFY_CHANNEL))
+        .await?;
+    // writes of other processes may have been missed before LISTEN
+    match table_cache.upgrade() {
+        Some(table_cache) => table_cache.invalidate_all(),
+        None => return Ok(()),
+    }
+    while let Some(table_id) = receiver.recv().await {
+        let Some(table_cache) = table_cache.upgrade() else {
+            return Ok(());
+        };
+        debug!("table cache notified of table {}", table_id);
+        table_cache.invalidate(Some(&table_id), None, None);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_info(table_id: &str, table_name: &str) -> TableInfo {
+        TableInfo {
+            table_id: table_id.to_string(),
+            table_namespace: "default".to_string(),
+            table_name: table_name.to_string(),
+            table_path: format!("file:///tmp/{}", table_name),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_table_cache_persists_and_invalidates() {
+        let dir = std::env::temp_dir().join(format!("lakesoul_table_cache_{}", uuid::Uuid::new_v4().simple()));
+        let source = cache_source("host=127.0.0.1 port=5432 dbname=lakesoul_test password=secret");
+        assert!(!source.contains("secret"));
+        let by_id = |table_id: &str| CacheKey::Id(table_id.to_string());
+        let by_name = |table_name: &str| CacheKey::Name("default".to_string(), table_name.to_string());
+
+        let minute = Duration::from_secs(60);
+        let cache = Arc::new(TableCache::open(&dir, source.clone(), minute, minute));
+        let generation = cache.generation();
+        cache.put(generation, table_info("table_1", "t1"));
+        cache.put(generation, table_info("table_2", "t2"));
+        assert_eq!(
+            cache.get::<TableInfo>(&CacheKey::Path("file:///tmp/t2".to_string())),
+            Some(table_info("table_2", "t2"))
+        );
+
+        // a fetch racing with a write is not cached
+        let generation = cache.generation();
+        cache.invalidate(Some("table_2"), None, None);
+        cache.put(generation, table_info("table_2", "t2"));
+        assert!(cache.get::<TableInfo>(&by_id("table_2")).is_none());
+
+        let reopened = Arc::new(TableCache::open(&dir, source.clone(), minute, minute));
+        assert_eq!(
+            reopened.get::<TableInfo>(&by_name("t1")),
+            Some(table_info("table_1", "t1"))
+        );
+        assert_eq!(reopened.table_ids(), vec!["table_1".to_string()]);
+
+        // a new table of the same name replaces the cached one
+        reopened.invalidate(Some("table_3"), Some(("default", "t1")), None);
+        assert!(reopened.get::<TableInfo>(&by_name("t1")).is_none());
+        // and the stale cache saving later does not restore it
+        cache.put(cache.generation(), table_info("table_4", "t4"));
+        let merged = TableCache::open(&dir, source.clone(), minute, minute);
+        assert!(merged.get::<TableInfo>(&by_id("table_1")).is_none());
+        assert!(merged.get::<TableInfo>(&by_id("table_4")).is_some());
+
+        // fetched entries are served for the ttl, loaded ones for the grace period
+        let generation = reopened.generation();
+        reopened.put(generation, table_info("table_1", "t1"));
+        let expired = Arc::new(TableCache::open(&dir, source.clone(), minute, Duration::ZERO));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(expired.get::<TableInfo>(&by_id("table_1")).is_none());
+        let generation = expired.generation();
+        expired.put(generation, table_info("table_1", "t1"));
+        assert!(expired.get::<TableInfo>(&by_id("table_1")).is_some());
+        assert_eq!(expired.metrics(), TableCacheMetrics { hits: 1, misses: 1 });
+
+        let other_database = TableCache::open(&dir, "dbname=other".to_string(), minute, minute);
+        assert!(other_database.table_ids().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_table_cache_saves_later() {
+        let dir = std::env::temp_dir().join(format!("lakesoul_table_cache_{}", uuid::Uuid::new_v4().simple()));
+        let minute = Duration::from_secs(60);
+        let cache = Arc::new(TableCache::open(&dir, "dbname=test".to_string(), minute, minute));
+        cache.put(cache.generation(), table_info("table_1", "t1"));
+        cache.put(cache.generation(), table_info("table_2", "t2"));
+        assert!(!dir.join(TABLE_CACHE_FILE_NAME).exists());
+
+        cache.flush().await;
+        let reopened = TableCache::open(&dir, "dbname=test".to_string(), minute, minute);
+        assert_eq!(reopened.table_ids(), vec!["table_1".to_string(), "table_2".to_string()]);
+
+        // the pending changes are written when the cache is dropped
+        cache.invalidate(Some("table_1"), None, None);
+        drop(cache);
+        let reopened = TableCache::open(&dir, "dbname=test".to_string(), minute, minute);
+        assert_eq!(reopened.table_ids(), vec!["table_2".to_string()]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
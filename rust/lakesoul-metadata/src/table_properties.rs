@@ -0,0 +1,215 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Registry of the table properties the engines and the metadata client act on, with the values they expect.
+//! Engines are free to store other properties, but a key that is a near miss of a known one, e.g.
+//! `hashBucketNumb`, is almost surely a typo silently disabling the feature of the known key.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::transfusion::config::{
+    CHANGE_TRACKING_ENABLED, COMMIT_CONFLICT_POLICY, DROPPED_COLUMN, HASH_BUCKET_NUM, LAST_TABLE_SCHEMA_CHANGE_TIME,
+    PARTITION_STATS_ENABLED,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PropertyType {
+    /// A positive integer, or -1 for tables without primary key.
+    BucketNum,
+    /// Whole days, as the engines read the ttl properties.
+    Days,
+    /// A column name.
+    Identifier,
+    /// Comma separated column names, possibly none.
+    IdentifierList,
+    Boolean,
+    /// Milliseconds since the epoch.
+    Timestamp,
+    /// Any non empty string.
+    NonEmpty,
+}
+
+impl PropertyType {
+    fn expected(self) -> &'static str {
+        match self {
+            PropertyType::BucketNum => "a positive integer, or -1 for tables without primary key",
+            PropertyType::Days => "a non negative integer number of days",
+            PropertyType::Identifier => "a column name",
+            PropertyType::IdentifierList => "comma separated column names",
+            PropertyType::Boolean => "true or false",
+            PropertyType::Timestamp => "a timestamp in milliseconds",
+            PropertyType::NonEmpty => "a non empty string",
+        }
+    }
+
+    fn accepts(self, value: &str) -> bool {
+        match self {
+            PropertyType::BucketNum => value == "-1" || value.parse::<i32>().is_ok_and(|num| num > 0),
+            PropertyType::Days => value.parse::<u32>().is_ok(),
+            PropertyType::Identifier => is_identifier(value),
+            PropertyType::IdentifierList => value.is_empty() || value.split(',').map(str::trim).all(is_identifier),
+            PropertyType::Boolean => value == "true" || value == "false",
+            PropertyType::Timestamp => value.parse::<i64>().is_ok_and(|timestamp| timestamp >= 0),
+            PropertyType::NonEmpty => !value.is_empty(),
+        }
+    }
+}
+
+const KNOWN_PROPERTIES: &[(&str, PropertyType)] = &[
+    (HASH_BUCKET_NUM, PropertyType::BucketNum),
+    ("hashPartitions", PropertyType::IdentifierList),
+    ("rangePartitions", PropertyType::IdentifierList),
+    ("partition.ttl", PropertyType::Days),
+    ("compaction.ttl", PropertyType::Days),
+    ("lakesoul_cdc_change_column", PropertyType::Identifier),
+    ("use_cdc", PropertyType::Boolean),
+    (CHANGE_TRACKING_ENABLED, PropertyType::Boolean),
+    (PARTITION_STATS_ENABLED, PropertyType::Boolean),
+    (COMMIT_CONFLICT_POLICY, PropertyType::NonEmpty),
+    (DROPPED_COLUMN, PropertyType::IdentifierList),
+    (LAST_TABLE_SCHEMA_CHANGE_TIME, PropertyType::Timestamp),
+];
+
+/// A table property that is likely not doing what it was set for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyIssue {
+    /// The key is not known, but so close to the known `similar_to` that it is likely a typo of it.
+    UnknownKey { key: String, similar_to: String },
+    /// The value of the known key is not of the expected type or range.
+    InvalidValue {
+        key: String,
+        value: String,
+        expected: String,
+    },
+}
+
+impl Display for PropertyIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyIssue::UnknownKey { key, similar_to } => {
+                write!(f, "unknown property '{}', did you mean '{}'?", key, similar_to)
+            }
+            PropertyIssue::InvalidValue { key, value, expected } => {
+                write!(f, "property '{}' is '{}', expected {}", key, value, expected)
+            }
+        }
+    }
+}
+
+/// Check the table properties against the known keys, in the order of the keys. Unknown keys are only reported
+/// when they are near misses of a known key, other properties of the engines are left alone.
+pub fn validate_table_properties(properties: &HashMap<String, String>) -> Vec<PropertyIssue> {
+    let mut keys = properties.keys().collect::<Vec<_>>();
+    keys.sort();
+    keys.into_iter()
+        .filter_map(|key| {
+            let value = &properties[key];
+            match KNOWN_PROPERTIES.iter().find(|(known, _)| *known == key.as_str()) {
+                Some((_, property_type)) if !property_type.accepts(value) => Some(PropertyIssue::InvalidValue {
+                    key: key.clone(),
+                    value: value.clone(),
+                    expected: property_type.expected().to_string(),
+                }),
+                Some(_) => None,
+                None => similar_known_key(key).map(|similar_to| PropertyIssue::UnknownKey {
+                    key: key.clone(),
+                    similar_to: similar_to.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// The known key sharing the longest prefix with `key`, ignoring case and separators, if the prefix covers at
+/// least three quarters of the longer of both.
+fn similar_known_key(key: &str) -> Option<&'static str> {
+    let key = normalize(key);
+    KNOWN_PROPERTIES
+        .iter()
+        .map(|(known, _)| {
+            let normalized = normalize(known);
+            let common = key.chars().zip(normalized.chars()).take_while(|(a, b)| a == b).count();
+            (*known, common, key.len().max(normalized.len()))
+        })
+        .filter(|(_, common, longer)| *common > 0 && common * 4 >= longer * 3)
+        .max_by_key(|(_, common, _)| *common)
+        .map(|(known, _, _)| known)
+}
+
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_table_properties() {
+        assert!(validate_table_properties(&properties(&[
+            ("hashBucketNum", "4"),
+            ("hashPartitions", "id, name"),
+            ("rangePartitions", ""),
+            ("compaction.ttl", "3"),
+            ("lakesoul_cdc_change_column", "rowKinds"),
+            ("spark.custom.option", "anything"),
+        ]))
+        .is_empty());
+        assert!(validate_table_properties(&properties(&[("hashBucketNum", "-1")])).is_empty());
+
+        assert_eq!(
+            validate_table_properties(&properties(&[
+                ("hashBucketNumb", "4"),
+                ("Compaction_TTL", "3"),
+                ("hashBucketNum", "0"),
+                ("lakesoul_cdc_change_column", "row kinds"),
+            ])),
+            [
+                PropertyIssue::UnknownKey {
+                    key: "Compaction_TTL".to_string(),
+                    similar_to: "compaction.ttl".to_string(),
+                },
+                PropertyIssue::InvalidValue {
+                    key: "hashBucketNum".to_string(),
+                    value: "0".to_string(),
+                    expected: PropertyType::BucketNum.expected().to_string(),
+                },
+                PropertyIssue::UnknownKey {
+                    key: "hashBucketNumb".to_string(),
+                    similar_to: "hashBucketNum".to_string(),
+                },
+                PropertyIssue::InvalidValue {
+                    key: "lakesoul_cdc_change_column".to_string(),
+                    value: "row kinds".to_string(),
+                    expected: PropertyType::Identifier.expected().to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_similar_known_key() {
+        assert_eq!(similar_known_key("hashBucketNumber"), Some("hashBucketNum"));
+        assert_eq!(similar_known_key("hashPartition"), Some("hashPartitions"));
+        assert_eq!(similar_known_key("partition_ttl"), Some("partition.ttl"));
+        assert_eq!(similar_known_key("hash"), None);
+        assert_eq!(similar_known_key("compression"), None);
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Propagation of the distributed trace of the caller to the metadata database, so that statements seen in
+//! pg_stat_activity or the server logs can be correlated with the trace they were issued by. The trace is given by
+//! running the operations of the client in [`TraceContext::scope`], and propagated as a W3C traceparent either in the
+//! application_name of the connection or as a comment prefixed to the statements.
+
+use std::future::Future;
+
+use tokio_postgres::Client;
+use tracing::debug;
+
+use crate::error::{LakeSoulMetaDataError, Result};
+use crate::ConnectionStatements;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// How the trace context of the caller reaches the metadata database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TracePropagation {
+    /// The trace context is ignored.
+    #[default]
+    Disabled,
+    /// The traceparent is appended to the application_name of the connection while the operation runs, at the
+    /// cost of a round trip whenever the trace changes.
+    ApplicationName,
+    /// The statements are prefixed by a `/* traceparent='..' */` comment, at the cost of preparing them again
+    /// for every operation of a trace.
+    SqlComment,
+}
+
+impl TryFrom<&str> for TracePropagation {
+    type Error = LakeSoulMetaDataError;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "" | "disabled" => Ok(Self::Disabled),
+            "application_name" => Ok(Self::ApplicationName),
+            "sql_comment" => Ok(Self::SqlComment),
+            _ => Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "unknown trace propagation {}, expected disabled, application_name or sql_comment",
+                value
+            ))),
+        }
+    }
+}
+
+/// Trace and span id of the caller, as in the W3C traceparent header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: String,
+    span_id: String,
+}
+
+impl TraceContext {
+    /// Fails with InvalidInput unless the trace id is 32 and the span id 16 hex digits, not all zero.
+    pub fn new(trace_id: &str, span_id: &str) -> Result<Self> {
+        let valid = |id: &str, len: usize| {
+            id.len() == len && id.bytes().all(|b| b.is_ascii_hexdigit()) && id.bytes().any(|b| b != b'0')
+        };
+        if !valid(trace_id, 32) || !valid(span_id, 16) {
+            return Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "invalid trace context {}-{}",
+                trace_id, span_id
+            )));
+        }
+        Ok(Self {
+            trace_id: trace_id.to_ascii_lowercase(),
+            span_id: span_id.to_ascii_lowercase(),
+        })
+    }
+
+    /// Parse a W3C traceparent header, e.g. `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    pub fn from_traceparent(traceparent: &str) -> Result<Self> {
+        match traceparent.trim().split('-').collect::<Vec<_>>()[..] {
+            [_version, trace_id, span_id, _flags] => Self::new(trace_id, span_id),
+            _ => Err(LakeSoulMetaDataError::InvalidInput(format!(
+                "invalid traceparent {}",
+                traceparent
+            ))),
+        }
+    }
+
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    pub fn span_id(&self) -> &str {
+        &self.span_id
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-01", self.trace_id, self.span_id)
+    }
+
+    /// Run `f` in this trace context, which the metadata operations of `f` propagate if the client is built
+    /// [`crate::MetaDataClientBuilder::with_trace_propagation`].
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// The trace context of the running operation, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+}
+
+/// Tag the connection with the trace context of the running operation, or clear the tag of a previous one.
+/// Tagging is best effort, a failure is logged and the operation runs untagged.
+pub(crate) async fn propagate(client: &Client, statements: &mut ConnectionStatements, propagation: TracePropagation) {
+    let traceparent = match propagation {
+        TracePropagation::Disabled => return,
+        _ => TraceContext::current().map(|context| context.traceparent()),
+    };
+    match propagation {
+        TracePropagation::SqlComment => {
+            statements.context.statement_comment =
+                traceparent.map(|traceparent| format!("traceparent='{}'", traceparent));
+        }
+        TracePropagation::ApplicationName if statements.application_name_trace != traceparent => {
+            let tagged = match &traceparent {
+                // keeps the application_name the connection was opened with, shortened to fit the traceparent
+                // into the 63 bytes postgres keeps
+                Some(traceparent) => client
+                    .execute(
+                        "select set_config('application_name', concat_ws(' ',
+                            nullif(left(reset_val, 62 - length($1::TEXT)), ''), $1::TEXT), false)
+                        from pg_settings where name = 'application_name'",
+                        &[traceparent],
+                    )
+                    .await
+                    .map(|_| ()),
+                None => client.batch_execute("RESET application_name").await,
+            };
+            match tagged {
+                Ok(()) => statements.application_name_trace = traceparent,
+                Err(err) => debug!("could not tag the connection with the trace context: {}", err),
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trace_context() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context = TraceContext::from_traceparent(traceparent).unwrap();
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id(), "00f067aa0ba902b7");
+        assert_eq!(context.traceparent(), traceparent);
+
+        // the ids end up in SQL, so anything but hex digits is rejected
+        assert!(TraceContext::new("4bf92f3577b34da6a3ce929d0e0e4736", "00f067aa0ba902b'").is_err());
+        assert!(TraceContext::new("00000000000000000000000000000000", "00f067aa0ba902b7").is_err());
+        assert!(TraceContext::from_traceparent("4bf92f3577b34da6a3ce929d0e0e4736").is_err());
+
+        assert_eq!(TraceContext::current(), None);
+        let current = context.clone().scope(async { TraceContext::current() }).await;
+        assert_eq!(current, Some(context));
+        assert_eq!(
+            TracePropagation::try_from("sql_comment").unwrap(),
+            TracePropagation::SqlComment
+        );
+        assert!(TracePropagation::try_from("header").is_err());
+    }
+}
@@ -17,14 +17,15 @@ use tokio_postgres::Client;
 
 use proto::proto::entity::{DataCommitInfo, DataFileOp, FileOp, JniWrapper, PartitionInfo, TableInfo};
 
-use crate::{DaoType, error::Result, execute_query, PARAM_DELIM, PreparedStatementMap};
+use crate::{DaoType, encode_query_params, error::Result, execute_query, PreparedStatementMap};
 use crate::error::LakeSoulMetaDataError;
+use crate::metadata_client::resolve_file_path;
 use crate::transfusion::config::{
-    LAKESOUL_HASH_PARTITION_SPLITTER, LAKESOUL_NON_PARTITION_TABLE_PART_DESC,
+    LAKESOUL_HASH_PARTITION_SPLITTER, LAKESOUL_NON_PARTITION_TABLE_PART_DESC, LAKESOUL_PARTITION_DESC_KV_DELIM,
     LAKESOUL_PARTITION_SPLITTER_OF_RANGE_AND_HASH, LAKESOUL_RANGE_PARTITION_SPLITTER,
 };
 
-mod config {
+pub(crate) mod config {
     #![allow(unused)]
 
     /// copy from DBConfig
@@ -52,6 +53,12 @@ mod config {
 
     pub const HASH_BUCKET_NUM: &str = "hashBucketNum";
 
+    pub const CHANGE_TRACKING_ENABLED: &str = "changeTrackingEnabled";
+
+    pub const PARTITION_STATS_ENABLED: &str = "partitionStatsEnabled";
+
+    pub const COMMIT_CONFLICT_POLICY: &str = "commitConflictPolicy";
+
     pub const DROPPED_COLUMN: &str = "droppedColumn";
     //
     pub const DROPPED_COLUMN_SPLITTER: &str = ",";
@@ -80,6 +87,7 @@ pub async fn split_desc_array(
 ) -> Result<SplitDescArray> {
     let db = RawClient::new(client, prepared);
     let table_info = db.get_table_info_by_table_name(table_name, namespace).await?;
+    // the raw client has no path mode, the paths stored relative are always resolved against the table path
     let data_files = db.get_table_data_info(&table_info.table_id).await?;
 
     // create splits
@@ -93,13 +101,13 @@ pub async fn split_desc_array(
                 .or_insert(HashMap::new())
                 .entry(df.bucket_id())
                 .or_insert(Vec::new())
-                .push(df.path.clone());
+                .push(resolve_file_path(&table_info.table_path, &df.path));
         } else {
             map.entry(df.partition_desc.as_str())
                 .or_insert(HashMap::new())
                 .entry(-1)
                 .or_insert(Vec::new())
-                .push(df.path.clone());
+                .push(resolve_file_path(&table_info.table_path, &df.path));
         }
     }
     // hash keys
@@ -158,7 +166,7 @@ impl<'a> RawClient<'_> {
         match self
             .query(
                 DaoType::SelectTableInfoByTableNameAndNameSpace as i32,
-                [table_name, namespace].join(PARAM_DELIM),
+                encode_query_params(&[table_name, namespace]),
             )
             .await
         {
@@ -256,7 +264,8 @@ impl<'a> RawClient<'_> {
             .map(|commit_id| format!("{:0>16x}{:0>16x}", commit_id.high, commit_id.low))
             .collect::<Vec<String>>()
             .join("");
-        let joined_string = [table_id.as_str(), partition_desc.as_str(), joined_commit_id.as_str()].join(PARAM_DELIM);
+        let joined_string =
+            encode_query_params(&[table_id.as_str(), partition_desc.as_str(), joined_commit_id.as_str()]);
         match self
             .query(
                 DaoType::ListDataCommitInfoByTableIdAndPartitionDescAndCommitList as i32,
@@ -359,6 +368,36 @@ pub fn parse_table_info_partitions(partitions: &str) -> (Vec<String>, Vec<String
     )
 }
 
+/// Range partition columns of a table, none if its partitions are malformed.
+pub fn range_partition_keys(partitions: &str) -> Vec<String> {
+    if partitions.contains(LAKESOUL_PARTITION_SPLITTER_OF_RANGE_AND_HASH) {
+        parse_table_info_partitions(partitions).0
+    } else {
+        vec![]
+    }
+}
+
+/// Canonical form of a partition_desc like `region=us,date=2023-01-01`, with the columns in the order of
+/// `range_keys` so that logically equal descs compare equal. Columns missing from `range_keys` follow sorted by name.
+/// Descs not made of `key=value` pairs, e.g. [`LAKESOUL_NON_PARTITION_TABLE_PART_DESC`], are kept as they are.
+pub fn canonical_partition_desc(partition_desc: &str, range_keys: &[String]) -> String {
+    let Some(mut kvs) = partition_desc
+        .split(LAKESOUL_RANGE_PARTITION_SPLITTER)
+        .map(|kv| kv.split_once(LAKESOUL_PARTITION_DESC_KV_DELIM))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return partition_desc.to_string();
+    };
+    kvs.sort_by_key(|(key, _)| {
+        let position = range_keys.iter().position(|range_key| range_key == key);
+        (position.unwrap_or(range_keys.len()), *key)
+    });
+    kvs.iter()
+        .map(|(key, value)| [*key, *value].join(LAKESOUL_PARTITION_DESC_KV_DELIM))
+        .collect::<Vec<_>>()
+        .join(LAKESOUL_RANGE_PARTITION_SPLITTER)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SplitDesc {
     pub file_paths: Vec<String>,
@@ -420,6 +459,26 @@ mod test {
         }
     }
 
+    #[test]
+    fn canonical_partition_desc_test() {
+        let range_keys = range_partition_keys("date,region;id");
+        assert_eq!(range_keys, vec!["date".to_string(), "region".to_string()]);
+        assert_eq!(
+            canonical_partition_desc("region=us,date=2023-01-01", &range_keys),
+            "date=2023-01-01,region=us"
+        );
+        assert_eq!(
+            canonical_partition_desc("date=2023-01-01,region=us", &range_keys),
+            "date=2023-01-01,region=us"
+        );
+        assert_eq!(canonical_partition_desc("b=1,a=2", &[]), "a=2,b=1");
+        assert_eq!(
+            canonical_partition_desc(LAKESOUL_NON_PARTITION_TABLE_PART_DESC, &range_keys),
+            LAKESOUL_NON_PARTITION_TABLE_PART_DESC
+        );
+        assert!(range_partition_keys("").is_empty());
+    }
+
     #[test]
     fn serialize_test() {
         let sd = SplitDesc {
@@ -0,0 +1,140 @@
+// SPDX-FileCopyrightText: 2024 LakeSoul Contributors
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Caps on the size of the metadata written by one client, keeping a runaway writer from bloating the rows every
+//! reader of a table has to fetch. Writes over a cap fail with LimitExceeded naming the cap and the actual size.
+
+use proto::proto::entity::{DataCommitInfo, TableInfo};
+
+use crate::error::{LakeSoulMetaDataError, Result};
+
+/// Caps checked before writing metadata, see [`crate::MetaDataClientBuilder::with_write_limits`].
+/// The defaults are far above what engines write, they only stop pathological payloads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteLimits {
+    /// Longest table or namespace name, in bytes.
+    pub max_name_length: usize,
+    /// Longest partition_desc, in bytes.
+    pub max_partition_desc_length: usize,
+    /// Largest properties JSON of a table or namespace, in bytes.
+    pub max_properties_size: usize,
+    /// Largest table schema JSON, in bytes.
+    pub max_schema_size: usize,
+    /// Most file ops in one data commit.
+    pub max_files_per_commit: usize,
+}
+
+impl Default for WriteLimits {
+    fn default() -> Self {
+        Self {
+            max_name_length: 1024,
+            max_partition_desc_length: 64 * 1024,
+            max_properties_size: 1024 * 1024,
+            max_schema_size: 16 * 1024 * 1024,
+            max_files_per_commit: 1_000_000,
+        }
+    }
+}
+
+fn check(limit: &str, max: usize, actual: usize, what: impl FnOnce() -> String) -> Result<()> {
+    if actual > max {
+        return Err(LakeSoulMetaDataError::LimitExceeded(format!(
+            "{} is {}, over {}={}",
+            what(),
+            actual,
+            limit,
+            max
+        )));
+    }
+    Ok(())
+}
+
+impl WriteLimits {
+    pub(crate) fn check_name(&self, name: &str) -> Result<()> {
+        check("max_name_length", self.max_name_length, name.len(), || {
+            "length of name".to_string()
+        })
+    }
+
+    pub(crate) fn check_partition_desc(&self, partition_desc: &str) -> Result<()> {
+        check(
+            "max_partition_desc_length",
+            self.max_partition_desc_length,
+            partition_desc.len(),
+            || "length of partition_desc".to_string(),
+        )
+    }
+
+    pub(crate) fn check_properties(&self, properties: &str) -> Result<()> {
+        check(
+            "max_properties_size",
+            self.max_properties_size,
+            properties.len(),
+            || "size of properties".to_string(),
+        )
+    }
+
+    pub(crate) fn check_schema(&self, schema: &str) -> Result<()> {
+        check("max_schema_size", self.max_schema_size, schema.len(), || {
+            "size of table schema".to_string()
+        })
+    }
+
+    pub(crate) fn check_table_info(&self, table_info: &TableInfo) -> Result<()> {
+        self.check_name(&table_info.table_name)?;
+        self.check_name(&table_info.table_namespace)?;
+        self.check_properties(&table_info.properties)?;
+        self.check_schema(&table_info.table_schema)
+    }
+
+    pub(crate) fn check_data_commit_info(&self, data_commit_info: &DataCommitInfo) -> Result<()> {
+        self.check_partition_desc(&data_commit_info.partition_desc)?;
+        check(
+            "max_files_per_commit",
+            self.max_files_per_commit,
+            data_commit_info.file_ops.len(),
+            || format!("number of files of commit to table '{}'", data_commit_info.table_id),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_limits() {
+        let limits = WriteLimits {
+            max_name_length: 4,
+            max_properties_size: 8,
+            max_files_per_commit: 1,
+            ..Default::default()
+        };
+        assert!(limits.check_name("abcd").is_ok());
+        match limits.check_name("abcde") {
+            Err(LakeSoulMetaDataError::LimitExceeded(message)) => {
+                assert_eq!(message, "length of name is 5, over max_name_length=4")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let table_info = TableInfo {
+            table_name: "t".to_string(),
+            table_namespace: "ns".to_string(),
+            properties: r#"{"k":"value"}"#.to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            limits.check_table_info(&table_info),
+            Err(LakeSoulMetaDataError::LimitExceeded(message)) if message.contains("max_properties_size=8")
+        ));
+        let data_commit_info = DataCommitInfo {
+            file_ops: vec![Default::default(); 2],
+            ..Default::default()
+        };
+        assert!(matches!(
+            limits.check_data_commit_info(&data_commit_info),
+            Err(LakeSoulMetaDataError::LimitExceeded(message)) if message.contains("max_files_per_commit=1")
+        ));
+    }
+}
@@ -9,6 +9,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     {
         std::env::set_var("PROTOC", protobuf_src::protoc());
     }
-    prost_build::compile_protos(&["src/entity.proto"], &["src/"])?;
+    prost_build::Config::new()
+        // table metadata is cached on disk as JSON by lakesoul-metadata
+        .type_attribute(
+            ".proto.entity.TableInfo",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .type_attribute(
+            ".proto.entity.TableNameId",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .compile_protos(&["src/entity.proto"], &["src/"])?;
     Ok(())
 }
@@ -9,3 +9,9 @@ delete from table_info;
 delete from table_path_id;
 delete from table_name_id;
 delete from partition_info;
+delete from partition_stats;
+delete from file_stats;
+delete from commit_provenance;
+delete from table_tag;
+delete from table_schema_history;
+delete from commit_schema_version;
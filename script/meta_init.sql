@@ -27,6 +27,8 @@ create table if not exists table_info
     primary key (table_id)
 );
 
+create index if not exists table_info_domain_table_id on table_info (domain, table_id);
+
 create table if not exists table_name_id
 (
     table_name      text,
@@ -83,9 +85,69 @@ create table if not exists partition_info
     snapshot       UUID[],
     expression     text,
     domain         text default 'public',
+    change_tracking boolean default 'false',
     primary key (table_id, partition_desc, version)
 );
 
+alter table partition_info add column if not exists change_tracking boolean default 'false';
+
+create table if not exists partition_stats
+(
+    table_id       text,
+    partition_desc text,
+    file_count     bigint,
+    total_bytes    bigint,
+    last_commit_ts bigint,
+    primary key (table_id, partition_desc)
+);
+
+create table if not exists file_stats
+(
+    commit_id UUID,
+    path      text,
+    stats     json,
+    primary key (commit_id, path)
+);
+
+create table if not exists commit_provenance
+(
+    commit_id      UUID,
+    table_id       text,
+    partition_desc text,
+    engine         text,
+    job_id         text,
+    user_name      text,
+    checkpoint_id  text,
+    primary key (table_id, commit_id)
+);
+
+create table if not exists table_tag
+(
+    table_id        text,
+    tag             text,
+    partition_descs text[],
+    versions        int[],
+    timestamp       bigint,
+    primary key (table_id, tag)
+);
+
+create table if not exists table_schema_history
+(
+    table_id     text,
+    version      int,
+    table_schema text,
+    replaced_at  bigint,
+    primary key (table_id, version)
+);
+
+create table if not exists commit_schema_version
+(
+    table_id       text,
+    commit_id      UUID,
+    schema_version int,
+    primary key (table_id, commit_id)
+);
+
 CREATE OR REPLACE FUNCTION partition_insert() RETURNS TRIGGER AS
 $$
 DECLARE
@@ -136,6 +198,64 @@ CREATE OR REPLACE TRIGGER partition_table_change
     FOR EACH ROW
 EXECUTE PROCEDURE partition_insert();
 
+-- notify the clients caching table metadata of every changed table
+CREATE OR REPLACE FUNCTION table_cache_notify() RETURNS TRIGGER AS
+$$
+BEGIN
+    perform pg_notify('lakesoul_table_cache_notify', OLD.table_id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER table_info_change
+    AFTER UPDATE OR DELETE
+    ON table_info
+    FOR EACH ROW
+EXECUTE PROCEDURE table_cache_notify();
+
+-- keep the replaced schema of a table, for reading the data written before
+CREATE OR REPLACE FUNCTION table_schema_history_insert() RETURNS TRIGGER AS
+$$
+BEGIN
+    insert into table_schema_history(table_id, version, table_schema, replaced_at)
+    select OLD.table_id, coalesce(max(version) + 1, 0), OLD.table_schema, (date_part('epoch', now()) * 1000)::BIGINT
+    from table_schema_history
+    where table_id = OLD.table_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER table_schema_change
+    AFTER UPDATE OF table_schema
+    ON table_info
+    FOR EACH ROW
+    WHEN (OLD.table_schema IS DISTINCT FROM NEW.table_schema)
+EXECUTE PROCEDURE table_schema_history_insert();
+
+CREATE OR REPLACE FUNCTION commit_schema_version_insert() RETURNS TRIGGER AS
+$$
+BEGIN
+    insert into commit_schema_version(table_id, commit_id, schema_version)
+    select NEW.table_id, NEW.commit_id, coalesce(max(version) + 1, 0)
+    from table_schema_history
+    where table_id = NEW.table_id
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER data_commit_schema_version
+    AFTER INSERT
+    ON data_commit_info
+    FOR EACH ROW
+EXECUTE PROCEDURE commit_schema_version_insert();
+
+CREATE OR REPLACE TRIGGER table_name_id_change
+    AFTER UPDATE OR DELETE
+    ON table_name_id
+    FOR EACH ROW
+EXECUTE PROCEDURE table_cache_notify();
+
 create table if not exists global_config
 (
     key  text,